@@ -0,0 +1,305 @@
+extern crate procrs;
+extern crate argparse;
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+use procrs::pid::{Pid, PidIter, PidQuery, QueryContext};
+use procrs::meminfo::Meminfo;
+use procrs::loadavg::Loadavg;
+use procrs::pressure::{Pressure, PressureLine};
+use procrs::rate::Rate;
+use procrs::{TaskId, ticks_per_second};
+use argparse::{ArgumentParser, Store, Collect};
+
+// The content type of the prometheus text format.
+const CONTENT_TYPE: &'static str = "text/plain; version=0.0.4";
+// How long a client can take to send its request or read the response,
+// so a stalled connection can't block the scrapes after it.
+const CLIENT_TIMEOUT: u64 = 10;
+
+fn main() {
+    let mut listen = "127.0.0.1:9256".to_owned();
+    let mut query_args: Vec<String> = Vec::new();
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Export process and system metrics for prometheus at /metrics");
+        ap.refer(&mut listen)
+            .add_option(&["-l", "--listen"], Store, "Address to listen on");
+        ap.refer(&mut query_args)
+            .add_option(&["-q", "--query"], Collect,
+                "A process query to aggregate, as label:query or just query (repeatable)");
+        ap.parse_args_or_exit();
+    }
+
+    let mut queries = Vec::new();
+    for arg in query_args {
+        match parse_query_arg(&arg) {
+            Ok(query) => queries.push(query),
+            Err(e) => {
+                println!("ERROR, {}", e);
+                return
+            }
+        }
+    }
+    let listener = match TcpListener::bind(&*listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            println!("ERROR, couldn't listen on {}: {}", listen, e);
+            return
+        }
+    };
+    let mut exporter = Exporter::new(queries);
+    // Scrapes are served one at a time, so the snapshots used for rates are
+    // only ever touched by one request.
+    for stream in listener.incoming() {
+        if let Ok(stream) = stream {
+            let _ = handle(stream, &mut exporter);
+        }
+    }
+}
+
+// Split "label:query" into its parts. Without a label, the query itself
+// is used as the label.
+fn parse_query_arg(arg: &str) -> Result<(String, PidQuery), String> {
+    let (label, query) = match arg.find(':') {
+        Some(i) => (&arg[..i], &arg[i + 1..]),
+        None => (arg, arg)
+    };
+    query.parse().map(|q| (label.to_owned(), q))
+        .map_err(|e| format!("Invalid query '{}': {}", arg, e))
+}
+
+// Answer a single http request, only GET /metrics is served.
+fn handle(stream: TcpStream, exporter: &mut Exporter) -> std::io::Result<()> {
+    try!(stream.set_read_timeout(Some(Duration::from_secs(CLIENT_TIMEOUT))));
+    try!(stream.set_write_timeout(Some(Duration::from_secs(CLIENT_TIMEOUT))));
+    let mut reader = BufReader::new(try!(stream.try_clone()));
+    let mut request = String::new();
+    try!(reader.read_line(&mut request));
+    // Skip the headers, nothing in them changes the response.
+    let mut header = String::new();
+    while try!(reader.read_line(&mut header)) > 0 && header.trim() != "" {
+        header.clear();
+    }
+    let mut split = request.split_whitespace();
+    let (status, body) = match (split.next(), split.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", exporter.scrape()),
+        (Some("GET"), Some("/")) =>
+            ("200 OK", "procrs-exporter, metrics are at /metrics\n".to_owned()),
+        (Some("GET"), _) => ("404 Not Found", "Not found\n".to_owned()),
+        _ => ("405 Method Not Allowed", "Method not allowed\n".to_owned()),
+    };
+    let mut stream = stream;
+    write!(stream, "HTTP/1.0 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+        Connection: close\r\n\r\n{}", status, CONTENT_TYPE, body.len(), body)
+}
+
+// The state kept between scrapes to calculate rates.
+struct Exporter {
+    queries: Vec<(String, PidQuery)>,
+    // The cpu ticks of every process at the last scrape, by pid.
+    last: Option<(Instant, HashMap<TaskId, u64>)>,
+}
+
+impl Exporter {
+    fn new(queries: Vec<(String, PidQuery)>) -> Self {
+        Exporter {
+            queries: queries,
+            last: None,
+        }
+    }
+
+    // Read everything, and format it as prometheus metrics.
+    fn scrape(&mut self) -> String {
+        let mut out = String::new();
+        if let Ok(minfo) = Meminfo::new() {
+            write_meminfo(&mut out, &minfo);
+        }
+        if let Ok(load) = Loadavg::new() {
+            write_loadavg(&mut out, &load);
+        }
+        let pressures = [("cpu", Pressure::cpu()), ("memory", Pressure::memory()),
+            ("io", Pressure::io())];
+        write_pressure(&mut out, &pressures.iter()
+            .filter_map(|&(res, ref p)| p.as_ref().ok().map(|p| (res, p)))
+            .collect::<Vec<_>>());
+        if !self.queries.is_empty() {
+            self.write_queries(&mut out);
+        }
+        out
+    }
+
+    // Scan /proc once, and aggregate the processes matching each query.
+    fn write_queries(&mut self, out: &mut String) {
+        let now = Instant::now();
+        let pids: Vec<Pid> = match PidIter::new() {
+            Ok(iter) => iter.filter_map(|p| p.ok()).collect(),
+            Err(_) => return
+        };
+        let ticks: HashMap<TaskId, u64> = pids.iter()
+            .map(|p| (p.pid, p.stat.utime + p.stat.stime))
+            .collect();
+        let mut rows = Vec::new();
+        for &(ref label, ref query) in &self.queries {
+            let context = QueryContext::new(query);
+            let matching: Vec<_> = pids.iter().filter(|p| p.query_with(query, &context)).collect();
+            let rss: u64 = matching.iter().filter_map(|p| p.status.vmrss).sum();
+            let threads: u64 = matching.iter().map(|p| p.stat.num_threads as u64).sum();
+            // Only processes in both snapshots count towards the rate, so one
+            // exiting doesn't make it go negative.
+            let cpu = self.last.as_ref().map(|&(then, ref last)| {
+                let delta: u64 = matching.iter()
+                    .filter_map(|p| last.get(&p.pid).map(|l| ticks[&p.pid].saturating_sub(*l)))
+                    .sum();
+                let ticks_per_sec = Rate::from_delta(delta, now.duration_since(then)).per_sec();
+                ticks_per_sec / ticks_per_second() as f64
+            });
+            rows.push((label.clone(), matching.len(), threads, rss, cpu));
+        }
+        self.last = Some((now, ticks));
+
+        metric(out, "procrs_query_processes", "gauge", "Processes matching the query");
+        for &(ref label, count, _, _, _) in &rows {
+            sample(out, "procrs_query_processes", &[("query", label)], count as f64);
+        }
+        metric(out, "procrs_query_threads", "gauge", "Threads of the processes matching the query");
+        for &(ref label, _, threads, _, _) in &rows {
+            sample(out, "procrs_query_threads", &[("query", label)], threads as f64);
+        }
+        metric(out, "procrs_query_resident_bytes", "gauge",
+            "Resident memory of the processes matching the query");
+        for &(ref label, _, _, rss, _) in &rows {
+            sample(out, "procrs_query_resident_bytes", &[("query", label)], rss as f64);
+        }
+        // The first scrape has nothing to compare with.
+        if rows.iter().any(|r| r.4.is_some()) {
+            metric(out, "procrs_query_cpu_usage_ratio", "gauge",
+                "Cpus used by the processes matching the query since the last scrape");
+            for &(ref label, _, _, _, cpu) in &rows {
+                if let Some(cpu) = cpu {
+                    sample(out, "procrs_query_cpu_usage_ratio", &[("query", label)], cpu);
+                }
+            }
+        }
+    }
+}
+
+fn write_meminfo(out: &mut String, minfo: &Meminfo) {
+    // Meminfo values are in kB.
+    // Available memory is missing before Linux 3.14.
+    let fields = [
+        ("procrs_memory_total_bytes", "Total usable memory", Some(minfo.memtotal)),
+        ("procrs_memory_free_bytes", "Unused memory", Some(minfo.memfree)),
+        ("procrs_memory_available_bytes", "Memory available without swapping",
+            minfo.memavailable),
+        ("procrs_memory_buffers_bytes", "Memory used by block device buffers",
+            Some(minfo.buffers)),
+        ("procrs_memory_cached_bytes", "Memory used by the page cache", Some(minfo.cached)),
+        ("procrs_memory_shared_bytes", "Memory used by shmem and tmpfs", Some(minfo.shmem)),
+        ("procrs_swap_total_bytes", "Total swap space", Some(minfo.swaptotal)),
+        ("procrs_swap_free_bytes", "Unused swap space", Some(minfo.swapfree)),
+    ];
+    for &(name, help, kb) in &fields {
+        if let Some(kb) = kb {
+            metric(out, name, "gauge", help);
+            sample(out, name, &[], (kb * 1024) as f64);
+        }
+    }
+}
+
+fn write_loadavg(out: &mut String, load: &Loadavg) {
+    metric(out, "procrs_load", "gauge", "Load average, over a period in minutes");
+    for &(period, value) in &[("1", load.load1), ("5", load.load5), ("15", load.load15)] {
+        sample(out, "procrs_load", &[("period", period)], value);
+    }
+    metric(out, "procrs_tasks_runnable", "gauge", "Currently runnable tasks");
+    sample(out, "procrs_tasks_runnable", &[], load.runnable as f64);
+    metric(out, "procrs_tasks", "gauge", "Tasks (processes and threads) on the system");
+    sample(out, "procrs_tasks", &[], load.tasks as f64);
+}
+
+fn write_pressure(out: &mut String, pressures: &[(&str, &Pressure)]) {
+    if pressures.is_empty() {
+        return
+    }
+    let lines: Vec<(&str, &str, &PressureLine)> = pressures.iter()
+        .flat_map(|&(res, p)| Some((res, "some", &p.some)).into_iter()
+            .chain(p.full.as_ref().map(|full| (res, "full", full))))
+        .collect();
+    metric(out, "procrs_pressure_ratio", "gauge",
+        "Share of time tasks were stalled on a resource, over a window");
+    for &(res, kind, line) in &lines {
+        for &(window, avg) in &[("10s", line.avg10), ("60s", line.avg60), ("300s", line.avg300)] {
+            sample(out, "procrs_pressure_ratio",
+                &[("resource", res), ("kind", kind), ("window", window)], avg / 100.0);
+        }
+    }
+    metric(out, "procrs_pressure_stalled_seconds_total", "counter",
+        "Total time tasks were stalled on a resource");
+    for &(res, kind, line) in &lines {
+        sample(out, "procrs_pressure_stalled_seconds_total", &[("resource", res), ("kind", kind)],
+            line.total as f64 / 1e6);
+    }
+}
+
+// Write the HELP and TYPE lines of a metric.
+fn metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+}
+
+// Write a single sample of a metric, with its labels.
+fn sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    let labels: Vec<_> = labels.iter()
+        .map(|&(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+        .collect();
+    let _ = match labels.is_empty() {
+        true => writeln!(out, "{} {}", name, value),
+        false => writeln!(out, "{}{{{}}} {}", name, labels.join(","), value),
+    };
+}
+
+// Escape a label value, which can't contain raw backslashes, quotes or newlines.
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[test]
+fn test_parse_query_arg() {
+    let (label, query) = parse_query_arg("web:nginx").unwrap();
+    assert_eq!(label, "web");
+    assert_eq!(format!("{:?}", query), format!("{:?}", "nginx".parse::<PidQuery>().unwrap()));
+    let (label, query) = parse_query_arg("rss>100M").unwrap();
+    assert_eq!(label, "rss>100M");
+    assert_eq!(format!("{:?}", query), format!("{:?}", "rss>100M".parse::<PidQuery>().unwrap()));
+    assert!(parse_query_arg("bad:rss>lots").is_err());
+}
+
+#[test]
+fn test_escape_label() {
+    assert_eq!(escape_label("nginx"), "nginx");
+    assert_eq!(escape_label("a\\b \"c\"\nd"), "a\\\\b \\\"c\\\"\\nd");
+}
+
+#[test]
+fn test_render() {
+    let mut out = String::new();
+    metric(&mut out, "procrs_tasks", "gauge", "Tasks on the system");
+    sample(&mut out, "procrs_tasks", &[], 74.0);
+    sample(&mut out, "procrs_query_processes", &[("query", "web"), ("kind", "a\"b")], 2.5);
+    assert_eq!(out, "# HELP procrs_tasks Tasks on the system\n# TYPE procrs_tasks gauge\n\
+        procrs_tasks 74\nprocrs_query_processes{query=\"web\",kind=\"a\\\"b\"} 2.5\n");
+
+    let mut out = String::new();
+    write_loadavg(&mut out, &Loadavg {
+        load1: 0.26,
+        load5: 0.3,
+        load15: 0.27,
+        runnable: 2,
+        tasks: 74,
+        last_pid: 29640,
+    });
+    assert!(out.contains("procrs_load{period=\"5\"} 0.3\n"));
+    assert!(out.contains("# TYPE procrs_tasks_runnable gauge\nprocrs_tasks_runnable 2\n"));
+}
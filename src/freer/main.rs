@@ -1,14 +1,29 @@
 extern crate procrs;
-#[macro_use]
-extern crate prettytable;
+extern crate argparse;
 
+use std::io;
 use procrs::meminfo;
-use prettytable::Table;
-use prettytable::format::FormatBuilder;
-use prettytable::format::Alignment;
+use procrs::output::{OutputFormat, OutputSink, TableSink};
+use procrs::format::{self, ByteUnits};
+use argparse::{ArgumentParser, Store, StoreConst};
 
 
 fn main () {
+    let mut format = OutputFormat::Table;
+    let mut units = None;
+    {
+        let mut ap = ArgumentParser::new();
+        ap.set_description("Display the amount of free and used memory");
+        ap.refer(&mut format)
+            .add_option(&["-f", "--format"], Store, "Output format, being table, json or csv");
+        ap.refer(&mut units)
+            .add_option(&["-H", "--human"], StoreConst(Some(ByteUnits::Binary)),
+                "Show sizes with binary units, like 1.5 GiB")
+            .add_option(&["--si"], StoreConst(Some(ByteUnits::Si)),
+                "Show sizes with SI units, like 1.6 GB");
+        ap.parse_args_or_exit();
+    }
+
     // Build the minfo
     let minfo = match meminfo::Meminfo::new() {
         Ok(minfo) => minfo,
@@ -20,31 +35,27 @@ fn main () {
     // Mem:       12202716     1666600      957368      401652     9578748     9989056
     // Swap:       6160380           0     6160380
 
-    // Start building the table
-    let mut table = Table::new();
+    let stdout = io::stdout();
+    let mut sink: Box<dyn OutputSink> = match format {
+        // Numbers are right aligned under their titles, like free.
+        OutputFormat::Table => Box::new(TableSink::new(stdout.lock()).padding(0, 3).right_align_from(1)),
+        _ => format.sink(stdout.lock())
+    };
+    // The row names have no title in a table, but need a key in json and csv.
+    let name_title = if format == OutputFormat::Table { "" } else { "type" };
     // Need to calculate used from other things
-    table.add_row(row!["", "total", "used", "free", "shared", "buff/cache", "available"]);
-    table.add_row(row!["Mem:", minfo.memtotal, minfo.mainused, minfo.memfree, minfo.shmem, minfo.maincached, minfo.memavailable]);
-    table.add_row(row!["Swap:", minfo.swaptotal, minfo.mainswapused, minfo.swapfree]);
-    // Make a format for it
-    let format = FormatBuilder::new()
-        .column_separator(' ')
-        .padding(0, 3)
-        .build();
-    table.set_format(format);
-    
-    for r in table.row_iter_mut() {
-        for cel in r.iter_mut() {
-            cel.align(Alignment::RIGHT);
-        }
-    }
-
-    for cel in table.column_iter_mut(0) {
-        cel.align(Alignment::LEFT);
-    }
-
-    table.printstd();
-
-
+    sink.write_table(&[name_title, "total", "used", "free", "shared", "buff/cache", "available"]).unwrap();
+    // Meminfo values are in kB, which are printed as is without a unit.
+    let size = |kb: u64| match units {
+        Some(units) => format::bytes(kb * 1024, units),
+        None => kb.to_string()
+    };
+    sink.write_row(&["Mem:".to_owned(), size(minfo.memtotal), size(minfo.mainused),
+        size(minfo.memfree), size(minfo.shmem), size(minfo.maincached),
+        // Kernels before 3.14 don't estimate available memory, so use free
+        // memory like older versions of free.
+        size(minfo.memavailable.unwrap_or(minfo.memfree))]).unwrap();
+    sink.write_row(&["Swap:".to_owned(), size(minfo.swaptotal),
+        size(minfo.mainswapused), size(minfo.swapfree)]).unwrap();
+    sink.finish().unwrap();
 }
-
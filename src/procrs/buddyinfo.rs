@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::Read;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// The free blocks of a single memory zone, from a row of /proc/buddyinfo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuddyZone {
+    /// The NUMA node of the zone.
+    pub node: u32,
+    /// The name of the zone, eg: "DMA32" or "Normal".
+    pub zone: String,
+    /// The number of free blocks of each order, where a block of order n is
+    /// 2^n contiguous pages.
+    pub free: Vec<u64>,
+}
+
+impl BuddyZone {
+    /// The total number of free pages in the zone.
+    pub fn free_pages(&self) -> u64 {
+        self.free.iter().enumerate().map(|(order, &count)| count << order).sum()
+    }
+
+    /// The number of free pages in blocks of at least this order, which can
+    /// be used for an allocation of this order.
+    pub fn free_pages_from(&self, order: usize) -> u64 {
+        self.free.iter().enumerate().skip(order).map(|(o, &count)| count << o).sum()
+    }
+
+    /// The external fragmentation index for allocations of this order, being
+    /// the share of free memory that is in blocks too small to use (the
+    /// kernel's unusable free space index).
+    ///
+    /// This ranges from 0, where every free page could be used, to 1 where
+    /// none could, eg: hugepage allocations (order 9 on x86) failing despite
+    /// plenty of free memory show up as an index near 1. A zone without free
+    /// memory has an index of 0, since that's a shortage not fragmentation.
+    pub fn fragmentation_index(&self, order: usize) -> f64 {
+        match self.free_pages() {
+            0 => 0.0,
+            total => (total - self.free_pages_from(order)) as f64 / total as f64
+        }
+    }
+}
+
+/// A struct containing the free blocks of each memory zone, from
+/// /proc/buddyinfo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Buddyinfo {
+    /// Each zone, in file order.
+    pub zones: Vec<BuddyZone>,
+}
+
+impl Buddyinfo {
+    /// Read the current free blocks from /proc/buddyinfo.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("buddyinfo"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcBuddyinfo, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcBuddyinfo, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Get a zone by node and name, eg: (0, "Normal").
+    pub fn zone(&self, node: u32, zone: &str) -> Option<&BuddyZone> {
+        self.zones.iter().find(|z| z.node == node && z.zone == zone)
+    }
+
+    /// Parse a String as a /proc/buddyinfo file, with lines like
+    /// "Node 0, zone   Normal   9989   7784   2827 ...".
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let err = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcBuddyinfo,
+            Some(field));
+        let mut zones = Vec::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let mut split = line.split_whitespace();
+            let node = match (split.next(), split.next()) {
+                (Some("Node"), Some(node)) => try!(
+                    node.trim_right_matches(',').parse().map_err(|_| err("node"))
+                ),
+                _ => return Err(err("node"))
+            };
+            let zone = match (split.next(), split.next()) {
+                (Some("zone"), Some(zone)) => zone.to_owned(),
+                _ => return Err(err("zone"))
+            };
+            zones.push(BuddyZone {
+                node: node,
+                zone: zone,
+                free: try!(
+                    split.map(|c| c.parse().map_err(|_| err("free"))).collect()
+                ),
+            });
+        }
+        Ok(Buddyinfo {
+            zones: zones,
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let buddyinfo = Buddyinfo::parse_string(
+"Node 0, zone      DMA      0      0      0      0      0      0      0      0      1      1      3
+Node 0, zone    DMA32      2      2      2      2      2      2      5      2      2      2    754
+Node 1, zone   Normal   9989   7784   2827    712    231    111     71     30      6      4     23
+").unwrap();
+    assert_eq!(buddyinfo.zones.len(), 3);
+    let dma = buddyinfo.zone(0, "DMA").unwrap();
+    assert_eq!(dma.free, vec![0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 3]);
+    assert_eq!(dma.free_pages(), 256 + 512 + 3 * 1024);
+    assert!(buddyinfo.zone(0, "Normal").is_none());
+    assert!(buddyinfo.zone(1, "Normal").is_some());
+    assert!(Buddyinfo::parse_string("Node 0, zone Normal x\n").is_err());
+    assert!(Buddyinfo::parse_string("zone Normal 1\n").is_err());
+}
+
+#[test]
+fn test_fragmentation_index() {
+    let zone = BuddyZone { node: 0, zone: "Normal".to_owned(), free: vec![4, 2, 1] };
+    // 4 + 4 + 4 free pages, with a third in each order.
+    assert_eq!(zone.free_pages(), 12);
+    assert_eq!(zone.fragmentation_index(0), 0.0);
+    assert!((zone.fragmentation_index(1) - 1.0 / 3.0).abs() < 1e-9);
+    assert!((zone.fragmentation_index(2) - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(zone.fragmentation_index(3), 1.0);
+    let empty = BuddyZone { node: 0, zone: "DMA".to_owned(), free: vec![0, 0] };
+    assert_eq!(empty.fragmentation_index(1), 0.0);
+}
+
+#[test]
+fn test_read() {
+    assert!(!Buddyinfo::new().unwrap().zones.is_empty());
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use error::ProcError;
+use pid::ioprio::{self, IoClass};
+use pid::nice;
+
+/// A token used to cancel a scan from another thread.
+///
+/// Clones share the same state, so cancelling any clone cancels them all.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a new token that isn't cancelled.
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Cancel any scans using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Conditions that stop a scan early, being a deadline and/or a cancel token.
+///
+/// These are checked between directory entries, so a single read that
+/// blocks (eg: on a hung NFS mount) can still exceed the deadline.
+#[derive(Debug, Clone, Default)]
+pub struct ScanBound {
+    /// Stop scanning after this instant.
+    pub deadline: Option<Instant>,
+    /// Stop scanning once this token is cancelled.
+    pub cancel: Option<CancelToken>,
+}
+
+impl ScanBound {
+    /// Whether the scan should stop now.
+    pub fn is_expired(&self) -> bool {
+        self.deadline.map(|d| Instant::now() >= d).unwrap_or(false) ||
+            self.cancel.as_ref().map(|c| c.is_cancelled()).unwrap_or(false)
+    }
+}
+
+/// The result of a scan that may have been stopped early.
+#[derive(Debug)]
+pub struct Partial<T> {
+    /// Results collected before the scan finished or was stopped.
+    pub items: Vec<T>,
+    /// Whether the scan was stopped early, so items are incomplete.
+    pub interrupted: bool,
+}
+
+/// Slows a scan down by pausing after every few directory entries, so
+/// monitoring doesn't compete with the workload of a loaded host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    /// Pause after this many directory entries.
+    pub every: usize,
+    /// How long to pause for.
+    pub pause: Duration,
+}
+
+impl Throttle {
+    /// Pause for pause after every entries.
+    pub fn new(every: usize, pause: Duration) -> Self {
+        Throttle {
+            every: every,
+            pause: pause,
+        }
+    }
+
+    /// Pause if count entries have been scanned, and count is a multiple of every.
+    pub fn after_entries(&self, count: usize) {
+        if self.every > 0 && count > 0 && count % self.every == 0 {
+            thread::sleep(self.pause);
+        }
+    }
+}
+
+/// Lower the cpu priority (nice 19) and I/O priority (idle class) of the
+/// calling thread, so a scan run from it only uses otherwise idle resources.
+///
+/// This can't be undone without CAP_SYS_NICE, so run scans from a
+/// dedicated thread if the rest of the program needs normal priority.
+pub fn lower_own_priority() -> Result<(), ProcError> {
+    // Linux applies the nice value of pid 0 to the calling thread only.
+    try!(nice::set_nice(0, nice::NICE_MAX));
+    ioprio::set_io_priority(0, IoClass::Idle, 0)
+}
+
+#[test]
+fn test_bound() {
+    use std::time::Duration;
+    assert!(!ScanBound::default().is_expired());
+    let token = CancelToken::new();
+    let bound = ScanBound { deadline: None, cancel: Some(token.clone()) };
+    assert!(!bound.is_expired());
+    token.cancel();
+    assert!(bound.is_expired());
+    let bound = ScanBound { deadline: Some(Instant::now() - Duration::from_millis(1)), cancel: None };
+    assert!(bound.is_expired());
+}
+
+#[test]
+fn test_throttle() {
+    let throttle = Throttle::new(2, Duration::from_millis(20));
+    let start = Instant::now();
+    throttle.after_entries(1);
+    assert!(start.elapsed() < Duration::from_millis(20));
+    throttle.after_entries(4);
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    // Zero disables the throttle, rather than dividing by zero.
+    Throttle::new(0, Duration::from_secs(60)).after_entries(3);
+}
+
+#[test]
+fn test_lower_own_priority() {
+    // Change a separate thread, so the priority of other tests isn't affected.
+    thread::spawn(|| {
+        lower_own_priority().unwrap();
+        let tid = ::pid::Pid::current_thread().unwrap().pid;
+        assert_eq!(ioprio::io_priority(tid).unwrap().class, IoClass::Idle);
+    }).join().unwrap();
+}
@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::io::Read;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// The command line the kernel was booted with, from /proc/cmdline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KernelCmdline {
+    /// Each parameter in order, being "key=value" pairs or bare flags like
+    /// "quiet" (with no value). Quotes around values are removed.
+    pub params: Vec<(String, Option<String>)>,
+    /// Arguments after "--", which the kernel passes to init.
+    pub init_args: Vec<String>,
+}
+
+impl KernelCmdline {
+    /// Read the kernel command line from /proc/cmdline.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("cmdline"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcCmdline, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcCmdline, e))
+        );
+        Ok(Self::parse_string(&contents))
+    }
+
+    /// Get the value of a parameter, eg: get("root"). If it's given more
+    /// than once the last value is used, like most kernel parameters. Flags
+    /// have a value of "".
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params.iter().rev()
+            .find(|&&(ref k, _)| k == key)
+            .map(|&(_, ref v)| v.as_ref().map(|v| &**v).unwrap_or(""))
+    }
+
+    /// Get every value of a parameter that can be given more than once, like
+    /// "console", in order.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.params.iter()
+            .filter(|&&(ref k, _)| k == key)
+            .map(|&(_, ref v)| v.as_ref().map(|v| &**v).unwrap_or(""))
+            .collect()
+    }
+
+    /// Whether a parameter was given, as a flag or with a value.
+    pub fn has(&self, key: &str) -> bool {
+        self.params.iter().any(|&(ref k, _)| k == key)
+    }
+
+    /// Parse a String as a kernel command line. Parameters are separated by
+    /// spaces, except inside double quotes.
+    fn parse_string(contents: &str) -> Self {
+        let mut params = Vec::new();
+        let mut init_args = Vec::new();
+        let mut after_dashes = false;
+        for word in split_quoted(contents.trim()) {
+            if after_dashes {
+                init_args.push(word);
+                continue;
+            }
+            if word == "--" {
+                after_dashes = true;
+                continue;
+            }
+            let mut split = word.splitn(2, '=');
+            let key = split.next().unwrap_or("").to_owned();
+            params.push((key, split.next().map(|v| v.to_owned())));
+        }
+        KernelCmdline {
+            params: params,
+            init_args: init_args,
+        }
+    }
+}
+
+/// Split on whitespace outside of double quotes, removing the quotes.
+fn split_quoted(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(word);
+                    word = String::new();
+                }
+            },
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[test]
+fn test_parsing() {
+    let cmdline = KernelCmdline::parse_string(
+        "BOOT_IMAGE=/vmlinuz-6.1 root=UUID=1234-abcd ro quiet console=tty0 \
+        console=ttyS0,115200 dyndbg=\"file ec.c +p\" root=/dev/sda2 -- single splash\n");
+    assert_eq!(cmdline.params[0], ("BOOT_IMAGE".to_owned(), Some("/vmlinuz-6.1".to_owned())));
+    assert_eq!(cmdline.params[2], ("ro".to_owned(), None));
+    assert_eq!(cmdline.get("root"), Some("/dev/sda2"));
+    assert_eq!(cmdline.get_all("root"), vec!["UUID=1234-abcd", "/dev/sda2"]);
+    assert_eq!(cmdline.get_all("console"), vec!["tty0", "ttyS0,115200"]);
+    assert_eq!(cmdline.get("dyndbg"), Some("file ec.c +p"));
+    assert_eq!(cmdline.get("quiet"), Some(""));
+    assert!(cmdline.has("ro"));
+    assert!(!cmdline.has("single"));
+    assert_eq!(cmdline.get("nosuch"), None);
+    assert_eq!(cmdline.init_args, vec!["single", "splash"]);
+    assert!(KernelCmdline::parse_string("\n").params.is_empty());
+}
+
+#[test]
+fn test_read() {
+    assert!(KernelCmdline::new().is_ok());
+}
@@ -0,0 +1,187 @@
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// Discard counters of a block device, added in Linux 4.18.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscardStats {
+    /// Discards completed successfully.
+    pub discards: u64,
+    /// Adjacent discards merged.
+    pub discards_merged: u64,
+    /// Sectors discarded.
+    pub sectors_discarded: u64,
+    /// Milliseconds spent discarding.
+    pub discard_ms: u64,
+}
+
+/// Flush counters of a block device, added in Linux 5.5.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlushStats {
+    /// Flush requests completed successfully.
+    pub flushes: u64,
+    /// Milliseconds spent flushing.
+    pub flush_ms: u64,
+}
+
+/// A struct containing the I/O counters of a block device from /proc/diskstats.
+///
+/// Sectors are always 512 bytes here, whatever the device's sector size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskStat {
+    /// Major device number.
+    pub major: u32,
+    /// Minor device number.
+    pub minor: u32,
+    /// Device name, eg: "sda" or "nvme0n1p1".
+    pub name: String,
+    /// Reads completed successfully.
+    pub reads: u64,
+    /// Adjacent reads merged.
+    pub reads_merged: u64,
+    /// Sectors read.
+    pub sectors_read: u64,
+    /// Milliseconds spent reading.
+    pub read_ms: u64,
+    /// Writes completed successfully.
+    pub writes: u64,
+    /// Adjacent writes merged.
+    pub writes_merged: u64,
+    /// Sectors written.
+    pub sectors_written: u64,
+    /// Milliseconds spent writing.
+    pub write_ms: u64,
+    /// I/Os currently in progress.
+    pub in_flight: u64,
+    /// Milliseconds spent doing I/O (the basis of %util).
+    pub io_ms: u64,
+    /// Weighted milliseconds spent doing I/O, accounting for queued I/Os.
+    pub weighted_io_ms: u64,
+    /// Discard counters, if the kernel has them.
+    pub discard: Option<DiscardStats>,
+    /// Flush counters, if the kernel has them.
+    pub flush: Option<FlushStats>,
+}
+
+/// Bytes in a sector, as counted by /proc/diskstats.
+pub const SECTOR_SIZE: u64 = 512;
+
+impl DiskStat {
+    /// Read the counters of every block device from /proc/diskstats.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let file = try!(
+            File::open(root::path("diskstats"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcDiskstats, e))
+        );
+        let lines =
+            BufReader::with_capacity(4096, file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcDiskstats, e))
+                );
+        Self::parse_string(lines)
+    }
+
+    /// Parse an Iterator of lines as a /proc/diskstats file.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I)
+        -> Result<Vec<Self>, ProcError> {
+        let mut disks = Vec::new();
+        for line in lines {
+            let line = try!(line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            disks.push(try!(Self::parse_line(&line)));
+        }
+        Ok(disks)
+    }
+
+    /// Parse a single line of /proc/diskstats.
+    fn parse_line(line: &str) -> Result<Self, ProcError> {
+        let mut split = line.split_whitespace();
+        let (major, minor, name) = match (split.next(), split.next(), split.next()) {
+            (Some(major), Some(minor), Some(name)) => (major, minor, name),
+            _ => return Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcDiskstats,
+                Some("missing device")))
+        };
+        let parse_err = |e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcDiskstats,
+            Some(e), Some("parsing counter"));
+        let counters = try!(
+            split.map(|n| n.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(&parse_err)
+        );
+        if counters.len() < 11 {
+            return Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcDiskstats,
+                Some("missing counters")));
+        }
+        Ok(DiskStat {
+            major: try!(major.parse().map_err(&parse_err)),
+            minor: try!(minor.parse().map_err(&parse_err)),
+            name: name.to_owned(),
+            reads: counters[0],
+            reads_merged: counters[1],
+            sectors_read: counters[2],
+            read_ms: counters[3],
+            writes: counters[4],
+            writes_merged: counters[5],
+            sectors_written: counters[6],
+            write_ms: counters[7],
+            in_flight: counters[8],
+            io_ms: counters[9],
+            weighted_io_ms: counters[10],
+            discard: match counters.len() >= 15 {
+                true => Some(DiscardStats {
+                    discards: counters[11],
+                    discards_merged: counters[12],
+                    sectors_discarded: counters[13],
+                    discard_ms: counters[14],
+                }),
+                false => None
+            },
+            flush: match counters.len() >= 17 {
+                true => Some(FlushStats {
+                    flushes: counters[15],
+                    flush_ms: counters[16],
+                }),
+                false => None
+            },
+        })
+    }
+
+    /// Bytes read.
+    pub fn bytes_read(&self) -> u64 {
+        self.sectors_read * SECTOR_SIZE
+    }
+
+    /// Bytes written.
+    pub fn bytes_written(&self) -> u64 {
+        self.sectors_written * SECTOR_SIZE
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let lines = "   8       0 sda 1200 30 96000 500 800 20 64000 900 0 1100 1400\n\
+                 259       0 nvme0n1 10 0 80 1 20 0 160 2 1 3 4 5 0 40 6 7 8\n"
+        .lines().map(|l| Ok(l.to_owned()));
+    let disks = DiskStat::parse_string(lines).unwrap();
+    assert_eq!(disks.len(), 2);
+    assert_eq!(disks[0].name, "sda");
+    assert_eq!((disks[0].major, disks[0].minor), (8, 0));
+    assert_eq!(disks[0].bytes_read(), 96000 * 512);
+    assert_eq!(disks[0].weighted_io_ms, 1400);
+    assert_eq!(disks[0].discard, None);
+    assert_eq!(disks[1].in_flight, 1);
+    assert_eq!(disks[1].discard, Some(DiscardStats {
+        discards: 5, discards_merged: 0, sectors_discarded: 40, discard_ms: 6 }));
+    assert_eq!(disks[1].flush, Some(FlushStats { flushes: 7, flush_ms: 8 }));
+}
+
+#[test]
+fn test_missing_counters() {
+    assert_eq!(DiskStat::parse_line("8 0 sda 1 2 3"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcDiskstats,
+            Some("missing counters"))));
+}
@@ -1,26 +1,74 @@
 use std::fmt;
-use std::io::Write;
+use std::io::{self, Write};
 use std::error::Error;
 
 /// A list of files contained in the /proc directory>
 ///
 /// This list is used to identify which file or directory an error is relating too.
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum ProcFile {
     /// /proc directory, contains files containg various pieces of information about the system.
     ProcDir,
     /// /proc/cmdline file, contains the cmdline used when starting the kernel.
     ProcCmdline,
+    /// /proc/version file, contains the kernel version and build information.
+    ProcVersion,
     /// /proc/cpuinfo file, contains information about the cpu.
     ProcCpuinfo,
     /// /proc/meminfo file, contains information about the memory resources of the system.
     ProcMeminfo,
+    /// /proc/filesystems file, contains the filesystem types supported by the kernel.
+    ProcFilesystems,
     /// /proc/stat file.
     ProcStat,
     /// /proc/uptime file, contains the uptime of the system.
     ProcUptime,
     /// /proc/status file.
     ProcStatus,
+    /// /proc/net/dev file, contains counters for each network interface.
+    ProcNetDev,
+    /// /proc/net/{tcp,tcp6,udp,udp6} files, contain the sockets of each protocol.
+    ProcNetSocket,
+    /// /proc/net/unix file, contains the unix domain sockets.
+    ProcNetUnix,
+    /// /proc/net/snmp file, contains the SNMP MIB counters.
+    ProcNetSnmp,
+    /// /proc/net/netstat file, contains extended network counters.
+    ProcNetNetstat,
+    /// /proc/net/sockstat file, contains IPv4 socket totals.
+    ProcNetSockstat,
+    /// /proc/net/sockstat6 file, contains IPv6 socket totals.
+    ProcNetSockstat6,
+    /// /proc/pressure directory, contains pressure stall information for cpu, memory and io.
+    ProcPressure,
+    /// /proc/loadavg file, contains the load averages and task counts.
+    ProcLoadavg,
+    /// /proc/vmstat file, contains virtual memory counters.
+    ProcVmstat,
+    /// /proc/buddyinfo file, contains the free blocks of each order in each memory zone.
+    ProcBuddyinfo,
+    /// /proc/slabinfo file, contains the kernel slab caches (readable by root).
+    ProcSlabinfo,
+    /// /proc/diskstats file, contains I/O counters for each block device.
+    ProcDiskstats,
+    /// /proc/interrupts file, contains the count of each interrupt on each cpu.
+    ProcInterrupts,
+    /// /proc/softirqs file, contains the count of each softirq on each cpu.
+    ProcSoftirqs,
+    /// /proc/sys/kernel/yama/ptrace_scope file, contains the Yama ptrace restrictions.
+    ProcYamaPtraceScope,
+    /// /proc/sys directory, contains the sysctls.
+    ProcSys,
+    /// /proc/keys file, contains the keys visible to the calling process.
+    ProcKeys,
+    /// /proc/key-users file, contains the key quota usage of each user.
+    ProcKeyUsers,
+    /// /proc/sysvipc/shm file, contains SysV shared memory segments.
+    ProcSysvipcShm,
+    /// /proc/sysvipc/sem file, contains SysV semaphore sets.
+    ProcSysvipcSem,
+    /// /proc/sysvipc/msg file, contains SysV message queues.
+    ProcSysvipcMsg,
 
     /// /proc/[pid] directory, contains files relating to the process at [pid].
     PidDir,
@@ -30,6 +78,64 @@ pub enum ProcFile {
     PidStat,
     /// /proc/[pid]/cmdline file, contains the cmdline given when starting the process.
     PidCmdline,
+    /// The scheduling priority (nice value) of a process, set with setpriority.
+    PidPriority,
+    /// /proc/[pid]/environ file, contains the environment the process was started with.
+    PidEnviron,
+    /// /proc/[pid]/wchan file, contains the kernel function the process is waiting in.
+    PidWchan,
+    /// /proc/[pid]/oom_score file, contains the badness score used by the OOM killer.
+    PidOomScore,
+    /// /proc/[pid]/oom_score_adj file, contains the adjustment to the OOM badness score.
+    PidOomScoreAdj,
+    /// /proc/[pid]/oom_adj file, contains the legacy OOM adjustment.
+    PidOomAdj,
+    /// /proc/[pid]/stack file, contains the kernel stack of the process.
+    PidStack,
+    /// /proc/[pid]/syscall file, contains the system call the process is blocked in.
+    PidSyscall,
+    /// /proc/[pid]/mounts file, contains the mounts in the mount namespace of the process.
+    PidMounts,
+    /// /proc/[pid]/mountinfo file, contains details of the mounts in the mount namespace.
+    PidMountinfo,
+    /// /proc/[pid]/root link, points to the root directory of the process.
+    PidRoot,
+    /// /proc/[pid]/exe link, points to the executable of the process.
+    PidExe,
+    /// /proc/[pid]/comm file, contains the name of the process or thread.
+    PidComm,
+    /// I/O priority of a process, from the ioprio syscalls rather than a file.
+    PidIoprio,
+    /// /proc/[pid]/personality file, contains the execution domain of the process.
+    PidPersonality,
+    /// /proc/[pid]/attr/current file, contains the security label of the process.
+    PidAttrCurrent,
+    /// /proc/[pid]/attr/exec file, contains the security label used on the next execve.
+    PidAttrExec,
+    /// /proc/[pid]/coredump_filter file, contains which mappings are written to core dumps.
+    PidCoredumpFilter,
+    /// /proc/[pid]/clear_refs file, resets page reference bits when written.
+    PidClearRefs,
+    /// /proc/[pid]/timerslack_ns file, contains the timer slack of the process.
+    PidTimerslackNs,
+    /// /proc/[pid]/ns directory, contains links to the namespaces of the process.
+    PidNs,
+    /// /proc/[pid]/cgroup file, contains the cgroups the process belongs to.
+    PidCgroup,
+    /// /proc/[pid]/fd directory, contains symlinks for each open file descriptor.
+    PidFd,
+    /// /proc/[pid]/fdinfo/[fd] file, contains information about an open file descriptor.
+    PidFdinfo,
+    /// /proc/[pid]/maps file, contains the memory mappings of the process.
+    PidMaps,
+    /// /proc/[pid]/smaps file, contains memory usage for each mapping of the process.
+    PidSmaps,
+    /// /proc/[pid]/smaps_rollup file, contains memory usage summed over all mappings.
+    PidSmapsRollup,
+    /// /proc/[pid]/numa_maps file, contains the NUMA placement of each mapping.
+    PidNumaMaps,
+    /// /proc/[pid]/io file, contains the I/O counters of the process.
+    PidIo,
 
     // TODO: Attach a pid to this directory
     /// /proc/[pid]/task directory, contains threads of a process.
@@ -41,15 +147,68 @@ impl Error for ProcFile {
         match *self {
             ProcFile::ProcDir => "/proc directory",
             ProcFile::ProcCmdline => "/proc/cmdline file",
+            ProcFile::ProcVersion => "/proc/version file",
             ProcFile::ProcCpuinfo => "/proc/cmdinfo file",
             ProcFile::ProcMeminfo => "/proc/meminfo file",
+            ProcFile::ProcFilesystems => "/proc/filesystems file",
             ProcFile::ProcStat => "/proc/stat file",
             ProcFile::ProcUptime => "/proc/uptime file",
             ProcFile::ProcStatus => "/proc/status file",
+            ProcFile::ProcNetDev => "/proc/net/dev file",
+            ProcFile::ProcNetSocket => "/proc/net socket table",
+            ProcFile::ProcNetUnix => "/proc/net/unix file",
+            ProcFile::ProcNetSnmp => "/proc/net/snmp file",
+            ProcFile::ProcNetNetstat => "/proc/net/netstat file",
+            ProcFile::ProcNetSockstat => "/proc/net/sockstat file",
+            ProcFile::ProcNetSockstat6 => "/proc/net/sockstat6 file",
+            ProcFile::ProcPressure => "/proc/pressure file",
+            ProcFile::ProcLoadavg => "/proc/loadavg file",
+            ProcFile::ProcVmstat => "/proc/vmstat file",
+            ProcFile::ProcBuddyinfo => "/proc/buddyinfo file",
+            ProcFile::ProcSlabinfo => "/proc/slabinfo file",
+            ProcFile::ProcDiskstats => "/proc/diskstats file",
+            ProcFile::ProcInterrupts => "/proc/interrupts file",
+            ProcFile::ProcSoftirqs => "/proc/softirqs file",
+            ProcFile::ProcYamaPtraceScope => "/proc/sys/kernel/yama/ptrace_scope file",
+            ProcFile::ProcSys => "/proc/sys file",
+            ProcFile::ProcKeys => "/proc/keys file",
+            ProcFile::ProcKeyUsers => "/proc/key-users file",
+            ProcFile::ProcSysvipcShm => "/proc/sysvipc/shm file",
+            ProcFile::ProcSysvipcSem => "/proc/sysvipc/sem file",
+            ProcFile::ProcSysvipcMsg => "/proc/sysvipc/msg file",
             ProcFile::PidDir => "/proc/[pid] directory",
             ProcFile::PidStatus => "/proc/[pid]/status file",
             ProcFile::PidStat => "/proc/[pid]/stat file",
             ProcFile::PidCmdline => "/proc/[pid]/cmdline file",
+            ProcFile::PidPriority => "priority of [pid]",
+            ProcFile::PidEnviron => "/proc/[pid]/environ file",
+            ProcFile::PidWchan => "/proc/[pid]/wchan file",
+            ProcFile::PidOomScore => "/proc/[pid]/oom_score file",
+            ProcFile::PidOomScoreAdj => "/proc/[pid]/oom_score_adj file",
+            ProcFile::PidOomAdj => "/proc/[pid]/oom_adj file",
+            ProcFile::PidStack => "/proc/[pid]/stack file",
+            ProcFile::PidSyscall => "/proc/[pid]/syscall file",
+            ProcFile::PidMounts => "/proc/[pid]/mounts file",
+            ProcFile::PidMountinfo => "/proc/[pid]/mountinfo file",
+            ProcFile::PidRoot => "/proc/[pid]/root link",
+            ProcFile::PidExe => "/proc/[pid]/exe link",
+            ProcFile::PidComm => "/proc/[pid]/comm file",
+            ProcFile::PidIoprio => "ioprio of [pid]",
+            ProcFile::PidPersonality => "/proc/[pid]/personality file",
+            ProcFile::PidAttrCurrent => "/proc/[pid]/attr/current file",
+            ProcFile::PidAttrExec => "/proc/[pid]/attr/exec file",
+            ProcFile::PidCoredumpFilter => "/proc/[pid]/coredump_filter file",
+            ProcFile::PidClearRefs => "/proc/[pid]/clear_refs file",
+            ProcFile::PidTimerslackNs => "/proc/[pid]/timerslack_ns file",
+            ProcFile::PidNs => "/proc/[pid]/ns directory",
+            ProcFile::PidCgroup => "/proc/[pid]/cgroup file",
+            ProcFile::PidFd => "/proc/[pid]/fd directory",
+            ProcFile::PidFdinfo => "/proc/[pid]/fdinfo file",
+            ProcFile::PidMaps => "/proc/[pid]/maps file",
+            ProcFile::PidSmaps => "/proc/[pid]/smaps file",
+            ProcFile::PidSmapsRollup => "/proc/[pid]/smaps_rollup file",
+            ProcFile::PidNumaMaps => "/proc/[pid]/numa_maps file",
+            ProcFile::PidIo => "/proc/[pid]/io file",
             ProcFile::PidTaskDir => "/proc/[pid]/task",
         }
     }
@@ -78,6 +237,12 @@ pub enum ProcOper {
     Opening,
     /// Error reading a file/directory.
     Reading,
+    /// Error writing a file.
+    Writing,
+    /// The value to write was invalid, so nothing was written.
+    Validating,
+    /// The file/directory isn't provided by this kernel version.
+    Unsupported,
     /// Error parsing a file/directory.
     Parsing,
     /// Error parsing a specific field in a file/directory.
@@ -89,6 +254,8 @@ impl ProcOper {
         match *self {
             ProcOper::Opening => false,
             ProcOper::Reading => false,
+            ProcOper::Writing => false,
+            ProcOper::Unsupported => false,
             _ => true
         }
     }
@@ -99,6 +266,9 @@ impl Error for ProcOper {
         match *self {
             ProcOper::Opening => "opening",
             ProcOper::Reading => "reading",
+            ProcOper::Writing => "writing",
+            ProcOper::Validating => "validating",
+            ProcOper::Unsupported => "unsupported by kernel",
             ProcOper::Parsing => "parsing",
             ProcOper::ParsingField => "parsing field"
         }
@@ -169,6 +339,39 @@ impl ProcError {
     pub fn is_hard(&self) -> bool {
         self.operation.is_hard()
     }
+
+    /// Whether the file doesn't exist (ENOENT), either because the process
+    /// has exited or because the kernel doesn't provide it.
+    pub fn is_not_found(&self) -> bool {
+        self.inner.as_ref()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+            .map(|e| e.kind() == io::ErrorKind::NotFound)
+            .unwrap_or(false)
+    }
+
+    /// Whether this error means the kernel is too old to provide the file.
+    pub fn is_unsupported_kernel(&self) -> bool {
+        self.operation == ProcOper::Unsupported
+    }
+
+    /// Whether this error was caused by the kernel not providing the file
+    /// (ENOENT or EINVAL), like attr files without a security module loaded.
+    pub fn is_unsupported(&self) -> bool {
+        self.inner.as_ref()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+            .map(|e| e.kind() == io::ErrorKind::NotFound ||
+                e.kind() == io::ErrorKind::InvalidInput)
+            .unwrap_or(false)
+    }
+
+    /// Whether this error was caused by a lack of permissions (EACCES or EPERM),
+    /// like when tuning a process owned by another user.
+    pub fn is_permission_denied(&self) -> bool {
+        self.inner.as_ref()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+            .map(|e| e.kind() == io::ErrorKind::PermissionDenied)
+            .unwrap_or(false)
+    }
 }
 
 impl Error for ProcError {
@@ -0,0 +1,283 @@
+use error::ProcFile;
+use version::KernelVersion;
+
+/// The type of a parsed field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// A signed integer.
+    Int,
+    /// An unsigned integer.
+    Uint,
+    /// Text, like a program name.
+    Text,
+    /// A process state, like "R" or "S".
+    State,
+    /// A set of signals.
+    Signals,
+    /// A bitmask, like a set of capabilities or flags.
+    Bitmask,
+    /// Real, effective, saved set and filesystem ids.
+    Ids,
+    /// A list of numbers, like cpus or group ids.
+    List,
+}
+
+impl FieldType {
+    /// The name of the type, for help text.
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            FieldType::Int => "int",
+            FieldType::Uint => "uint",
+            FieldType::Text => "text",
+            FieldType::State => "state",
+            FieldType::Signals => "signals",
+            FieldType::Bitmask => "bitmask",
+            FieldType::Ids => "ids",
+            FieldType::List => "list",
+        }
+    }
+}
+
+/// The unit of a numeric field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    /// No unit, or not a number.
+    None,
+    /// Bytes, which are converted from kB where the file uses kB.
+    Bytes,
+    /// Memory pages.
+    Pages,
+    /// Clock ticks (see ticks_per_second).
+    Ticks,
+    /// A count of events, like page faults.
+    Count,
+    /// A memory address.
+    Address,
+}
+
+impl Unit {
+    /// The name of the unit, for help text.
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            Unit::None => "",
+            Unit::Bytes => "bytes",
+            Unit::Pages => "pages",
+            Unit::Ticks => "ticks",
+            Unit::Count => "count",
+            Unit::Address => "address",
+        }
+    }
+}
+
+/// A description of a field parsed by this crate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    /// The name of the field, being the file and the struct field, eg:
+    /// "stat.utime" for PidStat::utime.
+    pub name: &'static str,
+    /// The file the field is read from.
+    pub file: ProcFile,
+    /// The type of the field.
+    pub field_type: FieldType,
+    /// The unit of the field.
+    pub unit: Unit,
+    /// The kernel version that added the field, or None if it's older than
+    /// 2.6. Fields are missing (None or empty) on older kernels.
+    pub since: Option<&'static str>,
+    /// A short description.
+    pub help: &'static str,
+}
+
+impl Field {
+    /// Whether a kernel has this field, so callers can skip fields the
+    /// running kernel doesn't provide.
+    pub fn is_available(&self, kernel: &KernelVersion) -> bool {
+        match self.since.map(|s| s.parse::<KernelVersion>()) {
+            Some(Ok(since)) => *kernel >= since,
+            _ => true
+        }
+    }
+}
+
+macro_rules! field {
+    ($name: expr, $file: ident, $field_type: ident, $unit: ident, $since: expr, $help: expr) => {
+        Field {
+            name: $name,
+            file: ProcFile::$file,
+            field_type: FieldType::$field_type,
+            unit: Unit::$unit,
+            since: $since,
+            help: $help,
+        }
+    }
+}
+
+/// Every field of a process parsed by this crate, in file order.
+pub const PID_FIELDS: &'static [Field] = &[
+    field!("stat.pid", PidStat, Int, None, None, "Process (or thread) id"),
+    field!("stat.comm", PidStat, Text, None, None, "Program name, or thread name"),
+    field!("stat.state", PidStat, State, None, None, "Process state"),
+    field!("stat.ppid", PidStat, Int, None, None, "Parent process id"),
+    field!("stat.pgrp", PidStat, Int, None, None, "Process group id"),
+    field!("stat.session", PidStat, Int, None, None, "Session id"),
+    field!("stat.tty_nr", PidStat, Int, None, None, "Controlling tty device"),
+    field!("stat.tpgid", PidStat, Int, None, None, "Foreground process group of the tty"),
+    field!("stat.flags", PidStat, Bitmask, None, None, "Kernel flags"),
+    field!("stat.minflt", PidStat, Uint, Count, None, "Minor page faults"),
+    field!("stat.cminflt", PidStat, Uint, Count, None, "Minor page faults of waited-for children"),
+    field!("stat.majflt", PidStat, Uint, Count, None, "Major page faults"),
+    field!("stat.cmajflt", PidStat, Uint, Count, None, "Major page faults of waited-for children"),
+    field!("stat.utime", PidStat, Uint, Ticks, None, "Time scheduled in user mode"),
+    field!("stat.stime", PidStat, Uint, Ticks, None, "Time scheduled in kernel mode"),
+    field!("stat.cutime", PidStat, Int, Ticks, None, "User time of waited-for children"),
+    field!("stat.cstime", PidStat, Int, Ticks, None, "Kernel time of waited-for children"),
+    field!("stat.priority", PidStat, Int, None, None, "Scheduling priority"),
+    field!("stat.nice", PidStat, Int, None, None, "Nice value (19 low to -20 high)"),
+    field!("stat.num_threads", PidStat, Int, Count, None, "Number of threads"),
+    field!("stat.itrealvalue", PidStat, Int, Ticks, None,
+        "Time before the next SIGALRM (always 0)"),
+    field!("stat.starttime", PidStat, Uint, Ticks, None, "Start time after boot"),
+    field!("stat.vsize", PidStat, Uint, Bytes, None, "Virtual memory size"),
+    field!("stat.rss", PidStat, Int, Pages, None, "Resident set size"),
+    field!("stat.rsslim", PidStat, Uint, Bytes, None, "Resident set size soft limit"),
+    field!("stat.startcode", PidStat, Uint, Address, None, "Start of program text"),
+    field!("stat.endcode", PidStat, Uint, Address, None, "End of program text"),
+    field!("stat.startstack", PidStat, Uint, Address, None, "Start (bottom) of the stack"),
+    field!("stat.kstkesp", PidStat, Uint, Address, None, "Stack pointer"),
+    field!("stat.kstkeip", PidStat, Uint, Address, None, "Instruction pointer"),
+    field!("stat.signal", PidStat, Signals, None, None, "Pending signals"),
+    field!("stat.blocked", PidStat, Signals, None, None, "Blocked signals"),
+    field!("stat.sigignore", PidStat, Signals, None, None, "Ignored signals"),
+    field!("stat.sigcatch", PidStat, Signals, None, None, "Caught signals"),
+    field!("stat.wchan", PidStat, Uint, Address, None, "Wait channel"),
+    field!("stat.nswap", PidStat, Uint, Pages, None, "Pages swapped (not maintained)"),
+    field!("stat.cnswap", PidStat, Uint, Pages, None, "Pages swapped by children (not maintained)"),
+    field!("stat.exit_signal", PidStat, Int, None, None, "Signal sent to the parent on exit"),
+    field!("stat.processor", PidStat, Int, None, None, "Cpu last run on"),
+    field!("stat.rt_priority", PidStat, Uint, None, None, "Real-time scheduling priority"),
+    field!("stat.policy", PidStat, Uint, None, None, "Scheduling policy"),
+    field!("stat.delayacct_blkio_ticks", PidStat, Uint, Ticks, Some("2.6.18"),
+        "Block I/O delays"),
+    field!("stat.guest_time", PidStat, Uint, Ticks, Some("2.6.24"), "Time running a guest cpu"),
+    field!("stat.cguest_time", PidStat, Int, Ticks, Some("2.6.24"),
+        "Guest time of waited-for children"),
+    field!("stat.start_data", PidStat, Uint, Address, Some("3.3"), "Start of data and bss"),
+    field!("stat.end_data", PidStat, Uint, Address, Some("3.3"), "End of data and bss"),
+    field!("stat.start_brk", PidStat, Uint, Address, Some("3.3"), "Start of the heap"),
+    field!("stat.arg_start", PidStat, Uint, Address, Some("3.5"), "Start of the arguments"),
+    field!("stat.arg_end", PidStat, Uint, Address, Some("3.5"), "End of the arguments"),
+    field!("stat.env_start", PidStat, Uint, Address, Some("3.5"), "Start of the environment"),
+    field!("stat.env_end", PidStat, Uint, Address, Some("3.5"), "End of the environment"),
+    field!("stat.exit_code", PidStat, Int, None, Some("3.5"), "Exit status of the thread"),
+    field!("status.name", PidStatus, Text, None, None, "Program name"),
+    field!("status.tgid", PidStatus, Int, None, None, "Thread group id (process id)"),
+    field!("status.pid", PidStatus, Int, None, None, "Thread id"),
+    field!("status.ppid", PidStatus, Int, None, None, "Parent process id"),
+    field!("status.tracerpid", PidStatus, Int, None, None, "Tracing process id, or 0"),
+    field!("status.uid", PidStatus, Ids, None, None, "User ids"),
+    field!("status.gid", PidStatus, Ids, None, None, "Group ids"),
+    field!("status.fdsize", PidStatus, Uint, Count, None, "File descriptor slots allocated"),
+    field!("status.vmpeak", PidStatus, Uint, Bytes, None, "Peak virtual memory size"),
+    field!("status.vmsize", PidStatus, Uint, Bytes, None, "Virtual memory size"),
+    field!("status.vmlck", PidStatus, Uint, Bytes, None, "Locked memory size"),
+    field!("status.vmpin", PidStatus, Uint, Bytes, Some("3.2"), "Pinned memory size"),
+    field!("status.vmhwm", PidStatus, Uint, Bytes, None, "Peak resident set size"),
+    field!("status.vmrss", PidStatus, Uint, Bytes, None, "Resident set size"),
+    field!("status.vmdata", PidStatus, Uint, Bytes, None, "Data segment size"),
+    field!("status.vmstk", PidStatus, Uint, Bytes, None, "Stack size"),
+    field!("status.vmexe", PidStatus, Uint, Bytes, None, "Text segment size"),
+    field!("status.vmlib", PidStatus, Uint, Bytes, None, "Shared library code size"),
+    field!("status.vmpte", PidStatus, Uint, Bytes, Some("2.6.10"), "Page table entries size"),
+    field!("status.vmpmd", PidStatus, Uint, Bytes, Some("4.0"), "Second-level page tables size"),
+    field!("status.vmswap", PidStatus, Uint, Bytes, Some("2.6.34"), "Swapped out anonymous memory"),
+    field!("status.threads", PidStatus, Uint, Count, None, "Number of threads"),
+    field!("status.state", PidStatus, State, None, None, "Process state"),
+    field!("status.umask", PidStatus, Uint, None, Some("4.7"), "File mode creation mask"),
+    field!("status.groups", PidStatus, List, None, None, "Supplementary group ids"),
+    field!("status.nstgid", PidStatus, List, None, Some("4.1"),
+        "Thread group id in each pid namespace"),
+    field!("status.nspid", PidStatus, List, None, Some("4.1"), "Thread id in each pid namespace"),
+    field!("status.nspgid", PidStatus, List, None, Some("4.1"),
+        "Process group id in each pid namespace"),
+    field!("status.nssid", PidStatus, List, None, Some("4.1"), "Session id in each pid namespace"),
+    field!("status.cpus_allowed", PidStatus, Bitmask, None, None, "Mask of cpus it may run on"),
+    field!("status.cpus_allowed_list", PidStatus, List, None, Some("2.6.26"),
+        "Cpus it may run on"),
+    field!("status.mems_allowed_list", PidStatus, List, None, Some("2.6.26"),
+        "Memory nodes it may allocate from"),
+    field!("status.voluntary_ctxt_switches", PidStatus, Uint, Count, Some("2.6.23"),
+        "Voluntary context switches"),
+    field!("status.nonvoluntary_ctxt_switches", PidStatus, Uint, Count, Some("2.6.23"),
+        "Involuntary context switches"),
+    field!("status.seccomp", PidStatus, Uint, None, Some("3.8"), "Seccomp mode"),
+    field!("status.seccomp_filters", PidStatus, Uint, Count, Some("5.9"),
+        "Seccomp filters attached"),
+    field!("status.sigpnd", PidStatus, Signals, None, None, "Signals pending for the thread"),
+    field!("status.shdpnd", PidStatus, Signals, None, None, "Signals pending for the process"),
+    field!("status.sigblk", PidStatus, Signals, None, None, "Blocked signals"),
+    field!("status.sigign", PidStatus, Signals, None, None, "Ignored signals"),
+    field!("status.sigcgt", PidStatus, Signals, None, None, "Caught signals"),
+    field!("status.capinh", PidStatus, Bitmask, None, None, "Inheritable capabilities"),
+    field!("status.capprm", PidStatus, Bitmask, None, None, "Permitted capabilities"),
+    field!("status.capeff", PidStatus, Bitmask, None, None, "Effective capabilities"),
+    field!("status.capbnd", PidStatus, Bitmask, None, Some("2.6.26"), "Capability bounding set"),
+    field!("status.capamb", PidStatus, Bitmask, None, Some("4.3"), "Ambient capabilities"),
+    field!("cmdline", PidCmdline, List, None, None, "Command line arguments"),
+    field!("wchan", PidWchan, Text, None, None, "Kernel function the process is sleeping in"),
+    field!("attr.current", PidAttrCurrent, Text, None, None, "Security label"),
+    field!("personality", PidPersonality, Bitmask, None, Some("2.6.28"), "Execution domain"),
+];
+
+/// Find a field by name, eg: "stat.utime". Names are case insensitive.
+pub fn find(name: &str) -> Option<&'static Field> {
+    PID_FIELDS.iter().find(|f| f.name.eq_ignore_ascii_case(name))
+}
+
+/// The fields read from a file.
+pub fn in_file(file: ProcFile) -> Vec<&'static Field> {
+    PID_FIELDS.iter().filter(|f| f.file == file).collect()
+}
+
+/// Check a list of field names, eg: from the command line, returning the
+/// first unknown name as an error.
+pub fn validate<'a, I: IntoIterator<Item=&'a str>>(names: I)
+    -> Result<Vec<&'static Field>, String> {
+    names.into_iter()
+        .map(|name| find(name).ok_or(format!("Unknown field '{}'", name)))
+        .collect()
+}
+
+#[test]
+fn test_find() {
+    let utime = find("stat.utime").unwrap();
+    assert_eq!(utime.file, ProcFile::PidStat);
+    assert_eq!(utime.unit, Unit::Ticks);
+    assert_eq!(find("STATUS.VMRSS").unwrap().unit, Unit::Bytes);
+    assert_eq!(find("status.capamb").unwrap().since, Some("4.3"));
+    assert!(find("utime").is_none());
+}
+
+#[test]
+fn test_available() {
+    let capamb = find("status.capamb").unwrap();
+    assert!(capamb.is_available(&KernelVersion::new(4, 3, 0)));
+    assert!(!capamb.is_available(&KernelVersion::new(4, 2, 8)));
+    assert!(find("stat.pid").unwrap().is_available(&KernelVersion::new(2, 6, 0)));
+}
+
+#[test]
+fn test_registry() {
+    use std::collections::HashSet;
+    let names: HashSet<_> = PID_FIELDS.iter().map(|f| f.name).collect();
+    assert_eq!(names.len(), PID_FIELDS.len());
+    // Every field of PidStat, except the extra fields of newer kernels.
+    assert_eq!(in_file(ProcFile::PidStat).len(), 52);
+    assert!(in_file(ProcFile::PidStatus).iter().all(|f| f.name.starts_with("status.")));
+}
+
+#[test]
+fn test_validate() {
+    assert_eq!(validate(vec!["stat.pid", "cmdline"]).unwrap().len(), 2);
+    assert_eq!(validate(vec!["stat.pid", "stat.bogus"]),
+        Err("Unknown field 'stat.bogus'".to_owned()));
+}
@@ -0,0 +1,117 @@
+use error::{ProcError, ProcFile, ProcOper};
+use sysctl;
+
+/// System wide file handle usage, from /proc/sys/fs/file-nr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHandles {
+    /// File handles allocated by the kernel.
+    pub allocated: u64,
+    /// Allocated file handles that aren't in use. Since Linux 2.6 this is
+    /// always 0, as handles are freed straight away.
+    pub free: u64,
+    /// The maximum number of file handles, being fs.file-max.
+    pub max: u64,
+}
+
+impl FileHandles {
+    /// Read the current file handle usage from /proc/sys/fs/file-nr.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::from_values(&try!(sysctl::read_values("fs.file-nr")))
+    }
+
+    /// File handles in use.
+    pub fn in_use(&self) -> u64 {
+        self.allocated.saturating_sub(self.free)
+    }
+
+    /// File handles that can still be opened before hitting the limit.
+    pub fn available(&self) -> u64 {
+        self.max.saturating_sub(self.in_use())
+    }
+
+    /// The share of the limit in use, from 0 to 1.
+    pub fn used(&self) -> f64 {
+        match self.max {
+            0 => 0.0,
+            max => self.in_use() as f64 / max as f64
+        }
+    }
+
+    fn from_values(values: &[u64]) -> Result<Self, ProcError> {
+        match *values {
+            [allocated, free, max] => Ok(FileHandles {
+                allocated: allocated,
+                free: free,
+                max: max,
+            }),
+            _ => Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcSys,
+                Some("file-nr needs 3 values")))
+        }
+    }
+}
+
+/// System wide inode usage, from /proc/sys/fs/inode-nr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InodeUsage {
+    /// Inodes allocated by the kernel.
+    pub allocated: u64,
+    /// Allocated inodes that aren't in use.
+    pub free: u64,
+}
+
+impl InodeUsage {
+    /// Read the current inode usage from /proc/sys/fs/inode-nr.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::from_values(&try!(sysctl::read_values("fs.inode-nr")))
+    }
+
+    /// Inodes in use.
+    pub fn in_use(&self) -> u64 {
+        self.allocated.saturating_sub(self.free)
+    }
+
+    fn from_values(values: &[u64]) -> Result<Self, ProcError> {
+        match *values {
+            [allocated, free] => Ok(InodeUsage {
+                allocated: allocated,
+                free: free,
+            }),
+            _ => Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcSys,
+                Some("inode-nr needs 2 values")))
+        }
+    }
+}
+
+/// The maximum number of file handles for the whole system
+/// (/proc/sys/fs/file-max).
+pub fn file_max() -> Result<u64, ProcError> {
+    sysctl::read("fs.file-max")
+}
+
+/// The highest RLIMIT_NOFILE a process can set, so the most file
+/// descriptors one process can open (/proc/sys/fs/nr_open).
+pub fn nr_open() -> Result<u64, ProcError> {
+    sysctl::read("fs.nr_open")
+}
+
+#[test]
+fn test_parsing() {
+    let handles = FileHandles::from_values(&[9472, 0, 100000]).unwrap();
+    assert_eq!(handles, FileHandles { allocated: 9472, free: 0, max: 100000 });
+    assert_eq!(handles.in_use(), 9472);
+    assert_eq!(handles.available(), 90528);
+    assert_eq!(handles.used(), 0.09472);
+    assert!(FileHandles::from_values(&[1, 2]).is_err());
+    let inodes = InodeUsage::from_values(&[15097, 120]).unwrap();
+    assert_eq!(inodes.in_use(), 14977);
+    assert!(InodeUsage::from_values(&[1, 2, 3]).is_err());
+}
+
+#[test]
+fn test_read() {
+    let handles = FileHandles::new().unwrap();
+    assert!(handles.allocated > 0);
+    assert_eq!(handles.max, file_max().unwrap());
+    assert!(InodeUsage::new().is_ok());
+    assert!(nr_open().unwrap() > 0);
+}
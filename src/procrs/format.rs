@@ -0,0 +1,281 @@
+use std::time::Duration;
+use {ticks_per_second, MemSize};
+
+/// The units used when formatting sizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteUnits {
+    /// Powers of 1024, like "1.5 MiB".
+    Binary,
+    /// Powers of 1000, like "1.5 MB".
+    Si,
+}
+
+impl ByteUnits {
+    fn base(&self) -> f64 {
+        match *self {
+            ByteUnits::Binary => 1024.0,
+            ByteUnits::Si => 1000.0,
+        }
+    }
+
+    fn suffixes(&self) -> [&'static str; 6] {
+        match *self {
+            ByteUnits::Binary => ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+            ByteUnits::Si => ["kB", "MB", "GB", "TB", "PB", "EB"],
+        }
+    }
+}
+
+/// Format a number with a comma between each group of three digits, like
+/// "1,234,567". This is the same regardless of locale.
+pub fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Format a number of seconds as [[dd-]hh:]mm:ss, like the elapsed time in ps.
+/// The days and hours are only included when needed.
+pub fn duration(secs: u64) -> String {
+    let (days, hours, mins, secs) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    match (days, hours) {
+        (0, 0) => format!("{:02}:{:02}", mins, secs),
+        (0, _) => format!("{:02}:{:02}:{:02}", hours, mins, secs),
+        _ => format!("{}-{:02}:{:02}:{:02}", days, hours, mins, secs),
+    }
+}
+
+/// Format a cpu time in clock ticks, like utime from stat, as a duration.
+pub fn cputime(ticks: u64) -> String {
+    duration(ticks / ticks_per_second())
+}
+
+/// Format a size in bytes with the largest unit that keeps the value at
+/// least one, to one decimal place, like "1.5 MiB". Sizes under a kilobyte
+/// are printed in bytes.
+pub fn bytes(n: u64, units: ByteUnits) -> String {
+    let base = units.base();
+    if (n as f64) < base {
+        return format!("{} B", n);
+    }
+    let mut value = n as f64 / base;
+    let mut suffix = 0;
+    while value >= base && suffix < units.suffixes().len() - 1 {
+        value /= base;
+        suffix += 1;
+    }
+    format!("{:.1} {}", value, units.suffixes()[suffix])
+}
+
+/// Parse a duration like "90s", "5m", "2h30m", "1.5h" or "1d", being numbers
+/// each followed by a unit of s, m, h, d or w. A bare number is in seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let err = || format!("Invalid duration '{}'", s);
+    let s_trim = s.trim();
+    if s_trim.is_empty() {
+        return Err(err());
+    }
+    if let Ok(secs) = s_trim.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+    let mut total = 0.0;
+    let mut rest = s_trim;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !c.is_digit(10) && c != '.').unwrap_or(rest.len());
+        let unit_end = rest[split..].find(|c: char| c.is_digit(10) || c == '.')
+            .map(|i| split + i).unwrap_or(rest.len());
+        let num: f64 = try!(rest[..split].parse().map_err(|_| err()));
+        let multiplier = match &rest[split..unit_end] {
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 60.0 * 60.0,
+            "d" => 24.0 * 60.0 * 60.0,
+            "w" => 7.0 * 24.0 * 60.0 * 60.0,
+            _ => return Err(err())
+        };
+        total += num * multiplier;
+        rest = &rest[unit_end..];
+    }
+    Ok(Duration::from_millis((total * 1000.0).round() as u64))
+}
+
+/// Parse a size in bytes like "4096", "512K", "1.5G" or "2GiB". Units are
+/// powers of 1024 and case insensitive, with an optional "B" or "iB".
+pub fn parse_size(s: &str) -> Result<MemSize, String> {
+    let err = || format!("Invalid size '{}'", s);
+    let s_trim = s.trim();
+    let split = s_trim.find(|c: char| !c.is_digit(10) && c != '.').unwrap_or(s_trim.len());
+    let (num, unit) = s_trim.split_at(split);
+    let num: f64 = try!(num.parse().map_err(|_| err()));
+    let multiplier = match &*unit.trim().to_uppercase() {
+        "" | "B" => 1u64,
+        "K" | "KB" | "KIB" => 1 << 10,
+        "M" | "MB" | "MIB" => 1 << 20,
+        "G" | "GB" | "GIB" => 1 << 30,
+        "T" | "TB" | "TIB" => 1 << 40,
+        _ => return Err(err())
+    };
+    Ok((num * multiplier as f64) as MemSize)
+}
+
+/// Match text against a glob pattern, where '*' matches any number of
+/// characters, '?' matches one character, and "[a-z]" matches one of a set
+/// of characters ("[!a-z]" or "[^a-z]" matching any other). A backslash
+/// makes the next character literal, so `\*` only matches a '*'. The whole
+/// text has to match, so "AWS_*" doesn't match "MY_AWS_REGION".
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // After a mismatch, retry from the last '*' with it matching one more
+    // character, which is enough since a later '*' can't do worse.
+    let mut star = None;
+    while t < text.len() {
+        if pattern.get(p) == Some(&'*') {
+            star = Some((p + 1, t));
+            p += 1;
+        } else if let Some(next) = glob_char(&pattern, p, text[t]) {
+            p = next;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p;
+            t = star_t + 1;
+            star = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match one character against the part of a glob pattern at p, giving the
+/// start of the next part if it matches.
+fn glob_char(pattern: &[char], p: usize, c: char) -> Option<usize> {
+    let matches = |matched, next| match matched {
+        true => Some(next),
+        false => None
+    };
+    match pattern.get(p) {
+        None => None,
+        Some(&'?') => Some(p + 1),
+        Some(&'\\') if p + 1 < pattern.len() => matches(pattern[p + 1] == c, p + 2),
+        Some(&'[') => {
+            let negate = pattern.get(p + 1) == Some(&'!') || pattern.get(p + 1) == Some(&'^');
+            let start = if negate { p + 2 } else { p + 1 };
+            let mut i = start;
+            let mut found = false;
+            loop {
+                match pattern.get(i) {
+                    // Without a closing ']', the '[' is literal.
+                    None => return matches(c == '[', p + 1),
+                    // A ']' straight after the '[' is part of the set.
+                    Some(&']') if i > start => break,
+                    Some(&low) if pattern.get(i + 1) == Some(&'-') &&
+                        pattern.get(i + 2).map(|&h| h != ']').unwrap_or(false) => {
+                        found |= low <= c && c <= pattern[i + 2];
+                        i += 3;
+                    },
+                    Some(&single) => {
+                        found |= single == c;
+                        i += 1;
+                    },
+                }
+            }
+            matches(found != negate, i + 1)
+        },
+        Some(&literal) => matches(literal == c, p + 1),
+    }
+}
+
+#[test]
+fn test_thousands() {
+    assert_eq!(thousands(0), "0");
+    assert_eq!(thousands(999), "999");
+    assert_eq!(thousands(1000), "1,000");
+    assert_eq!(thousands(1234567), "1,234,567");
+    assert_eq!(thousands(u64::max_value()), "18,446,744,073,709,551,615");
+}
+
+#[test]
+fn test_duration() {
+    assert_eq!(duration(0), "00:00");
+    assert_eq!(duration(61), "01:01");
+    assert_eq!(duration(3600), "01:00:00");
+    assert_eq!(duration(86400 * 3 + 3723), "3-01:02:03");
+    assert_eq!(cputime(ticks_per_second() * 90), "01:30");
+}
+
+#[test]
+fn test_bytes() {
+    assert_eq!(bytes(512, ByteUnits::Binary), "512 B");
+    assert_eq!(bytes(1536, ByteUnits::Binary), "1.5 KiB");
+    assert_eq!(bytes(1500, ByteUnits::Si), "1.5 kB");
+    assert_eq!(bytes(3 * 1024 * 1024 * 1024, ByteUnits::Binary), "3.0 GiB");
+    assert_eq!(bytes(u64::max_value(), ByteUnits::Binary), "16.0 EiB");
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+    assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+    assert_eq!(parse_duration("2h30m"), Ok(Duration::from_secs(9000)));
+    assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+    assert_eq!(parse_duration("1w1d"), Ok(Duration::from_secs(8 * 86400)));
+    assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("0.5s"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_duration(""), Err("Invalid duration ''".to_owned()));
+    assert!(parse_duration("h").is_err());
+    assert!(parse_duration("5").is_ok());
+    assert!(parse_duration("5x").is_err());
+    assert!(parse_duration("5mm").is_err());
+    assert!(parse_duration("-5m").is_err());
+    assert!(parse_duration("1.2.3s").is_err());
+}
+
+#[test]
+fn test_parse_size() {
+    assert_eq!(parse_size("4096"), Ok(4096));
+    assert_eq!(parse_size("4096b"), Ok(4096));
+    assert_eq!(parse_size("512K"), Ok(512 * 1024));
+    assert_eq!(parse_size("512kb"), Ok(512 * 1024));
+    assert_eq!(parse_size("1.5G"), Ok(3 << 29));
+    assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+    assert_eq!(parse_size("1T"), Ok(1 << 40));
+    assert_eq!(parse_size(""), Err("Invalid size ''".to_owned()));
+    assert!(parse_size("G").is_err());
+    assert!(parse_size("5X").is_err());
+    assert!(parse_size("-5M").is_err());
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*TOKEN*", "GITHUB_TOKEN"));
+    assert!(glob_match("*TOKEN*", "TOKEN"));
+    assert!(glob_match("AWS_*", "AWS_REGION"));
+    assert!(!glob_match("AWS_*", "MY_AWS_REGION"));
+    assert!(glob_match("*_KEY", "API_KEY"));
+    assert!(!glob_match("*_KEY", "API_KEYS"));
+    assert!(glob_match("A*B*C", "AxxBxxC"));
+    assert!(!glob_match("A*B*C", "AxxC"));
+    assert!(glob_match("PATH", "PATH"));
+    assert!(!glob_match("PATH", "PATHS"));
+    assert!(!glob_match("AB*BA", "ABA"));
+    assert!(glob_match("fire?ox", "firefox"));
+    assert!(!glob_match("fire?ox", "fireox"));
+    assert!(glob_match("[a-c]at", "bat"));
+    assert!(!glob_match("[a-c]at", "rat"));
+    assert!(glob_match("[!a-c]at", "rat"));
+    assert!(glob_match("[]x]", "]"));
+    assert!(glob_match("a[", "a["));
+    assert!(glob_match("2\\*3", "2*3"));
+    assert!(!glob_match("2\\*3", "2x3"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("k?orker*", "kworker/0:1"));
+}
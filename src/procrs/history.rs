@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::vec_deque;
+use std::time::{Duration, Instant};
+use TaskId;
+
+/// A fixed size window of timestamped samples, dropping the oldest sample
+/// when full.
+///
+/// Samples are expected to be pushed in time order.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    samples: VecDeque<(Instant, T)>,
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    /// Create a buffer holding at most capacity samples.
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity,
+        }
+    }
+
+    /// Add a sample taken now.
+    pub fn push(&mut self, value: T) {
+        self.push_at(Instant::now(), value);
+    }
+
+    /// Add a sample taken at a given instant.
+    pub fn push_at(&mut self, at: Instant, value: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((at, value));
+    }
+
+    /// Maximum number of samples kept.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of samples currently kept.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether there are no samples.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Remove all samples.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// The oldest sample.
+    pub fn oldest(&self) -> Option<&(Instant, T)> {
+        self.samples.front()
+    }
+
+    /// The newest sample.
+    pub fn latest(&self) -> Option<&(Instant, T)> {
+        self.samples.back()
+    }
+
+    /// Iterate over the samples, oldest first.
+    pub fn iter(&self) -> vec_deque::Iter<'_, (Instant, T)> {
+        self.samples.iter()
+    }
+
+    /// The newest sample taken at or before an instant.
+    pub fn at(&self, at: Instant) -> Option<&(Instant, T)> {
+        self.samples.iter().rev().find(|&&(t, _)| t <= at)
+    }
+
+    /// The samples taken at or after an instant, oldest first.
+    pub fn since(&self, since: Instant) -> Vec<&(Instant, T)> {
+        self.samples.iter().filter(|&&(t, _)| t >= since).collect()
+    }
+
+    /// The samples taken within a window ending at the newest sample.
+    pub fn window(&self, window: Duration) -> Vec<&(Instant, T)> {
+        match self.latest() {
+            Some(&(end, _)) => self.samples.iter()
+                .filter(|&&(t, _)| end.duration_since(t) <= window)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Smallest value of a sample field, eg: buf.min(|s| s.rss as f64).
+    pub fn min<F: Fn(&T) -> f64>(&self, field: F) -> Option<f64> {
+        self.samples.iter().map(|&(_, ref v)| field(v))
+            .fold(None, |m, v| Some(m.map_or(v, |m: f64| m.min(v))))
+    }
+
+    /// Largest value of a sample field.
+    pub fn max<F: Fn(&T) -> f64>(&self, field: F) -> Option<f64> {
+        self.samples.iter().map(|&(_, ref v)| field(v))
+            .fold(None, |m, v| Some(m.map_or(v, |m: f64| m.max(v))))
+    }
+
+    /// Mean value of a sample field.
+    pub fn avg<F: Fn(&T) -> f64>(&self, field: F) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let sum: f64 = self.samples.iter().map(|&(_, ref v)| field(v)).sum();
+        Some(sum / self.samples.len() as f64)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RingBuffer<T> {
+    type Item = &'a (Instant, T);
+    type IntoIter = vec_deque::Iter<'a, (Instant, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A RingBuffer of samples for each pid.
+#[derive(Debug, Clone)]
+pub struct PidHistory<T> {
+    buffers: HashMap<TaskId, RingBuffer<T>>,
+    capacity: usize,
+}
+
+impl<T> PidHistory<T> {
+    /// Create a history holding at most capacity samples per pid.
+    pub fn new(capacity: usize) -> Self {
+        PidHistory {
+            buffers: HashMap::new(),
+            capacity: capacity,
+        }
+    }
+
+    /// Add a sample for a pid taken at a given instant.
+    pub fn push_at(&mut self, pid: TaskId, at: Instant, value: T) {
+        let capacity = self.capacity;
+        self.buffers.entry(pid)
+            .or_insert_with(|| RingBuffer::new(capacity))
+            .push_at(at, value);
+    }
+
+    /// The samples of a pid.
+    pub fn get(&self, pid: TaskId) -> Option<&RingBuffer<T>> {
+        self.buffers.get(&pid)
+    }
+
+    /// Forget pids that weren't seen in the last scan, so exited processes
+    /// don't accumulate.
+    pub fn retain<I: IntoIterator<Item=TaskId>>(&mut self, live: I) {
+        let live: HashSet<TaskId> = live.into_iter().collect();
+        self.buffers.retain(|pid, _| live.contains(pid));
+    }
+
+    /// Number of pids with samples.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Whether there are no pids with samples.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+#[test]
+fn test_ring_buffer() {
+    let start = Instant::now();
+    let mut buf = RingBuffer::new(3);
+    assert_eq!(buf.min(|&v: &u64| v as f64), None);
+    for i in 0..5u64 {
+        buf.push_at(start + Duration::from_secs(i), i * 10);
+    }
+    assert_eq!(buf.len(), 3);
+    assert_eq!(buf.iter().map(|&(_, v)| v).collect::<Vec<_>>(), vec![20, 30, 40]);
+    assert_eq!(buf.oldest().unwrap().1, 20);
+    assert_eq!(buf.latest().unwrap().1, 40);
+    assert_eq!(buf.at(start + Duration::from_millis(3500)).unwrap().1, 30);
+    assert!(buf.at(start).is_none());
+    assert_eq!(buf.since(start + Duration::from_secs(3)).len(), 2);
+    assert_eq!(buf.window(Duration::from_secs(1)).len(), 2);
+    assert_eq!(buf.min(|&v| v as f64), Some(20.0));
+    assert_eq!(buf.max(|&v| v as f64), Some(40.0));
+    assert_eq!(buf.avg(|&v| v as f64), Some(30.0));
+}
+
+#[test]
+fn test_pid_history() {
+    let now = Instant::now();
+    let mut history = PidHistory::new(2);
+    history.push_at(1, now, 1);
+    history.push_at(2, now, 2);
+    history.push_at(2, now, 3);
+    history.push_at(2, now, 4);
+    assert_eq!(history.get(2).unwrap().len(), 2);
+    history.retain(vec![2]);
+    assert!(history.get(1).is_none());
+    assert_eq!(history.len(), 1);
+}
@@ -0,0 +1,181 @@
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+use error::{ProcError, ProcFile, ProcOper};
+use rate::Rate;
+use root;
+
+/// A row of /proc/interrupts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interrupt {
+    /// The irq number, or a name like "NMI" or "LOC" for architecture
+    /// specific interrupts.
+    pub irq: String,
+    /// The count on each cpu, in the same order as Interrupts::cpus. A few
+    /// rows (eg: "ERR") only have a single system wide count.
+    pub counts: Vec<u64>,
+    /// The interrupt controller, eg: "IO-APIC" or "PCI-MSI". Only numbered
+    /// irqs have one.
+    pub chip: Option<String>,
+    /// The hardware irq number and trigger type on the controller, eg: "2-edge".
+    pub hwirq: Option<String>,
+    /// The devices using a numbered irq, or the description of a named one.
+    pub device: Option<String>,
+}
+
+impl Interrupt {
+    /// The count across every cpu.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// A struct containing the interrupt counters from /proc/interrupts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interrupts {
+    /// The online cpus, which are the columns of the counts.
+    pub cpus: Vec<u32>,
+    /// Each interrupt, in file order.
+    pub interrupts: Vec<Interrupt>,
+}
+
+impl Interrupts {
+    /// Read the current counters from /proc/interrupts.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("interrupts"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcInterrupts, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcInterrupts, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Get an interrupt by irq number or name, eg: "0" or "LOC".
+    pub fn get(&self, irq: &str) -> Option<&Interrupt> {
+        self.interrupts.iter().find(|i| i.irq == irq)
+    }
+
+    /// Parse a String as a /proc/interrupts file. The header names the online
+    /// cpus, so the number of count columns varies between systems.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let mut lines = contents.lines();
+        let cpus = try!(
+            lines.next()
+                .ok_or(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcInterrupts,
+                    Some("missing header")))
+                .and_then(|header| header.split_whitespace()
+                    .map(|cpu| cpu.trim_left_matches("CPU").parse()
+                        .map_err(|e| ProcError::new(ProcOper::ParsingField,
+                            ProcFile::ProcInterrupts, Some(e), Some("cpu"))))
+                    .collect::<Result<Vec<u32>, _>>())
+        );
+        let mut interrupts = Vec::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            interrupts.push(try!(Self::parse_line(line, cpus.len())));
+        }
+        Ok(Interrupts {
+            cpus: cpus,
+            interrupts: interrupts,
+        })
+    }
+
+    /// Parse a line like "  0:  46  0   IO-APIC   2-edge      timer".
+    fn parse_line(line: &str, ncpus: usize) -> Result<Interrupt, ProcError> {
+        let mut split = line.splitn(2, ':');
+        let (irq, rest) = match (split.next(), split.next()) {
+            (Some(irq), Some(rest)) => (irq.trim(), rest),
+            _ => return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcInterrupts,
+                Some("missing irq")))
+        };
+        let mut fields = rest.split_whitespace().peekable();
+        let mut counts = Vec::with_capacity(ncpus);
+        while counts.len() < ncpus {
+            match fields.peek().and_then(|f| f.parse().ok()) {
+                Some(count) => counts.push(count),
+                None => break
+            }
+            fields.next();
+        }
+        let numbered = irq.parse::<u32>().is_ok();
+        let (chip, hwirq) = match numbered {
+            true => (fields.next().map(|f| f.to_owned()), fields.next().map(|f| f.to_owned())),
+            false => (None, None)
+        };
+        let device = fields.collect::<Vec<_>>().join(" ");
+        Ok(Interrupt {
+            irq: irq.to_owned(),
+            counts: counts,
+            chip: chip,
+            hwirq: hwirq,
+            device: match device.is_empty() {
+                true => None,
+                false => Some(device)
+            },
+        })
+    }
+}
+
+/// Calculate the rate of each interrupt present in both samples, taken
+/// elapsed apart, with the busiest first. A sudden jump in the rate of one
+/// irq is the sign of an interrupt storm.
+pub fn rates(last: &Interrupts, current: &Interrupts, elapsed: Duration)
+    -> Vec<(String, Rate<u64>)> {
+    let mut rates: Vec<_> = current.interrupts.iter().filter_map(|cur|
+        last.get(&cur.irq).map(|l|
+            (cur.irq.clone(), Rate::from_delta(cur.total().saturating_sub(l.total()), elapsed))
+        )
+    ).collect();
+    rates.sort_by(|a, b| b.1.delta.cmp(&a.1.delta));
+    rates
+}
+
+#[test]
+fn test_parsing() {
+    let interrupts = Interrupts::parse_string(
+"           CPU0       CPU1       CPU3
+  0:         46          0          0   IO-APIC   2-edge      timer
+  9:          0          4          0   IO-APIC   9-fasteoi   acpi
+ 24:       1203        310          7   PCI-MSI 524288-edge      ahci[0000:00:1f.2], ehci_hcd:usb1
+NMI:          1          2          3   Non-maskable interrupts
+ERR:          0
+").unwrap();
+    assert_eq!(interrupts.cpus, vec![0, 1, 3]);
+    assert_eq!(interrupts.interrupts.len(), 5);
+    assert_eq!(interrupts.interrupts[0], Interrupt {
+        irq: "0".to_owned(),
+        counts: vec![46, 0, 0],
+        chip: Some("IO-APIC".to_owned()),
+        hwirq: Some("2-edge".to_owned()),
+        device: Some("timer".to_owned()),
+    });
+    let ahci = interrupts.get("24").unwrap();
+    assert_eq!(ahci.total(), 1520);
+    assert_eq!(ahci.device, Some("ahci[0000:00:1f.2], ehci_hcd:usb1".to_owned()));
+    let nmi = interrupts.get("NMI").unwrap();
+    assert_eq!(nmi.chip, None);
+    assert_eq!(nmi.device, Some("Non-maskable interrupts".to_owned()));
+    assert_eq!(interrupts.get("ERR").unwrap().counts, vec![0]);
+}
+
+#[test]
+fn test_rates() {
+    let header = "           CPU0       CPU1\n";
+    let last = Interrupts::parse_string(&format!("{}  1:  10  10   IO-APIC   1-edge  i8042\n\
+        LOC:  100  100   Local timer interrupts\n", header)).unwrap();
+    let current = Interrupts::parse_string(&format!("{}  1:  5010  10   IO-APIC   1-edge  i8042\n\
+        LOC:  200  200   Local timer interrupts\n", header)).unwrap();
+    let rates = rates(&last, &current, Duration::from_secs(2));
+    assert_eq!(rates[0].0, "1");
+    assert_eq!(rates[0].1.per_sec(), 2500.0);
+    assert_eq!(rates[1].1.per_sec(), 100.0);
+}
+
+#[test]
+fn test_read() {
+    let interrupts = Interrupts::new().unwrap();
+    assert!(!interrupts.cpus.is_empty());
+}
@@ -0,0 +1,300 @@
+use std::time::Duration;
+use error::{ProcError, ProcFile, ProcOper};
+use read_file;
+use root;
+
+/// When a key expires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyTimeout {
+    /// The key never expires.
+    Permanent,
+    /// The key has expired.
+    Expired,
+    /// The key expires after this long, rounded down to the largest unit
+    /// (eg: "3h" is between 3 and 4 hours).
+    In(Duration),
+}
+
+/// The state flags of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFlags {
+    /// The key has been instantiated (I).
+    pub instantiated: bool,
+    /// The key has been revoked (R).
+    pub revoked: bool,
+    /// The key is dead, its type was unregistered (D).
+    pub dead: bool,
+    /// The key counts towards the user's quota (Q).
+    pub quota: bool,
+    /// The key is being constructed by a callback to userspace (U).
+    pub under_construction: bool,
+    /// The key is negatively instantiated (N).
+    pub negative: bool,
+    /// The key has been invalidated (i).
+    pub invalidated: bool,
+}
+
+/// A key visible to the calling process, from a row of /proc/keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    /// The serial number of the key.
+    pub serial: u32,
+    /// The state of the key.
+    pub flags: KeyFlags,
+    /// The number of references to the key.
+    pub usage: u32,
+    /// When the key expires.
+    pub timeout: KeyTimeout,
+    /// The permissions of the key, as a bitmask of possessor, user, group
+    /// and other permissions.
+    pub perm: u32,
+    /// The uid owning the key.
+    pub uid: u32,
+    /// The gid of the key.
+    pub gid: u32,
+    /// The type of key, eg: "keyring" or "user".
+    pub key_type: String,
+    /// The description of the key, with a summary from its type (eg: the
+    /// number of keys in a keyring).
+    pub description: String,
+}
+
+impl Key {
+    /// Read the keys visible to the calling process, from /proc/keys.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&root::path("keys"), ProcFile::ProcKeys));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/keys file, with lines like
+    /// "221f5cfe I--Q---  2 perm 1f3f0000  0 65534 keyring   _uid.0: empty".
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        let err = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcKeys,
+            Some(field));
+        let mut keys = Vec::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let mut split = line.split_whitespace();
+            let mut next = || split.next().ok_or(err("missing field"));
+            let serial = try!(u32::from_str_radix(try!(next()), 16).map_err(|_| err("serial")));
+            let flags = try!(parse_key_flags(try!(next())).ok_or(err("flags")));
+            let usage = try!(try!(next()).parse().map_err(|_| err("usage")));
+            let timeout = try!(parse_timeout(try!(next())).ok_or(err("timeout")));
+            let perm = try!(u32::from_str_radix(try!(next()), 16).map_err(|_| err("perm")));
+            let uid = try!(try!(next()).parse().map_err(|_| err("uid")));
+            let gid = try!(try!(next()).parse().map_err(|_| err("gid")));
+            let key_type = try!(next()).to_owned();
+            keys.push(Key {
+                serial: serial,
+                flags: flags,
+                usage: usage,
+                timeout: timeout,
+                perm: perm,
+                uid: uid,
+                gid: gid,
+                key_type: key_type,
+                // The description can contain spaces, so is the rest of the line.
+                description: try!(description(line).ok_or(err("description"))),
+            });
+        }
+        Ok(keys)
+    }
+}
+
+/// A user's key quota usage, from a row of /proc/key-users.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyUser {
+    /// The uid of the user.
+    pub uid: u32,
+    /// The number of references to the user's key accounting.
+    pub usage: u32,
+    /// Keys owned by the user.
+    pub nkeys: u32,
+    /// Keys owned by the user that have been instantiated.
+    pub nikeys: u32,
+    /// Keys counting towards the quota.
+    pub qnkeys: u32,
+    /// The maximum number of keys the user may own.
+    pub maxkeys: u32,
+    /// Bytes of key data counting towards the quota.
+    pub qnbytes: u64,
+    /// The maximum bytes of key data the user may own.
+    pub maxbytes: u64,
+}
+
+impl KeyUser {
+    /// Read the key quota usage of every user with keys, from /proc/key-users.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&root::path("key-users"), ProcFile::ProcKeyUsers));
+        Self::parse_string(&contents)
+    }
+
+    /// The share of the key count quota in use, from 0 to 1.
+    pub fn keys_used(&self) -> f64 {
+        match self.maxkeys {
+            0 => 0.0,
+            max => self.qnkeys as f64 / max as f64
+        }
+    }
+
+    /// The share of the key data quota in use, from 0 to 1.
+    pub fn bytes_used(&self) -> f64 {
+        match self.maxbytes {
+            0 => 0.0,
+            max => self.qnbytes as f64 / max as f64
+        }
+    }
+
+    /// Parse a String as a /proc/key-users file, with lines like
+    /// "    0:     9 8/8 3/1000000 40/25000000".
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        let err = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcKeyUsers,
+            Some(field));
+        let mut users = Vec::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            let mut split = line.split_whitespace();
+            let mut next = || split.next().ok_or(err("missing field"));
+            let uid = try!(try!(next()).trim_right_matches(':').parse().map_err(|_| err("uid")));
+            let usage = try!(try!(next()).parse().map_err(|_| err("usage")));
+            let (nkeys, nikeys) = try!(parse_pair(try!(next())).ok_or(err("nkeys")));
+            let (qnkeys, maxkeys) = try!(parse_pair(try!(next())).ok_or(err("qnkeys")));
+            let (qnbytes, maxbytes) = try!(parse_pair(try!(next())).ok_or(err("qnbytes")));
+            users.push(KeyUser {
+                uid: uid,
+                usage: usage,
+                nkeys: nkeys as u32,
+                nikeys: nikeys as u32,
+                qnkeys: qnkeys as u32,
+                maxkeys: maxkeys as u32,
+                qnbytes: qnbytes,
+                maxbytes: maxbytes,
+            });
+        }
+        Ok(users)
+    }
+}
+
+/// Parse key flags like "I--Q---", where '-' is an unset flag.
+fn parse_key_flags(flags: &str) -> Option<KeyFlags> {
+    if flags.len() != 7 {
+        return None;
+    }
+    let set = |i: usize, c: char| flags[i..].starts_with(c);
+    Some(KeyFlags {
+        instantiated: set(0, 'I'),
+        revoked: set(1, 'R'),
+        dead: set(2, 'D'),
+        quota: set(3, 'Q'),
+        under_construction: set(4, 'U'),
+        negative: set(5, 'N'),
+        invalidated: set(6, 'i'),
+    })
+}
+
+/// Parse a key timeout like "perm", "expd", "45s", "5m", "3h", "2d" or "1w".
+fn parse_timeout(timeout: &str) -> Option<KeyTimeout> {
+    match timeout {
+        "perm" => return Some(KeyTimeout::Permanent),
+        "expd" => return Some(KeyTimeout::Expired),
+        _ => {}
+    }
+    if timeout.len() < 2 {
+        return None;
+    }
+    let (num, unit) = timeout.split_at(timeout.len() - 1);
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None
+    };
+    num.parse::<u64>().ok().map(|n| KeyTimeout::In(Duration::from_secs(n * multiplier)))
+}
+
+/// Parse a pair of numbers like "8/8".
+fn parse_pair(pair: &str) -> Option<(u64, u64)> {
+    let mut split = pair.splitn(2, '/');
+    match (split.next().and_then(|n| n.parse().ok()), split.next().and_then(|n| n.parse().ok())) {
+        (Some(a), Some(b)) => Some((a, b)),
+        _ => None
+    }
+}
+
+/// The description of a key, being everything after the eighth field.
+fn description(line: &str) -> Option<String> {
+    let mut rest = line.trim_left();
+    for _ in 0..8 {
+        let end = match rest.find(char::is_whitespace) {
+            Some(end) => end,
+            None => return None
+        };
+        rest = rest[end..].trim_left();
+    }
+    Some(rest.to_owned())
+}
+
+#[test]
+fn test_keys() {
+    let keys = Key::parse_string(
+"080ea8dc I------     1 perm 1f030000     0     0 keyring   .dns_resolver: empty
+221f5cfe I--Q---     2 perm 1f3f0000     0 65534 keyring   _uid.0: empty
+3a8e3b2f I--Q---     1   3h 3f010000  1000  1000 user      my token: 32
+0c2d4e1a IR-Q---     1 expd 3f010000  1000  1000 user      old: 8
+").unwrap();
+    assert_eq!(keys.len(), 4);
+    assert_eq!(keys[1], Key {
+        serial: 0x221f5cfe,
+        flags: KeyFlags {
+            instantiated: true,
+            revoked: false,
+            dead: false,
+            quota: true,
+            under_construction: false,
+            negative: false,
+            invalidated: false,
+        },
+        usage: 2,
+        timeout: KeyTimeout::Permanent,
+        perm: 0x1f3f0000,
+        uid: 0,
+        gid: 65534,
+        key_type: "keyring".to_owned(),
+        description: "_uid.0: empty".to_owned(),
+    });
+    assert_eq!(keys[2].timeout, KeyTimeout::In(Duration::from_secs(3 * 3600)));
+    assert_eq!(keys[2].description, "my token: 32");
+    assert_eq!(keys[3].timeout, KeyTimeout::Expired);
+    assert!(keys[3].flags.revoked);
+    assert!(Key::parse_string("080ea8dc I------ 1 perm\n").is_err());
+    assert!(Key::parse_string("080ea8dc I------ 1 3x 1f030000 0 0 keyring a\n").is_err());
+}
+
+#[test]
+fn test_key_users() {
+    let users = KeyUser::parse_string("    0:     9 8/8 3/1000000 40/25000000\n\
+ 1000:     4 3/3 2/200 100/20000\n").unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0], KeyUser {
+        uid: 0,
+        usage: 9,
+        nkeys: 8,
+        nikeys: 8,
+        qnkeys: 3,
+        maxkeys: 1000000,
+        qnbytes: 40,
+        maxbytes: 25000000,
+    });
+    assert_eq!(users[1].keys_used(), 0.01);
+    assert_eq!(users[1].bytes_used(), 0.005);
+    assert!(KeyUser::parse_string("0: 9 8/8 3\n").is_err());
+}
+
+#[test]
+fn test_read() {
+    // These files only exist with CONFIG_KEYS.
+    if ::std::path::Path::new("/proc/keys").exists() {
+        assert!(Key::new().is_ok());
+        assert!(KeyUser::new().is_ok());
+    }
+}
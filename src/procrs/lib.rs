@@ -1,11 +1,149 @@
+extern crate libc;
+#[macro_use]
+extern crate serde_json;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+// Without the log feature, logging compiles to nothing. The arguments are
+// still type checked, so the build doesn't break when the feature is enabled.
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ($($arg:tt)*) => { if false { let _ = format!($($arg)*); } }
+}
+#[cfg(not(feature = "log"))]
+macro_rules! warn {
+    ($($arg:tt)*) => { if false { let _ = format!($($arg)*); } }
+}
+
 /// Get information about a process (/proc/[pid]/)
 pub mod pid;
 /// The error type used for this crate
 pub mod error;
+/// Choose where procfs is read from (eg: /host/proc in a container)
+pub mod root;
+/// Build fake proc trees for tests (with the testing feature)
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 /// Get informmation about system memory
 pub mod meminfo;
+/// Get information about networking (/proc/net/)
+pub mod net;
+/// Get mounted filesystems and supported filesystem types (mounts, mountinfo, filesystems)
+pub mod mounts;
+/// Get the system uptime (/proc/uptime)
+pub mod uptime;
+/// Get boot time, context switch and process counters (/proc/stat)
+pub mod stat;
+/// Get the kernel command line (/proc/cmdline)
+pub mod cmdline;
+/// Get the kernel version (/proc/version and /proc/sys/kernel/osrelease)
+pub mod version;
+/// Read and write sysctls by name (/proc/sys)
+pub mod sysctl;
+/// Get kernel keys and key quota usage (/proc/keys and /proc/key-users)
+pub mod keys;
+/// Get file handle and inode usage and limits (/proc/sys/fs/)
+pub mod filehandles;
+/// Get SysV shared memory, semaphores and message queues, like ipcs (/proc/sysvipc/)
+pub mod sysvipc;
+/// Look up local users by name or uid (/etc/passwd)
+pub mod users;
+/// Get the system load averages (/proc/loadavg)
+pub mod loadavg;
+/// Get virtual memory counters (/proc/vmstat)
+pub mod vmstat;
+/// Get the free blocks of each memory zone, for fragmentation (/proc/buddyinfo)
+pub mod buddyinfo;
+/// Get kernel slab cache usage, like slabtop (/proc/slabinfo)
+pub mod slabinfo;
+/// Get I/O counters for block devices (/proc/diskstats)
+pub mod diskstats;
+/// Get interrupt counters for each cpu (/proc/interrupts)
+pub mod interrupts;
+/// Get softirq counters for each cpu (/proc/softirqs)
+pub mod softirqs;
+/// Get pressure stall information (/proc/pressure/)
+pub mod pressure;
+/// Monitor the resource usage of the calling process
+pub mod selfcheck;
+/// Bound and throttle scans with deadlines, cancellation and pauses
+pub mod cancel;
+/// Generate incident reports of the system and its processes
+pub mod report;
+/// Read processes and system state together in one pass
+pub mod snapshot;
+/// Version the JSON formats written by this crate
+pub mod schema;
+/// Describe every parsed field, for generating help and checking field names
+pub mod fields;
+/// Render tables and records as text, JSON or CSV
+pub mod output;
+/// Format and parse numbers, durations, sizes and globs for people
+pub mod format;
+/// Keep short windows of historical samples
+pub mod history;
+/// Convert counter deltas into per-second rates
+pub mod rate;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+use error::{ProcError, ProcFile, ProcOper};
 
 /// The type used to repesent pids
 pub type TaskId = i32;
 /// The type used to repesent memory (in bytes)
 pub type MemSize = u64;
+
+/// Clock ticks per second used for times in /proc (USER_HZ), from
+/// sysconf(_SC_CLK_TCK). If it can't be found, 100 is assumed.
+pub fn ticks_per_second() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as u64,
+        _ => 100
+    }
+}
+
+/// The size of a memory page in bytes, from sysconf(_SC_PAGESIZE). If it
+/// can't be found, 4096 is assumed.
+pub fn page_size() -> MemSize {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as MemSize,
+        _ => 4096
+    }
+}
+
+/// Convert a time in clock ticks (eg: PidStat::utime) to a Duration.
+pub fn ticks_to_duration(ticks: u64) -> Duration {
+    let hertz = ticks_per_second();
+    Duration::from_secs(ticks / hertz) + Duration::from_nanos(ticks % hertz * 1_000_000_000 / hertz)
+}
+
+/// Read a whole file into a String, with errors naming which proc file it
+/// was.
+pub(crate) fn read_file(path: &Path, file: ProcFile) -> Result<String, ProcError> {
+    let mut contents = String::new();
+    try!(
+        File::open(path)
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, file, e))
+            .and_then(|mut f|
+                f.read_to_string(&mut contents)
+                    .map_err(|e| ProcError::new_err(ProcOper::Reading, file, e))
+            )
+    );
+    Ok(contents)
+}
+
+#[test]
+fn test_ticks() {
+    let hertz = ticks_per_second();
+    assert!(hertz > 0);
+    assert_eq!(ticks_to_duration(hertz * 3 + hertz / 4), Duration::from_millis(3250));
+}
+
+#[test]
+fn test_page_size() {
+    assert!(page_size().is_power_of_two());
+}
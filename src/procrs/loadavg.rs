@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::Read;
+use error::{ProcError, ProcFile, ProcOper};
+use TaskId;
+use root;
+
+/// A struct containing the load averages from /proc/loadavg.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loadavg {
+    /// Average number of runnable or uninterruptible tasks over the last minute.
+    pub load1: f64,
+    /// Average over the last 5 minutes.
+    pub load5: f64,
+    /// Average over the last 15 minutes.
+    pub load15: f64,
+    /// Number of currently runnable tasks.
+    pub runnable: u32,
+    /// Number of tasks (processes and threads) on the system.
+    pub tasks: u32,
+    /// The most recently assigned pid.
+    pub last_pid: TaskId,
+}
+
+impl Loadavg {
+    /// Read the current load averages from /proc/loadavg.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("loadavg"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcLoadavg, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcLoadavg, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/loadavg file, eg: "0.15 0.27 0.26 2/73 4334".
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let mut split = contents.split(|c: char| c.is_whitespace() || c == '/')
+            .filter(|s| !s.is_empty());
+        macro_rules! next {
+            ($field: expr) => {
+                try!(split.next()
+                    .ok_or(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcLoadavg,
+                        Some($field)))
+                    .and_then(|s| s.parse()
+                        .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcLoadavg,
+                            Some(e), Some($field)))
+                    ))
+            }
+        }
+        Ok(Loadavg {
+            load1: next!("load1"),
+            load5: next!("load5"),
+            load15: next!("load15"),
+            runnable: next!("runnable"),
+            tasks: next!("tasks"),
+            last_pid: next!("last_pid"),
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    assert_eq!(Loadavg::parse_string("0.15 0.27 0.26 2/73 4334\n"),
+        Ok(Loadavg { load1: 0.15, load5: 0.27, load15: 0.26, runnable: 2, tasks: 73, last_pid: 4334 }));
+}
+
+#[test]
+fn test_missing_tasks() {
+    assert_eq!(Loadavg::parse_string("0.15 0.27 0.26 2\n"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcLoadavg, Some("tasks"))));
+}
+
+#[test]
+fn test_read() {
+    assert!(Loadavg::new().unwrap().tasks > 0);
+}
@@ -3,6 +3,7 @@ use std::fs::File;
 use std::io;
 use std::io::BufRead;
 use std::collections::HashMap;
+use root;
 
 #[derive(Debug)]
 pub enum MeminfoError {
@@ -14,7 +15,7 @@ impl fmt::Display for MeminfoError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             MeminfoError::Io(ref err) => err.fmt(f),
-            MeminfoError::NotFound => write!(f, "Unknown error occured"),
+            MeminfoError::NotFound => write!(f, "Missing field in meminfo"),
         }
     }
 }
@@ -29,7 +30,8 @@ impl From<io::Error> for MeminfoError {
 pub struct Meminfo {
     pub memtotal: u64,
     pub memfree: u64,
-    pub memavailable: u64,
+    /// None before Linux 3.14.
+    pub memavailable: Option<u64>,
     pub buffers: u64,
     pub cached: u64,
     pub swapcached: u64,
@@ -61,15 +63,19 @@ pub struct Meminfo {
     pub vmalloctotal: u64,
     pub vmallocused: u64,
     pub vmallocchunk: u64,
-    pub hardwarecorrupted: u64,
-    pub anonhugepages: u64,
-    pub hugepagestotal: u64,
-    pub hugepagesfree: u64,
-    pub hugepagsersvd: u64,
-    pub hugepagessurp: u64,
-    pub hugepagessize: u64,
-    pub directmap4k: u64,
-    pub directmap2m: u64,
+    /// None without CONFIG_MEMORY_FAILURE.
+    pub hardwarecorrupted: Option<u64>,
+    /// None without transparent huge pages.
+    pub anonhugepages: Option<u64>,
+    /// The HugePages fields are None without CONFIG_HUGETLBFS.
+    pub hugepagestotal: Option<u64>,
+    pub hugepagesfree: Option<u64>,
+    pub hugepagsersvd: Option<u64>,
+    pub hugepagessurp: Option<u64>,
+    pub hugepagessize: Option<u64>,
+    /// The DirectMap fields are None on architectures other than x86.
+    pub directmap4k: Option<u64>,
+    pub directmap2m: Option<u64>,
     // pub directmap1g: u64,
     pub mainused: u64,
     pub maincached: u64,
@@ -85,7 +91,7 @@ impl Meminfo {
     pub fn new() -> Result<Self, MeminfoError> {
         // Create an interim hashmap
         // Read the file?
-        let minfo_file: File = try!(File::open("/proc/meminfo"));
+        let minfo_file: File = try!(File::open(root::path("meminfo")));
         // Parse the file
         // How to we make sure this error is propogated correctly?
         let lines = try!(io::BufReader::new(minfo_file)
@@ -94,27 +100,32 @@ impl Meminfo {
         let mut hmap = try!(lines.iter().map(|line| Self::parse_line(line)).collect::<Result<HashMap<_, _>, _>>()  );
         //  Calculate some of the other values
         // kb_main_used = kb_main_total - kb_main_free - kb_main_cached - kb_main_buffe
-        let total = hmap.get("MemTotal").unwrap().clone();
-        let free = hmap.get("MemFree").unwrap().clone();
-        let cached = hmap.get("Cached").unwrap().clone();
-        let buffer = hmap.get("Buffers").unwrap().clone();
+        let total = try!(Self::get(&hmap, "MemTotal"));
+        let free = try!(Self::get(&hmap, "MemFree"));
+        let cached = try!(Self::get(&hmap, "Cached"));
+        let buffer = try!(Self::get(&hmap, "Buffers"));
         let used = total - free - cached - buffer;
         hmap.insert("MainUsed".to_owned(), used);
 
         // kb_main_cached = kb_page_cache + kb_slab
-        let page_cache = hmap.get("Cached").unwrap().clone();
-        let slab = hmap.get("Slab").unwrap().clone();
+        let page_cache = try!(Self::get(&hmap, "Cached"));
+        let slab = try!(Self::get(&hmap, "Slab"));
         hmap.insert("MainCached".to_owned(), (page_cache + slab) );
 
         // kb_swap_used = kb_swap_total - kb_swap_free
-        let swap_total = hmap.get("SwapTotal").unwrap().clone();
-        let swap_free = hmap.get("SwapFree").unwrap().clone();
+        let swap_total = try!(Self::get(&hmap, "SwapTotal"));
+        let swap_free = try!(Self::get(&hmap, "SwapFree"));
         hmap.insert("MainSwapUsed".to_owned(), (swap_total - swap_free));
 
         // Populate the results
         Self::build_minfo(hmap)
     }
 
+    // Get a field that every supported kernel has.
+    fn get(hmap: &HashMap<String, u64>, key: &str) -> Result<u64, MeminfoError> {
+        hmap.get(key).cloned().ok_or(MeminfoError::NotFound)
+    }
+
     // This builds up the hash map.
     fn parse_line(line: &str) -> Result<(String, u64), MeminfoError> {
         // Find the : offset
@@ -127,55 +138,56 @@ impl Meminfo {
 
     //This then takes the values out and puts them into an minfo
     fn build_minfo(hmap: HashMap<String, u64>) -> Result<Meminfo, MeminfoError> {
-        // REALLY REALLY improve this handling of Option types ...
+        // Which fields exist depends on the kernel version and config, so
+        // those that can be missing are optional.
         let minfo = Meminfo {
-            memtotal: hmap.get("MemTotal").unwrap().clone(),
-            memfree: hmap.get("MemFree").unwrap().clone(),
-            memavailable: hmap.get("MemAvailable").unwrap().clone(),
-            buffers: hmap.get("Buffers").unwrap().clone(),
-            cached: hmap.get("Cached").unwrap().clone(),
-            swapcached: hmap.get("SwapCached").unwrap().clone(),
-            active: hmap.get("Active").unwrap().clone(),
-            inactive: hmap.get("Inactive").unwrap().clone(),
-            activeanon: hmap.get("Active(anon)").unwrap().clone(),
-            inactiveanon: hmap.get("Inactive(anon)").unwrap().clone(),
-            activefile: hmap.get("Active(file)").unwrap().clone(),
-            inactivefile: hmap.get("Inactive(file)").unwrap().clone(),
-            unevictable: hmap.get("Unevictable").unwrap().clone(),
-            mlocked: hmap.get("Mlocked").unwrap().clone(),
-            swaptotal: hmap.get("SwapTotal").unwrap().clone(),
-            swapfree: hmap.get("SwapFree").unwrap().clone(),
-            dirty: hmap.get("Dirty").unwrap().clone(),
-            writeback: hmap.get("Writeback").unwrap().clone(),
-            anonpages: hmap.get("AnonPages").unwrap().clone(),
-            mapped: hmap.get("Mapped").unwrap().clone(),
-            shmem: hmap.get("Shmem").unwrap().clone(),
-            slab: hmap.get("Slab").unwrap().clone(),
-            srelclaimable: hmap.get("SReclaimable").unwrap().clone(),
-            sunreclaim: hmap.get("SUnreclaim").unwrap().clone(),
-            kernelstack: hmap.get("KernelStack").unwrap().clone(),
-            pagetables: hmap.get("PageTables").unwrap().clone(),
-            nfsunstable: hmap.get("NFS_Unstable").unwrap().clone(),
-            bounce: hmap.get("Bounce").unwrap().clone(),
-            writebacktmp: hmap.get("WritebackTmp").unwrap().clone(),
-            commitlimit: hmap.get("CommitLimit").unwrap().clone(),
-            committedas: hmap.get("Committed_AS").unwrap().clone(),
-            vmalloctotal: hmap.get("VmallocTotal").unwrap().clone(),
-            vmallocused: hmap.get("VmallocUsed").unwrap().clone(),
-            vmallocchunk: hmap.get("VmallocChunk").unwrap().clone(),
-            hardwarecorrupted: hmap.get("HardwareCorrupted").unwrap().clone(),
-            anonhugepages: hmap.get("AnonHugePages").unwrap().clone(),
-            hugepagestotal: hmap.get("HugePages_Total").unwrap().clone(),
-            hugepagesfree: hmap.get("HugePages_Free").unwrap().clone(),
-            hugepagsersvd: hmap.get("HugePages_Rsvd").unwrap().clone(),
-            hugepagessurp: hmap.get("HugePages_Surp").unwrap().clone(),
-            hugepagessize: hmap.get("Hugepagesize").unwrap().clone(),
-            directmap4k: hmap.get("DirectMap4k").unwrap().clone(),
-            directmap2m: hmap.get("DirectMap2M").unwrap().clone(),
-            // directmap1g: hmap.get("DirectMap1G").unwrap().clone(),
-            mainused: hmap.get("MainUsed").unwrap().clone(),
-            maincached: hmap.get("MainCached").unwrap().clone(),
-            mainswapused: hmap.get("MainSwapUsed").unwrap().clone(),
+            memtotal: try!(Self::get(&hmap, "MemTotal")),
+            memfree: try!(Self::get(&hmap, "MemFree")),
+            memavailable: hmap.get("MemAvailable").cloned(),
+            buffers: try!(Self::get(&hmap, "Buffers")),
+            cached: try!(Self::get(&hmap, "Cached")),
+            swapcached: try!(Self::get(&hmap, "SwapCached")),
+            active: try!(Self::get(&hmap, "Active")),
+            inactive: try!(Self::get(&hmap, "Inactive")),
+            activeanon: try!(Self::get(&hmap, "Active(anon)")),
+            inactiveanon: try!(Self::get(&hmap, "Inactive(anon)")),
+            activefile: try!(Self::get(&hmap, "Active(file)")),
+            inactivefile: try!(Self::get(&hmap, "Inactive(file)")),
+            unevictable: try!(Self::get(&hmap, "Unevictable")),
+            mlocked: try!(Self::get(&hmap, "Mlocked")),
+            swaptotal: try!(Self::get(&hmap, "SwapTotal")),
+            swapfree: try!(Self::get(&hmap, "SwapFree")),
+            dirty: try!(Self::get(&hmap, "Dirty")),
+            writeback: try!(Self::get(&hmap, "Writeback")),
+            anonpages: try!(Self::get(&hmap, "AnonPages")),
+            mapped: try!(Self::get(&hmap, "Mapped")),
+            shmem: try!(Self::get(&hmap, "Shmem")),
+            slab: try!(Self::get(&hmap, "Slab")),
+            srelclaimable: try!(Self::get(&hmap, "SReclaimable")),
+            sunreclaim: try!(Self::get(&hmap, "SUnreclaim")),
+            kernelstack: try!(Self::get(&hmap, "KernelStack")),
+            pagetables: try!(Self::get(&hmap, "PageTables")),
+            nfsunstable: try!(Self::get(&hmap, "NFS_Unstable")),
+            bounce: try!(Self::get(&hmap, "Bounce")),
+            writebacktmp: try!(Self::get(&hmap, "WritebackTmp")),
+            commitlimit: try!(Self::get(&hmap, "CommitLimit")),
+            committedas: try!(Self::get(&hmap, "Committed_AS")),
+            vmalloctotal: try!(Self::get(&hmap, "VmallocTotal")),
+            vmallocused: try!(Self::get(&hmap, "VmallocUsed")),
+            vmallocchunk: try!(Self::get(&hmap, "VmallocChunk")),
+            hardwarecorrupted: hmap.get("HardwareCorrupted").cloned(),
+            anonhugepages: hmap.get("AnonHugePages").cloned(),
+            hugepagestotal: hmap.get("HugePages_Total").cloned(),
+            hugepagesfree: hmap.get("HugePages_Free").cloned(),
+            hugepagsersvd: hmap.get("HugePages_Rsvd").cloned(),
+            hugepagessurp: hmap.get("HugePages_Surp").cloned(),
+            hugepagessize: hmap.get("Hugepagesize").cloned(),
+            directmap4k: hmap.get("DirectMap4k").cloned(),
+            directmap2m: hmap.get("DirectMap2M").cloned(),
+            // directmap1g: hmap.get("DirectMap1G").cloned(),
+            mainused: try!(Self::get(&hmap, "MainUsed")),
+            maincached: try!(Self::get(&hmap, "MainCached")),
+            mainswapused: try!(Self::get(&hmap, "MainSwapUsed")),
         };
         Ok(minfo)
     }
@@ -195,3 +207,24 @@ impl fmt::Display for Meminfo {
     // Should it accept display units?
 
 
+
+#[test]
+fn test_optional_fields() {
+    use testing::{FakeProc, MEMINFO};
+    let fake = FakeProc::new().unwrap();
+    fake.file("meminfo", MEMINFO).unwrap();
+    let minfo = fake.run(Meminfo::new).unwrap();
+    assert_eq!(minfo.memavailable, Some(5652192));
+    assert_eq!(minfo.mainused, 6158152 - 3820856 - 1993828 - 17624);
+    // Kernels before 3.14 have no MemAvailable.
+    let old: String = MEMINFO.lines().filter(|l| !l.starts_with("MemAvailable:"))
+        .map(|l| format!("{}\n", l)).collect();
+    fake.file("meminfo", &old).unwrap();
+    assert_eq!(fake.run(Meminfo::new).unwrap().memavailable, None);
+    // Fields every kernel has are still required.
+    fake.file("meminfo", "MemTotal:        6158152 kB\n").unwrap();
+    match fake.run(Meminfo::new) {
+        Err(MeminfoError::NotFound) => {},
+        r => panic!("expected NotFound, got {:?}", r),
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use error::{ProcError, ProcFile, ProcOper};
+use read_file;
+use root;
+
+/// Mount options by name, with the value of options like "mode=755".
+/// Flags like "rw" have no value.
+pub type MountOptions = HashMap<String, Option<String>>;
+
+/// A mounted filesystem, from a mounts file (eg: /proc/self/mounts).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    /// The device or server mounted, eg: "/dev/sda1" or "server:/export".
+    pub source: String,
+    /// Where the filesystem is mounted.
+    pub mount_point: PathBuf,
+    /// The filesystem type, eg: "ext4".
+    pub fstype: String,
+    /// The mount options.
+    pub options: MountOptions,
+}
+
+impl Mount {
+    /// Read the mounts visible to this process, from /proc/self/mounts.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        Self::new_dir(&root::path("self"))
+    }
+
+    /// Read the mounts file inside a process directory, being the mounts
+    /// in the mount namespace of that process.
+    pub fn new_dir(pid_dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&pid_dir.join("mounts"), ProcFile::PidMounts));
+        Self::parse_string(&contents)
+    }
+
+    /// Whether the filesystem isn't backed by a block device, like proc,
+    /// tmpfs or nfs, given the filesystems from Filesystem::new(). Types the
+    /// kernel doesn't list (eg: a module that was unloaded) are assumed to
+    /// be block-backed.
+    pub fn is_virtual(&self, filesystems: &[Filesystem]) -> bool {
+        is_virtual(filesystems, &self.fstype).unwrap_or(false)
+    }
+
+    /// Parse a String as a mounts file, with a line like
+    /// "/dev/sda1 / ext4 rw,relatime 0 0" for each mount.
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        contents.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|line| {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                if fields.len() < 4 {
+                    return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::PidMounts,
+                        Some("missing fields")));
+                }
+                Ok(Mount {
+                    source: unescape(fields[0]),
+                    mount_point: PathBuf::from(unescape(fields[1])),
+                    fstype: fields[2].to_owned(),
+                    options: parse_options(fields[3]),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A filesystem type supported by the kernel, from /proc/filesystems.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filesystem {
+    /// Whether the filesystem doesn't need a block device (nodev), like
+    /// virtual and network filesystems.
+    pub nodev: bool,
+    /// The filesystem type, eg: "ext4" or "tmpfs".
+    pub fstype: String,
+}
+
+impl Filesystem {
+    /// Read the filesystem types supported by the kernel, from /proc/filesystems.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&root::path("filesystems"), ProcFile::ProcFilesystems));
+        Ok(Self::parse_string(&contents))
+    }
+
+    /// Parse a String as a /proc/filesystems file, with lines like
+    /// "nodev\tproc" or "\text4".
+    fn parse_string(contents: &str) -> Vec<Self> {
+        contents.lines()
+            .filter_map(|line| {
+                let mut split = line.split('\t');
+                match (split.next(), split.next()) {
+                    (Some(flag), Some(fstype)) if !fstype.trim().is_empty() => Some(Filesystem {
+                        nodev: flag.trim() == "nodev",
+                        fstype: fstype.trim().to_owned(),
+                    }),
+                    _ => None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Whether a filesystem type is virtual (nodev), or None if the kernel
+/// doesn't list it. A subtype like "fuse.sshfs" is looked up by its type.
+pub fn is_virtual(filesystems: &[Filesystem], fstype: &str) -> Option<bool> {
+    let fstype = fstype.split('.').next().unwrap_or(fstype);
+    filesystems.iter().find(|f| f.fstype == fstype).map(|f| f.nodev)
+}
+
+/// A mounted filesystem, from a mountinfo file (eg: /proc/self/mountinfo).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountInfo {
+    /// A unique id for the mount.
+    pub mount_id: u32,
+    /// The id of the parent mount, or of itself for the root of the namespace.
+    pub parent_id: u32,
+    /// The major device number of the filesystem, as used in diskstats and maps.
+    pub major: u32,
+    /// The minor device number of the filesystem.
+    pub minor: u32,
+    /// The directory within the filesystem that is mounted (eg: for bind mounts).
+    pub root: PathBuf,
+    /// Where the filesystem is mounted.
+    pub mount_point: PathBuf,
+    /// The per-mount options, eg: "ro" or "nosuid".
+    pub mount_options: MountOptions,
+    /// Optional fields, like "shared:1" or "master:2".
+    pub optional_fields: Vec<String>,
+    /// The filesystem type, eg: "ext4".
+    pub fstype: String,
+    /// The device or server mounted, eg: "/dev/sda1".
+    pub source: String,
+    /// The per-superblock options, shared by every mount of the filesystem.
+    pub super_options: MountOptions,
+}
+
+impl MountInfo {
+    /// Read the mounts visible to this process, from /proc/self/mountinfo.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        Self::new_dir(&root::path("self"))
+    }
+
+    /// Read the mountinfo file inside a process directory, being the mounts
+    /// in the mount namespace of that process.
+    pub fn new_dir(pid_dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&pid_dir.join("mountinfo"), ProcFile::PidMountinfo));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a mountinfo file.
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        contents.lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(Self::parse_line)
+            .collect()
+    }
+
+    /// Parse a line of a mountinfo file, like
+    /// "36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue".
+    fn parse_line(line: &str) -> Result<Self, ProcError> {
+        let err = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::PidMountinfo,
+            Some(field));
+        let fields: Vec<_> = line.split_whitespace().collect();
+        // The optional fields are ended by a single "-".
+        let separator = try!(fields.iter().position(|&f| f == "-").ok_or(err("separator")));
+        if separator < 6 || fields.len() < separator + 3 {
+            return Err(err("missing fields"));
+        }
+        let mut device = fields[2].splitn(2, ':');
+        let (major, minor) = match (device.next(), device.next()) {
+            (Some(major), Some(minor)) => (major, minor),
+            _ => return Err(err("major:minor"))
+        };
+        Ok(MountInfo {
+            mount_id: try!(fields[0].parse().map_err(|_| err("mount id"))),
+            parent_id: try!(fields[1].parse().map_err(|_| err("parent id"))),
+            major: try!(major.parse().map_err(|_| err("major"))),
+            minor: try!(minor.parse().map_err(|_| err("minor"))),
+            root: PathBuf::from(unescape(fields[3])),
+            mount_point: PathBuf::from(unescape(fields[4])),
+            mount_options: parse_options(fields[5]),
+            optional_fields: fields[6..separator].iter().map(|f| f.to_string()).collect(),
+            fstype: fields[separator + 1].to_owned(),
+            source: unescape(fields[separator + 2]),
+            super_options: fields.get(separator + 3).map(|o| parse_options(o))
+                .unwrap_or(HashMap::new()),
+        })
+    }
+}
+
+/// Find the mount a path is on, being the deepest mount point containing it.
+/// When a mount point is mounted over, the last mount is used.
+pub fn containing<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts.iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .fold(None, |deepest: Option<&Mount>, m| match deepest {
+            Some(d) if d.mount_point.components().count() > m.mount_point.components().count() =>
+                Some(d),
+            _ => Some(m)
+        })
+}
+
+/// Find the mounts of a device, given its major and minor numbers, like
+/// those in diskstats or the maps of a process.
+pub fn by_device(mounts: &[MountInfo], major: u32, minor: u32) -> Vec<&MountInfo> {
+    mounts.iter()
+        .filter(|m| m.major == major && m.minor == minor)
+        .collect()
+}
+
+/// Split comma separated mount options into a map.
+fn parse_options(options: &str) -> MountOptions {
+    options.split(',')
+        .filter(|o| !o.is_empty())
+        .map(|o| {
+            let mut split = o.splitn(2, '=');
+            (split.next().unwrap_or(o).to_owned(), split.next().map(|v| v.to_owned()))
+        })
+        .collect()
+}
+
+/// Undo the octal escapes (eg: "\040" for space) used in mount paths.
+pub fn unescape(path: &str) -> String {
+    let mut out = String::new();
+    let mut rest = path;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        match rest.get(i + 1..i + 4).and_then(|o| u8::from_str_radix(o, 8).ok()) {
+            Some(c) => {
+                out.push(c as char);
+                rest = &rest[i + 4..];
+            },
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[test]
+fn test_mounts() {
+    let mounts = Mount::parse_string("/dev/sda1 / ext4 rw,relatime,errors=remount-ro 0 0\n\
+server:/export /mnt/my\\040share nfs4 rw,vers=4.2 0 0\n").unwrap();
+    assert_eq!(mounts[0].source, "/dev/sda1");
+    assert_eq!(mounts[0].options.get("relatime"), Some(&None));
+    assert_eq!(mounts[0].options.get("errors"), Some(&Some("remount-ro".to_owned())));
+    assert_eq!(mounts[1].mount_point, Path::new("/mnt/my share"));
+    assert_eq!(containing(&mounts, Path::new("/mnt/my share/file")).unwrap().fstype, "nfs4");
+    assert_eq!(containing(&mounts, Path::new("/home/file")).unwrap().fstype, "ext4");
+    // Path::starts_with compares whole components.
+    assert_eq!(containing(&mounts, Path::new("/mnt/my shared")).unwrap().fstype, "ext4");
+    assert!(Mount::parse_string("/dev/sda1 /\n").is_err());
+}
+
+#[test]
+fn test_mountinfo() {
+    let mounts = MountInfo::parse_string(
+        "36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue\n\
+         23 28 0:22 / /proc rw,nosuid,nodev,noexec,relatime shared:13 - proc proc rw\n\
+         28 1 8:1 / / rw,relatime - ext4 /dev/sda1 rw\n").unwrap();
+    assert_eq!(mounts[0], MountInfo {
+        mount_id: 36,
+        parent_id: 35,
+        major: 98,
+        minor: 0,
+        root: PathBuf::from("/mnt1"),
+        mount_point: PathBuf::from("/mnt2"),
+        mount_options: parse_options("rw,noatime"),
+        optional_fields: vec!["master:1".to_owned()],
+        fstype: "ext3".to_owned(),
+        source: "/dev/root".to_owned(),
+        super_options: parse_options("rw,errors=continue"),
+    });
+    assert!(mounts[2].optional_fields.is_empty());
+    assert_eq!(by_device(&mounts, 8, 1)[0].mount_point, Path::new("/"));
+    assert!(by_device(&mounts, 8, 2).is_empty());
+    assert_eq!(MountInfo::parse_line("28 1 8:1 / / rw,relatime ext4 /dev/sda1 rw"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::PidMountinfo,
+            Some("separator"))));
+}
+
+#[test]
+fn test_filesystems() {
+    let filesystems = Filesystem::parse_string("nodev\tsysfs\nnodev\tproc\n\text4\n\tfuseblk\n\
+nodev\tfuse\n");
+    assert_eq!(filesystems.len(), 5);
+    assert_eq!(filesystems[2], Filesystem { nodev: false, fstype: "ext4".to_owned() });
+    assert_eq!(is_virtual(&filesystems, "proc"), Some(true));
+    assert_eq!(is_virtual(&filesystems, "ext4"), Some(false));
+    assert_eq!(is_virtual(&filesystems, "fuse.sshfs"), Some(true));
+    assert_eq!(is_virtual(&filesystems, "btrfs"), None);
+    let mounts = Mount::parse_string("proc /proc proc rw 0 0\n/dev/sda1 / ext4 rw 0 0\n\
+/dev/sdb1 /data btrfs rw 0 0\n").unwrap();
+    assert!(mounts[0].is_virtual(&filesystems));
+    assert!(!mounts[1].is_virtual(&filesystems));
+    assert!(!mounts[2].is_virtual(&filesystems));
+}
+
+#[test]
+fn test_read() {
+    assert!(!Mount::new().unwrap().is_empty());
+    assert!(!MountInfo::new().unwrap().is_empty());
+    assert!(Filesystem::new().unwrap().iter().any(|f| f.fstype == "proc" && f.nodev));
+}
@@ -0,0 +1,215 @@
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use std::path::Path;
+use std::time::Duration;
+use error::{ProcError, ProcFile, ProcOper};
+use rate::Rate;
+use root;
+
+/// A struct containing the counters of a network interface from a net/dev file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetDev {
+    /// Name of the interface.
+    pub interface: String,
+    /// Bytes received.
+    pub rx_bytes: u64,
+    /// Packets received.
+    pub rx_packets: u64,
+    /// Receive errors.
+    pub rx_errs: u64,
+    /// Received packets dropped.
+    pub rx_drop: u64,
+    /// Receive fifo overruns.
+    pub rx_fifo: u64,
+    /// Receive framing errors.
+    pub rx_frame: u64,
+    /// Compressed packets received.
+    pub rx_compressed: u64,
+    /// Multicast packets received.
+    pub rx_multicast: u64,
+    /// Bytes transmitted.
+    pub tx_bytes: u64,
+    /// Packets transmitted.
+    pub tx_packets: u64,
+    /// Transmit errors.
+    pub tx_errs: u64,
+    /// Transmitted packets dropped.
+    pub tx_drop: u64,
+    /// Transmit fifo overruns.
+    pub tx_fifo: u64,
+    /// Collisions.
+    pub tx_colls: u64,
+    /// Carrier losses.
+    pub tx_carrier: u64,
+    /// Compressed packets transmitted.
+    pub tx_compressed: u64,
+}
+
+impl NetDev {
+    /// Read the counters of every interface in the caller's network namespace
+    /// from /proc/net/dev.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Read the net/dev file inside a directory, being /proc (or /proc/[pid]
+    /// for the network namespace of a process).
+    pub fn new_dir(dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let dev_file = try!(
+            File::open(dir.join("net/dev"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcNetDev, e))
+        );
+        let lines =
+            BufReader::with_capacity(4096, dev_file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcNetDev, e))
+                );
+        Self::parse_string(lines)
+    }
+
+    /// Parse an Iterator of lines as a net/dev file.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I)
+        -> Result<Vec<Self>, ProcError> {
+        let mut devs = Vec::new();
+        // The first two lines are headers.
+        for line in lines.skip(2) {
+            let line = try!(line);
+            let mut split = line.splitn(2, ':');
+            let interface = split.next().unwrap_or("").trim();
+            let counters = try!(
+                split.next()
+                    .ok_or(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetDev,
+                        Some("missing colon")))
+                    .and_then(|c|
+                        c.split_whitespace()
+                            .map(|n| n.parse::<u64>())
+                            .collect::<Result<Vec<_>, _>>()
+                            .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcNetDev,
+                                Some(e), Some("parsing counter")))
+                    )
+            );
+            if counters.len() < 16 {
+                return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetDev,
+                    Some("missing counters")));
+            }
+            devs.push(NetDev {
+                interface: interface.to_owned(),
+                rx_bytes: counters[0],
+                rx_packets: counters[1],
+                rx_errs: counters[2],
+                rx_drop: counters[3],
+                rx_fifo: counters[4],
+                rx_frame: counters[5],
+                rx_compressed: counters[6],
+                rx_multicast: counters[7],
+                tx_bytes: counters[8],
+                tx_packets: counters[9],
+                tx_errs: counters[10],
+                tx_drop: counters[11],
+                tx_fifo: counters[12],
+                tx_colls: counters[13],
+                tx_carrier: counters[14],
+                tx_compressed: counters[15],
+            });
+        }
+        Ok(devs)
+    }
+}
+
+/// The rate of traffic on an interface between two samples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetRate {
+    /// Name of the interface.
+    pub interface: String,
+    /// Bytes received per second.
+    pub rx_bytes_per_sec: f64,
+    /// Bytes transmitted per second.
+    pub tx_bytes_per_sec: f64,
+    /// Packets received per second.
+    pub rx_packets_per_sec: f64,
+    /// Packets transmitted per second.
+    pub tx_packets_per_sec: f64,
+}
+
+/// Calculate the rates of each interface present in both samples, taken
+/// elapsed apart.
+pub fn rates(last: &[NetDev], current: &[NetDev], elapsed: Duration) -> Vec<NetRate> {
+    // Counters restart from zero when an interface is recreated with the same name.
+    let per_sec = |now: u64, then: u64| Rate::from_delta(now.saturating_sub(then), elapsed).per_sec();
+    current.iter().filter_map(|cur|
+        last.iter().find(|l| l.interface == cur.interface).map(|l|
+            NetRate {
+                interface: cur.interface.clone(),
+                rx_bytes_per_sec: per_sec(cur.rx_bytes, l.rx_bytes),
+                tx_bytes_per_sec: per_sec(cur.tx_bytes, l.tx_bytes),
+                rx_packets_per_sec: per_sec(cur.rx_packets, l.rx_packets),
+                tx_packets_per_sec: per_sec(cur.tx_packets, l.tx_packets),
+            }
+        )
+    ).collect()
+}
+
+#[test]
+fn test_parsing() {
+    let lines = "Inter-|   Receive                                                |  Transmit\n \
+face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed\n    \
+lo: 23777986    2257    0    0    0     0          0         0 23777986    2257    0    0    0     0       0          0\n  \
+eth0:  772655     120    1    2    3     4          5         6    13434     111    7    8    9    10      11         12\n"
+        .lines().map(|l| Ok(l.to_owned()));
+    let devs = NetDev::parse_string(lines).unwrap();
+    assert_eq!(devs.len(), 2);
+    assert_eq!(devs[0].interface, "lo");
+    assert_eq!(devs[1], NetDev {
+        interface: "eth0".to_owned(),
+        rx_bytes: 772655,
+        rx_packets: 120,
+        rx_errs: 1,
+        rx_drop: 2,
+        rx_fifo: 3,
+        rx_frame: 4,
+        rx_compressed: 5,
+        rx_multicast: 6,
+        tx_bytes: 13434,
+        tx_packets: 111,
+        tx_errs: 7,
+        tx_drop: 8,
+        tx_fifo: 9,
+        tx_colls: 10,
+        tx_carrier: 11,
+        tx_compressed: 12,
+    });
+}
+
+#[test]
+fn test_missing_counters() {
+    let lines = "a\nb\n  eth0: 1 2 3\n".lines().map(|l| Ok(l.to_owned()));
+    assert_eq!(NetDev::parse_string(lines),
+        Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetDev, Some("missing counters"))));
+}
+
+#[test]
+fn test_rates() {
+    let dev = |name: &str, rx: u64, tx: u64| NetDev {
+        interface: name.to_owned(),
+        rx_bytes: rx, rx_packets: rx / 100, rx_errs: 0, rx_drop: 0, rx_fifo: 0,
+        rx_frame: 0, rx_compressed: 0, rx_multicast: 0,
+        tx_bytes: tx, tx_packets: tx / 100, tx_errs: 0, tx_drop: 0, tx_fifo: 0,
+        tx_colls: 0, tx_carrier: 0, tx_compressed: 0,
+    };
+    let last = vec![dev("eth0", 1000, 2000), dev("gone0", 0, 0)];
+    let current = vec![dev("eth0", 3000, 2500), dev("new0", 10, 10)];
+    let rates = rates(&last, &current, Duration::from_secs(2));
+    assert_eq!(rates, vec![NetRate {
+        interface: "eth0".to_owned(),
+        rx_bytes_per_sec: 1000.0,
+        tx_bytes_per_sec: 250.0,
+        rx_packets_per_sec: 10.0,
+        tx_packets_per_sec: 2.5,
+    }]);
+}
+
+#[test]
+fn test_read() {
+    assert!(NetDev::new().unwrap().iter().any(|d| d.interface == "lo"));
+}
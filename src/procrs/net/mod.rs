@@ -0,0 +1,12 @@
+/// Get network interface counters (/proc/net/dev)
+pub mod dev;
+/// Get socket tables (/proc/net/tcp, tcp6, udp and udp6)
+pub mod socket;
+/// Get the unix domain socket table (/proc/net/unix)
+pub mod unix;
+/// Get protocol counters (/proc/net/snmp and /proc/net/netstat)
+pub mod snmp;
+/// Get socket totals (/proc/net/sockstat and sockstat6)
+pub mod sockstat;
+/// Find the processes holding sockets open, like ss -p
+pub mod resolve;
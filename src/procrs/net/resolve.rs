@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::path::Path;
+use error::ProcError;
+use net::socket::NetSocket;
+use net::unix::UnixSocket;
+use pid::fd::{self, PidFd};
+use TaskId;
+use root;
+
+/// A process holding a socket open, and the fd it is open as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOwner {
+    /// The pid of the process.
+    pub pid: TaskId,
+    /// The file descriptor of the socket in the process.
+    pub fd: i32,
+}
+
+/// A socket with the processes holding it open, like a line of ss -p.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Owned<T> {
+    /// The socket table entry.
+    pub socket: T,
+    /// The processes holding the socket open, sorted by pid. This is empty
+    /// for sockets not attached to an fd (eg: TIME_WAIT), or held by
+    /// processes that couldn't be inspected.
+    pub owners: Vec<SocketOwner>,
+}
+
+/// A map from socket inodes to the processes holding them open, built by
+/// scanning the fds of every process.
+///
+/// Processes that can't be inspected (eg: owned by another user) are
+/// skipped, so run as root for a complete map.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InodeMap {
+    owners: HashMap<u64, Vec<SocketOwner>>,
+}
+
+impl InodeMap {
+    /// Scan the fds of every process in /proc.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Scan the fds of every process in a proc directory.
+    pub fn new_dir(proc_dir: &Path) -> Result<Self, ProcError> {
+        let mut map = InodeMap::default();
+        try!(fd::scan(proc_dir, |pid, _, fds| map.add(pid, &fds)));
+        for owners in map.owners.values_mut() {
+            owners.sort_by_key(|o| (o.pid, o.fd));
+        }
+        Ok(map)
+    }
+
+    /// Add the socket fds of a process.
+    fn add(&mut self, pid: TaskId, fds: &[PidFd]) {
+        for fd in fds {
+            if let Some(inode) = fd.socket_inode() {
+                self.owners.entry(inode).or_insert_with(Vec::new)
+                    .push(SocketOwner { pid: pid, fd: fd.fd });
+            }
+        }
+    }
+
+    /// The processes holding a socket inode open.
+    pub fn owners(&self, inode: u64) -> &[SocketOwner] {
+        self.owners.get(&inode).map(|o| &o[..]).unwrap_or(&[])
+    }
+
+    /// The pids holding a socket inode open, without duplicates.
+    pub fn pids(&self, inode: u64) -> Vec<TaskId> {
+        let mut pids: Vec<_> = self.owners(inode).iter().map(|o| o.pid).collect();
+        pids.dedup();
+        pids
+    }
+
+    /// The number of socket inodes held open.
+    pub fn len(&self) -> usize {
+        self.owners.len()
+    }
+
+    /// Whether no sockets are held open.
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+
+    /// Attach the owners to each socket.
+    pub fn join<T, I>(&self, sockets: I) -> Vec<Owned<T>>
+        where I: IntoIterator<Item=T>, T: HasInode {
+        sockets.into_iter()
+            .map(|s| {
+                let owners = self.owners(s.inode()).to_vec();
+                Owned { socket: s, owners: owners }
+            })
+            .collect()
+    }
+}
+
+/// A socket table entry with an inode, which matches socket:[inode] fd links.
+pub trait HasInode {
+    /// The inode of the socket.
+    fn inode(&self) -> u64;
+}
+
+impl HasInode for NetSocket {
+    fn inode(&self) -> u64 {
+        self.inode
+    }
+}
+
+impl HasInode for UnixSocket {
+    fn inode(&self) -> u64 {
+        self.inode
+    }
+}
+
+/// Read every TCP and UDP socket of the network namespace of this process,
+/// with the processes holding them open (ss -tup).
+pub fn sockets() -> Result<Vec<Owned<NetSocket>>, ProcError> {
+    let sockets = try!(NetSocket::new_all());
+    Ok(try!(InodeMap::new()).join(sockets))
+}
+
+/// Read every unix socket of the network namespace of this process, with
+/// the processes holding them open (ss -xp).
+pub fn unix_sockets() -> Result<Vec<Owned<UnixSocket>>, ProcError> {
+    let sockets = try!(UnixSocket::new());
+    Ok(try!(InodeMap::new()).join(sockets))
+}
+
+/// Find the processes holding open the sockets listening on a local port.
+pub fn listening_pids(port: u16) -> Result<Vec<TaskId>, ProcError> {
+    let map = try!(InodeMap::new());
+    let mut pids: Vec<_> = try!(::net::socket::listening_on(port)).iter()
+        .flat_map(|s| map.pids(s.inode))
+        .collect();
+    pids.sort();
+    pids.dedup();
+    Ok(pids)
+}
+
+#[test]
+fn test_map() {
+    use pid::fd::FdTarget;
+    let mut map = InodeMap::default();
+    map.add(10, &[
+        PidFd { fd: 3, target: FdTarget::Socket(500), info: None },
+        PidFd { fd: 4, target: FdTarget::Pipe(501), info: None },
+        PidFd { fd: 5, target: FdTarget::Socket(502), info: None },
+        PidFd { fd: 6, target: FdTarget::Socket(500), info: None },
+    ]);
+    map.add(11, &[PidFd { fd: 0, target: FdTarget::Socket(502), info: None }]);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.owners(500), &[SocketOwner { pid: 10, fd: 3 }, SocketOwner { pid: 10, fd: 6 }]);
+    assert_eq!(map.pids(500), vec![10]);
+    assert_eq!(map.pids(502), vec![10, 11]);
+    assert!(map.owners(501).is_empty());
+}
+
+#[test]
+fn test_resolve() {
+    use std::net::TcpListener;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let me = ::std::process::id() as TaskId;
+    assert_eq!(listening_pids(port).unwrap(), vec![me]);
+    let owned = sockets().unwrap();
+    let mine = owned.iter().find(|o| o.socket.local.port() == port && o.socket.is_listening());
+    assert_eq!(mine.unwrap().owners[0].pid, me);
+}
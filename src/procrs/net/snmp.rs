@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use read_file;
+use root;
+
+/// Counters grouped by table name (eg: "Tcp"), then by counter name (eg: "ActiveOpens").
+pub type Tables = HashMap<String, HashMap<String, i64>>;
+
+/// IP counters, from the Ip table of /proc/net/snmp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpStats {
+    /// Whether this host forwards packets (1) or not (2).
+    pub forwarding: u64,
+    /// The default TTL of outgoing packets.
+    pub default_ttl: u64,
+    pub in_receives: u64,
+    pub in_hdr_errors: u64,
+    pub in_addr_errors: u64,
+    pub forw_datagrams: u64,
+    pub in_unknown_protos: u64,
+    pub in_discards: u64,
+    pub in_delivers: u64,
+    pub out_requests: u64,
+    pub out_discards: u64,
+    pub out_no_routes: u64,
+    pub reasm_fails: u64,
+    pub frag_fails: u64,
+}
+
+/// ICMP counters, from the Icmp table of /proc/net/snmp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IcmpStats {
+    pub in_msgs: u64,
+    pub in_errors: u64,
+    pub in_csum_errors: u64,
+    pub in_dest_unreachs: u64,
+    pub in_time_excds: u64,
+    pub in_echos: u64,
+    pub in_echo_reps: u64,
+    pub out_msgs: u64,
+    pub out_errors: u64,
+    pub out_dest_unreachs: u64,
+    pub out_echos: u64,
+    pub out_echo_reps: u64,
+}
+
+/// TCP counters, from the Tcp table of /proc/net/snmp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TcpStats {
+    pub rto_algorithm: u64,
+    /// Minimum retransmission timeout, in milliseconds.
+    pub rto_min: u64,
+    /// Maximum retransmission timeout, in milliseconds.
+    pub rto_max: u64,
+    /// Maximum number of connections, which is -1 when dynamic.
+    pub max_conn: i64,
+    pub active_opens: u64,
+    pub passive_opens: u64,
+    pub attempt_fails: u64,
+    pub estab_resets: u64,
+    /// Connections currently established. This is a gauge, not a counter.
+    pub curr_estab: u64,
+    pub in_segs: u64,
+    pub out_segs: u64,
+    pub retrans_segs: u64,
+    pub in_errs: u64,
+    pub out_rsts: u64,
+    pub in_csum_errors: u64,
+}
+
+/// UDP counters, from the Udp table of /proc/net/snmp.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UdpStats {
+    pub in_datagrams: u64,
+    pub no_ports: u64,
+    pub in_errors: u64,
+    pub out_datagrams: u64,
+    pub rcvbuf_errors: u64,
+    pub sndbuf_errors: u64,
+    pub in_csum_errors: u64,
+}
+
+/// A struct containing the SNMP MIB counters from /proc/net/snmp.
+///
+/// Counters missing from the running kernel are 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snmp {
+    pub ip: IpStats,
+    pub icmp: IcmpStats,
+    pub tcp: TcpStats,
+    pub udp: UdpStats,
+    /// Every counter by table and name, including the ones above.
+    pub tables: Tables,
+}
+
+impl Snmp {
+    /// Read the counters of the network namespace of this process.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Read the net/snmp file inside a directory, being /proc (or /proc/[pid]
+    /// for the network namespace of a process).
+    pub fn new_dir(dir: &Path) -> Result<Self, ProcError> {
+        let contents = try!(read_file(&dir.join("net/snmp"), ProcFile::ProcNetSnmp));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/net/snmp file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let tables = try!(parse_tables(contents, ProcFile::ProcNetSnmp));
+        let ip = |name: &str| get_u64(&tables, "Ip", name);
+        let icmp = |name: &str| get_u64(&tables, "Icmp", name);
+        let tcp = |name: &str| get_u64(&tables, "Tcp", name);
+        let udp = |name: &str| get_u64(&tables, "Udp", name);
+        Ok(Snmp {
+            ip: IpStats {
+                forwarding: ip("Forwarding"),
+                default_ttl: ip("DefaultTTL"),
+                in_receives: ip("InReceives"),
+                in_hdr_errors: ip("InHdrErrors"),
+                in_addr_errors: ip("InAddrErrors"),
+                forw_datagrams: ip("ForwDatagrams"),
+                in_unknown_protos: ip("InUnknownProtos"),
+                in_discards: ip("InDiscards"),
+                in_delivers: ip("InDelivers"),
+                out_requests: ip("OutRequests"),
+                out_discards: ip("OutDiscards"),
+                out_no_routes: ip("OutNoRoutes"),
+                reasm_fails: ip("ReasmFails"),
+                frag_fails: ip("FragFails"),
+            },
+            icmp: IcmpStats {
+                in_msgs: icmp("InMsgs"),
+                in_errors: icmp("InErrors"),
+                in_csum_errors: icmp("InCsumErrors"),
+                in_dest_unreachs: icmp("InDestUnreachs"),
+                in_time_excds: icmp("InTimeExcds"),
+                in_echos: icmp("InEchos"),
+                in_echo_reps: icmp("InEchoReps"),
+                out_msgs: icmp("OutMsgs"),
+                out_errors: icmp("OutErrors"),
+                out_dest_unreachs: icmp("OutDestUnreachs"),
+                out_echos: icmp("OutEchos"),
+                out_echo_reps: icmp("OutEchoReps"),
+            },
+            tcp: TcpStats {
+                rto_algorithm: tcp("RtoAlgorithm"),
+                rto_min: tcp("RtoMin"),
+                rto_max: tcp("RtoMax"),
+                max_conn: get(&tables, "Tcp", "MaxConn").unwrap_or(0),
+                active_opens: tcp("ActiveOpens"),
+                passive_opens: tcp("PassiveOpens"),
+                attempt_fails: tcp("AttemptFails"),
+                estab_resets: tcp("EstabResets"),
+                curr_estab: tcp("CurrEstab"),
+                in_segs: tcp("InSegs"),
+                out_segs: tcp("OutSegs"),
+                retrans_segs: tcp("RetransSegs"),
+                in_errs: tcp("InErrs"),
+                out_rsts: tcp("OutRsts"),
+                in_csum_errors: tcp("InCsumErrors"),
+            },
+            udp: UdpStats {
+                in_datagrams: udp("InDatagrams"),
+                no_ports: udp("NoPorts"),
+                in_errors: udp("InErrors"),
+                out_datagrams: udp("OutDatagrams"),
+                rcvbuf_errors: udp("RcvbufErrors"),
+                sndbuf_errors: udp("SndbufErrors"),
+                in_csum_errors: udp("InCsumErrors"),
+            },
+            tables: tables,
+        })
+    }
+
+    /// Get a counter by table and name, eg: ("Udp", "IgnoredMulti").
+    pub fn get(&self, table: &str, name: &str) -> Option<i64> {
+        get(&self.tables, table, name)
+    }
+}
+
+/// Extended TCP counters, from the TcpExt table of /proc/net/netstat.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TcpExtStats {
+    pub syncookies_sent: u64,
+    pub syncookies_recv: u64,
+    pub syncookies_failed: u64,
+    /// Times the accept queue of a listening socket overflowed.
+    pub listen_overflows: u64,
+    /// Connections dropped while listening, including overflows.
+    pub listen_drops: u64,
+    pub tcp_timeouts: u64,
+    pub tcp_lost_retransmit: u64,
+    pub tcp_abort_on_timeout: u64,
+    pub tcp_abort_on_memory: u64,
+    pub tcp_backlog_drop: u64,
+    pub tcp_retrans_fail: u64,
+}
+
+/// Extended IP counters, from the IpExt table of /proc/net/netstat.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IpExtStats {
+    pub in_no_routes: u64,
+    pub in_truncated_pkts: u64,
+    pub in_octets: u64,
+    pub out_octets: u64,
+    pub in_mcast_pkts: u64,
+    pub out_mcast_pkts: u64,
+    pub in_bcast_pkts: u64,
+    pub out_bcast_pkts: u64,
+}
+
+/// A struct containing the extended counters from /proc/net/netstat.
+///
+/// Counters missing from the running kernel are 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Netstat {
+    pub tcp_ext: TcpExtStats,
+    pub ip_ext: IpExtStats,
+    /// Every counter by table and name, including the ones above.
+    pub tables: Tables,
+}
+
+impl Netstat {
+    /// Read the counters of the network namespace of this process.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Read the net/netstat file inside a directory, being /proc (or
+    /// /proc/[pid] for the network namespace of a process).
+    pub fn new_dir(dir: &Path) -> Result<Self, ProcError> {
+        let contents = try!(read_file(&dir.join("net/netstat"), ProcFile::ProcNetNetstat));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/net/netstat file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let tables = try!(parse_tables(contents, ProcFile::ProcNetNetstat));
+        let tcp = |name: &str| get_u64(&tables, "TcpExt", name);
+        let ip = |name: &str| get_u64(&tables, "IpExt", name);
+        Ok(Netstat {
+            tcp_ext: TcpExtStats {
+                syncookies_sent: tcp("SyncookiesSent"),
+                syncookies_recv: tcp("SyncookiesRecv"),
+                syncookies_failed: tcp("SyncookiesFailed"),
+                listen_overflows: tcp("ListenOverflows"),
+                listen_drops: tcp("ListenDrops"),
+                tcp_timeouts: tcp("TCPTimeouts"),
+                tcp_lost_retransmit: tcp("TCPLostRetransmit"),
+                tcp_abort_on_timeout: tcp("TCPAbortOnTimeout"),
+                tcp_abort_on_memory: tcp("TCPAbortOnMemory"),
+                tcp_backlog_drop: tcp("TCPBacklogDrop"),
+                tcp_retrans_fail: tcp("TCPRetransFail"),
+            },
+            ip_ext: IpExtStats {
+                in_no_routes: ip("InNoRoutes"),
+                in_truncated_pkts: ip("InTruncatedPkts"),
+                in_octets: ip("InOctets"),
+                out_octets: ip("OutOctets"),
+                in_mcast_pkts: ip("InMcastPkts"),
+                out_mcast_pkts: ip("OutMcastPkts"),
+                in_bcast_pkts: ip("InBcastPkts"),
+                out_bcast_pkts: ip("OutBcastPkts"),
+            },
+            tables: tables,
+        })
+    }
+
+    /// Get a counter by table and name, eg: ("TcpExt", "TCPFastOpenActive").
+    pub fn get(&self, table: &str, name: &str) -> Option<i64> {
+        get(&self.tables, table, name)
+    }
+}
+
+/// Parse the tables of a snmp or netstat file. Each table is a line of
+/// names followed by a line of values, both prefixed by the table name:
+///
+/// Tcp: RtoAlgorithm RtoMin ...
+/// Tcp: 1 200 ...
+fn parse_tables(contents: &str, file: ProcFile) -> Result<Tables, ProcError> {
+    let mut tables = HashMap::new();
+    let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+    while let Some(names) = lines.next() {
+        let values = try!(
+            lines.next()
+                .ok_or(ProcError::new_more(ProcOper::Parsing, file, Some("missing values")))
+        );
+        let mut names = names.split_whitespace();
+        let mut values = values.split_whitespace();
+        let table = match (names.next(), values.next()) {
+            (Some(a), Some(b)) if a == b => a.trim_right_matches(':'),
+            _ => return Err(ProcError::new_more(ProcOper::Parsing, file,
+                Some("mismatched table names")))
+        };
+        let mut counters = HashMap::new();
+        for (name, value) in names.zip(values) {
+            let value = try!(
+                value.parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, file,
+                        Some(e), Some("parsing counter")))
+            );
+            counters.insert(name.to_owned(), value);
+        }
+        tables.insert(table.to_owned(), counters);
+    }
+    Ok(tables)
+}
+
+/// Get a counter from a table, or None if it's missing.
+pub(crate) fn get(tables: &Tables, table: &str, name: &str) -> Option<i64> {
+    tables.get(table).and_then(|t| t.get(name)).cloned()
+}
+
+/// Get a counter from a table, being 0 if it's missing or negative.
+pub(crate) fn get_u64(tables: &Tables, table: &str, name: &str) -> u64 {
+    get(tables, table, name).map(|v| v.max(0) as u64).unwrap_or(0)
+}
+
+#[test]
+fn test_snmp() {
+    let snmp = Snmp::parse_string(
+        "Ip: Forwarding DefaultTTL InReceives InHdrErrors\n\
+         Ip: 1 64 1500 2\n\
+         Icmp: InMsgs InErrors\n\
+         Icmp: 45 1\n\
+         Tcp: RtoAlgorithm RtoMin RtoMax MaxConn ActiveOpens CurrEstab RetransSegs\n\
+         Tcp: 1 200 120000 -1 3000 12 77\n\
+         Udp: InDatagrams NoPorts IgnoredMulti\n\
+         Udp: 900 4 31\n").unwrap();
+    assert_eq!(snmp.ip.forwarding, 1);
+    assert_eq!(snmp.ip.default_ttl, 64);
+    assert_eq!(snmp.ip.in_hdr_errors, 2);
+    assert_eq!(snmp.icmp.in_msgs, 45);
+    assert_eq!(snmp.tcp.max_conn, -1);
+    assert_eq!(snmp.tcp.active_opens, 3000);
+    assert_eq!(snmp.tcp.curr_estab, 12);
+    assert_eq!(snmp.tcp.retrans_segs, 77);
+    assert_eq!(snmp.tcp.in_segs, 0);
+    assert_eq!(snmp.udp.no_ports, 4);
+    assert_eq!(snmp.get("Udp", "IgnoredMulti"), Some(31));
+    assert_eq!(snmp.get("Udp", "Missing"), None);
+}
+
+#[test]
+fn test_netstat() {
+    let netstat = Netstat::parse_string(
+        "TcpExt: SyncookiesSent ListenOverflows ListenDrops TCPTimeouts\n\
+         TcpExt: 0 17 19 250\n\
+         IpExt: InNoRoutes InOctets OutOctets\n\
+         IpExt: 0 123456789 98765\n").unwrap();
+    assert_eq!(netstat.tcp_ext.listen_overflows, 17);
+    assert_eq!(netstat.tcp_ext.listen_drops, 19);
+    assert_eq!(netstat.tcp_ext.tcp_timeouts, 250);
+    assert_eq!(netstat.ip_ext.in_octets, 123456789);
+    assert_eq!(netstat.get("TcpExt", "SyncookiesSent"), Some(0));
+}
+
+#[test]
+fn test_bad_tables() {
+    assert_eq!(Snmp::parse_string("Ip: Forwarding\n"),
+        Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetSnmp,
+            Some("missing values"))));
+    assert_eq!(Snmp::parse_string("Ip: Forwarding\nTcp: 1\n"),
+        Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetSnmp,
+            Some("mismatched table names"))));
+}
+
+#[test]
+fn test_read() {
+    assert!(Snmp::new().unwrap().tables.contains_key("Tcp"));
+    assert!(Netstat::new().unwrap().tables.contains_key("TcpExt"));
+}
@@ -0,0 +1,288 @@
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// The socket tables in a net directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// TCP over IPv4 (net/tcp).
+    Tcp,
+    /// TCP over IPv6 (net/tcp6).
+    Tcp6,
+    /// UDP over IPv4 (net/udp).
+    Udp,
+    /// UDP over IPv6 (net/udp6).
+    Udp6,
+}
+
+impl Protocol {
+    /// All the protocols, in the order their tables are usually read.
+    pub fn all() -> [Protocol; 4] {
+        [Protocol::Tcp, Protocol::Tcp6, Protocol::Udp, Protocol::Udp6]
+    }
+
+    /// The name of the table file in the net directory.
+    pub fn file_name(&self) -> &'static str {
+        match *self {
+            Protocol::Tcp => "tcp",
+            Protocol::Tcp6 => "tcp6",
+            Protocol::Udp => "udp",
+            Protocol::Udp6 => "udp6",
+        }
+    }
+}
+
+/// The state of a socket. UDP sockets use the same values, being either
+/// Established (connected) or Close.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TcpState {
+    Established,
+    SynSent,
+    SynRecv,
+    FinWait1,
+    FinWait2,
+    TimeWait,
+    Close,
+    CloseWait,
+    LastAck,
+    Listen,
+    Closing,
+    NewSynRecv,
+    /// A state this library doesn't know about.
+    Unknown(u8),
+}
+
+impl From<u8> for TcpState {
+    fn from(state: u8) -> Self {
+        match state {
+            0x01 => TcpState::Established,
+            0x02 => TcpState::SynSent,
+            0x03 => TcpState::SynRecv,
+            0x04 => TcpState::FinWait1,
+            0x05 => TcpState::FinWait2,
+            0x06 => TcpState::TimeWait,
+            0x07 => TcpState::Close,
+            0x08 => TcpState::CloseWait,
+            0x09 => TcpState::LastAck,
+            0x0A => TcpState::Listen,
+            0x0B => TcpState::Closing,
+            0x0C => TcpState::NewSynRecv,
+            s => TcpState::Unknown(s)
+        }
+    }
+}
+
+/// A struct containing an entry from a socket table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetSocket {
+    /// The table this entry came from.
+    pub protocol: Protocol,
+    /// Local address and port.
+    pub local: SocketAddr,
+    /// Remote address and port, unspecified when not connected.
+    pub remote: SocketAddr,
+    /// State of the socket.
+    pub state: TcpState,
+    /// Effective uid of the socket owner.
+    pub uid: u32,
+    /// Inode of the socket, which matches socket:[inode] fd links.
+    pub inode: u64,
+}
+
+impl NetSocket {
+    /// Read a socket table of the network namespace of this process.
+    pub fn new(protocol: Protocol) -> Result<Vec<Self>, ProcError> {
+        Self::new_dir(&root::root(), protocol)
+    }
+
+    /// Read every socket table of the network namespace of this process.
+    pub fn new_all() -> Result<Vec<Self>, ProcError> {
+        Self::new_dir_all(&root::root())
+    }
+
+    /// Read a socket table inside a directory, being /proc (or /proc/[pid]
+    /// for the network namespace of a process).
+    pub fn new_dir(dir: &Path, protocol: Protocol) -> Result<Vec<Self>, ProcError> {
+        let socket_file = try!(
+            File::open(dir.join("net").join(protocol.file_name()))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcNetSocket, e))
+        );
+        let lines =
+            BufReader::with_capacity(4096, socket_file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcNetSocket, e))
+                );
+        Self::parse_string(lines, protocol)
+    }
+
+    /// Read every socket table inside a directory.
+    pub fn new_dir_all(dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let mut sockets = Vec::new();
+        for protocol in Protocol::all().iter() {
+            match Self::new_dir(dir, *protocol) {
+                Ok(mut s) => sockets.append(&mut s),
+                // Kernels without IPv6 don't have the tcp6 and udp6 tables.
+                Err(ref e) if !e.is_hard() &&
+                    (*protocol == Protocol::Tcp6 || *protocol == Protocol::Udp6) => {},
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(sockets)
+    }
+
+    /// Whether this socket is waiting for connections (TCP), or bound
+    /// without a remote address (UDP).
+    pub fn is_listening(&self) -> bool {
+        match self.protocol {
+            Protocol::Tcp | Protocol::Tcp6 => self.state == TcpState::Listen,
+            Protocol::Udp | Protocol::Udp6 =>
+                self.state == TcpState::Close && self.remote.port() == 0 &&
+                    self.remote.ip().is_unspecified(),
+        }
+    }
+
+    /// Parse an Iterator of lines as a socket table.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I, protocol: Protocol)
+        -> Result<Vec<Self>, ProcError> {
+        let mut sockets = Vec::new();
+        // The first line is a header.
+        for line in lines.skip(1) {
+            let line = try!(line);
+            sockets.push(try!(Self::parse_line(&line, protocol)));
+        }
+        Ok(sockets)
+    }
+
+    /// Parse a single line of a socket table.
+    fn parse_line(line: &str, protocol: Protocol) -> Result<Self, ProcError> {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetSocket,
+                Some("missing fields")));
+        }
+        let state = try!(
+            u8::from_str_radix(fields[3], 16)
+                .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcNetSocket,
+                    Some(e), Some("st")))
+        );
+        Ok(NetSocket {
+            protocol: protocol,
+            local: try!(parse_addr(fields[1], "local_address")),
+            remote: try!(parse_addr(fields[2], "rem_address")),
+            state: TcpState::from(state),
+            uid: try!(
+                fields[7].parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcNetSocket,
+                        Some(e), Some("uid")))
+            ),
+            inode: try!(
+                fields[9].parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcNetSocket,
+                        Some(e), Some("inode")))
+            ),
+        })
+    }
+}
+
+/// Find the sockets listening on a local port, across every socket table
+/// of the network namespace of this process.
+///
+/// Match the inodes against Pid::owned_sockets() to find the processes, or
+/// use net::resolve::listening_pids().
+pub fn listening_on(port: u16) -> Result<Vec<NetSocket>, ProcError> {
+    Ok(try!(NetSocket::new_all())
+        .into_iter()
+        .filter(|s| s.local.port() == port && s.is_listening())
+        .collect())
+}
+
+/// Parse an address like "0100007F:0035". The address is printed as 32 bit
+/// words in host byte order, so each word is converted back to bytes.
+fn parse_addr(addr: &str, field: &'static str) -> Result<SocketAddr, ProcError> {
+    let err = || ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcNetSocket, Some(field));
+    let mut split = addr.splitn(2, ':');
+    let (ip_hex, port_hex) = match (split.next(), split.next()) {
+        (Some(i), Some(p)) => (i, p),
+        _ => return Err(err())
+    };
+    let port = try!(u16::from_str_radix(port_hex, 16).map_err(|_| err()));
+    let mut bytes = Vec::with_capacity(16);
+    for i in 0..ip_hex.len() / 8 {
+        let word = try!(
+            ip_hex.get(i * 8..i * 8 + 8)
+                .and_then(|w| u32::from_str_radix(w, 16).ok())
+                .ok_or_else(&err)
+        );
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    let ip = match bytes.len() {
+        4 => IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+        16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&bytes);
+            IpAddr::V6(Ipv6Addr::from(octets))
+        },
+        _ => return Err(err())
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+#[test]
+fn test_parsing() {
+    let ipv4 = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n   \
+0: 0100007F:0035 00000000:0000 0A 00000000:00000000 00:00000000 00000000   101        0 16043 1 0000000000000000 100 0 0 10 0\n   \
+1: 0F02000A:D6A4 2A0C1CAC:01BB 01 00000000:00000000 02:0000093A 00000000  1000        0 88211 2 0000000000000000 20 4 30 10 -1\n";
+    let sockets = NetSocket::parse_string(ipv4.lines().map(|l| Ok(l.to_owned())), Protocol::Tcp)
+        .unwrap();
+    assert_eq!(sockets.len(), 2);
+    assert_eq!(sockets[0], NetSocket {
+        protocol: Protocol::Tcp,
+        local: "127.0.0.1:53".parse().unwrap(),
+        remote: "0.0.0.0:0".parse().unwrap(),
+        state: TcpState::Listen,
+        uid: 101,
+        inode: 16043,
+    });
+    assert_eq!(sockets[1].local, "10.0.2.15:54948".parse().unwrap());
+    assert_eq!(sockets[1].remote, "172.28.12.42:443".parse().unwrap());
+    assert_eq!(sockets[1].state, TcpState::Established);
+
+    let ipv6 = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n   \
+0: 00000000000000000000000001000000:0277 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 20301 2 0000000000000000 0\n";
+    let sockets = NetSocket::parse_string(ipv6.lines().map(|l| Ok(l.to_owned())), Protocol::Udp6)
+        .unwrap();
+    assert_eq!(sockets[0].local, "[::1]:631".parse().unwrap());
+    assert_eq!(sockets[0].state, TcpState::Close);
+    assert!(sockets[0].is_listening());
+}
+
+#[test]
+fn test_listening() {
+    let mut socket = NetSocket {
+        protocol: Protocol::Tcp,
+        local: "0.0.0.0:22".parse().unwrap(),
+        remote: "0.0.0.0:0".parse().unwrap(),
+        state: TcpState::Listen,
+        uid: 0,
+        inode: 1,
+    };
+    assert!(socket.is_listening());
+    socket.state = TcpState::Established;
+    assert!(!socket.is_listening());
+    socket.protocol = Protocol::Udp;
+    socket.state = TcpState::Established;
+    socket.remote = "10.0.0.1:53".parse().unwrap();
+    assert!(!socket.is_listening());
+    assert!(NetSocket::new(Protocol::Tcp).is_ok());
+}
+
+#[test]
+fn test_bad_address() {
+    assert_eq!(parse_addr("0100007F", "local_address"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcNetSocket,
+            Some("local_address"))));
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use read_file;
+use net::snmp::{Tables, get, get_u64};
+use root;
+
+/// TCP socket totals, from the TCP line of /proc/net/sockstat.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TcpSockstat {
+    /// Sockets in use, not counting TIME_WAIT.
+    pub inuse: u64,
+    /// Sockets no longer attached to a file descriptor.
+    pub orphan: u64,
+    /// Sockets in TIME_WAIT.
+    pub tw: u64,
+    /// Sockets allocated, including TIME_WAIT and orphans.
+    pub alloc: u64,
+    /// Memory used by socket buffers, in pages.
+    pub mem: u64,
+}
+
+/// UDP socket totals, from the UDP line of /proc/net/sockstat.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UdpSockstat {
+    /// Sockets in use.
+    pub inuse: u64,
+    /// Memory used by socket buffers, in pages.
+    pub mem: u64,
+}
+
+/// A struct containing the IPv4 socket totals from /proc/net/sockstat.
+///
+/// Totals missing from the running kernel are 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sockstat {
+    /// Sockets of every protocol in use.
+    pub sockets_used: u64,
+    pub tcp: TcpSockstat,
+    pub udp: UdpSockstat,
+    pub udplite_inuse: u64,
+    pub raw_inuse: u64,
+    /// IP fragments waiting to be reassembled.
+    pub frag_inuse: u64,
+    /// Memory used by IP fragments waiting to be reassembled, in bytes.
+    pub frag_memory: u64,
+    /// Every total by protocol and name (eg: "TCP", "orphan"), including
+    /// the ones above.
+    pub tables: Tables,
+}
+
+impl Sockstat {
+    /// Read the socket totals of the network namespace of this process.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Read the net/sockstat file inside a directory, being /proc (or
+    /// /proc/[pid] for the network namespace of a process).
+    pub fn new_dir(dir: &Path) -> Result<Self, ProcError> {
+        let contents = try!(read_file(&dir.join("net/sockstat"), ProcFile::ProcNetSockstat));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/net/sockstat file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let tables = try!(parse_lines(contents, ProcFile::ProcNetSockstat));
+        let get = |table: &str, name: &str| get_u64(&tables, table, name);
+        Ok(Sockstat {
+            sockets_used: get("sockets", "used"),
+            tcp: TcpSockstat {
+                inuse: get("TCP", "inuse"),
+                orphan: get("TCP", "orphan"),
+                tw: get("TCP", "tw"),
+                alloc: get("TCP", "alloc"),
+                mem: get("TCP", "mem"),
+            },
+            udp: UdpSockstat {
+                inuse: get("UDP", "inuse"),
+                mem: get("UDP", "mem"),
+            },
+            udplite_inuse: get("UDPLITE", "inuse"),
+            raw_inuse: get("RAW", "inuse"),
+            frag_inuse: get("FRAG", "inuse"),
+            frag_memory: get("FRAG", "memory"),
+            tables: tables,
+        })
+    }
+
+    /// Get a total by protocol and name, eg: ("TCP", "orphan").
+    pub fn get(&self, table: &str, name: &str) -> Option<i64> {
+        get(&self.tables, table, name)
+    }
+}
+
+/// A struct containing the IPv6 socket totals from /proc/net/sockstat6.
+///
+/// Totals missing from the running kernel are 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sockstat6 {
+    pub tcp_inuse: u64,
+    pub udp_inuse: u64,
+    pub udplite_inuse: u64,
+    pub raw_inuse: u64,
+    /// IPv6 fragments waiting to be reassembled.
+    pub frag_inuse: u64,
+    /// Memory used by IPv6 fragments waiting to be reassembled, in bytes.
+    pub frag_memory: u64,
+    /// Every total by protocol and name (eg: "TCP6", "inuse"), including
+    /// the ones above.
+    pub tables: Tables,
+}
+
+impl Sockstat6 {
+    /// Read the IPv6 socket totals of the network namespace of this process.
+    pub fn new() -> Result<Self, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Read the net/sockstat6 file inside a directory, being /proc (or
+    /// /proc/[pid] for the network namespace of a process).
+    pub fn new_dir(dir: &Path) -> Result<Self, ProcError> {
+        let contents = try!(read_file(&dir.join("net/sockstat6"), ProcFile::ProcNetSockstat6));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/net/sockstat6 file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let tables = try!(parse_lines(contents, ProcFile::ProcNetSockstat6));
+        let get = |table: &str, name: &str| get_u64(&tables, table, name);
+        Ok(Sockstat6 {
+            tcp_inuse: get("TCP6", "inuse"),
+            udp_inuse: get("UDP6", "inuse"),
+            udplite_inuse: get("UDPLITE6", "inuse"),
+            raw_inuse: get("RAW6", "inuse"),
+            frag_inuse: get("FRAG6", "inuse"),
+            frag_memory: get("FRAG6", "memory"),
+            tables: tables,
+        })
+    }
+
+    /// Get a total by protocol and name, eg: ("UDP6", "inuse").
+    pub fn get(&self, table: &str, name: &str) -> Option<i64> {
+        get(&self.tables, table, name)
+    }
+}
+
+/// Parse the lines of a sockstat file. Each line is a protocol followed by
+/// pairs of names and values:
+///
+/// TCP: inuse 10 orphan 0 tw 0 alloc 10 mem 0
+fn parse_lines(contents: &str, file: ProcFile) -> Result<Tables, ProcError> {
+    let mut tables = HashMap::new();
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        let mut split = line.split_whitespace();
+        let table = match split.next() {
+            Some(table) if table.ends_with(':') => table.trim_right_matches(':'),
+            _ => return Err(ProcError::new_more(ProcOper::Parsing, file,
+                Some("missing protocol")))
+        };
+        let mut counters = HashMap::new();
+        while let Some(name) = split.next() {
+            let value = try!(
+                split.next()
+                    .ok_or(ProcError::new_more(ProcOper::ParsingField, file,
+                        Some("missing value")))
+                    .and_then(|v| v.parse()
+                        .map_err(|e| ProcError::new(ProcOper::ParsingField, file,
+                            Some(e), Some("parsing total"))))
+            );
+            counters.insert(name.to_owned(), value);
+        }
+        tables.insert(table.to_owned(), counters);
+    }
+    Ok(tables)
+}
+
+#[test]
+fn test_sockstat() {
+    let sockstat = Sockstat::parse_string(
+        "sockets: used 245\n\
+         TCP: inuse 12 orphan 1 tw 7 alloc 20 mem 3\n\
+         UDP: inuse 4 mem 2\n\
+         UDPLITE: inuse 0\n\
+         RAW: inuse 1\n\
+         FRAG: inuse 0 memory 0\n").unwrap();
+    assert_eq!(sockstat.sockets_used, 245);
+    assert_eq!(sockstat.tcp, TcpSockstat {
+        inuse: 12,
+        orphan: 1,
+        tw: 7,
+        alloc: 20,
+        mem: 3,
+    });
+    assert_eq!(sockstat.udp, UdpSockstat { inuse: 4, mem: 2 });
+    assert_eq!(sockstat.raw_inuse, 1);
+    assert_eq!(sockstat.get("TCP", "orphan"), Some(1));
+    assert_eq!(sockstat.get("SCTP", "inuse"), None);
+}
+
+#[test]
+fn test_sockstat6() {
+    let sockstat = Sockstat6::parse_string(
+        "TCP6: inuse 3\n\
+         UDP6: inuse 2\n\
+         UDPLITE6: inuse 0\n\
+         RAW6: inuse 1\n\
+         FRAG6: inuse 0 memory 0\n").unwrap();
+    assert_eq!(sockstat.tcp_inuse, 3);
+    assert_eq!(sockstat.udp_inuse, 2);
+    assert_eq!(sockstat.raw_inuse, 1);
+}
+
+#[test]
+fn test_bad_lines() {
+    assert_eq!(Sockstat::parse_string("TCP inuse 1\n"),
+        Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetSockstat,
+            Some("missing protocol"))));
+    assert_eq!(Sockstat::parse_string("TCP: inuse\n"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcNetSockstat,
+            Some("missing value"))));
+}
+
+#[test]
+fn test_read() {
+    assert!(Sockstat::new().unwrap().tables.contains_key("TCP"));
+    if Path::new("/proc/net/sockstat6").exists() {
+        assert!(Sockstat6::new().is_ok());
+    }
+}
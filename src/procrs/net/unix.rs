@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// The flag set on sockets that are accepting connections (__SO_ACCEPTCON).
+const ACCEPTING_CONNECTIONS: u32 = 1 << 16;
+
+/// The type of a unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixType {
+    Stream,
+    Dgram,
+    SeqPacket,
+    /// A type this library doesn't know about.
+    Unknown(u16),
+}
+
+impl From<u16> for UnixType {
+    fn from(socket_type: u16) -> Self {
+        match socket_type {
+            1 => UnixType::Stream,
+            2 => UnixType::Dgram,
+            5 => UnixType::SeqPacket,
+            t => UnixType::Unknown(t)
+        }
+    }
+}
+
+/// The state of a unix socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnixState {
+    Free,
+    Unconnected,
+    Connecting,
+    Connected,
+    Disconnecting,
+    /// A state this library doesn't know about.
+    Unknown(u8),
+}
+
+impl From<u8> for UnixState {
+    fn from(state: u8) -> Self {
+        match state {
+            0 => UnixState::Free,
+            1 => UnixState::Unconnected,
+            2 => UnixState::Connecting,
+            3 => UnixState::Connected,
+            4 => UnixState::Disconnecting,
+            s => UnixState::Unknown(s)
+        }
+    }
+}
+
+/// A struct containing an entry from the unix socket table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnixSocket {
+    /// The number of references to the socket.
+    pub ref_count: u32,
+    /// The socket flags.
+    pub flags: u32,
+    /// The type of the socket.
+    pub socket_type: UnixType,
+    /// The state of the socket.
+    pub state: UnixState,
+    /// Inode of the socket, which matches socket:[inode] fd links.
+    pub inode: u64,
+    /// The path the socket is bound to, if any. Abstract sockets start with '@'.
+    pub path: Option<String>,
+}
+
+impl UnixSocket {
+    /// Read the unix socket table of the network namespace of this process.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        Self::new_dir(&root::root())
+    }
+
+    /// Read the unix socket table inside a directory, being /proc (or
+    /// /proc/[pid] for the network namespace of a process).
+    pub fn new_dir(dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let unix_file = try!(
+            File::open(dir.join("net/unix"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcNetUnix, e))
+        );
+        let lines =
+            BufReader::with_capacity(4096, unix_file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcNetUnix, e))
+                );
+        Self::parse_string(lines)
+    }
+
+    /// Whether this socket is accepting connections.
+    pub fn is_listening(&self) -> bool {
+        self.flags & ACCEPTING_CONNECTIONS != 0
+    }
+
+    /// Parse an Iterator of lines as a unix socket table.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I)
+        -> Result<Vec<Self>, ProcError> {
+        let mut sockets = Vec::new();
+        // The first line is a header.
+        for line in lines.skip(1) {
+            let line = try!(line);
+            sockets.push(try!(Self::parse_line(&line)));
+        }
+        Ok(sockets)
+    }
+
+    /// Parse a single line of the unix socket table, like
+    /// "0000000000000000: 00000002 00000000 00010000 0001 01 20157 /run/foo.sock".
+    fn parse_line(line: &str) -> Result<Self, ProcError> {
+        // The path is everything after the inode, and may contain spaces.
+        let mut fields = Vec::with_capacity(7);
+        let mut rest = line;
+        for _ in 0..7 {
+            rest = rest.trim_left();
+            let end = rest.find(' ').unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        if fields[6].is_empty() {
+            return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcNetUnix,
+                Some("missing fields")));
+        }
+        let hex = |i: usize, name: &'static str| u32::from_str_radix(fields[i], 16)
+            .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcNetUnix,
+                Some(e), Some(name)));
+        let path = rest.trim();
+        Ok(UnixSocket {
+            ref_count: try!(hex(1, "RefCount")),
+            flags: try!(hex(3, "Flags")),
+            socket_type: UnixType::from(try!(hex(4, "Type")) as u16),
+            state: UnixState::from(try!(hex(5, "St")) as u8),
+            inode: try!(
+                fields[6].parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcNetUnix,
+                        Some(e), Some("Inode")))
+            ),
+            path: match path.is_empty() {
+                true => None,
+                false => Some(path.to_owned())
+            },
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let unix = "Num       RefCount Protocol Flags    Type St Inode Path\n\
+0000000000000000: 00000002 00000000 00010000 0001 01 20157 /run/systemd/private\n\
+0000000000000000: 00000003 00000000 00000000 0001 03 21340\n\
+0000000000000000: 00000002 00000000 00000000 0002 01  9988 @/tmp/.X11 unix/X0\n";
+    let sockets = UnixSocket::parse_string(unix.lines().map(|l| Ok(l.to_owned()))).unwrap();
+    assert_eq!(sockets.len(), 3);
+    assert_eq!(sockets[0], UnixSocket {
+        ref_count: 2,
+        flags: 0x10000,
+        socket_type: UnixType::Stream,
+        state: UnixState::Unconnected,
+        inode: 20157,
+        path: Some("/run/systemd/private".to_owned()),
+    });
+    assert!(sockets[0].is_listening());
+    assert_eq!(sockets[1].state, UnixState::Connected);
+    assert_eq!(sockets[1].path, None);
+    assert!(!sockets[1].is_listening());
+    assert_eq!(sockets[2].socket_type, UnixType::Dgram);
+    assert_eq!(sockets[2].inode, 9988);
+    assert_eq!(sockets[2].path, Some("@/tmp/.X11 unix/X0".to_owned()));
+}
+
+#[test]
+fn test_missing_fields() {
+    assert!(UnixSocket::parse_line("0000000000000000: 00000002 00000000").is_err());
+    assert!(UnixSocket::new().is_ok());
+}
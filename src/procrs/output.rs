@@ -0,0 +1,320 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+use serde_json::{Map, Number, Value};
+use schema::SCHEMA_VERSION;
+
+/// Somewhere to render tabular output, so binaries can support several
+/// formats without changing their main loop.
+///
+/// Output is a sequence of tables and records, and finish() must be called
+/// once everything has been written.
+pub trait OutputSink {
+    /// Start a new table with these column titles. Rows written after this
+    /// belong to it.
+    fn write_table(&mut self, titles: &[&str]) -> io::Result<()>;
+    /// Write a row of the current table, with a value for each title.
+    fn write_row(&mut self, row: &[String]) -> io::Result<()>;
+    /// Write a standalone record between tables, like a group heading.
+    fn write_record(&mut self, record: &[(&str, String)]) -> io::Result<()>;
+    /// Write anything still buffered.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// The output formats with a built in OutputSink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Aligned columns for people.
+    Table,
+    /// One JSON object per row or record (JSON lines).
+    Json,
+    /// Comma separated values, with a header line for each table.
+    Csv,
+}
+
+impl OutputFormat {
+    /// Create a sink for this format writing to out.
+    pub fn sink<'a, W: Write + 'a>(&self, out: W) -> Box<dyn OutputSink + 'a> {
+        match *self {
+            OutputFormat::Table => Box::new(TableSink::new(out)),
+            OutputFormat::Json => Box::new(JsonSink::new(out)),
+            OutputFormat::Csv => Box::new(CsvSink::new(out)),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &*s.to_lowercase() {
+            "table" => OutputFormat::Table,
+            "json" => OutputFormat::Json,
+            "csv" => OutputFormat::Csv,
+            _ => return Err(format!("Invalid output format '{}'", s)),
+        })
+    }
+}
+
+/// Renders tables as aligned columns. Each table is buffered until the
+/// next table, record or finish(), since column widths depend on every row.
+///
+/// This replaces prettytable 0.6, which crashed printing any table with
+/// current compilers (it transmutes between structs of different layout).
+pub struct TableSink<W: Write> {
+    out: W,
+    titles: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    padding: (usize, usize),
+    right_align_from: Option<usize>,
+}
+
+impl<W: Write> TableSink<W> {
+    /// Create a sink with columns separated by a space.
+    pub fn new(out: W) -> Self {
+        TableSink {
+            out: out,
+            titles: None,
+            rows: Vec::new(),
+            padding: (1, 1),
+            right_align_from: None,
+        }
+    }
+
+    /// Set the padding on the left and right of each cell.
+    pub fn padding(mut self, left: usize, right: usize) -> Self {
+        self.padding = (left, right);
+        self
+    }
+
+    /// Right align every column from this one onwards, like numbers in free.
+    pub fn right_align_from(mut self, column: usize) -> Self {
+        self.right_align_from = Some(column);
+        self
+    }
+
+    fn flush_table(&mut self) -> io::Result<()> {
+        let rows: Vec<Vec<String>> = self.titles.take().into_iter()
+            .chain(self.rows.drain(..))
+            .collect();
+        let mut widths: Vec<usize> = Vec::new();
+        for row in &rows {
+            for (i, cell) in row.iter().enumerate() {
+                let len = cell.chars().count();
+                match widths.get_mut(i) {
+                    Some(width) => *width = (*width).max(len),
+                    None => widths.push(len),
+                }
+            }
+        }
+        let (left, right) = self.padding;
+        for row in &rows {
+            let mut line = String::new();
+            for (i, &width) in widths.iter().enumerate() {
+                let cell = row.get(i).map(|c| &**c).unwrap_or("");
+                let fill = width - cell.chars().count();
+                let right_aligned = self.right_align_from.map(|from| i >= from).unwrap_or(false);
+                if i > 0 {
+                    line.push(' ');
+                }
+                line.extend(::std::iter::repeat(' ').take(left));
+                if right_aligned {
+                    line.extend(::std::iter::repeat(' ').take(fill));
+                    line.push_str(cell);
+                } else {
+                    line.push_str(cell);
+                    line.extend(::std::iter::repeat(' ').take(fill));
+                }
+                line.extend(::std::iter::repeat(' ').take(right));
+            }
+            try!(writeln!(self.out, "{}", line.trim_right()));
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> OutputSink for TableSink<W> {
+    fn write_table(&mut self, titles: &[&str]) -> io::Result<()> {
+        try!(self.flush_table());
+        self.titles = Some(titles.iter().map(|t| t.to_string()).collect());
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[String]) -> io::Result<()> {
+        self.rows.push(row.to_vec());
+        Ok(())
+    }
+
+    fn write_record(&mut self, record: &[(&str, String)]) -> io::Result<()> {
+        try!(self.flush_table());
+        let fields: Vec<_> = record.iter().map(|&(k, ref v)| format!("{}={}", k, v)).collect();
+        writeln!(self.out, "{}", fields.join(" "))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        try!(self.flush_table());
+        self.out.flush()
+    }
+}
+
+/// Renders each row and record as a JSON object on its own line, with rows
+/// keyed by the lowercased column titles.
+///
+/// Every object has a schema_version (see schema::check). Values that are
+/// plain numbers are written as JSON numbers, and the rest as strings.
+pub struct JsonSink<W: Write> {
+    out: W,
+    keys: Vec<String>,
+}
+
+impl<W: Write> JsonSink<W> {
+    /// Create a JSON lines sink.
+    pub fn new(out: W) -> Self {
+        JsonSink {
+            out: out,
+            keys: Vec::new(),
+        }
+    }
+
+    fn write_object<'a, I: Iterator<Item=(&'a str, &'a String)>>(&mut self, fields: I)
+        -> io::Result<()> {
+        let mut object: Map<String, Value> = fields
+            .map(|(k, v)| (k.to_owned(), json_value(v)))
+            .collect();
+        object.insert("schema_version".to_owned(), Value::from(SCHEMA_VERSION));
+        writeln!(self.out, "{}", Value::Object(object))
+    }
+}
+
+/// Convert a value to a JSON number if it's written like one, so it reads
+/// back the same (eg: "12" and "0.5", but not "007" or "1e3").
+fn json_value(value: &str) -> Value {
+    let number = value.parse::<u64>().ok().map(Number::from)
+        .or_else(|| value.parse::<i64>().ok().map(Number::from))
+        .or_else(|| value.parse::<f64>().ok().and_then(Number::from_f64));
+    match number {
+        Some(ref n) if n.to_string() == value => Value::Number(n.clone()),
+        _ => Value::String(value.to_owned()),
+    }
+}
+
+impl<W: Write> OutputSink for JsonSink<W> {
+    fn write_table(&mut self, titles: &[&str]) -> io::Result<()> {
+        self.keys = titles.iter().map(|t| t.to_lowercase()).collect();
+        Ok(())
+    }
+
+    fn write_row(&mut self, row: &[String]) -> io::Result<()> {
+        let keys = self.keys.clone();
+        self.write_object(keys.iter().map(|k| &**k).zip(row.iter()))
+    }
+
+    fn write_record(&mut self, record: &[(&str, String)]) -> io::Result<()> {
+        self.write_object(record.iter().map(|&(k, ref v)| (k, v)))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Renders tables as comma separated values. Records are written as a
+/// table with a single row.
+pub struct CsvSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CsvSink<W> {
+    /// Create a CSV sink.
+    pub fn new(out: W) -> Self {
+        CsvSink {
+            out: out,
+        }
+    }
+
+    fn write_line<S: AsRef<str>>(&mut self, fields: &[S]) -> io::Result<()> {
+        let fields: Vec<_> = fields.iter().map(|f| csv_escape(f.as_ref())).collect();
+        writeln!(self.out, "{}", fields.join(","))
+    }
+}
+
+impl<W: Write> OutputSink for CsvSink<W> {
+    fn write_table(&mut self, titles: &[&str]) -> io::Result<()> {
+        self.write_line(titles)
+    }
+
+    fn write_row(&mut self, row: &[String]) -> io::Result<()> {
+        self.write_line(row)
+    }
+
+    fn write_record(&mut self, record: &[(&str, String)]) -> io::Result<()> {
+        let keys: Vec<_> = record.iter().map(|&(k, _)| k).collect();
+        let values: Vec<_> = record.iter().map(|&(_, ref v)| &**v).collect();
+        try!(self.write_line(&keys));
+        self.write_line(&values)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Quote a CSV field if it contains a comma, quote or newline.
+fn csv_escape(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+fn render(format: OutputFormat) -> String {
+    let mut out = Vec::new();
+    {
+        let mut sink = format.sink(&mut out);
+        sink.write_record(&[("group", "root".to_owned())]).unwrap();
+        sink.write_table(&["Pid", "Cmd"]).unwrap();
+        sink.write_row(&["1".to_owned(), "init, \"the\" first".to_owned()]).unwrap();
+        sink.finish().unwrap();
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_csv() {
+    assert_eq!(render(OutputFormat::Csv), "group\nroot\nPid,Cmd\n1,\"init, \"\"the\"\" first\"\n");
+}
+
+#[test]
+fn test_json() {
+    assert_eq!(render(OutputFormat::Json),
+        "{\"group\":\"root\",\"schema_version\":1}\n\
+        {\"cmd\":\"init, \\\"the\\\" first\",\"pid\":1,\"schema_version\":1}\n");
+    assert_eq!(json_value("12"), json!(12));
+    assert_eq!(json_value("-3"), json!(-3));
+    assert_eq!(json_value("0.5"), json!(0.5));
+    assert_eq!(json_value("007"), json!("007"));
+    assert_eq!(json_value("1e3"), json!("1e3"));
+    assert_eq!(json_value("NaN"), json!("NaN"));
+    assert_eq!(json_value("1.2G"), json!("1.2G"));
+}
+
+#[test]
+fn test_table() {
+    assert_eq!(render(OutputFormat::Table),
+        "group=root\n Pid   Cmd\n 1     init, \"the\" first\n");
+    let mut out = Vec::new();
+    {
+        let mut sink = TableSink::new(&mut out).padding(0, 1).right_align_from(1);
+        sink.write_row(&["Mem:".to_owned(), "100".to_owned(), "5".to_owned()]).unwrap();
+        sink.write_row(&["Swap:".to_owned(), "2".to_owned()]).unwrap();
+        sink.finish().unwrap();
+    }
+    assert_eq!(String::from_utf8(out).unwrap(), "Mem:   100  5\nSwap:    2\n");
+}
+
+#[test]
+fn test_format() {
+    assert_eq!("CSV".parse(), Ok(OutputFormat::Csv));
+    assert!("xml".parse::<OutputFormat>().is_err());
+}
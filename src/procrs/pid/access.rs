@@ -0,0 +1,134 @@
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use error::{ProcError, ProcFile, ProcOper};
+use TaskId;
+use super::{Pid, read_value};
+use root;
+
+/// The capability that allows inspecting and tracing any process.
+const CAP_SYS_PTRACE: u64 = 1 << 19;
+
+/// The Yama ptrace_scope setting (/proc/sys/kernel/yama/ptrace_scope),
+/// which restricts attaching to processes with ptrace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PtraceScope {
+    /// Any process with the same uid can be attached to.
+    Classic,
+    /// Only descendants can be attached to, without CAP_SYS_PTRACE.
+    Restricted,
+    /// Only processes with CAP_SYS_PTRACE can attach.
+    AdminOnly,
+    /// Nothing can attach.
+    NoAttach,
+}
+
+/// Read the Yama ptrace_scope setting, or None if Yama isn't enabled.
+pub fn ptrace_scope() -> Option<PtraceScope> {
+    let scope: u8 = match read_value(&root::path("sys/kernel/yama/ptrace_scope"),
+        ProcFile::ProcYamaPtraceScope) {
+        Ok(scope) => scope,
+        Err(_) => return None
+    };
+    Some(match scope {
+        0 => PtraceScope::Classic,
+        1 => PtraceScope::Restricted,
+        2 => PtraceScope::AdminOnly,
+        _ => PtraceScope::NoAttach,
+    })
+}
+
+/// Whether a process has CAP_SYS_PTRACE. Without a capability mask (old
+/// kernels), root is assumed to have it.
+fn has_sys_ptrace(pid: &Pid) -> bool {
+    match pid.status.capeff {
+        Some(caps) => caps & CAP_SYS_PTRACE != 0,
+        None => pid.status.uid.1 == 0
+    }
+}
+
+/// Whether a process is dumpable. Processes that changed credentials (eg:
+/// running a setuid binary) aren't, and the kernel makes their /proc/[pid]
+/// directory owned by root.
+fn is_dumpable(pid: &Pid) -> Result<bool, ProcError> {
+    let meta = try!(
+        fs::metadata(&pid.dir)
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidDir, e))
+    );
+    Ok(meta.uid() != 0 || pid.status.uid.1 == 0)
+}
+
+/// Predict whether caller can read the ptrace protected files of target,
+/// like environ, io, smaps, mem and the fd and exe links.
+///
+/// This follows the kernel's PTRACE_MODE_READ_FSCREDS check: the caller's
+/// filesystem uid and gid must match the real, effective and saved ids of the
+/// target, and the target must be dumpable, unless the caller has
+/// CAP_SYS_PTRACE. Yama doesn't restrict reads, and security modules like
+/// SELinux can still deny access.
+pub fn can_inspect(caller: &Pid, target: &Pid) -> bool {
+    if caller.status.tgid == target.status.tgid || has_sys_ptrace(caller) {
+        return true;
+    }
+    let (fsuid, fsgid) = (caller.status.uid.3, caller.status.gid.3);
+    let (uid, gid) = (target.status.uid, target.status.gid);
+    let ids_match = uid.0 == fsuid && uid.1 == fsuid && uid.2 == fsuid &&
+        gid.0 == fsgid && gid.1 == fsgid && gid.2 == fsgid;
+    // If the dumpable check can't be done the process has likely exited,
+    // which makes any read fail.
+    ids_match && is_dumpable(target).unwrap_or(false)
+}
+
+/// Predict whether caller can attach to target with ptrace, which also
+/// depends on the Yama ptrace_scope setting.
+pub fn can_attach(caller: &Pid, target: &Pid, scope: Option<PtraceScope>) -> bool {
+    if !can_inspect(caller, target) {
+        return false;
+    }
+    match scope {
+        None | Some(PtraceScope::Classic) => true,
+        Some(PtraceScope::Restricted) =>
+            has_sys_ptrace(caller) || is_descendant(target, caller.status.tgid),
+        Some(PtraceScope::AdminOnly) => has_sys_ptrace(caller),
+        Some(PtraceScope::NoAttach) => false,
+    }
+}
+
+/// Whether a process is a descendant of the process with this tgid, by
+/// walking up its parents.
+fn is_descendant(pid: &Pid, ancestor: TaskId) -> bool {
+    pid.ancestors().any(|p| p.pid == ancestor)
+}
+
+#[test]
+fn test_can_inspect() {
+    let myself = Pid::myself().unwrap();
+    assert!(can_inspect(&myself, &myself));
+    let mut caller = Pid::myself().unwrap();
+    caller.status.tgid = -1;
+    caller.status.capeff = Some(0);
+    let mut target = Pid::myself().unwrap();
+    target.status.uid = (1000, 1000, 1000, 1000);
+    target.status.gid = (1000, 1000, 1000, 1000);
+    caller.status.uid = (1000, 1000, 1000, 1000);
+    caller.status.gid = (1000, 1000, 1000, 1000);
+    // The directory is owned by the real euid of this process, so the faked
+    // target only looks dumpable when the tests don't run as root.
+    assert_eq!(can_inspect(&caller, &target), is_dumpable(&target).unwrap());
+    // A setuid target keeps the real uid of the caller, but not the effective.
+    target.status.uid.1 = 0;
+    assert!(!can_inspect(&caller, &target));
+    caller.status.capeff = Some(CAP_SYS_PTRACE);
+    assert!(can_inspect(&caller, &target));
+}
+
+#[test]
+fn test_can_attach() {
+    let mut caller = Pid::myself().unwrap();
+    caller.status.capeff = Some(0);
+    let target = Pid::myself().unwrap();
+    assert!(can_attach(&caller, &target, Some(PtraceScope::Classic)));
+    assert!(!can_attach(&caller, &target, Some(PtraceScope::AdminOnly)));
+    assert!(!can_attach(&caller, &target, Some(PtraceScope::NoAttach)));
+    assert!(is_descendant(&target, target.stat.ppid));
+    assert!(!is_descendant(&target, target.status.tgid));
+}
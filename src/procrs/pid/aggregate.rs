@@ -0,0 +1,127 @@
+use std::str::FromStr;
+use std::collections::{HashMap, BTreeMap};
+use super::Pid;
+use super::cgroup;
+use {TaskId, MemSize};
+#[cfg(test)]
+use testing::FakePid;
+
+/// A field that processes can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// Real user id.
+    User,
+    /// Cgroup path (preferring the unified hierarchy).
+    Cgroup,
+    /// Program name.
+    Comm,
+}
+
+impl GroupBy {
+    /// Get the key of a process for this grouping.
+    pub fn key(&self, pid: &Pid) -> String {
+        match *self {
+            GroupBy::User => pid.status.uid.0.to_string(),
+            GroupBy::Cgroup => pid.cgroups().ok()
+                .and_then(|c| cgroup::primary_path(&c).map(|p| p.to_owned()))
+                .unwrap_or("-".to_owned()),
+            GroupBy::Comm => pid.stat.comm.clone(),
+        }
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &*s.to_lowercase() {
+            "user" | "uid" => GroupBy::User,
+            "cgroup" => GroupBy::Cgroup,
+            "comm" | "cmd" | "name" => GroupBy::Comm,
+            _ => return Err(format!("Invalid group field '{}'", s)),
+        })
+    }
+}
+
+/// A group of processes sharing a key, with aggregate resource usage.
+#[derive(Debug)]
+pub struct Group {
+    /// The key shared by the processes in this group.
+    pub key: String,
+    /// The processes in this group, in their original order.
+    pub pids: Vec<Pid>,
+    /// Total resident set size of the group.
+    pub rss: MemSize,
+    /// Total cpu time (user + system) of the group, in clock ticks.
+    pub cputime: u64,
+}
+
+impl Group {
+    /// Number of processes in this group.
+    pub fn count(&self) -> usize {
+        self.pids.len()
+    }
+}
+
+/// Split processes into groups, in the order each key is first seen.
+pub fn group_by(pids: Vec<Pid>, by: GroupBy) -> Vec<Group> {
+    let mut groups: Vec<Group> = Vec::new();
+    let mut indexes = HashMap::new();
+    for pid in pids {
+        let key = by.key(&pid);
+        let index = *indexes.entry(key.clone()).or_insert_with(|| {
+            groups.push(Group {
+                key: key,
+                pids: Vec::new(),
+                rss: 0,
+                cputime: 0,
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[index];
+        group.rss += pid.status.vmrss.unwrap_or(0);
+        group.cputime += pid.stat.utime + pid.stat.stime;
+        group.pids.push(pid);
+    }
+    groups
+}
+
+/// Count how many threads last ran on each CPU, for each thread group.
+///
+/// This only shows where threads were last scheduled, so a short scan can
+/// miss threads that move between CPUs.
+pub fn cpu_spread(threads: &[Pid]) -> HashMap<TaskId, BTreeMap<i32, usize>> {
+    let mut spread: HashMap<TaskId, BTreeMap<i32, usize>> = HashMap::new();
+    for thread in threads {
+        if let Some(cpu) = thread.stat.processor {
+            *spread.entry(thread.status.tgid).or_insert_with(BTreeMap::new)
+                .entry(cpu).or_insert(0) += 1;
+        }
+    }
+    spread
+}
+
+#[test]
+fn test_cpu_spread() {
+    let worker = FakePid::new(10, 1, "worker").to_pid();
+    let mut threads = vec![worker.clone(), worker.clone(), worker];
+    threads[0].stat.processor = Some(0);
+    threads[1].stat.processor = Some(3);
+    threads[2].stat.processor = Some(3);
+    let tgid = threads[0].status.tgid;
+    let spread = cpu_spread(&threads);
+    assert_eq!(spread.len(), 1);
+    assert_eq!(spread[&tgid].iter().collect::<Vec<_>>(), vec![(&0, &1), (&3, &2)]);
+}
+
+#[test]
+fn test_group_by() {
+    let pids = vec![
+        FakePid { rss: Some(4096), ..FakePid::new(10, 1, "nginx") }.to_pid(),
+        FakePid { rss: Some(8192), ..FakePid::new(11, 1, "nginx") }.to_pid(),
+    ];
+    let groups = group_by(pids, GroupBy::Comm);
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].count(), 2);
+    assert_eq!(groups[0].rss, 12288);
+}
@@ -0,0 +1,72 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+
+/// A struct containing the security attributes (LSM labels) of a process.
+///
+/// Each label is None when no security module provides one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidAttr {
+    /// The current label, eg: "system_u:system_r:sshd_t:s0" for SELinux,
+    /// or "/usr/sbin/cupsd (enforce)" for AppArmor.
+    pub current: Option<String>,
+    /// The label the process will change to on its next execve.
+    pub exec: Option<String>,
+}
+
+impl PidAttr {
+    /// Generate a PidAttr struct given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        Ok(PidAttr {
+            current: try!(read_label(&pid_dir.join("attr/current"), ProcFile::PidAttrCurrent)),
+            exec: try!(read_label(&pid_dir.join("attr/exec"), ProcFile::PidAttrExec)),
+        })
+    }
+}
+
+/// Read the current label of a process, given its process directory.
+pub fn read_current(pid_dir: &Path) -> Result<Option<String>, ProcError> {
+    read_label(&pid_dir.join("attr/current"), ProcFile::PidAttrCurrent)
+}
+
+/// Read a label from an attr file. Without a security module these files
+/// are missing or fail with EINVAL, which isn't an error.
+fn read_label(path: &Path, file: ProcFile) -> Result<Option<String>, ProcError> {
+    let mut contents = String::new();
+    let result = File::open(path)
+        .map_err(|e| ProcError::new_err(ProcOper::Opening, file, e))
+        .and_then(|mut f|
+            f.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, file, e))
+        );
+    match result {
+        Ok(_) => Ok(parse_label(&contents)),
+        Err(ref e) if e.is_unsupported() => Ok(None),
+        Err(e) => Err(e)
+    }
+}
+
+/// Parse the contents of an attr file, which may be nul or newline terminated.
+fn parse_label(label: &str) -> Option<String> {
+    match label.trim_right_matches(|c| c == '\0' || c == '\n') {
+        "" => None,
+        l => Some(l.to_owned())
+    }
+}
+
+#[test]
+fn test_parse_label() {
+    assert_eq!(parse_label(""), None);
+    assert_eq!(parse_label("\0"), None);
+    assert_eq!(parse_label("unconfined_u:unconfined_r:unconfined_t:s0\0"),
+        Some("unconfined_u:unconfined_r:unconfined_t:s0".to_owned()));
+    assert_eq!(parse_label("/usr/sbin/cupsd (enforce)\n"),
+        Some("/usr/sbin/cupsd (enforce)".to_owned()));
+}
+
+#[test]
+fn test_read() {
+    let attr = PidAttr::new(Path::new("/proc/self")).unwrap();
+    assert_eq!(::pid::Pid::myself().unwrap().label().unwrap(), attr.current);
+}
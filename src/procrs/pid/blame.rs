@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use error::ProcError;
+use super::fd::{self, PidFd, FdTarget};
+use super::maps::PidMap;
+use TaskId;
+use root;
+
+/// How a process is using a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Usage {
+    /// Open as this file descriptor.
+    Fd(i32),
+    /// Mapped into memory (eg: a shared library).
+    Mmap,
+    /// The current working directory.
+    Cwd,
+    /// The root directory.
+    Root,
+    /// The executable.
+    Exe,
+}
+
+/// A process using a file, and how it is using it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blame {
+    /// The pid of the process.
+    pub pid: TaskId,
+    /// Each way the process is using the file.
+    pub uses: Vec<Usage>,
+}
+
+/// Find how a process is using a path, given its process directory.
+///
+/// When path is a directory (eg: a mount point), anything underneath
+/// it also counts, so this answers what is keeping it busy.
+pub fn uses(pid_dir: &Path, path: &Path) -> Result<Vec<Usage>, ProcError> {
+    fd_uses(pid_dir, path, &try!(PidFd::new(pid_dir)))
+}
+
+/// Find how a process is using a path, given its process directory and
+/// its already read fds.
+fn fd_uses(pid_dir: &Path, path: &Path, fds: &[PidFd]) -> Result<Vec<Usage>, ProcError> {
+    let mut uses = Vec::new();
+    let matches = |p: &Path| p.starts_with(path);
+    let links = [("cwd", Usage::Cwd), ("root", Usage::Root), ("exe", Usage::Exe)];
+    for &(link, ref usage) in links.iter() {
+        // Kernel threads have no cwd or exe, so missing links are skipped.
+        if let Ok(target) = fs::read_link(pid_dir.join(link)) {
+            // Everything is under the root directory, so only an exact match counts.
+            if target == path || (*usage != Usage::Root && matches(&target)) {
+                uses.push(usage.clone());
+            }
+        }
+    }
+    for fd in fds {
+        if let FdTarget::Path(ref target) = fd.target {
+            if matches(target) {
+                uses.push(Usage::Fd(fd.fd));
+            }
+        }
+    }
+    let mapped = try!(PidMap::new(pid_dir)).iter()
+        .filter_map(|m| m.pathname.as_ref())
+        .any(|p| matches(Path::new(p)));
+    if mapped {
+        uses.push(Usage::Mmap);
+    }
+    Ok(uses)
+}
+
+/// Find every process using a path, like fuser.
+///
+/// Processes that can't be inspected (eg: owned by another user) are skipped,
+/// so run as root for a complete answer.
+pub fn who_has_open(path: &Path) -> Result<Vec<Blame>, ProcError> {
+    // Compare against the path the kernel reports, without symlinks.
+    let path = fs::canonicalize(path).unwrap_or(PathBuf::from(path));
+    let mut blames = Vec::new();
+    try!(fd::scan(&root::root(), |pid, pid_dir, fds|
+        match fd_uses(pid_dir, &path, &fds) {
+            Ok(ref u) if u.is_empty() => {},
+            Ok(u) => blames.push(Blame { pid: pid, uses: u }),
+            Err(_) => {}
+        }
+    ));
+    blames.sort_by_key(|b| b.pid);
+    Ok(blames)
+}
+
+#[test]
+fn test_uses() {
+    let exe = fs::read_link("/proc/self/exe").unwrap();
+    let uses = uses(Path::new("/proc/self"), &exe).unwrap();
+    assert!(uses.contains(&Usage::Exe));
+    assert!(uses.contains(&Usage::Mmap));
+    let cwd = fs::read_link("/proc/self/cwd").unwrap();
+    assert!(who_has_open(&cwd).unwrap().iter()
+        .any(|b| b.uses.contains(&Usage::Cwd)));
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+
+/// A struct containing one line of the cgroup file for a process.
+///
+/// Each line describes the cgroup the process belongs to in one hierarchy.
+/// On the cgroup v2 unified hierarchy the id is 0 and there are no controllers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidCgroup {
+    /// Hierarchy id.
+    pub hierarchy: u32,
+    /// Controllers bound to this hierarchy (eg: "cpu", "name=systemd").
+    pub controllers: Vec<String>,
+    /// Path of the cgroup, relative to the hierarchy mount point.
+    pub path: String,
+}
+
+impl PidCgroup {
+    /// Generate a Vec of PidCgroup structs given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let cgroup_file = try!(
+            File::open(pid_dir.join("cgroup"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidCgroup, e))
+        );
+
+        BufReader::with_capacity(4096, cgroup_file)
+            .lines()
+            .map(|r|
+                r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidCgroup, e))
+                    .and_then(|l| Self::parse_line(&l))
+            ).collect()
+    }
+
+    /// Parse a line of a /proc/[pid]/cgroup file, eg: "4:memory:/user.slice".
+    fn parse_line(line: &str) -> Result<Self, ProcError> {
+        // The path may contain colons, so only split twice.
+        let mut split = line.splitn(3, ':');
+        let hierarchy = try!(
+            split.next().unwrap_or("").parse()
+                .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::PidCgroup,
+                    Some(e), Some("hierarchy")))
+        );
+        let controllers = try!(split.next().ok_or(
+            ProcError::new_more(ProcOper::Parsing, ProcFile::PidCgroup, Some("missing controllers"))
+        ));
+        let path = try!(split.next().ok_or(
+            ProcError::new_more(ProcOper::Parsing, ProcFile::PidCgroup, Some("missing path"))
+        ));
+        Ok(PidCgroup {
+            hierarchy: hierarchy,
+            controllers: controllers.split(',')
+                .filter(|c| !c.is_empty())
+                .map(|c| c.to_owned())
+                .collect(),
+            path: path.to_owned(),
+        })
+    }
+}
+
+/// Get the most useful cgroup path for a process, preferring the
+/// unified (v2) hierarchy.
+pub fn primary_path(cgroups: &[PidCgroup]) -> Option<&str> {
+    cgroups.iter()
+        .find(|c| c.hierarchy == 0 && c.controllers.is_empty())
+        .or(cgroups.first())
+        .map(|c| &*c.path)
+}
+
+#[test]
+fn test_parsing() {
+    assert_eq!(PidCgroup::parse_line("4:memory:/process_api/a:b"), Ok(PidCgroup {
+        hierarchy: 4,
+        controllers: vec!["memory".to_owned()],
+        path: "/process_api/a:b".to_owned(),
+    }));
+    assert_eq!(PidCgroup::parse_line("2:cpu,cpuacct:/"), Ok(PidCgroup {
+        hierarchy: 2,
+        controllers: vec!["cpu".to_owned(), "cpuacct".to_owned()],
+        path: "/".to_owned(),
+    }));
+    assert_eq!(PidCgroup::parse_line("0::/user.slice").unwrap().controllers, Vec::<String>::new());
+    assert_eq!(PidCgroup::parse_line("0:"),
+        Err(ProcError::new_more(ProcOper::Parsing, ProcFile::PidCgroup, Some("missing path"))));
+}
+
+#[test]
+fn test_primary_path() {
+    let cgroups = vec![
+        PidCgroup::parse_line("4:memory:/docker/abc").unwrap(),
+        PidCgroup::parse_line("0::/system.slice").unwrap(),
+    ];
+    assert_eq!(primary_path(&cgroups), Some("/system.slice"));
+    assert_eq!(primary_path(&cgroups[..1]), Some("/docker/abc"));
+    assert_eq!(primary_path(&[]), None);
+}
@@ -0,0 +1,182 @@
+use std::str::FromStr;
+use format;
+use super::Pid;
+
+/// A numeric field of a process that queries can compare, eg: rss>500M.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryField {
+    /// Process (or thread) id.
+    Pid,
+    /// Parent process id.
+    Ppid,
+    /// Real user id.
+    Uid,
+    /// Resident set size in bytes (eg: 500M).
+    Rss,
+    /// Virtual memory size in bytes (eg: 2G).
+    Vsz,
+    /// Average cpu usage over the lifetime of the process, as a percentage
+    /// of one cpu (eg: 10%).
+    Cpu,
+    /// Number of threads.
+    Threads,
+    /// Nice value, from 19 (low priority) to -20 (high priority).
+    Nice,
+    /// Kernel scheduling priority.
+    Priority,
+    /// Time since the process started in seconds (eg: 2h30m).
+    Age,
+}
+
+impl QueryField {
+    /// Whether the value of this field needs the system uptime.
+    pub fn needs_uptime(&self) -> bool {
+        match *self {
+            QueryField::Cpu | QueryField::Age => true,
+            _ => false
+        }
+    }
+
+    /// Get the value of this field for a process, or None if it's unknown
+    /// (eg: kernel threads have no rss). Cpu usage and age need the system
+    /// uptime, which should be read once for a whole scan.
+    pub fn value(&self, pid: &Pid, uptime: Option<f64>) -> Option<f64> {
+        Some(match *self {
+            QueryField::Pid => pid.stat.pid as f64,
+            QueryField::Ppid => pid.stat.ppid as f64,
+            QueryField::Uid => pid.status.uid.0 as f64,
+            QueryField::Rss => match pid.status.vmrss {
+                Some(rss) => rss as f64,
+                None => return None
+            },
+            QueryField::Vsz => pid.stat.vsize as f64,
+            QueryField::Cpu => match uptime {
+                Some(u) => pid.stat.cpu_percent_lifetime(u),
+                None => return None
+            },
+            QueryField::Threads => pid.stat.num_threads as f64,
+            QueryField::Nice => pid.stat.nice as f64,
+            QueryField::Priority => pid.stat.priority as f64,
+            QueryField::Age => match uptime {
+                Some(u) => {
+                    let age = pid.stat.age(u);
+                    age.as_secs() as f64 + age.subsec_nanos() as f64 / 1e9
+                },
+                None => return None
+            },
+        })
+    }
+
+    /// Parse a value for this field, with the units it is written in.
+    pub fn parse_value(&self, value: &str) -> Result<f64, String> {
+        match *self {
+            QueryField::Rss | QueryField::Vsz => format::parse_size(value).map(|s| s as f64),
+            QueryField::Age => format::parse_duration(value)
+                .map(|d| d.as_secs() as f64 + d.subsec_nanos() as f64 / 1e9),
+            QueryField::Cpu => value.trim_right_matches('%').parse()
+                .map_err(|_| format!("Invalid percentage '{}'", value)),
+            _ => value.parse().map_err(|_| format!("Invalid number '{}'", value)),
+        }
+    }
+}
+
+impl FromStr for QueryField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &*s.to_lowercase() {
+            "pid" | "tid" => QueryField::Pid,
+            "ppid" => QueryField::Ppid,
+            "uid" => QueryField::Uid,
+            "rss" => QueryField::Rss,
+            "vsz" | "vsize" => QueryField::Vsz,
+            "cpu" => QueryField::Cpu,
+            "threads" => QueryField::Threads,
+            "nice" => QueryField::Nice,
+            "priority" | "pri" => QueryField::Priority,
+            "age" => QueryField::Age,
+            _ => return Err(format!("Invalid query field '{}'", s)),
+        })
+    }
+}
+
+/// How a field is compared to a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The field is less than the value ('<').
+    Less,
+    /// The field is less than or equal to the value ('<=').
+    LessOrEqual,
+    /// The field is equal to the value ('=', from equality queries like
+    /// "nice=0").
+    Equal,
+    /// The field is greater than or equal to the value ('>=').
+    GreaterOrEqual,
+    /// The field is greater than the value ('>').
+    Greater,
+}
+
+impl Comparison {
+    /// Compare a field value against the query value.
+    pub fn matches(&self, field: f64, value: f64) -> bool {
+        match *self {
+            Comparison::Less => field < value,
+            Comparison::LessOrEqual => field <= value,
+            Comparison::Equal => field == value,
+            Comparison::GreaterOrEqual => field >= value,
+            Comparison::Greater => field > value,
+        }
+    }
+}
+
+/// Parse a comparison like "rss>500M", "threads>=100" or "nice<0", or
+/// return None if the query isn't a comparison (having no '<' or '>', or an
+/// '=' before them like "cmdline=a>b").
+pub fn parse(query: &str) -> Option<Result<(QueryField, Comparison, f64), String>> {
+    let start = match query.find(|c| c == '<' || c == '>' || c == '=') {
+        Some(start) if !query[start..].starts_with('=') => start,
+        _ => return None
+    };
+    let (field, rest) = query.split_at(start);
+    let (comparison, value) = match (rest.starts_with('<'), rest[1..].starts_with('=')) {
+        (true, true) => (Comparison::LessOrEqual, &rest[2..]),
+        (true, false) => (Comparison::Less, &rest[1..]),
+        (false, true) => (Comparison::GreaterOrEqual, &rest[2..]),
+        (false, false) => (Comparison::Greater, &rest[1..]),
+    };
+    Some(field.trim().parse::<QueryField>().and_then(|field|
+        field.parse_value(value.trim()).map(|value| (field, comparison, value))
+    ))
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse("rss>500M"),
+        Some(Ok((QueryField::Rss, Comparison::Greater, 500.0 * 1048576.0))));
+    assert_eq!(parse("threads>=100"),
+        Some(Ok((QueryField::Threads, Comparison::GreaterOrEqual, 100.0))));
+    assert_eq!(parse("nice<0"), Some(Ok((QueryField::Nice, Comparison::Less, 0.0))));
+    assert_eq!(parse("nice<=-5"), Some(Ok((QueryField::Nice, Comparison::LessOrEqual, -5.0))));
+    assert_eq!(parse("cpu>10%"), Some(Ok((QueryField::Cpu, Comparison::Greater, 10.0))));
+    assert_eq!(parse("age>1h"), Some(Ok((QueryField::Age, Comparison::Greater, 3600.0))));
+    assert_eq!(parse("name=sshd"), None);
+    assert_eq!(parse("cmdline=a>b"), None);
+    assert_eq!(parse("colour>3"), Some(Err("Invalid query field 'colour'".to_owned())));
+    assert_eq!(parse("threads>many"), Some(Err("Invalid number 'many'".to_owned())));
+}
+
+#[cfg(test)]
+use uptime::Uptime;
+
+#[test]
+fn test_value() {
+    let myself = Pid::myself().unwrap();
+    let uptime = Uptime::new().unwrap().uptime;
+    assert_eq!(QueryField::Pid.value(&myself, None), Some(myself.stat.pid as f64));
+    assert!(QueryField::Threads.value(&myself, None).unwrap() >= 1.0);
+    assert!(QueryField::Cpu.value(&myself, Some(uptime)).unwrap() >= 0.0);
+    assert!(QueryField::Age.value(&myself, Some(uptime)).unwrap() >= 0.0);
+    assert_eq!(QueryField::Cpu.value(&myself, None), None);
+    assert!(Comparison::GreaterOrEqual.matches(1.0, 1.0));
+    assert!(!Comparison::Less.matches(1.0, 1.0));
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+use super::Pid;
+use super::stat::PidState;
+use rate::Rate;
+use MemSize;
+#[cfg(test)]
+use testing::FakePid;
+
+/// A field that differs between two processes, with the old and new values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The process state.
+    State(PidState, PidState),
+    /// The resident set size in bytes, if known.
+    Rss(Option<MemSize>, Option<MemSize>),
+    /// The number of threads.
+    Threads(u32, u32),
+    /// The user and system cpu time in clock ticks.
+    Cputime(u64, u64),
+    /// The command line.
+    Cmdline(Vec<String>, Vec<String>),
+}
+
+impl Change {
+    /// The name of the changed field.
+    pub fn field(&self) -> &'static str {
+        match *self {
+            Change::State(..) => "state",
+            Change::Rss(..) => "rss",
+            Change::Threads(..) => "threads",
+            Change::Cputime(..) => "cputime",
+            Change::Cmdline(..) => "cmdline",
+        }
+    }
+}
+
+/// The differences between two processes, or the same process read twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidDiff {
+    /// The fields that changed, in a fixed order.
+    pub changes: Vec<Change>,
+}
+
+impl PidDiff {
+    /// Compare two processes, with old being the earlier or reference process.
+    pub fn new(old: &Pid, new: &Pid) -> Self {
+        let mut changes = Vec::new();
+        if old.stat.state != new.stat.state {
+            changes.push(Change::State(old.stat.state.clone(), new.stat.state.clone()));
+        }
+        if old.status.vmrss != new.status.vmrss {
+            changes.push(Change::Rss(old.status.vmrss, new.status.vmrss));
+        }
+        if old.status.threads != new.status.threads {
+            changes.push(Change::Threads(old.status.threads, new.status.threads));
+        }
+        let (old_time, new_time) =
+            (old.stat.utime + old.stat.stime, new.stat.utime + new.stat.stime);
+        if old_time != new_time {
+            changes.push(Change::Cputime(old_time, new_time));
+        }
+        if old.cmdline != new.cmdline {
+            changes.push(Change::Cmdline(old.cmdline.clone(), new.cmdline.clone()));
+        }
+        PidDiff {
+            changes: changes
+        }
+    }
+
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// The rates of a process's counters between two readings of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidRates {
+    /// User and system cpu time in clock ticks.
+    pub cputime: Rate<u64>,
+    /// Minor page faults.
+    pub minflt: Rate<u64>,
+    /// Major page faults.
+    pub majflt: Rate<u64>,
+    /// Voluntary context switches, if status has them.
+    pub voluntary_ctxt_switches: Option<Rate<u64>>,
+    /// Involuntary context switches, if status has them.
+    pub nonvoluntary_ctxt_switches: Option<Rate<u64>>,
+}
+
+impl PidRates {
+    /// Calculate the rates between an old and new reading of a process,
+    /// taken elapsed apart.
+    pub fn new(old: &Pid, new: &Pid, elapsed: Duration) -> Self {
+        let opt_rate = |old: Option<u64>, new: Option<u64>|
+            old.and_then(|o| new.map(|n| Rate::new(o, n, elapsed)));
+        PidRates {
+            cputime: Rate::new(old.stat.utime + old.stat.stime,
+                new.stat.utime + new.stat.stime, elapsed),
+            minflt: Rate::new(old.stat.minflt, new.stat.minflt, elapsed),
+            majflt: Rate::new(old.stat.majflt, new.stat.majflt, elapsed),
+            voluntary_ctxt_switches: opt_rate(old.status.voluntary_ctxt_switches,
+                new.status.voluntary_ctxt_switches),
+            nonvoluntary_ctxt_switches: opt_rate(old.status.nonvoluntary_ctxt_switches,
+                new.status.nonvoluntary_ctxt_switches),
+        }
+    }
+}
+
+#[test]
+fn test_rates() {
+    let old = FakePid::new(10, 1, "sh").to_pid();
+    let mut new = FakePid { utime: 30, stime: 10, ..FakePid::new(10, 1, "sh") }.to_pid();
+    new.stat.minflt = old.stat.minflt + 400;
+    new.status.voluntary_ctxt_switches = None;
+    let rates = PidRates::new(&old, &new, Duration::from_secs(4));
+    assert_eq!(rates.cputime.per_sec(), 10.0);
+    assert_eq!(rates.minflt.per_sec(), 100.0);
+    assert_eq!(rates.voluntary_ctxt_switches, None);
+}
+
+#[test]
+fn test_diff() {
+    let old = FakePid::new(10, 1, "sh").to_pid();
+    let new = FakePid {
+        state: 'Z',
+        utime: 5,
+        num_threads: 2,
+        cmdline: vec!["worker".to_owned()],
+        ..FakePid::new(10, 1, "sh")
+    }.to_pid();
+    let diff = old.diff(&new);
+    assert_eq!(diff.changes.iter().map(|c| c.field()).collect::<Vec<_>>(),
+        vec!["state", "threads", "cputime", "cmdline"]);
+    assert_eq!(diff.changes[1], Change::Threads(old.status.threads, old.status.threads + 1));
+    assert!(old.diff(&old).is_empty());
+}
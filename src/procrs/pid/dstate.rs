@@ -0,0 +1,149 @@
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use read_file;
+use mounts::{self, Mount};
+use super::fd::{PidFd, FdTarget};
+
+/// Filesystem types that are backed by a network, and commonly cause hung tasks.
+const NETWORK_FILESYSTEMS: [&'static str; 11] = [
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ceph", "9p", "afs", "glusterfs", "lustre",
+    "fuse.sshfs"
+];
+
+/// What a process is doing according to /proc/[pid]/syscall.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PidSyscall {
+    /// The process is running on a cpu.
+    Running,
+    /// The process is blocked in the kernel, but not in a system call.
+    Blocked {
+        /// Stack pointer.
+        sp: u64,
+        /// Program counter.
+        pc: u64,
+    },
+    /// The process is blocked in a system call.
+    Syscall {
+        /// The system call number.
+        nr: i64,
+        /// The arguments of the system call.
+        args: [u64; 6],
+        /// Stack pointer.
+        sp: u64,
+        /// Program counter.
+        pc: u64,
+    },
+}
+
+impl PidSyscall {
+    /// Generate a PidSyscall given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        let contents = try!(read_file(&pid_dir.join("syscall"), ProcFile::PidSyscall));
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/[pid]/syscall file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let contents = contents.trim();
+        if contents == "running" {
+            return Ok(PidSyscall::Running);
+        }
+        let err = || ProcError::new_more(ProcOper::Parsing, ProcFile::PidSyscall,
+            Some("parsing value"));
+        let fields: Vec<_> = contents.split_whitespace().collect();
+        let hex = |s: &str| u64::from_str_radix(s.trim_left_matches("0x"), 16).map_err(|_| err());
+        let nr: i64 = try!(fields.get(0).ok_or_else(&err).and_then(|n| n.parse().map_err(|_| err())));
+        match (nr, fields.len()) {
+            (-1, 3) => Ok(PidSyscall::Blocked {
+                sp: try!(hex(fields[1])),
+                pc: try!(hex(fields[2])),
+            }),
+            (_, 9) => {
+                let mut args = [0; 6];
+                for (arg, field) in args.iter_mut().zip(&fields[1..7]) {
+                    *arg = try!(hex(field));
+                }
+                Ok(PidSyscall::Syscall {
+                    nr: nr,
+                    args: args,
+                    sp: try!(hex(fields[7])),
+                    pc: try!(hex(fields[8])),
+                })
+            },
+            _ => Err(ProcError::new_more(ProcOper::Parsing, ProcFile::PidSyscall,
+                Some("unexpected number of fields")))
+        }
+    }
+}
+
+/// The usual information needed to work out why a process is stuck in
+/// uninterruptible sleep (D state).
+///
+/// Parts that can't be read (eg: the kernel stack needs root) are left empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DStateReport {
+    /// The kernel function the process is sleeping in.
+    pub wchan: Option<String>,
+    /// The kernel stack, innermost function first.
+    pub stack: Option<Vec<String>>,
+    /// The system call the process is blocked in.
+    pub syscall: Option<PidSyscall>,
+    /// Open file descriptors on network filesystems (eg: NFS, CIFS), with the filesystem type.
+    pub network_fds: Vec<(PidFd, String)>,
+}
+
+impl DStateReport {
+    /// Gather a report given a process directory and its wchan.
+    pub fn new(pid_dir: &Path, wchan: Option<String>) -> Self {
+        let mounts = Mount::new_dir(pid_dir).unwrap_or(Vec::new());
+        let network_fds = PidFd::new(pid_dir).unwrap_or(Vec::new()).into_iter()
+            .filter_map(|fd| {
+                let fstype = match fd.target {
+                    FdTarget::Path(ref path) =>
+                        mounts::containing(&mounts, path).map(|m| m.fstype.clone()),
+                    _ => None
+                };
+                fstype.filter(|t| NETWORK_FILESYSTEMS.contains(&t.as_str())).map(|t| (fd, t))
+            })
+            .collect();
+        DStateReport {
+            wchan: wchan,
+            stack: read_file(&pid_dir.join("stack"), ProcFile::PidStack)
+                .ok().map(|s| parse_stack(&s)),
+            syscall: PidSyscall::new(pid_dir).ok(),
+            network_fds: network_fds,
+        }
+    }
+}
+
+/// Parse a /proc/[pid]/stack file, eg: "[<0>] nfs_wait_bit_killable+0x1d/0x90 [nfs]",
+/// keeping only the function names.
+fn parse_stack(stack: &str) -> Vec<String> {
+    stack.lines()
+        .filter_map(|l| l.splitn(2, "] ").nth(1))
+        .map(|f| f.split(|c| c == '+' || c == ' ').next().unwrap_or(f).to_owned())
+        .collect()
+}
+
+#[test]
+fn test_parse_syscall() {
+    assert_eq!(PidSyscall::parse_string("running\n"), Ok(PidSyscall::Running));
+    assert_eq!(PidSyscall::parse_string("-1 0x7ffc8f5e9a28 0x7f3b2c4e1b7d\n"),
+        Ok(PidSyscall::Blocked { sp: 0x7ffc8f5e9a28, pc: 0x7f3b2c4e1b7d }));
+    assert_eq!(PidSyscall::parse_string(
+        "0 0x3 0x7fd2a1740000 0x20000 0x7fd2a1773b60 0xffffffff 0x0 0x7ffdbde0da28 0x7fd2a185c2ad\n"),
+        Ok(PidSyscall::Syscall {
+            nr: 0,
+            args: [0x3, 0x7fd2a1740000, 0x20000, 0x7fd2a1773b60, 0xffffffff, 0x0],
+            sp: 0x7ffdbde0da28,
+            pc: 0x7fd2a185c2ad,
+        }));
+    assert!(PidSyscall::parse_string("0 0x3").is_err());
+}
+
+#[test]
+fn test_parse_stack() {
+    assert_eq!(parse_stack("[<0>] nfs_wait_bit_killable+0x1d/0x90 [nfs]\n\
+[<0>] __rpc_execute+0x11e/0x3e0 [sunrpc]\n[<0>] do_syscall_64+0x5b/0x1a0\n"),
+        vec!["nfs_wait_bit_killable", "__rpc_execute", "do_syscall_64"]);
+}
@@ -0,0 +1,125 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use format::glob_match;
+
+/// Patterns of variable names that usually hold secrets.
+pub const DEFAULT_REDACT_PATTERNS: [&'static str; 8] = [
+    "*TOKEN*", "*PASSWORD*", "*PASSWD*", "*SECRET*", "*KEY*", "*CREDENTIAL*", "*AUTH*",
+    "*COOKIE*"
+];
+
+/// The value shown in place of a redacted variable.
+pub const REDACTED: &'static str = "<redacted>";
+
+/// Read the environment a process was started with, given its process
+/// directory, as (name, value) pairs in order.
+///
+/// This needs the same permissions as ptrace, and doesn't reflect changes
+/// the process made after starting. Values are returned as is, use a
+/// Redactor before showing them to anyone.
+pub fn environ(pid_dir: &Path) -> Result<Vec<(String, String)>, ProcError> {
+    let mut contents = Vec::new();
+    try!(
+        File::open(pid_dir.join("environ"))
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidEnviron, e))
+            .and_then(|mut f|
+                f.read_to_end(&mut contents)
+                    .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidEnviron, e))
+            )
+    );
+    Ok(parse_environ(&String::from_utf8_lossy(&contents)))
+}
+
+/// Parse the NUL separated "NAME=value" entries of an environ file.
+fn parse_environ(contents: &str) -> Vec<(String, String)> {
+    contents.split('\0')
+        .filter(|e| !e.is_empty())
+        .map(|e| {
+            let mut split = e.splitn(2, '=');
+            (split.next().unwrap_or("").to_owned(), split.next().unwrap_or("").to_owned())
+        })
+        .collect()
+}
+
+/// Hides the values of environment variables whose names match any of a
+/// list of patterns, so environments can be shared without leaking secrets.
+///
+/// Patterns are globs (see format::glob_match) matched case insensitively
+/// against the whole name, eg: "*TOKEN*" or "AWS_*".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redactor {
+    patterns: Vec<String>,
+}
+
+impl Redactor {
+    /// Create a Redactor with no patterns, which redacts nothing.
+    pub fn new() -> Self {
+        Redactor {
+            patterns: Vec::new(),
+        }
+    }
+
+    /// Add a pattern of variable names to redact.
+    pub fn pattern(mut self, pattern: &str) -> Self {
+        self.patterns.push(pattern.to_uppercase());
+        self
+    }
+
+    /// Whether the value of a variable with this name is redacted.
+    pub fn is_redacted(&self, name: &str) -> bool {
+        let name = name.to_uppercase();
+        self.patterns.iter().any(|p| glob_match(p, &name))
+    }
+
+    /// Redact a single value, given the name of its variable.
+    pub fn redact_value(&self, name: &str, value: &str) -> String {
+        match self.is_redacted(name) {
+            true => REDACTED.to_owned(),
+            false => value.to_owned()
+        }
+    }
+
+    /// Redact the values of an environment, keeping every name.
+    pub fn redact(&self, environ: &[(String, String)]) -> Vec<(String, String)> {
+        environ.iter()
+            .map(|&(ref name, ref value)| (name.clone(), self.redact_value(name, value)))
+            .collect()
+    }
+}
+
+impl Default for Redactor {
+    /// A Redactor with DEFAULT_REDACT_PATTERNS.
+    fn default() -> Self {
+        DEFAULT_REDACT_PATTERNS.iter().fold(Redactor::new(), |r, p| r.pattern(p))
+    }
+}
+
+#[test]
+fn test_parse_environ() {
+    assert_eq!(parse_environ("HOME=/root\0EMPTY=\0EQ=a=b\0"), vec![
+        ("HOME".to_owned(), "/root".to_owned()),
+        ("EMPTY".to_owned(), "".to_owned()),
+        ("EQ".to_owned(), "a=b".to_owned()),
+    ]);
+    assert!(parse_environ("").is_empty());
+}
+
+#[test]
+fn test_redact() {
+    let redactor = Redactor::default().pattern("db_*");
+    let environ = parse_environ("PATH=/bin\0github_token=abc\0DB_HOST=db1\0MYSQL_PASSWORD=x\0");
+    assert_eq!(redactor.redact(&environ), vec![
+        ("PATH".to_owned(), "/bin".to_owned()),
+        ("github_token".to_owned(), REDACTED.to_owned()),
+        ("DB_HOST".to_owned(), REDACTED.to_owned()),
+        ("MYSQL_PASSWORD".to_owned(), REDACTED.to_owned()),
+    ]);
+    assert!(!Redactor::new().is_redacted("SECRET"));
+}
+
+#[test]
+fn test_read_environ() {
+    assert!(environ(Path::new("/proc/self")).is_ok());
+}
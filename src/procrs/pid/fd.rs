@@ -0,0 +1,213 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use error::{ProcError, ProcFile, ProcOper};
+use TaskId;
+
+/// What an open file descriptor refers to, from the /proc/[pid]/fd/[fd] symlink.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FdTarget {
+    /// A file or directory on a filesystem.
+    Path(PathBuf),
+    /// A socket, with its inode.
+    Socket(u64),
+    /// A pipe, with its inode.
+    Pipe(u64),
+    /// An anonymous inode, with its type (eg: "eventfd", "[timerfd]").
+    AnonInode(String),
+    /// Some other target the kernel reports as "type:[value]".
+    Other(String),
+}
+
+impl FdTarget {
+    /// Decode the target of a /proc/[pid]/fd/[fd] symlink.
+    pub fn parse(target: &Path) -> FdTarget {
+        let text = target.to_string_lossy();
+        if text.starts_with('/') {
+            return FdTarget::Path(target.to_path_buf());
+        }
+        let inode = |prefix: &str| text[prefix.len()..].trim_matches(|c| c == '[' || c == ']').parse().ok();
+        if text.starts_with("socket:") {
+            if let Some(i) = inode("socket:") {
+                return FdTarget::Socket(i);
+            }
+        } else if text.starts_with("pipe:") {
+            if let Some(i) = inode("pipe:") {
+                return FdTarget::Pipe(i);
+            }
+        } else if text.starts_with("anon_inode:") {
+            return FdTarget::AnonInode(text["anon_inode:".len()..].to_owned());
+        }
+        FdTarget::Other(text.into_owned())
+    }
+}
+
+/// A struct containing information from the fdinfo file for a file descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FdInfo {
+    /// Current file offset.
+    pub pos: u64,
+    /// Flags the file was opened with (O_* flags, displayed in octal).
+    pub flags: u32,
+    /// Id of the mount containing the file (linux 3.15+).
+    pub mnt_id: Option<u64>,
+}
+
+impl FdInfo {
+    /// Read /proc/[pid]/fdinfo/[fd] given a process directory and fd.
+    pub fn new(pid_dir: &Path, fd: i32) -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(pid_dir.join("fdinfo").join(fd.to_string()))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidFdinfo, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidFdinfo, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/[pid]/fdinfo/[fd] file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let (mut pos, mut flags, mut mnt_id) = (None, None, None);
+        for line in contents.lines() {
+            let mut split = line.splitn(2, ':');
+            let key = split.next().unwrap_or("").trim();
+            let value = split.next().unwrap_or("").trim();
+            let err = |e, key| ProcError::new(ProcOper::ParsingField, ProcFile::PidFdinfo,
+                Some(e), Some(key));
+            match key {
+                "pos" => pos = Some(try!(value.parse().map_err(|e| err(e, "pos")))),
+                "flags" => flags = Some(try!(u32::from_str_radix(value, 8).map_err(|e| err(e, "flags")))),
+                "mnt_id" => mnt_id = Some(try!(value.parse().map_err(|e| err(e, "mnt_id")))),
+                _ => continue,
+            }
+        }
+        let missing = |more| ProcError::new_more(ProcOper::ParsingField, ProcFile::PidFdinfo, Some(more));
+        Ok(FdInfo {
+            pos: try!(pos.ok_or(missing("missing pos"))),
+            flags: try!(flags.ok_or(missing("missing flags"))),
+            mnt_id: mnt_id,
+        })
+    }
+}
+
+/// A struct describing an open file descriptor of a process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidFd {
+    /// The file descriptor number.
+    pub fd: i32,
+    /// What the file descriptor refers to.
+    pub target: FdTarget,
+    /// The fdinfo for this file descriptor, if it could be read.
+    pub info: Option<FdInfo>,
+}
+
+impl PidFd {
+    /// Generate a Vec of PidFd structs given a process directory.
+    ///
+    /// File descriptors that are closed while scanning are skipped.
+    pub fn new(pid_dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let fd_dir = pid_dir.join("fd");
+        let entries = try!(
+            fs::read_dir(&fd_dir)
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidFd, e))
+        );
+        let mut fds = Vec::new();
+        for entry in entries {
+            let entry = try!(
+                entry.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidFd, e))
+            );
+            let fd = match entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+                Some(fd) => fd,
+                None => continue,
+            };
+            let target = match fs::read_link(entry.path()) {
+                Ok(t) => t,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(ProcError::new_err(ProcOper::Reading, ProcFile::PidFd, e)),
+            };
+            fds.push(PidFd {
+                fd: fd,
+                target: FdTarget::parse(&target),
+                info: FdInfo::new(pid_dir, fd).ok(),
+            });
+        }
+        fds.sort_by_key(|f| f.fd);
+        Ok(fds)
+    }
+
+    /// The inode of the socket this file descriptor refers to, if it's a socket.
+    pub fn socket_inode(&self) -> Option<u64> {
+        match self.target {
+            FdTarget::Socket(inode) => Some(inode),
+            _ => None
+        }
+    }
+}
+
+/// Read the fds of every process in a proc directory, calling f with the
+/// pid, process directory and fds of each.
+///
+/// Processes that exit while scanning, or can't be inspected (eg: owned by
+/// another user), are skipped, so run as root for a complete scan.
+pub fn scan<F: FnMut(TaskId, &Path, Vec<PidFd>)>(proc_dir: &Path, mut f: F)
+    -> Result<(), ProcError> {
+    let entries = try!(
+        fs::read_dir(proc_dir)
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcDir, e))
+    );
+    for entry in entries {
+        let entry = try!(
+            entry.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcDir, e))
+        );
+        let pid = match entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            Some(pid) => pid,
+            None => continue
+        };
+        if let Ok(fds) = PidFd::new(&entry.path()) {
+            f(pid, &entry.path(), fds);
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_target_parse() {
+    assert_eq!(FdTarget::parse(Path::new("/dev/null")), FdTarget::Path(PathBuf::from("/dev/null")));
+    assert_eq!(FdTarget::parse(Path::new("socket:[39472]")), FdTarget::Socket(39472));
+    assert_eq!(FdTarget::parse(Path::new("pipe:[1234]")), FdTarget::Pipe(1234));
+    assert_eq!(FdTarget::parse(Path::new("anon_inode:[eventfd]")), FdTarget::AnonInode("[eventfd]".to_owned()));
+    assert_eq!(FdTarget::parse(Path::new("anon_inode:inotify")), FdTarget::AnonInode("inotify".to_owned()));
+    assert_eq!(FdTarget::parse(Path::new("net:[4026531840]")), FdTarget::Other("net:[4026531840]".to_owned()));
+}
+
+#[test]
+fn test_fdinfo_parsing() {
+    assert_eq!(FdInfo::parse_string("pos:\t0\nflags:\t0100002\nmnt_id:\t25\nino:\t3\n"),
+        Ok(FdInfo { pos: 0, flags: 0o100002, mnt_id: Some(25) }));
+    assert_eq!(FdInfo::parse_string("pos:\t10\nflags:\t02\n"),
+        Ok(FdInfo { pos: 10, flags: 2, mnt_id: None }));
+    assert_eq!(FdInfo::parse_string("pos:\t10\n"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::PidFdinfo, Some("missing flags"))));
+}
+
+#[test]
+fn test_scan() {
+    use std::os::unix::fs::symlink;
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    fake.process(&FakePid::new(10, 1, "nginx")).unwrap();
+    fake.process(&FakePid::new(11, 1, "gone")).unwrap();
+    fs::create_dir(fake.path().join("10/fd")).unwrap();
+    symlink("socket:[500]", fake.path().join("10/fd/3")).unwrap();
+    symlink("/dev/null", fake.path().join("10/fd/4")).unwrap();
+    // Pid 11 has no fd directory, like a process that exited, so is skipped.
+    let mut scanned = Vec::new();
+    scan(fake.path(), |pid, dir, fds| {
+        assert_eq!(dir, fake.path().join("10"));
+        scanned.push((pid, fds.iter().filter_map(|fd| fd.socket_inode()).collect::<Vec<_>>()));
+    }).unwrap();
+    assert_eq!(scanned, vec![(10, vec![500])]);
+}
@@ -0,0 +1,108 @@
+use error::ProcError;
+use mounts::{MountInfo, MountOptions};
+use root;
+
+/// How much of other users' processes /proc shows, from the hidepid mount
+/// option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HidePid {
+    /// Every process directory can be read (hidepid=0 or off).
+    Off,
+    /// Other users' process directories are listed, but their files can't be
+    /// read (hidepid=1 or noaccess).
+    NoAccess,
+    /// Other users' processes aren't listed at all (hidepid=2 or invisible).
+    Invisible,
+    /// Only processes that could be ptraced are listed (hidepid=4 or
+    /// ptraceable, Linux 5.8+).
+    Ptraceable,
+}
+
+impl HidePid {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "0" | "off" => Some(HidePid::Off),
+            "1" | "noaccess" => Some(HidePid::NoAccess),
+            "2" | "invisible" => Some(HidePid::Invisible),
+            "4" | "ptraceable" => Some(HidePid::Ptraceable),
+            _ => None
+        }
+    }
+}
+
+/// The restrictions /proc is mounted with, which hide processes or files
+/// from unprivileged users on hardened hosts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcRestrictions {
+    /// Which processes of other users are hidden.
+    pub hidepid: HidePid,
+    /// Members of this group are exempt from hidepid.
+    pub gid: Option<u32>,
+    /// Only process directories are shown, hiding system files like
+    /// /proc/meminfo (subset=pid, Linux 5.8+).
+    pub subset_pid: bool,
+}
+
+impl ProcRestrictions {
+    /// Find the restrictions of the procfs root (usually /proc), from the
+    /// mount table of the calling process. If it isn't in the mount table,
+    /// it's assumed unrestricted.
+    pub fn new() -> Result<Self, ProcError> {
+        let mounts = try!(MountInfo::new());
+        let proc_root = root::root();
+        // The last mount over the root is the one that's visible.
+        Ok(mounts.iter().rev()
+            .find(|m| m.fstype == "proc" && m.mount_point == proc_root)
+            .map(|m| Self::from_options(&m.super_options))
+            .unwrap_or(ProcRestrictions::default()))
+    }
+
+    /// Whether any processes or files are hidden.
+    pub fn is_restricted(&self) -> bool {
+        self.hidepid != HidePid::Off || self.subset_pid
+    }
+
+    fn from_options(options: &MountOptions) -> Self {
+        let get = |key| options.get(key).and_then(|v| v.as_ref());
+        ProcRestrictions {
+            hidepid: get("hidepid").and_then(|v| HidePid::parse(v)).unwrap_or(HidePid::Off),
+            gid: get("gid").and_then(|v| v.parse().ok()),
+            subset_pid: get("subset").map(|v| v == "pid").unwrap_or(false),
+        }
+    }
+}
+
+impl Default for ProcRestrictions {
+    /// No restrictions.
+    fn default() -> Self {
+        ProcRestrictions {
+            hidepid: HidePid::Off,
+            gid: None,
+            subset_pid: false,
+        }
+    }
+}
+
+#[test]
+fn test_from_options() {
+    let options = |opts: &[(&str, Option<&str>)]| -> MountOptions {
+        opts.iter().map(|&(k, v)| (k.to_owned(), v.map(|v| v.to_owned()))).collect()
+    };
+    assert_eq!(ProcRestrictions::from_options(&options(&[("rw", None)])),
+        ProcRestrictions::default());
+    let hardened = ProcRestrictions::from_options(&options(&[
+        ("rw", None), ("hidepid", Some("invisible")), ("gid", Some("27")), ("subset", Some("pid"))
+    ]));
+    assert_eq!(hardened.hidepid, HidePid::Invisible);
+    assert_eq!(hardened.gid, Some(27));
+    assert!(hardened.subset_pid);
+    assert!(hardened.is_restricted());
+    let old = ProcRestrictions::from_options(&options(&[("hidepid", Some("1"))]));
+    assert_eq!(old.hidepid, HidePid::NoAccess);
+    assert!(!ProcRestrictions::default().is_restricted());
+}
+
+#[test]
+fn test_read() {
+    assert!(ProcRestrictions::new().is_ok());
+}
@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+
+/// A struct containing the I/O counters of a process, from /proc/[pid]/io.
+///
+/// Reading another user's process needs the same permissions as ptrace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PidIo {
+    /// Bytes read by read(2) and similar calls, including from the page cache.
+    pub rchar: u64,
+    /// Bytes written by write(2) and similar calls.
+    pub wchar: u64,
+    /// Read syscalls.
+    pub syscr: u64,
+    /// Write syscalls.
+    pub syscw: u64,
+    /// Bytes fetched from storage.
+    pub read_bytes: u64,
+    /// Bytes caused to be sent to storage.
+    pub write_bytes: u64,
+    /// Bytes that were written, but not sent to storage because the file
+    /// was truncated.
+    pub cancelled_write_bytes: u64,
+}
+
+impl PidIo {
+    /// Generate a PidIo struct given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        let mut contents = String::new();
+        try!(
+            File::open(pid_dir.join("io"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidIo, e))
+                .and_then(|mut f|
+                    f.read_to_string(&mut contents)
+                        .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidIo, e))
+                )
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as an io file, with lines like "read_bytes: 4096".
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let mut io = PidIo::default();
+        for line in contents.lines() {
+            let mut split = line.splitn(2, ':');
+            let key = split.next().unwrap_or("").trim();
+            let value = match key {
+                "rchar" => &mut io.rchar,
+                "wchar" => &mut io.wchar,
+                "syscr" => &mut io.syscr,
+                "syscw" => &mut io.syscw,
+                "read_bytes" => &mut io.read_bytes,
+                "write_bytes" => &mut io.write_bytes,
+                "cancelled_write_bytes" => &mut io.cancelled_write_bytes,
+                _ => continue,
+            };
+            *value = try!(
+                split.next().unwrap_or("").trim().parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::PidIo,
+                        Some(e), Some("parsing counter")))
+            );
+        }
+        Ok(io)
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let io = PidIo::parse_string("rchar: 3980\nwchar: 12\nsyscr: 9\nsyscw: 1\n\
+        read_bytes: 8192\nwrite_bytes: 4096\ncancelled_write_bytes: 0\n").unwrap();
+    assert_eq!(io.rchar, 3980);
+    assert_eq!(io.read_bytes, 8192);
+    assert_eq!(io.write_bytes, 4096);
+    assert!(PidIo::parse_string("rchar: lots\n").is_err());
+    assert!(PidIo::new(Path::new("/proc/self")).is_ok());
+}
@@ -0,0 +1,116 @@
+use std::io;
+use libc;
+use error::{ProcError, ProcFile, ProcOper};
+use TaskId;
+
+/// Target a single thread (or process) with the ioprio syscalls.
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+/// The class is stored above the level in an ioprio value.
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+/// Lowest (least important) level in the RealTime and BestEffort classes.
+pub const IOPRIO_LEVEL_MAX: u8 = 7;
+
+/// The I/O scheduling class of a process (see ionice).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    /// No class set, the priority is derived from the nice value.
+    None,
+    /// Served first, regardless of other processes.
+    RealTime,
+    /// The default class.
+    BestEffort,
+    /// Only served when no other process needs the disk.
+    Idle,
+}
+
+/// The I/O priority of a process, being a class and a level from 0 (highest) to 7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoPriority {
+    /// The scheduling class.
+    pub class: IoClass,
+    /// The level within the class, only used by RealTime and BestEffort.
+    pub level: u8,
+}
+
+impl IoPriority {
+    /// Decode an ioprio value returned by the kernel.
+    fn from_raw(raw: i32) -> Self {
+        let class = match raw >> IOPRIO_CLASS_SHIFT {
+            1 => IoClass::RealTime,
+            2 => IoClass::BestEffort,
+            3 => IoClass::Idle,
+            _ => IoClass::None,
+        };
+        IoPriority {
+            class: class,
+            level: (raw & ((1 << IOPRIO_CLASS_SHIFT) - 1)) as u8,
+        }
+    }
+
+    /// Encode as an ioprio value for the kernel.
+    fn to_raw(&self) -> i32 {
+        let class = match self.class {
+            IoClass::None => 0,
+            IoClass::RealTime => 1,
+            IoClass::BestEffort => 2,
+            IoClass::Idle => 3,
+        };
+        class << IOPRIO_CLASS_SHIFT | self.level as i32
+    }
+}
+
+/// Get the I/O priority of a thread or process.
+pub fn io_priority(pid: TaskId) -> Result<IoPriority, ProcError> {
+    let raw = unsafe {
+        libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, pid)
+    };
+    if raw < 0 {
+        return Err(ProcError::new_err(ProcOper::Reading, ProcFile::PidIoprio,
+            io::Error::last_os_error()));
+    }
+    Ok(IoPriority::from_raw(raw as i32))
+}
+
+/// Set the I/O priority of a thread or process.
+///
+/// Using the RealTime class, or changing a process of another user, requires root.
+pub fn set_io_priority(pid: TaskId, class: IoClass, level: u8) -> Result<(), ProcError> {
+    if level > IOPRIO_LEVEL_MAX {
+        return Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidIoprio,
+            Some("ioprio level out of range")));
+    }
+    let prio = IoPriority {
+        class: class,
+        level: level,
+    };
+    let ret = unsafe {
+        libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, pid, prio.to_raw())
+    };
+    if ret < 0 {
+        return Err(ProcError::new_err(ProcOper::Writing, ProcFile::PidIoprio,
+            io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_raw() {
+    let prio = IoPriority { class: IoClass::BestEffort, level: 4 };
+    assert_eq!(prio.to_raw(), 0x4004);
+    assert_eq!(IoPriority::from_raw(0x4004), prio);
+    assert_eq!(IoPriority::from_raw(0x6000), IoPriority { class: IoClass::Idle, level: 0 });
+}
+
+#[test]
+fn test_get_set() {
+    use std::thread;
+    // Change a separate thread, so the priority of other tests isn't affected.
+    thread::spawn(|| {
+        let tid = ::pid::Pid::current_thread().unwrap().pid;
+        set_io_priority(tid, IoClass::BestEffort, 6).unwrap();
+        assert_eq!(io_priority(tid).unwrap(), IoPriority { class: IoClass::BestEffort, level: 6 });
+        assert_eq!(set_io_priority(tid, IoClass::BestEffort, 8),
+            Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidIoprio,
+                Some("ioprio level out of range"))));
+    }).join().unwrap();
+}
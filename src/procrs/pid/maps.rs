@@ -0,0 +1,199 @@
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A struct containing information about a single mapping in the maps file.
+///
+/// This struct contains one line of the /proc/[pid]/maps or
+/// /proc/[tgid]/task/[tid]/maps file.
+pub struct PidMap {
+    /// Start address of the mapping.
+    pub start: u64,
+    /// End address of the mapping.
+    pub end: u64,
+    /// Permissions of the mapping (eg: "r-xp").
+    pub perms: String,
+    /// Offset into the mapped file.
+    pub offset: u64,
+    /// Device of the mapped file (major:minor).
+    pub dev: (u32, u32),
+    /// Inode of the mapped file (0 if none).
+    pub inode: u64,
+    /// Pathname of the mapping, or pseudo-path like "[heap]".
+    pub pathname: Option<String>,
+}
+
+impl PidMap {
+    /// Generate a Vec of PidMap structs given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let maps_file = try!(
+            File::open(pid_dir.join("maps"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidMaps, e))
+        );
+
+        BufReader::with_capacity(4096, maps_file)
+            .lines()
+            .map(|r|
+                r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidMaps, e))
+                    .and_then(|l| Self::parse_line(&l, ProcFile::PidMaps))
+            ).collect()
+    }
+
+    /// Parse a mapping line, eg:
+    /// "00400000-0040b000 r-xp 00000000 08:01 1234    /bin/cat"
+    ///
+    /// This is shared with the smaps parser, so errors are reported against the given file.
+    pub fn parse_line(line: &str, file: ProcFile) -> Result<Self, ProcError> {
+        let err = |more| ProcError::new_more(ProcOper::Parsing, file, Some(more));
+        let mut split = line.splitn(6, ' ');
+        let range = try!(split.next().ok_or(err("missing address range")));
+        let perms = try!(split.next().ok_or(err("missing perms")));
+        let offset = try!(split.next().ok_or(err("missing offset")));
+        let dev = try!(split.next().ok_or(err("missing device")));
+        let inode = try!(split.next().ok_or(err("missing inode")));
+        let pathname = split.next()
+            .map(|s| s.trim_left())
+            .and_then(|s| if s.is_empty() { None } else { Some(s.to_owned()) });
+
+        let mut range_split = range.splitn(2, '-');
+        let start = try!(parse_hex(range_split.next(), file, "parsing start address"));
+        let end = try!(parse_hex(range_split.next(), file, "parsing end address"));
+        if end < start {
+            return Err(err("end address before start address"));
+        }
+        let offset = try!(parse_hex(Some(offset), file, "parsing offset"));
+        let mut dev_split = dev.splitn(2, ':');
+        let major = try!(parse_hex(dev_split.next(), file, "parsing device"));
+        let minor = try!(parse_hex(dev_split.next(), file, "parsing device"));
+        let inode = try!(
+            inode.parse()
+                .map_err(|e| ProcError::new(ProcOper::ParsingField, file,
+                    Some(e), Some("parsing inode")))
+        );
+        Ok(PidMap {
+            start: start,
+            end: end,
+            perms: perms.to_owned(),
+            offset: offset,
+            dev: (major as u32, minor as u32),
+            inode: inode,
+            pathname: pathname,
+        })
+    }
+
+    /// Whether this mapping isn't backed by a file (eg: heap, stack or anonymous mmap).
+    pub fn is_anonymous(&self) -> bool {
+        self.inode == 0 &&
+            !self.pathname.as_ref().map(|p| p.starts_with('/')).unwrap_or(false)
+    }
+
+    /// Whether this mapping is readable.
+    pub fn is_readable(&self) -> bool {
+        self.perms.starts_with('r')
+    }
+
+    /// Whether this mapping is writable.
+    pub fn is_writable(&self) -> bool {
+        self.perms.chars().nth(1) == Some('w')
+    }
+
+    /// Whether this mapping is executable.
+    pub fn is_executable(&self) -> bool {
+        self.perms.chars().nth(2) == Some('x')
+    }
+
+    /// Whether this mapping is shared (rather than private copy-on-write).
+    pub fn is_shared(&self) -> bool {
+        self.perms.chars().nth(3) == Some('s')
+    }
+
+    /// Size of this mapping in bytes.
+    pub fn size(&self) -> u64 {
+        self.end - self.start
+    }
+}
+
+/// Get the paths of shared libraries loaded by a process, given its mappings.
+///
+/// Each path is only returned once, in the order it is first mapped.
+pub fn shared_libraries(maps: &[PidMap]) -> Vec<String> {
+    let mut libs: Vec<String> = Vec::new();
+    for map in maps {
+        if let Some(ref path) = map.pathname {
+            if path.starts_with('/') && path.contains(".so") && !libs.contains(path) {
+                libs.push(path.clone());
+            }
+        }
+    }
+    libs
+}
+
+/// Parse an optional hex string, emitting a ProcError with $more on failure.
+fn parse_hex(hex: Option<&str>, file: ProcFile, more: &'static str) -> Result<u64, ProcError> {
+    hex.ok_or(ProcError::new_more(ProcOper::Parsing, file, Some(more)))
+        .and_then(|s|
+            u64::from_str_radix(s, 16)
+                .map_err(|e| ProcError::new(ProcOper::ParsingField, file,
+                    Some(e), Some(more)))
+        )
+}
+
+#[test]
+fn test_parsing() {
+    let map = PidMap::parse_line(
+        "7f3c1a400000-7f3c1a5b5000 r-xp 00028000 fe:00 1054637                    /usr/lib/x86_64-linux-gnu/libc.so.6",
+        ProcFile::PidMaps).unwrap();
+    assert_eq!(map, PidMap {
+        start: 0x7f3c1a400000,
+        end: 0x7f3c1a5b5000,
+        perms: "r-xp".to_owned(),
+        offset: 0x28000,
+        dev: (0xfe, 0),
+        inode: 1054637,
+        pathname: Some("/usr/lib/x86_64-linux-gnu/libc.so.6".to_owned()),
+    });
+    assert!(map.is_executable());
+    assert!(!map.is_writable());
+    assert!(!map.is_anonymous());
+    assert_eq!(map.size(), 0x1b5000);
+}
+
+#[test]
+fn test_anonymous() {
+    let heap = PidMap::parse_line("55d0c8a4e000-55d0c8a6f000 rw-p 00000000 00:00 0                          [heap]",
+        ProcFile::PidMaps).unwrap();
+    assert!(heap.is_anonymous());
+    assert!(!heap.is_executable());
+    let anon = PidMap::parse_line("7f3c1a3f0000-7f3c1a3f3000 rw-p 00000000 00:00 0 ",
+        ProcFile::PidMaps).unwrap();
+    assert_eq!(anon.pathname, None);
+    assert!(anon.is_anonymous());
+}
+
+#[test]
+fn test_shared_libraries() {
+    let maps = vec![
+        PidMap::parse_line("00400000-0040b000 r-xp 00000000 08:01 1234 /bin/cat", ProcFile::PidMaps),
+        PidMap::parse_line("7f00-7f10 r--p 00000000 08:01 99 /lib/libc.so.6", ProcFile::PidMaps),
+        PidMap::parse_line("7f10-7f20 r-xp 00001000 08:01 99 /lib/libc.so.6", ProcFile::PidMaps),
+        PidMap::parse_line("7f20-7f30 rw-p 00000000 00:00 0 [stack]", ProcFile::PidMaps),
+    ].into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(shared_libraries(&maps), vec!["/lib/libc.so.6".to_owned()]);
+}
+
+#[test]
+fn test_invalid_range() {
+    assert_eq!(PidMap::parse_line("0040b000-00400000 r-xp 00000000 08:01 1234 /bin/cat",
+        ProcFile::PidMaps),
+        Err(ProcError::new_more(ProcOper::Parsing, ProcFile::PidMaps,
+            Some("end address before start address"))));
+}
+
+#[test]
+fn test_invalid_inode() {
+    assert_eq!(PidMap::parse_line("00400000-0040b000 r-xp 00000000 08:01 a /bin/cat", ProcFile::PidMaps),
+        Err(ProcError::new(ProcOper::ParsingField, ProcFile::PidMaps,
+            Some("a".parse::<u8>().unwrap_err()), Some("parsing inode"))));
+}
@@ -1,27 +1,115 @@
 use std::io;
 use std::io::prelude::*;
 use std::fs::{self, File, ReadDir, DirEntry};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::vec;
+use std::num::ParseIntError;
+use std::time::{Duration, SystemTime};
 use std::io::BufReader;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::error::Error;
+use std::os::unix::fs::MetadataExt;
 
 /// Get process stats (/proc/[pid]/stat)
 pub mod stat;
 /// Get process status (/proc/[pid]/status)
 pub mod status;
+/// Decode signal masks from stat and status
+pub mod signal;
+/// Get process cgroups (/proc/[pid]/cgroup)
+pub mod cgroup;
+/// Get process file descriptors (/proc/[pid]/fd and /proc/[pid]/fdinfo)
+pub mod fd;
+/// Sample network counters of a process's network namespace (/proc/[pid]/net/dev)
+pub mod netns;
+/// Get process OOM killer settings (/proc/[pid]/oom_score and oom_score_adj)
+pub mod oom;
+/// Get process execution domain and flags (/proc/[pid]/personality)
+pub mod personality;
+/// Get process security labels (/proc/[pid]/attr)
+pub mod attr;
+/// Diagnose processes stuck in uninterruptible sleep
+pub mod dstate;
+/// Find zombie processes and the parents that should reap them
+pub mod zombie;
+/// Find processes using a file or directory (like fuser)
+pub mod blame;
+/// Compare the fields of two processes
+pub mod diff;
+/// Get and set process I/O priority (like ionice)
+pub mod ioprio;
+/// Set writable process tunables, like oom_score_adj and timerslack_ns
+pub mod tune;
+/// Get process memory mappings (/proc/[pid]/maps)
+pub mod maps;
+/// Get process memory mappings with usage (/proc/[pid]/smaps)
+pub mod smaps;
+/// Get process memory placement on NUMA nodes (/proc/[pid]/numa_maps)
+pub mod numa;
+/// Sort processes by multiple fields
+pub mod sort;
+/// Compare numeric fields of processes in queries, like rss>500M
+pub mod compare;
+/// Group processes and aggregate their resource usage
+pub mod aggregate;
+/// Relationships between processes, like counting children
+pub mod tree;
+/// Find processes actively swapping memory back in
+pub mod swap;
+/// Find processes running with elevated privileges
+pub mod privilege;
+/// Group processes by mount namespace and find chroots and containers
+pub mod mountns;
+/// Predict whether ptrace protected files of a process can be read
+pub mod access;
+/// Read process environments, and redact secrets from them
+pub mod environ;
+/// Detect restrictions on /proc, like the hidepid mount option
+pub mod hidepid;
+/// Read the I/O counters of a process
+pub mod ioacct;
+/// Read and set the nice value of a process
+pub mod nice;
 
 use self::stat::PidStat;
 use self::status::PidStatus;
+use self::cgroup::PidCgroup;
+use self::fd::PidFd;
+use self::maps::PidMap;
+use self::oom::PidOom;
+use self::ioacct::PidIo;
+use self::attr::PidAttr;
+use self::personality::Personality;
+use self::blame::Usage;
+use self::diff::{PidDiff, PidRates};
+use self::dstate::DStateReport;
+use self::stat::PidState;
+use self::tune::{CoredumpFilter, ClearRefs};
+use self::ioprio::{IoPriority, IoClass};
+use self::netns::NetNsSampler;
+use self::smaps::{PidSmap, PidSmapsRollup};
+use self::numa::PidNumaMaps;
+use self::compare::{QueryField, Comparison};
+use self::tree::{ParentMap, Ancestors, ProcessTree};
 use error::{ProcError, ProcFile, ProcOper};
-use TaskId;
+use cancel::{ScanBound, Partial, Throttle};
+use pressure::{Pressure, MemPressure};
+use uptime::Uptime;
+use stat::KernelStat;
+use net::socket::{NetSocket, Protocol};
+use net::unix::UnixSocket;
+use format;
+use users;
+use root;
+use {TaskId, MemSize};
 
 /// A struct containing information about a process.
 ///
 /// This struct contains information from various files inside the
 /// /proc/[pid] directory (for the respective pid).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pid {
     // FIXME: Take Vec<PidFile> to indicate which things to parse
     /// The tid of this process
@@ -32,39 +120,379 @@ pub struct Pid {
     pub status: Box<PidStatus>,
     /// The /proc/[pid]/cmdline file
     pub cmdline: Vec<String>,
+    /// How much of this process could be read.
+    pub access: AccessLevel,
     /// If this is a thread, this is set to true.
     /// Threads will never have tasks attached.
     is_thread: bool,
     /// Vec of threads under /proc/[pid]/tasks/[tid]
     threads: Option<Vec<Pid>>,
+    /// The /proc/[pid] directory this process was read from.
+    dir: PathBuf,
 }
 
 impl Pid {
     /// Create a new Pid struct for a process, given a pid.
     pub fn new(pid: TaskId) -> Result<Self, ProcError> {
-        let pid_dir = Path::new("/proc");
-        Self::new_dir(pid_dir, pid)
+        Self::new_dir(&root::root(), pid)
+    }
+
+    /// Create a new Pid struct for the calling process, using /proc/self.
+    pub fn myself() -> Result<Self, ProcError> {
+        let dir = try!(Self::resolve_self("self"));
+        Self::new_path(dir, false)
+    }
+
+    /// Create a new Pid struct for the calling thread, using /proc/thread-self.
+    pub fn current_thread() -> Result<Self, ProcError> {
+        let dir = try!(Self::resolve_self("thread-self"));
+        Self::new_path(dir, true)
+    }
+
+    /// Resolve a /proc/self style symlink, so the resulting Pid keeps
+    /// refering to the same task even when used from another thread.
+    fn resolve_self(link: &'static str) -> Result<PathBuf, ProcError> {
+        let proc_dir = root::root();
+        fs::read_link(proc_dir.join(link))
+            .map(|target| proc_dir.join(target))
+            .map_err(|e| ProcError::new(ProcOper::Opening, ProcFile::PidDir, Some(e), Some(link)))
     }
 
     fn new_dir(proc_dir: &Path, pid: TaskId) -> Result<Self, ProcError> {
-        let proc_dir = proc_dir.join(pid.to_string());
-        let pid_stat = try!(PidStat::new(&proc_dir));
+        Self::new_path(proc_dir.join(pid.to_string()), false)
+    }
+
+    /// Create a new Pid struct given a /proc/[pid] or /proc/[pid]/task/[tid] directory.
+    ///
+    /// If the files of the process can't be read, like other users'
+    /// processes when /proc is mounted with hidepid=1, a Restricted Pid is
+    /// returned instead of an error.
+    fn new_path(proc_dir: PathBuf, is_thread: bool) -> Result<Self, ProcError> {
+        let pid_stat = match PidStat::new(&proc_dir) {
+            Ok(stat) => stat,
+            Err(ref e) if e.is_permission_denied() =>
+                return Self::new_restricted(proc_dir, is_thread),
+            Err(e) => return Err(e)
+        };
         let pid_status = try!(PidStatus::new(&proc_dir));
         let cmdline = try!(Self::read_cmdline(&proc_dir));
 
         Ok(Pid {
-            pid: pid,
+            pid: pid_stat.pid,
             stat: Box::new(pid_stat),
             status: Box::new(pid_status),
             cmdline: cmdline,
-            is_thread: false,
+            access: AccessLevel::Full,
+            is_thread: is_thread,
             threads: None,
+            dir: proc_dir,
         })
     }
 
+    /// Create a Restricted Pid struct, with only the pid from the directory
+    /// name and the owner of the directory.
+    fn new_restricted(proc_dir: PathBuf, is_thread: bool) -> Result<Self, ProcError> {
+        let pid = try!(
+            proc_dir.file_name().and_then(|n| n.to_str()).and_then(|n| n.parse().ok())
+                .ok_or(ProcError::new_more(ProcOper::Parsing, ProcFile::PidDir,
+                    Some("pid from directory name")))
+        );
+        let meta = try!(
+            fs::metadata(&proc_dir)
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidDir, e))
+        );
+        debug!("{} can't be read, using a restricted pid", proc_dir.display());
+        Ok(Pid {
+            pid: pid,
+            stat: Box::new(PidStat::unreadable(pid)),
+            status: Box::new(PidStatus::unreadable(pid, meta.uid(), meta.gid())),
+            cmdline: Vec::new(),
+            access: AccessLevel::Restricted,
+            is_thread: is_thread,
+            threads: None,
+            dir: proc_dir,
+        })
+    }
+
+    /// Re-read the /proc/[pid]/stat file for this process, to take
+    /// a new sample of its counters.
+    pub fn read_stat(&self) -> Result<PidStat, ProcError> {
+        PidStat::new(&self.dir)
+    }
+
+    /// Classify how memory pressure is affecting this process, given an
+    /// earlier sample of its stat file, the time since that sample, and
+    /// optionally the system memory pressure.
+    pub fn memory_pressure(&self, previous: &PidStat, elapsed: Duration, psi: Option<&Pressure>)
+        -> MemPressure {
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        let majflt_rate = match elapsed {
+            e if e > 0.0 => self.stat.majflt.saturating_sub(previous.majflt) as f64 / e,
+            _ => 0.0
+        };
+        MemPressure::classify(majflt_rate, self.status.vmswap, psi)
+    }
+
+    /// Read the /proc/[pid]/cgroup file for this process.
+    pub fn cgroups(&self) -> Result<Vec<PidCgroup>, ProcError> {
+        PidCgroup::new(&self.dir)
+    }
+
+    /// Read the open file descriptors of this process.
+    pub fn fds(&self) -> Result<Vec<PidFd>, ProcError> {
+        PidFd::new(&self.dir)
+    }
+
+    /// Find how this process is using a path, through its fds, memory
+    /// mappings, cwd, root or exe. Directories also match anything underneath.
+    pub fn uses(&self, path: &Path) -> Result<Vec<Usage>, ProcError> {
+        blame::uses(&self.dir, path)
+    }
+
+    /// Read a socket table of this process's network namespace.
+    pub fn sockets(&self, protocol: Protocol) -> Result<Vec<NetSocket>, ProcError> {
+        NetSocket::new_dir(&self.dir, protocol)
+    }
+
+    /// Find the tcp and udp sockets this process has open, by matching the
+    /// socket inodes of its fds against the socket tables of its namespace.
+    pub fn owned_sockets(&self) -> Result<Vec<NetSocket>, ProcError> {
+        let inodes = try!(self.socket_inodes());
+        if inodes.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(try!(NetSocket::new_dir_all(&self.dir)).into_iter()
+            .filter(|s| inodes.contains(&s.inode))
+            .collect())
+    }
+
+    /// Read the unix socket table of this process's network namespace.
+    pub fn unix_sockets(&self) -> Result<Vec<UnixSocket>, ProcError> {
+        UnixSocket::new_dir(&self.dir)
+    }
+
+    /// Find the unix sockets this process has open, like owned_sockets().
+    pub fn owned_unix_sockets(&self) -> Result<Vec<UnixSocket>, ProcError> {
+        let inodes = try!(self.socket_inodes());
+        if inodes.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(try!(UnixSocket::new_dir(&self.dir)).into_iter()
+            .filter(|s| inodes.contains(&s.inode))
+            .collect())
+    }
+
+    /// Get the inodes of the sockets in this process's fds.
+    fn socket_inodes(&self) -> Result<Vec<u64>, ProcError> {
+        Ok(try!(self.fds()).iter().filter_map(|fd| fd.socket_inode()).collect())
+    }
+
+    /// Read the inode of the network namespace of this process.
+    pub fn net_namespace(&self) -> Result<u64, ProcError> {
+        netns::net_namespace(&self.dir)
+    }
+
+    /// Predict whether this process can read the ptrace protected files of
+    /// target, like environ, io and smaps, so doomed reads can be skipped.
+    pub fn can_inspect(&self, target: &Pid) -> bool {
+        access::can_inspect(self, target)
+    }
+
+    /// Read the inode of the mount namespace of this process.
+    pub fn mount_namespace(&self) -> Result<u64, ProcError> {
+        mountns::mount_namespace(&self.dir)
+    }
+
+    /// Read the root directory of this process, which differs from "/" in a chroot.
+    pub fn root(&self) -> Result<PathBuf, ProcError> {
+        mountns::root(&self.dir)
+    }
+
+    /// Create a sampler for the network counters of this process's network namespace.
+    pub fn net_sampler(&self) -> NetNsSampler {
+        NetNsSampler::new(&self.dir)
+    }
+
+    /// How long ago this process started.
+    pub fn age(&self) -> Result<Duration, ProcError> {
+        Uptime::new().map(|u| self.stat.age(u.uptime))
+    }
+
+    /// When this process started, from the boot time in /proc/stat.
+    pub fn start_time(&self) -> Result<SystemTime, ProcError> {
+        KernelStat::new().map(|s| s.ticks_to_time(self.stat.starttime))
+    }
+
+    /// How long this process has been running (like ps etime). Unlike
+    /// comparing start_time with the current time, this isn't affected by
+    /// changes to the wall clock.
+    pub fn elapsed(&self) -> Result<Duration, ProcError> {
+        self.age()
+    }
+
+    /// Read the path of the executable of this process. If the executable was
+    /// deleted or replaced since it was started, the path ends in " (deleted)".
+    pub fn exe(&self) -> Result<PathBuf, ProcError> {
+        fs::read_link(self.dir.join("exe"))
+            .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidExe, e))
+    }
+
+    /// Read the current name of this process from /proc/[pid]/comm. For a thread
+    /// this is the thread name, which may have changed since this Pid was read.
+    pub fn comm(&self) -> Result<String, ProcError> {
+        read_value::<String>(&self.dir.join("comm"), ProcFile::PidComm)
+    }
+
+    /// Set the name of this process or thread (at most 15 bytes).
+    pub fn set_comm(&self, name: &str) -> Result<(), ProcError> {
+        tune::set_comm(&self.dir, name)
+    }
+
+    /// Read the security labels of this process.
+    pub fn attr(&self) -> Result<PidAttr, ProcError> {
+        PidAttr::new(&self.dir)
+    }
+
+    /// Read the execution domain and flags of this process, which needs
+    /// ptrace permissions on it.
+    pub fn personality(&self) -> Result<Personality, ProcError> {
+        Personality::new(&self.dir)
+    }
+
+    /// Read the current SELinux or AppArmor label of this process, being
+    /// None without a security module.
+    pub fn label(&self) -> Result<Option<String>, ProcError> {
+        attr::read_current(&self.dir)
+    }
+
+    /// If this process is in uninterruptible sleep (D state), gather its
+    /// wchan, kernel stack, current system call and fds on network filesystems.
+    /// Returns None if the process isn't in D state.
+    pub fn dstate_report(&self) -> Result<Option<DStateReport>, ProcError> {
+        // The state may have changed since this Pid was read.
+        if try!(self.read_stat()).state != PidState::Waiting {
+            return Ok(None);
+        }
+        // wchan may be restricted, so don't fail if it can't be read.
+        Ok(Some(DStateReport::new(&self.dir, self.wchan().unwrap_or(None))))
+    }
+
+    /// List the fields that changed from this process to other, being either
+    /// a later reading of the same process or a different process.
+    pub fn diff(&self, other: &Pid) -> PidDiff {
+        PidDiff::new(self, other)
+    }
+
+    /// Calculate counter rates from this process to a later reading of it,
+    /// taken elapsed later.
+    pub fn rates(&self, later: &Pid, elapsed: Duration) -> PidRates {
+        PidRates::new(self, later, elapsed)
+    }
+
+    /// Read the I/O counters of this process.
+    pub fn io(&self) -> Result<PidIo, ProcError> {
+        PidIo::new(&self.dir)
+    }
+
+    /// Read the OOM killer settings of this process.
+    pub fn oom(&self) -> Result<PidOom, ProcError> {
+        PidOom::new(&self.dir)
+    }
+
+    /// Set the oom_score_adj of this process (-1000 to 1000).
+    pub fn set_oom_score_adj(&self, score_adj: i32) -> Result<(), ProcError> {
+        tune::set_oom_score_adj(&self.dir, score_adj)
+    }
+
+    /// Set which kinds of mappings are written when this process dumps core.
+    pub fn set_coredump_filter(&self, filter: CoredumpFilter) -> Result<(), ProcError> {
+        tune::set_coredump_filter(&self.dir, filter)
+    }
+
+    /// Clear the page reference bits of this process, or reset its peak rss.
+    pub fn clear_refs(&self, refs: ClearRefs) -> Result<(), ProcError> {
+        tune::clear_refs(&self.dir, refs)
+    }
+
+    /// Get the I/O priority of this process (or thread).
+    pub fn io_priority(&self) -> Result<IoPriority, ProcError> {
+        ioprio::io_priority(self.pid)
+    }
+
+    /// Set the I/O priority of this process (or thread), level being 0 (highest) to 7.
+    pub fn set_io_priority(&self, class: IoClass, level: u8) -> Result<(), ProcError> {
+        ioprio::set_io_priority(self.pid, class, level)
+    }
+
+    /// Read the current nice value of this process (or thread), unlike the
+    /// value in stat which is from when it was read.
+    pub fn nice(&self) -> Result<i32, ProcError> {
+        nice::nice(self.pid)
+    }
+
+    /// Renice this process (or thread), nice being -20 (highest priority) to 19.
+    ///
+    /// Only the given thread is changed, so renice each of the threads
+    /// (see TidIter) to change a whole multithreaded process.
+    pub fn set_nice(&self, nice: i32) -> Result<(), ProcError> {
+        nice::set_nice(self.pid, nice)
+    }
+
+    /// Read the timer slack of this process in nanoseconds.
+    ///
+    /// Kernels older than 4.6 return an Unsupported error.
+    pub fn timer_slack(&self) -> Result<u64, ProcError> {
+        tune::timer_slack(&self.dir)
+    }
+
+    /// Set the timer slack of this process in nanoseconds, 0 resets it to the default.
+    pub fn set_timer_slack(&self, slack_ns: u64) -> Result<(), ProcError> {
+        tune::set_timer_slack(&self.dir, slack_ns)
+    }
+
+    /// Read the timer slack of this process as a Duration.
+    pub fn timer_slack_duration(&self) -> Result<Duration, ProcError> {
+        tune::timer_slack_duration(&self.dir)
+    }
+
+    /// Set the timer slack of this process, a zero Duration resets it to the default.
+    pub fn set_timer_slack_duration(&self, slack: Duration) -> Result<(), ProcError> {
+        tune::set_timer_slack_duration(&self.dir, slack)
+    }
+
+    /// Read the /proc/[pid]/maps file for this process.
+    pub fn maps(&self) -> Result<Vec<PidMap>, ProcError> {
+        PidMap::new(&self.dir)
+    }
+
+    /// Read the /proc/[pid]/smaps file for this process.
+    ///
+    /// This isn't read when creating a Pid, since it can be expensive
+    /// for processes with many mappings.
+    pub fn smaps(&self) -> Result<Vec<PidSmap>, ProcError> {
+        PidSmap::new(&self.dir)
+    }
+
+    /// Read the memory usage summed over all mappings of this process.
+    pub fn smaps_rollup(&self) -> Result<PidSmapsRollup, ProcError> {
+        PidSmapsRollup::new_dir(&self.dir)
+    }
+
+    /// Read the /proc/[pid]/numa_maps file for this process.
+    pub fn numa_maps(&self) -> Result<PidNumaMaps, ProcError> {
+        PidNumaMaps::new(&self.dir)
+    }
+
+    /// Read the environment this process was started with, as (name, value)
+    /// pairs. Values aren't redacted, see environ::Redactor.
+    pub fn environ(&self) -> Result<Vec<(String, String)>, ProcError> {
+        environ::environ(&self.dir)
+    }
+
     /// Given a /proc/[pid] directory, read the respective /proc/[pid]/cmdline
     /// file and return them in a Vec.
     fn read_cmdline(proc_dir: &Path) -> Result<Vec<String>, ProcError> {
+        debug!("reading {}/cmdline", proc_dir.display());
         File::open(proc_dir.join("cmdline"))
             .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidCmdline, e))
             .and_then(|file| {
@@ -90,39 +518,213 @@ impl Pid {
             )
     }
 
+    /// Read the kernel function this process is sleeping in (its wchan),
+    /// returning None if it isn't sleeping in the kernel.
+    pub fn wchan(&self) -> Result<Option<String>, ProcError> {
+        let mut contents = String::new();
+        try!(
+            File::open(self.dir.join("wchan"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidWchan, e))
+                .and_then(|mut file|
+                    file.read_to_string(&mut contents)
+                        .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidWchan, e))
+                )
+        );
+        Ok(parse_wchan(&contents))
+    }
+
+    /// Read the parent of this process.
+    ///
+    /// If the parent exited since this process was read (or its pid was
+    /// reused by a newer process), this process was reparented, so its
+    /// current parent is read instead. Processes without a parent (like
+    /// init and kthreadd) give an error.
+    ///
+    /// The parent is read from the same /proc this process was read from.
+    pub fn parent(&self) -> Result<Pid, ProcError> {
+        let no_parent = || ProcError::new_more(ProcOper::Opening, ProcFile::PidDir,
+            Some("no parent process"));
+        if self.stat.ppid == 0 {
+            return Err(no_parent());
+        }
+        match Pid::new_dir(self.proc_root(), self.stat.ppid) {
+            Ok(parent) if parent.stat.starttime <= self.stat.starttime => return Ok(parent),
+            _ => {}
+        }
+        let ppid = try!(self.read_stat()).ppid;
+        match ppid {
+            0 => Err(no_parent()),
+            ppid => Pid::new_dir(self.proc_root(), ppid),
+        }
+    }
+
+    /// The /proc directory this process was read from, above
+    /// /proc/[pid] (or /proc/[pid]/task/[tid] for a thread).
+    fn proc_root(&self) -> &Path {
+        let depth = match self.is_thread {
+            true => 3,
+            false => 1
+        };
+        self.dir.ancestors().nth(depth).unwrap_or(&self.dir)
+    }
+
+    /// Iterate over the parent of this process, its parent, and so on up to
+    /// init (pid 1), without scanning /proc.
+    pub fn ancestors(&self) -> Ancestors {
+        Ancestors::new(self.clone())
+    }
+
+    /// Read the children of this process, in pid order.
+    ///
+    /// This uses the task/[tid]/children files when the kernel has them
+    /// (CONFIG_PROC_CHILDREN), and otherwise scans /proc for processes with
+    /// this parent. Children that exit while being read are skipped.
+    ///
+    /// Like parent(), the children are read from the same /proc this process
+    /// was read from.
+    pub fn children(&self) -> Result<Vec<Pid>, ProcError> {
+        let proc_root = self.proc_root();
+        let mut children = match tree::read_children(&self.dir) {
+            Some(pids) => pids.into_iter()
+                .filter_map(|p| Pid::new_dir(proc_root, p).ok())
+                .collect(),
+            None => try!(PidIter::new_dir_query(proc_root, PidQuery::PpidQuery(self.pid)))
+                .filter_map(|p| p.ok())
+                .collect::<Vec<_>>(),
+        };
+        children.sort_by_key(|p| p.pid);
+        Ok(children)
+    }
+
+    /// Read the children of this process, their children, and so on, in pid
+    /// order. Like children(), this only scans /proc if the kernel lacks
+    /// the children files.
+    pub fn descendants(&self) -> Result<Vec<Pid>, ProcError> {
+        let proc_root = self.proc_root();
+        let mut descendants = match tree::read_children(&self.dir) {
+            Some(children) => {
+                let mut pids = Vec::new();
+                let mut stack = children;
+                while let Some(pid) = stack.pop() {
+                    // The process exited, or its children can't be read.
+                    let dir = proc_root.join(pid.to_string());
+                    if let Some(children) = tree::read_children(&dir) {
+                        stack.extend(children);
+                    }
+                    pids.push(pid);
+                }
+                pids.into_iter().filter_map(|p| Pid::new_dir(proc_root, p).ok()).collect()
+            },
+            None => {
+                let pids = try!(PidIter::new_dir_query(proc_root, PidQuery::NoneQuery));
+                let tree = ProcessTree::new(pids.filter_map(|p| p.ok()));
+                tree.descendants(self.pid).into_iter()
+                    .filter_map(|p| tree.get(p).cloned())
+                    .collect::<Vec<_>>()
+            },
+        };
+        descendants.sort_by_key(|p| p.pid);
+        Ok(descendants)
+    }
+
+    /// Whether the files of this process could be read, rather than it
+    /// being a Restricted placeholder.
+    fn is_readable(&self) -> bool {
+        self.access == AccessLevel::Full
+    }
+
     /// Determine whether this process matches this query
-    fn query(&self, query: &PidQuery) -> bool {
+    ///
+    /// The parent of a Restricted process is unknown, so it never matches
+    /// ppid, descendant or ancestor queries.
+    ///
+    /// This reads the system state the query needs (like the uptime for
+    /// ages) on every call, so use query_with to match many processes.
+    pub fn query(&self, query: &PidQuery) -> bool {
+        self.query_with(query, &QueryContext::new(query))
+    }
+
+    /// Determine whether this process matches this query, using system
+    /// state read once for a whole scan.
+    pub fn query_with(&self, query: &PidQuery, context: &QueryContext) -> bool {
         match *query {
             PidQuery::PidQuery(q) => PidQuery::taskid_query(self.stat.pid, q),
-            PidQuery::PpidQuery(q) => PidQuery::taskid_query(self.stat.ppid, q),
+            PidQuery::PpidQuery(q) =>
+                self.is_readable() && PidQuery::taskid_query(self.stat.ppid, q),
+            PidQuery::UidQuery(q) => self.status.uid.0 == q,
             PidQuery::NameQuery(ref q) => PidQuery::string_query(&self.stat.comm, &q),
             PidQuery::CmdlineQuery(ref q) => PidQuery::string_query(&self.cmdline.join(" "), &q),
+            PidQuery::ExactNameQuery(ref q) => self.stat.comm == *q,
+            PidQuery::ExactCmdlineQuery(ref q) => self.cmdline.join(" ") == *q,
+            PidQuery::OlderThan(d) => context.uptime.map(|u| self.stat.age(u) > d).unwrap_or(false),
+            PidQuery::NewerThan(d) => context.uptime.map(|u| self.stat.age(u) < d).unwrap_or(false),
+            PidQuery::RssAbove(m) => self.status.vmrss.map(|r| r > m).unwrap_or(false),
+            PidQuery::VszAbove(m) => self.stat.vsize > m,
+            PidQuery::Compare(field, comparison, value) =>
+                field.value(self, context.uptime).map(|v| comparison.matches(v, value))
+                    .unwrap_or(false),
+            PidQuery::DescendantOf(q) => self.is_readable() && context.parents.as_ref()
+                .map(|m| m.is_descendant(self.stat.pid, self.stat.ppid, q)).unwrap_or(false),
+            PidQuery::AncestorOf(q) => self.is_readable() && context.parents.as_ref()
+                .map(|m| m.is_ancestor(self.stat.pid, q)).unwrap_or(false),
+            PidQuery::OpenFileQuery(ref p) => self.uses(p).map(|u| !u.is_empty()).unwrap_or(false),
+            PidQuery::And(ref qs) => qs.iter().all(|q| self.query_with(q, context)),
+            PidQuery::Or(ref qs) => qs.iter().any(|q| self.query_with(q, context)),
+            PidQuery::Not(ref q) => !self.query_with(q, context),
             PidQuery::NoneQuery => true
         }
     }
 
-    pub fn tasks(&mut self) -> Option<Vec<Pid>> {
+    /// Determine whether this process, or any of its threads, matches this
+    /// query.
+    pub fn query_any_thread(&self, query: &PidQuery) -> bool {
+        self.query_any_thread_with(query, &QueryContext::new(query))
+    }
+
+    /// Determine whether this process, or any of its threads, matches this
+    /// query, using system state read once for a whole scan.
+    ///
+    /// If the threads can't be read (eg: the process exited), none of them
+    /// match.
+    pub fn query_any_thread_with(&self, query: &PidQuery, context: &QueryContext) -> bool {
+        self.query_with(query, context) || match self.tasks_query(PidQuery::NoneQuery) {
+            Ok(Some(threads)) => threads.iter().any(|t| t.query_with(query, context)),
+            _ => false
+        }
+    }
+
+    /// Count how many of this process's threads last ran on each CPU.
+    ///
+    /// A thread has no threads of its own, so gives an empty count. This
+    /// gives an error if the threads can't be read (eg: the process exited).
+    pub fn cpu_spread(&self) -> Result<BTreeMap<i32, usize>, ProcError> {
+        let threads = try!(self.tasks_query(PidQuery::NoneQuery)).unwrap_or_default();
+        Ok(aggregate::cpu_spread(&threads).remove(&self.status.tgid).unwrap_or_default())
+    }
+
+    /// Read the threads of this process, or None if this is a thread.
+    pub fn tasks(&self) -> Result<Option<Vec<Pid>>, ProcError> {
         self.tasks_query(PidQuery::NoneQuery)
     }
 
-    // TODO: Work out if this really should return Option<_>
-    // or Option<Result<Vec<Pid>>>. Otherwise the error is uncaught.
-    pub fn tasks_query(&self, query: PidQuery) -> Option<Vec<Pid>> {
+    /// Read the threads of this process that match a query, or None if
+    /// this is a thread.
+    ///
+    /// This gives an error if the task directory can't be read, like when
+    /// the process has exited or /proc is mounted with hidepid.
+    pub fn tasks_query(&self, query: PidQuery) -> Result<Option<Vec<Pid>>, ProcError> {
         if self.is_thread {
-            return None;
+            return Ok(None);
         }
 
-        PidIter::new_tid_query(self.pid, query.clone()).unwrap()
-            .filter(|p| {
-                let query = query.clone();
-                match *p {
-                    Ok(ref pid) => pid.query(&query),
-                    Err(_) => true
-                }
-            }).collect::<Result<Vec<_>, _>>().ok()
+        try!(PidIter::new_tid_query(self.pid, query))
+            .collect::<Result<Vec<_>, _>>().map(Some)
     }
 }
 
+/// Threads are represented by the same struct as processes.
+pub type Tid = Pid;
+
 impl PartialEq for Pid {
     fn eq(&self, other: &Self) -> bool {
         self.stat.pid.eq(&other.stat.pid)
@@ -141,12 +743,74 @@ impl Ord for Pid {
     }
 }
 
+/// Read a file containing a single value, like /proc/[pid]/oom_score.
+fn read_value<T>(path: &Path, file: ProcFile) -> Result<T, ProcError>
+    where T: FromStr, T::Err: Error + 'static {
+    debug!("reading {}", path.display());
+    let mut contents = String::new();
+    try!(
+        File::open(path)
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, file, e))
+            .and_then(|mut f|
+                f.read_to_string(&mut contents)
+                    .map_err(|e| ProcError::new_err(ProcOper::Reading, file, e))
+            )
+    );
+    contents.trim().parse()
+        .map_err(|e| ProcError::new_err(ProcOper::Parsing, file, e))
+}
+
+/// Write a single value to a file, like /proc/[pid]/oom_score_adj.
+fn write_value(path: &Path, file: ProcFile, value: &str) -> Result<(), ProcError> {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| ProcError::new_err(ProcOper::Opening, file, e))
+        .and_then(|mut f|
+            f.write_all(value.as_bytes())
+                .map_err(|e| ProcError::new_err(ProcOper::Writing, file, e))
+        )
+}
+
+/// Read the inode of one of the namespaces of a process (like "mnt" or
+/// "net"), given its process directory. The ns/[kind] link looks like
+/// "mnt:[4026531841]".
+fn ns_inode(pid_dir: &Path, kind: &'static str) -> Result<u64, ProcError> {
+    let target = try!(
+        fs::read_link(pid_dir.join("ns").join(kind))
+            .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidNs, e))
+    );
+    let prefix = format!("{}:[", kind);
+    target.to_string_lossy()
+        .trim_left_matches(&*prefix)
+        .trim_right_matches(']')
+        .parse()
+        .map_err(|e| ProcError::new(ProcOper::Parsing, ProcFile::PidNs, Some(e), Some(kind)))
+}
+
+/// Parse the contents of a wchan file, which is "0" (or empty on newer
+/// kernels) when the process isn't waiting.
+fn parse_wchan(wchan: &str) -> Option<String> {
+    match wchan.trim() {
+        "" | "0" => None,
+        w => Some(w.to_owned())
+    }
+}
+
+/// Parse a string as a kB memory string.
+fn parse_mem(mem_str: &str) -> Result<MemSize, ParseIntError> {
+    mem_str.trim_right_matches(" kB")
+        .parse::<MemSize>()
+        .map(|n| n * 1024)
+}
+
 /// A list of files in the pid directory.
 #[derive(PartialEq, Eq, Hash)]
 pub enum PidFile {
     PidStatus,
     PidStat,
-    PidCmdline
+    PidCmdline,
+    PidAttrCurrent
 }
 
 /// An Iterator over processes in the system.
@@ -158,6 +822,22 @@ pub struct PidIter {
     dir: String,
     dir_iter: ReadDir,
     query: PidQuery,
+    /// Number of matching processes left to skip.
+    offset: usize,
+    /// Number of matching processes left to yield, if limited.
+    limit: Option<usize>,
+    /// Deadline and cancellation for this scan.
+    bound: ScanBound,
+    /// Whether the scan was stopped early by the bound.
+    interrupted: bool,
+    /// Pauses between directory entries, if throttled.
+    throttle: Option<Throttle>,
+    /// Number of directory entries read so far.
+    scanned: usize,
+    /// Whether processes also match through their threads.
+    thread_match: ThreadMatch,
+    /// The system state the query needs, read once for the scan.
+    context: QueryContext,
 }
 
 impl PidIter {
@@ -169,23 +849,86 @@ impl PidIter {
     /// Create a new iterator over all processes in /proc, but only yield
     /// processes that match the given query.
     pub fn new_query(query: PidQuery) -> Result<Self, ProcError> {
-        let dir_name = "/proc".to_owned();
-        let proc_dir = Path::new(&dir_name);
+        Self::new_dir_query(&root::root(), query)
+    }
+
+    /// Create an iterator over the processes in a given /proc directory
+    /// that match the given query.
+    fn new_dir_query(proc_dir: &Path, query: PidQuery) -> Result<Self, ProcError> {
+        let dir_name = proc_dir.to_string_lossy().into_owned();
         let dir_iter = try!(
             fs::read_dir(proc_dir)
                 .map_err(|e|
                     ProcError::new(ProcOper::Opening, ProcFile::ProcDir, Some(e), Some("PidIter"))
                 )
         );
+        let context = QueryContext::new(&query);
         Ok(PidIter {
             dir: dir_name.clone(),
             dir_iter: dir_iter,
             query: query,
+            offset: 0,
+            limit: None,
+            bound: ScanBound::default(),
+            interrupted: false,
+            throttle: None,
+            scanned: 0,
+            thread_match: ThreadMatch::default(),
+            context: context,
+        })
+    }
+
+    /// Stop scanning /proc once n matching processes have been yielded.
+    ///
+    /// Unlike Iterator::take, errors don't count towards the limit.
+    pub fn take_matching(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Skip the first n matching processes.
+    ///
+    /// Unlike Iterator::skip, errors are never skipped.
+    pub fn skip_matching(mut self, n: usize) -> Self {
+        self.offset = n;
+        self
+    }
+
+    /// Stop scanning once the deadline passes or the token is cancelled.
+    pub fn bounded(mut self, bound: ScanBound) -> Self {
+        self.bound = bound;
+        self
+    }
+
+    /// Choose whether processes also match the query through their threads.
+    pub fn thread_match(mut self, thread_match: ThreadMatch) -> Self {
+        self.thread_match = thread_match;
+        self
+    }
+
+    /// Pause between directory entries, to limit the load of the scan.
+    pub fn throttled(mut self, throttle: Throttle) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Whether the scan was stopped early by its bound.
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Collect all matching processes, returning what was found so far
+    /// if the scan is stopped early by its bound.
+    pub fn collect_partial(mut self) -> Result<Partial<Pid>, ProcError> {
+        let items = try!(self.by_ref().collect::<Result<Vec<_>, _>>());
+        Ok(Partial {
+            items: items,
+            interrupted: self.interrupted,
         })
     }
 
     fn new_tid_query(pid: TaskId, query: PidQuery) -> Result<Self, ProcError> {
-        let dir_name = format!("/proc/{}/task", pid);
+        let dir_name = root::pid_dir(pid).join("task").to_string_lossy().into_owned();
         let task_dir = Path::new(&dir_name);
         let dir_iter = try!(
             fs::read_dir(task_dir)
@@ -194,16 +937,26 @@ impl PidIter {
                         Some(e), Some("PidIter"))
                 )
         );
+        let context = QueryContext::new(&query);
         Ok(PidIter {
             dir: dir_name.clone(),
             dir_iter: dir_iter,
-            query: query
+            query: query,
+            offset: 0,
+            limit: None,
+            bound: ScanBound::default(),
+            interrupted: false,
+            throttle: None,
+            scanned: 0,
+            thread_match: ThreadMatch::default(),
+            context: context,
         })
     }
 
     /// Given a DirEntry, try to create a Pid struct, and only return if
     /// it matches the query, and is complete.
-    fn proc_dir_filter(entry_opt: Result<DirEntry, io::Error>, query: &PidQuery, dir_name: &str)
+    fn proc_dir_filter(entry_opt: Result<DirEntry, io::Error>, query: &PidQuery,
+        context: &QueryContext, dir_name: &str, thread_match: ThreadMatch)
         -> Option<Result<Pid, ProcError>> {
         let file = entry_opt
             .map_err(|e|
@@ -230,11 +983,16 @@ impl PidIter {
                         if e.is_hard() {
                             return Some(Err(e));
                         } else {
+                            warn!("skipping {}/{}: {}", dir_name, pid, e);
                             return None;
                         }
                     }
                 };
-                match prc.query(&query) {
+                let matches = match thread_match {
+                    ThreadMatch::Process => prc.query_with(&query, context),
+                    ThreadMatch::AnyThread => prc.query_any_thread_with(&query, context),
+                };
+                match matches {
                     true => Some(Ok(prc)),
                     false => None
                 }
@@ -248,18 +1006,43 @@ impl Iterator for PidIter {
     type Item = Result<Pid, ProcError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for entry in self.dir_iter.by_ref() {
-            match Self::proc_dir_filter(entry, &self.query, &self.dir) {
-                some @ Some(_) => return some,
+        if self.limit == Some(0) {
+            return None;
+        }
+        loop {
+            if self.bound.is_expired() {
+                debug!("scan of {} stopped by its bound", self.dir);
+                self.interrupted = true;
+                return None;
+            }
+            let entry = match self.dir_iter.next() {
+                Some(entry) => entry,
+                None => return None
+            };
+            self.scanned += 1;
+            if let Some(ref throttle) = self.throttle {
+                throttle.after_entries(self.scanned);
+            }
+            match Self::proc_dir_filter(entry, &self.query, &self.context, &self.dir,
+                self.thread_match) {
+                Some(Ok(_)) if self.offset > 0 => self.offset -= 1,
+                some @ Some(Ok(_)) => {
+                    self.limit = self.limit.map(|l| l - 1);
+                    return some;
+                },
+                err @ Some(Err(_)) => return err,
                 None => continue
             }
         }
-        None
     }
 
-    /// Size may be anywhere from 0 to number of dirs.
+    /// Size may be anywhere from 0 to number of dirs (or the limit).
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (0, self.dir_iter.size_hint().1)
+        match (self.limit, self.dir_iter.size_hint().1) {
+            (Some(l), Some(u)) => (0, Some(if l < u { l } else { u })),
+            (Some(l), None) => (0, Some(l)),
+            (None, u) => (0, u),
+        }
     }
 }
 
@@ -277,19 +1060,41 @@ pub struct TidIter {
 impl TidIter {
     /// Create a new iterator over all tasks in /proc.
     pub fn new() -> Result<Self, ProcError> {
-            println!("{:?}", 3);
         Self::new_query(PidQuery::NoneQuery)
     }
 
     /// Create a new iterator over all tasks in /proc, but only yield
     /// those that match the given query.
+    ///
+    /// Each thread is matched by itself, since thread names can differ from
+    /// their process (eg: "tokio-runtime-w"). Processes are only skipped by
+    /// the rest of the query, so each thread is read once.
     pub fn new_query(query: PidQuery) -> Result<Self, ProcError> {
         Ok(TidIter{
-            pid_iter: try!(PidIter::new_query(query.clone())),
+            pid_iter: try!(PidIter::new_query(query.without_names())),
             task_iter: None,
             query: query,
         })
     }
+
+    /// Stop scanning processes once the deadline passes or the token is cancelled.
+    ///
+    /// Threads of a process that has already been read are still yielded.
+    pub fn bounded(mut self, bound: ScanBound) -> Self {
+        self.pid_iter = self.pid_iter.bounded(bound);
+        self
+    }
+
+    /// Pause between processes, to limit the load of the scan.
+    pub fn throttled(mut self, throttle: Throttle) -> Self {
+        self.pid_iter = self.pid_iter.throttled(throttle);
+        self
+    }
+
+    /// Whether the scan was stopped early by its bound.
+    pub fn interrupted(&self) -> bool {
+        self.pid_iter.interrupted()
+    }
 }
 
 impl Iterator for TidIter {
@@ -303,9 +1108,11 @@ impl Iterator for TidIter {
                     Some(Err(e)) => { return Some(Err(e)) },
                     None => { return None; }
                 };
-                let tasks_vec = pid.tasks_query(self.query.clone());
-                if let Some(vec) = tasks_vec {
-                    self.task_iter = Some(vec.into_iter());
+                match pid.tasks_query(self.query.clone()) {
+                    Ok(Some(vec)) => self.task_iter = Some(vec.into_iter()),
+                    Ok(None) => {},
+                    Err(ref e) if !e.is_hard() => warn!("skipping threads of {}: {}", pid.pid, e),
+                    Err(e) => return Some(Err(e)),
                 }
                 continue;
             } else {
@@ -319,6 +1126,34 @@ impl Iterator for TidIter {
     }
 }
 
+/// How much of a process could be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLevel {
+    /// The stat, status and cmdline files were read.
+    Full,
+    /// The files of the process couldn't be read (eg: /proc is mounted with
+    /// hidepid=1), so only the pid and owning uid and gid are known. Every
+    /// other field is empty or 0.
+    Restricted,
+}
+
+/// How a query is matched against a process with several threads, whose
+/// names and other fields can differ from the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadMatch {
+    /// Only match the process itself (its main thread).
+    Process,
+    /// Match a process if it or any of its threads match, eg: so a name
+    /// query finds a process by the name of one of its threads.
+    AnyThread,
+}
+
+impl Default for ThreadMatch {
+    fn default() -> Self {
+        ThreadMatch::Process
+    }
+}
+
 #[derive(Clone, Debug)]
 /// A list of query types for process querying.
 pub enum PidQuery {
@@ -326,10 +1161,42 @@ pub enum PidQuery {
     PidQuery(TaskId),
     /// Query by ppid
     PpidQuery(TaskId),
+    /// Query by real uid
+    UidQuery(u32),
     /// Query by program name
     NameQuery(String),
     /// Query by cmdline contents (joined with space)
     CmdlineQuery(String),
+    /// Query by whole program name, so "sh" doesn't match "bash" (like pgrep -x)
+    ExactNameQuery(String),
+    /// Query by whole cmdline (joined with space)
+    ExactCmdlineQuery(String),
+    /// Query for processes that started more than this long ago
+    OlderThan(Duration),
+    /// Query for processes that started less than this long ago
+    NewerThan(Duration),
+    /// Query for processes with a resident set size above this many bytes
+    RssAbove(MemSize),
+    /// Query for processes with a virtual memory size above this many bytes
+    VszAbove(MemSize),
+    /// Query for processes with a numeric field compared to a value
+    Compare(QueryField, Comparison, f64),
+    /// Query for processes below this process (its children, their
+    /// children and so on, but not the process itself)
+    DescendantOf(TaskId),
+    /// Query for processes above this process (its parent, their parent and
+    /// so on, but not the process itself)
+    AncestorOf(TaskId),
+    /// Query for processes using a file or anything under a directory, through
+    /// an fd, memory mapping, cwd, root or exe (like fuser). The path should
+    /// be canonical, like the paths the kernel reports.
+    OpenFileQuery(PathBuf),
+    /// Query for processes matching every one of these queries
+    And(Vec<PidQuery>),
+    /// Query for processes matching any one of these queries
+    Or(Vec<PidQuery>),
+    /// Query for processes not matching this query
+    Not(Box<PidQuery>),
     /// An empty query that always matches
     NoneQuery
 }
@@ -344,9 +1211,27 @@ impl PidQuery {
     /// type=query is supported for the following types;
     /// pid -> PidQuery
     /// ppid -> PpidQuery
-    /// name -> NameQuery
-    /// cmdline -> CmdlineQuery
+    /// uid -> UidQuery
+    /// user -> UidQuery, by user name or uid (eg: user=postgres)
+    /// descendant_of -> DescendantOf
+    /// ancestor_of -> AncestorOf
+    /// file -> OpenFileQuery (eg: file=/mnt/usb)
+    /// name -> NameQuery (eg: name=fire, or a glob like name=fire*)
+    /// cmdline -> CmdlineQuery (eg: cmdline=*--config=/etc/*)
+    /// name= -> ExactNameQuery (eg: name==sh)
+    /// cmdline= -> ExactCmdlineQuery
+    /// older_than -> OlderThan (eg: older_than=2h30m)
+    /// newer_than -> NewerThan
+    /// rss_above -> RssAbove (eg: rss_above=1.5G)
+    /// vsz_above -> VszAbove
+    ///
+    /// Numeric fields can be compared with <, <=, =, >= and >, for the
+    /// fields pid, ppid, uid, rss, vsz, cpu, threads, nice, priority and age
+    /// (eg: rss>500M, cpu>10%, threads>=100, nice<0, age>1h).
     fn create_query(query: &str) -> Result<PidQuery, String> {
+        if let Some(compare) = compare::parse(query) {
+            return compare.map(|(f, c, v)| PidQuery::Compare(f, c, v));
+        }
         let splits: Vec<_> = query.splitn(2, '=').collect();
 
         match splits.len() {
@@ -363,22 +1248,121 @@ impl PidQuery {
                         .or(Err("Query value for type 'pid' not valid".to_owned())),
                     "ppid" => q_tid.map(|q| PidQuery::PpidQuery(q))
                         .or(Err("Query value for type 'ppid' not valid".to_owned())),
+                    "uid" => q_text.parse().map(PidQuery::UidQuery)
+                        .or(Err("Query value for type 'uid' not valid".to_owned())),
+                    "user" => q_text.parse().ok().or_else(|| users::uid_by_name(&q_text))
+                        .map(PidQuery::UidQuery)
+                        .ok_or(format!("Unknown user '{}'", q_text)),
+                    "descendant_of" => q_tid.map(PidQuery::DescendantOf)
+                        .or(Err("Query value for type 'descendant_of' not valid".to_owned())),
+                    "ancestor_of" => q_tid.map(PidQuery::AncestorOf)
+                        .or(Err("Query value for type 'ancestor_of' not valid".to_owned())),
+                    "file" => Ok(PidQuery::OpenFileQuery(
+                        fs::canonicalize(&q_text).unwrap_or(PathBuf::from(q_text)))),
+                    // A second '=' (eg: name==sh) matches the whole text.
+                    "name" if q_text.starts_with('=') =>
+                        Ok(PidQuery::ExactNameQuery(q_text[1..].to_owned())),
+                    "cmdline" if q_text.starts_with('=') =>
+                        Ok(PidQuery::ExactCmdlineQuery(q_text[1..].to_owned())),
                     "name" => Ok(PidQuery::NameQuery(q_text)),
                     "cmdline" => Ok(PidQuery::CmdlineQuery(q_text)),
-                    _ => Err("Invalid query type".to_owned())
+                    "older_than" => format::parse_duration(&q_text).map(PidQuery::OlderThan),
+                    "newer_than" => format::parse_duration(&q_text).map(PidQuery::NewerThan),
+                    "rss_above" => format::parse_size(&q_text).map(PidQuery::RssAbove),
+                    "vsz_above" => format::parse_size(&q_text).map(PidQuery::VszAbove),
+                    field => match field.parse::<QueryField>() {
+                        Ok(field) => field.parse_value(&q_text)
+                            .map(|v| PidQuery::Compare(field, Comparison::Equal, v)),
+                        Err(_) => Err("Invalid query type".to_owned())
+                    }
                 }
             }
         }
     }
 
+    /// This query with name queries replaced by ones that match any process,
+    /// so that processes with matching threads aren't skipped.
+    fn without_names(&self) -> PidQuery {
+        match *self {
+            PidQuery::NameQuery(_) | PidQuery::ExactNameQuery(_) => PidQuery::NoneQuery,
+            PidQuery::And(ref qs) => PidQuery::And(qs.iter().map(|q| q.without_names()).collect()),
+            PidQuery::Or(ref qs) => PidQuery::Or(qs.iter().map(|q| q.without_names()).collect()),
+            // Negating the replacement would match nothing, so anything
+            // negating a name query has to match every process.
+            PidQuery::Not(ref q) if q.has_names() => PidQuery::NoneQuery,
+            ref q => q.clone()
+        }
+    }
+
+    /// Whether this query contains a name query.
+    fn has_names(&self) -> bool {
+        self.contains(&|q| match *q {
+            PidQuery::NameQuery(_) | PidQuery::ExactNameQuery(_) => true,
+            _ => false
+        })
+    }
+
+    /// Whether any query within this one (or this one, if it doesn't
+    /// combine others) is matched by a function.
+    fn contains<F: Fn(&PidQuery) -> bool>(&self, f: &F) -> bool {
+        match *self {
+            PidQuery::And(ref qs) | PidQuery::Or(ref qs) => qs.iter().any(|q| q.contains(f)),
+            PidQuery::Not(ref q) => q.contains(f),
+            ref q => f(q)
+        }
+    }
+
     /// Match a pid by simple equality.
     pub fn taskid_query(tid: TaskId, query: TaskId) -> bool {
         tid == query
     }
 
-    /// For strings, use a substring search.
+    /// For strings, use a substring search. Queries containing '*', '?' or
+    /// '[' are globs (see format::glob_match) matched against the whole text
+    /// instead, so "fire*" only matches text starting with "fire".
     pub fn string_query(text: &str, query: &str) -> bool {
-        text.contains(query)
+        match query.contains(|c| c == '*' || c == '?' || c == '[') {
+            true => format::glob_match(query, text),
+            false => text.contains(query)
+        }
+    }
+}
+
+/// The system state that queries need besides the process itself, read
+/// once for a scan rather than for every process.
+#[derive(Debug, Clone, Default)]
+pub struct QueryContext {
+    /// The system uptime in seconds, if the query needs it and it could
+    /// be read.
+    pub uptime: Option<f64>,
+    /// The parent of every process, if the query needs it and /proc could
+    /// be read. Processes started after it was read are found through their
+    /// own parent.
+    pub parents: Option<ParentMap>,
+}
+
+impl QueryContext {
+    /// Read the system state a query needs.
+    pub fn new(query: &PidQuery) -> Self {
+        let needs_uptime = query.contains(&|q| match *q {
+            PidQuery::OlderThan(_) | PidQuery::NewerThan(_) => true,
+            PidQuery::Compare(field, _, _) => field.needs_uptime(),
+            _ => false
+        });
+        let needs_parents = query.contains(&|q| match *q {
+            PidQuery::DescendantOf(_) | PidQuery::AncestorOf(_) => true,
+            _ => false
+        });
+        QueryContext {
+            uptime: match needs_uptime {
+                true => Uptime::new().ok().map(|u| u.uptime),
+                false => None
+            },
+            parents: match needs_parents {
+                true => ParentMap::new().ok(),
+                false => None
+            },
+        }
     }
 }
 
@@ -389,3 +1373,361 @@ impl FromStr for PidQuery {
         Self::create_query(s)
     }
 }
+
+#[test]
+fn test_myself() {
+    let myself = Pid::myself().unwrap();
+    assert_eq!(myself.pid, myself.status.tgid);
+    let thread = Tid::current_thread().unwrap();
+    assert_eq!(thread.status.tgid, myself.pid);
+    assert_eq!(thread.pid, thread.status.pid);
+}
+
+#[test]
+fn test_restricted() {
+    use std::env;
+    // Stand in for a process directory of another user under hidepid=1,
+    // whose directory can be seen but not read.
+    let parent = env::temp_dir().join(format!("procrs-restricted-{}", Pid::myself().unwrap().pid));
+    let dir = parent.join("4321");
+    fs::create_dir_all(&dir).unwrap();
+    let pid = Pid::new_restricted(dir.clone(), false).unwrap();
+    let meta = fs::metadata(&dir).unwrap();
+    fs::remove_dir_all(&parent).unwrap();
+    assert_eq!(pid.pid, 4321);
+    assert_eq!(pid.access, AccessLevel::Restricted);
+    assert_eq!(pid.status.uid.1, meta.uid());
+    assert!(pid.cmdline.is_empty());
+    assert!(pid.query(&PidQuery::PidQuery(4321)));
+    assert!(!pid.query(&PidQuery::NameQuery("init".to_owned())));
+    assert!(Pid::new_restricted(PathBuf::from("/proc/self"), false).is_err());
+    assert_eq!(Pid::myself().unwrap().access, AccessLevel::Full);
+}
+
+#[test]
+fn test_restricted_scan() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::thread;
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    fake.process(&FakePid::new(1, 0, "init")).unwrap();
+    fake.process(&FakePid::new(4321, 1, "secret")).unwrap();
+    fs::set_permissions(fake.path().join("4321/stat"), fs::Permissions::from_mode(0o000))
+        .unwrap();
+    let proc_dir = fake.path().to_owned();
+    // Scan as an unprivileged filesystem user, since root can read the file
+    // anyway. The fsuid (and the capabilities it drops) is per-thread.
+    let mut pids = thread::spawn(move || {
+        unsafe { ::libc::setfsuid(65534) };
+        root::with_root(proc_dir, ||
+            PidIter::new().unwrap().map(|p| p.unwrap()).collect::<Vec<_>>())
+    }).join().unwrap();
+    pids.sort_by_key(|p| p.pid);
+    assert_eq!(pids.iter().map(|p| (p.pid, p.access)).collect::<Vec<_>>(),
+        vec![(1, AccessLevel::Full), (4321, AccessLevel::Restricted)]);
+    let restricted = &pids[1];
+    assert_eq!(restricted.stat.state, PidState::Unknown);
+    assert!(restricted.query(&PidQuery::PidQuery(4321)));
+    assert!(!restricted.query(&PidQuery::PpidQuery(0)));
+    assert!(!restricted.query(&PidQuery::DescendantOf(0)));
+    assert!(pids[0].query(&PidQuery::PpidQuery(0)));
+}
+
+#[test]
+fn test_ns_inode() {
+    use std::os::unix::fs::symlink;
+    use testing::FakeProc;
+    let fake = FakeProc::new().unwrap();
+    let dir = fake.path().join("10");
+    fs::create_dir_all(dir.join("ns")).unwrap();
+    symlink("mnt:[4026531841]", dir.join("ns/mnt")).unwrap();
+    symlink("net:[bogus]", dir.join("ns/net")).unwrap();
+    assert_eq!(ns_inode(&dir, "mnt").unwrap(), 4026531841);
+    assert!(ns_inode(&dir, "net").unwrap_err().is_hard());
+    assert!(ns_inode(&dir, "pid").unwrap_err().is_not_found());
+}
+
+#[test]
+fn test_parse_wchan() {
+    assert_eq!(parse_wchan("0"), None);
+    assert_eq!(parse_wchan(""), None);
+    assert_eq!(parse_wchan("do_epoll_wait"), Some("do_epoll_wait".to_owned()));
+    assert!(Pid::myself().unwrap().wchan().is_ok());
+}
+
+#[test]
+fn test_take_matching() {
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    for &(pid, comm) in &[(1, "init"), (10, "sshd"), (11, "sshd"), (12, "sshd"), (20, "cron")] {
+        fake.process(&FakePid::new(pid, 1, comm)).unwrap();
+    }
+    // A stat file that can't be parsed is an error, whatever the query.
+    fake.file("30/stat", "garbage").unwrap();
+    fake.run(|| {
+        let sshd = || PidIter::new_query("name=sshd".parse().unwrap()).unwrap();
+        let count = |iter: PidIter| iter.filter(|p| p.is_ok()).count();
+        assert_eq!(count(sshd().take_matching(2)), 2);
+        assert_eq!(count(sshd().take_matching(5)), 3);
+        assert_eq!(count(sshd().skip_matching(2)), 1);
+        assert_eq!(count(sshd().skip_matching(1).take_matching(1)), 1);
+        // Errors are never skipped.
+        let skipped: Vec<_> = sshd().skip_matching(3).collect();
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].is_err());
+    });
+}
+
+#[test]
+fn test_missing_tasks() {
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    fake.process(&FakePid::new(1, 0, "init")).unwrap();
+    fake.process(&FakePid::new(10, 1, "sshd")).unwrap();
+    // The process exited after its stat file was read.
+    fs::remove_dir_all(fake.path().join("10/task")).unwrap();
+    fake.run(|| {
+        let sshd = Pid::new(10).unwrap();
+        assert!(sshd.tasks().is_err());
+        assert!(sshd.query_any_thread(&"name=sshd".parse().unwrap()));
+        assert!(!sshd.query_any_thread(&"name=init".parse().unwrap()));
+        let found: Vec<_> = PidIter::new_query("name=init".parse().unwrap()).unwrap()
+            .thread_match(ThreadMatch::AnyThread)
+            .map(|p| p.unwrap().pid)
+            .collect();
+        assert_eq!(found, vec![1]);
+        let threads: Vec<_> = TidIter::new().unwrap().map(|t| t.unwrap().pid).collect();
+        assert_eq!(threads, vec![1]);
+    });
+}
+
+#[test]
+fn test_bounded() {
+    use cancel::CancelToken;
+    let token = CancelToken::new();
+    token.cancel();
+    let bound = ScanBound { deadline: None, cancel: Some(token) };
+    let partial = PidIter::new().unwrap().bounded(bound).collect_partial().unwrap();
+    assert!(partial.interrupted);
+    assert!(partial.items.is_empty());
+}
+
+#[test]
+fn test_fds() {
+    let fds = Pid::myself().unwrap().fds().unwrap();
+    assert!(fds.iter().any(|f| f.info.is_some()));
+}
+
+#[test]
+fn test_cpu_spread() {
+    let pid = Pid::myself().unwrap();
+    let spread = pid.cpu_spread().unwrap();
+    assert!(spread.values().sum::<usize>() >= 1);
+
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    fake.process(&FakePid::new(10, 1, "sh")).unwrap();
+    fake.run(|| {
+        let pid = Pid::new(10).unwrap();
+        assert_eq!(pid.cpu_spread().unwrap().values().sum::<usize>(), 1);
+        // The process exited after it was read.
+        fs::remove_dir_all(fake.path().join("10")).unwrap();
+        assert!(pid.cpu_spread().is_err());
+    });
+}
+
+#[test]
+fn test_thread_names() {
+    use std::sync::mpsc::channel;
+    use std::thread;
+    let (ready_tx, ready_rx) = channel();
+    let (done_tx, done_rx) = channel::<()>();
+    let handle = thread::Builder::new().name("procrs-named".to_owned()).spawn(move || {
+        ready_tx.send(Tid::current_thread().unwrap().comm().unwrap()).unwrap();
+        done_rx.recv().ok();
+    }).unwrap();
+    assert_eq!(ready_rx.recv().unwrap(), "procrs-named");
+    let found = TidIter::new_query(PidQuery::NameQuery("procrs-named".to_owned())).unwrap()
+        .filter_map(|t| t.ok())
+        .any(|t| t.status.tgid == Pid::myself().unwrap().pid);
+    assert!(found);
+    let query = PidQuery::ExactNameQuery("procrs-named".to_owned());
+    let myself = Pid::myself().unwrap();
+    assert!(!myself.query(&query));
+    assert!(myself.query_any_thread(&query));
+    let found = PidIter::new_query(query).unwrap()
+        .thread_match(ThreadMatch::AnyThread)
+        .filter_map(|p| p.ok())
+        .any(|p| p.pid == myself.pid);
+    assert!(found);
+    done_tx.send(()).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_start_time() {
+    let myself = Pid::myself().unwrap();
+    let start = myself.start_time().unwrap();
+    assert!(start > KernelStat::new().unwrap().boot_time());
+    assert!(start <= SystemTime::now());
+    assert!(myself.elapsed().unwrap() < Duration::from_secs(86400));
+}
+
+#[test]
+fn test_age_query() {
+    let myself = Pid::myself().unwrap();
+    assert!(myself.query(&PidQuery::NewerThan(Duration::from_secs(86400 * 365))));
+    assert!(!myself.query(&PidQuery::OlderThan(Duration::from_secs(86400 * 365))));
+}
+
+#[test]
+fn test_memory_query() {
+    let myself = Pid::myself().unwrap();
+    assert!(myself.query(&PidQuery::RssAbove(0)));
+    assert!(myself.query(&PidQuery::VszAbove(myself.stat.vsize - 1)));
+    assert!(!myself.query(&PidQuery::VszAbove(myself.stat.vsize)));
+}
+
+#[test]
+fn test_uid_query() {
+    let myself = Pid::myself().unwrap();
+    assert!(myself.query(&PidQuery::UidQuery(myself.status.uid.0)));
+    assert!(!myself.query(&PidQuery::UidQuery(myself.status.uid.0 + 1)));
+    match "user=root".parse() {
+        Ok(PidQuery::UidQuery(0)) => {},
+        q => panic!("{:?}", q),
+    }
+    match "user=1000".parse() {
+        Ok(PidQuery::UidQuery(1000)) => {},
+        q => panic!("{:?}", q),
+    }
+    assert_eq!("user=no such user".parse::<PidQuery>().err(),
+        Some("Unknown user 'no such user'".to_owned()));
+    assert!("uid=root".parse::<PidQuery>().is_err());
+}
+
+#[test]
+fn test_compare_query() {
+    let myself = Pid::myself().unwrap();
+    let query = |q: &str| myself.query(&q.parse().unwrap());
+    assert!(query(&format!("pid>={}", myself.pid)));
+    assert!(!query(&format!("pid<{}", myself.pid)));
+    assert!(query(&format!("threads={}", myself.stat.num_threads)));
+    assert!(query("rss>0"));
+    assert!(query("age<100d"));
+    assert!(!query("vsz<1"));
+    assert_eq!("threads=lots".parse::<PidQuery>().err(), Some("Invalid number 'lots'".to_owned()));
+}
+
+#[test]
+fn test_family_query() {
+    use std::process::Command;
+    let myself = Pid::myself().unwrap();
+    let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+    let child_pid = Pid::new(child.id() as TaskId).unwrap();
+    assert!(child_pid.query(&PidQuery::DescendantOf(myself.pid)));
+    assert!(child_pid.query(&PidQuery::DescendantOf(1)) || myself.stat.ppid == 0);
+    assert!(!myself.query(&PidQuery::DescendantOf(myself.pid)));
+    assert!(!myself.query(&PidQuery::DescendantOf(child_pid.pid)));
+    assert!(myself.query(&format!("ancestor_of={}", child_pid.pid).parse().unwrap()));
+    let parent = Pid::new(myself.stat.ppid).unwrap();
+    assert!(parent.query(&PidQuery::AncestorOf(myself.pid)));
+    assert!(!child_pid.query(&PidQuery::AncestorOf(myself.pid)));
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_open_file_query() {
+    let myself = Pid::myself().unwrap();
+    let exe = fs::read_link("/proc/self/exe").unwrap();
+    assert!(myself.query(&PidQuery::OpenFileQuery(exe.clone())));
+    assert!(myself.query(&format!("file={}", exe.display()).parse().unwrap()));
+    // The test binary is open (and mapped) inside its directory.
+    assert!(myself.query(&PidQuery::OpenFileQuery(exe.parent().unwrap().to_path_buf())));
+    assert!(!myself.query(&PidQuery::OpenFileQuery(PathBuf::from("/nonexistent"))));
+}
+
+#[test]
+fn test_exact_query() {
+    let myself = Pid::myself().unwrap();
+    let comm = myself.stat.comm.clone();
+    let query = |q: &str| myself.query(&q.parse().unwrap());
+    assert!(query(&format!("name=={}", comm)));
+    assert!(query(&format!("name={}", &comm[1..])));
+    assert!(!query(&format!("name=={}", &comm[1..])));
+    assert!(query(&format!("cmdline=={}", myself.cmdline.join(" "))));
+    assert!(!myself.query(&PidQuery::ExactCmdlineQuery(myself.cmdline[0].clone() + " x")));
+    match "name==sh".parse() {
+        Ok(PidQuery::ExactNameQuery(ref q)) if q == "sh" => {},
+        q => panic!("{:?}", q),
+    }
+}
+
+#[test]
+fn test_glob_query() {
+    assert!(PidQuery::string_query("firefox", "fire"));
+    assert!(PidQuery::string_query("firefox", "fire*"));
+    assert!(!PidQuery::string_query("wildfire", "fire*"));
+    assert!(PidQuery::string_query("wildfire", "*fire*"));
+    assert!(PidQuery::string_query("nginx --config=/etc/nginx.conf", "*--config=/etc/*"));
+    assert!(!PidQuery::string_query("nginx --config=/srv/nginx.conf", "*--config=/etc/*"));
+    let myself = Pid::myself().unwrap();
+    assert!(myself.query(&format!("name={}*", &myself.stat.comm[..2]).parse().unwrap()));
+}
+
+#[test]
+fn test_combined_query() {
+    let myself = Pid::myself().unwrap();
+    let me = || PidQuery::PidQuery(myself.pid);
+    let other = || PidQuery::PidQuery(-1);
+    assert!(myself.query(&PidQuery::And(vec![me(), PidQuery::RssAbove(0)])));
+    assert!(!myself.query(&PidQuery::And(vec![me(), other()])));
+    assert!(myself.query(&PidQuery::Or(vec![other(), me()])));
+    assert!(!myself.query(&PidQuery::Or(vec![])));
+    assert!(myself.query(&PidQuery::Not(Box::new(other()))));
+    assert!(myself.query(&PidQuery::And(vec![PidQuery::Not(Box::new(PidQuery::Or(vec![other()]))),
+        me()])));
+    let name = PidQuery::NameQuery("thread-name".to_owned());
+    match PidQuery::And(vec![name.clone(), me()]).without_names() {
+        PidQuery::And(qs) => match qs[0] { PidQuery::NoneQuery => {}, ref q => panic!("{:?}", q) },
+        q => panic!("{:?}", q),
+    }
+    match PidQuery::Not(Box::new(name)).without_names() {
+        PidQuery::NoneQuery => {},
+        q => panic!("{:?}", q),
+    }
+}
+
+#[test]
+fn test_owned_unix_sockets() {
+    use std::os::unix::net::UnixDatagram;
+    let (_a, _b) = UnixDatagram::pair().unwrap();
+    let sockets = Pid::myself().unwrap().owned_unix_sockets().unwrap();
+    assert!(sockets.iter().filter(|s| s.socket_type == ::net::unix::UnixType::Dgram).count() >= 2);
+}
+
+#[test]
+fn test_threshold_query_parsing() {
+    match "older_than=2h30m".parse() {
+        Ok(PidQuery::OlderThan(d)) => assert_eq!(d, Duration::from_secs(9000)),
+        q => panic!("{:?}", q),
+    }
+    match "rss_above=1.5G".parse() {
+        Ok(PidQuery::RssAbove(m)) => assert_eq!(m, 3 << 29),
+        q => panic!("{:?}", q),
+    }
+    assert_eq!("vsz_above=lots".parse::<PidQuery>().err(), Some("Invalid size 'lots'".to_owned()));
+}
+
+#[test]
+fn test_throttled() {
+    let count = PidIter::new().unwrap().count();
+    let start = ::std::time::Instant::now();
+    let throttled = PidIter::new().unwrap()
+        .throttled(Throttle::new(1, Duration::from_millis(1)))
+        .count();
+    // Processes may come and go between the scans, but /proc always has pid 1.
+    assert!(throttled > 0 && count > 0);
+    assert!(start.elapsed() >= Duration::from_millis(1));
+}
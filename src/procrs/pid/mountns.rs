@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use error::{ProcError, ProcFile, ProcOper};
+use TaskId;
+use mounts::MountInfo;
+use super::{Pid, PidIter};
+
+/// Names of processes that start containers, so a process under one of
+/// these is expected to have a different root or mounts.
+pub const CONTAINER_RUNTIMES: [&'static str; 10] = [
+    "containerd-shim", "containerd-shim-runc-v2", "runc", "crun", "conmon", "dockerd",
+    "podman", "lxc-start", "systemd-nspawn", "bwrap"
+];
+
+/// A group of processes sharing a mount namespace and root directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountNs {
+    /// The inode of the mount namespace.
+    pub inode: u64,
+    /// The root directory of these processes, as seen from the caller.
+    pub root: PathBuf,
+    /// The processes in this group, in scan order.
+    pub pids: Vec<TaskId>,
+    /// Whether the root differs from init's root (eg: a chroot).
+    pub root_differs: bool,
+    /// Whether the mount points differ from init's.
+    pub mounts_differ: bool,
+    /// The container runtime found among the ancestors of the first process, if any.
+    pub runtime: Option<String>,
+}
+
+impl MountNs {
+    /// Whether these processes are isolated from init's view of the
+    /// filesystem without a known container runtime, like a hand-made chroot.
+    pub fn is_unmanaged(&self) -> bool {
+        (self.root_differs || self.mounts_differ) && self.runtime.is_none()
+    }
+}
+
+/// Read the inode of the mount namespace of a process, given its process directory.
+pub fn mount_namespace(pid_dir: &Path) -> Result<u64, ProcError> {
+    super::ns_inode(pid_dir, "mnt")
+}
+
+/// Read the root directory of a process, given its process directory.
+pub fn root(pid_dir: &Path) -> Result<PathBuf, ProcError> {
+    fs::read_link(pid_dir.join("root"))
+        .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidRoot, e))
+}
+
+/// Read the mount points of a process, in mount order, given its process directory.
+pub fn mount_points(pid_dir: &Path) -> Result<Vec<PathBuf>, ProcError> {
+    Ok(try!(MountInfo::new_dir(pid_dir)).into_iter()
+        .map(|m| m.mount_point)
+        .collect())
+}
+
+/// Find the first container runtime among the ancestors of a process.
+fn find_runtime(pid: TaskId, parents: &HashMap<TaskId, (TaskId, String)>) -> Option<String> {
+    let mut current = pid;
+    // Bound the walk, in case pid reuse during the scan created a cycle.
+    for _ in 0..parents.len() {
+        let &(ppid, ref comm) = match parents.get(&current) {
+            Some(p) => p,
+            None => return None
+        };
+        if CONTAINER_RUNTIMES.contains(&comm.as_str()) {
+            return Some(comm.clone());
+        }
+        current = ppid;
+    }
+    None
+}
+
+/// Group every process by mount namespace and root, comparing each group
+/// with init (pid 1).
+///
+/// Reading another process's root and mounts needs the same permissions as
+/// ptrace, so processes that can't be inspected are skipped.
+pub fn mount_namespaces() -> Result<Vec<MountNs>, ProcError> {
+    let init_dir = ::root::pid_dir(1);
+    let init_root = try!(root(&init_dir));
+    let init_mounts = try!(mount_points(&init_dir));
+    let pids: Vec<Pid> = try!(PidIter::new()).filter_map(|p| p.ok()).collect();
+    let parents = pids.iter()
+        .map(|p| (p.stat.pid, (p.stat.ppid, p.stat.comm.clone())))
+        .collect();
+
+    let mut groups: Vec<MountNs> = Vec::new();
+    let mut indexes = HashMap::new();
+    for pid in &pids {
+        let dir = ::root::pid_dir(pid.stat.pid);
+        let (inode, pid_root) = match (mount_namespace(&dir), root(&dir)) {
+            (Ok(inode), Ok(pid_root)) => (inode, pid_root),
+            _ => continue
+        };
+        let index = match indexes.get(&(inode, pid_root.clone())) {
+            Some(&index) => index,
+            None => {
+                let mounts_differ = mount_points(&dir).map(|m| m != init_mounts).unwrap_or(false);
+                groups.push(MountNs {
+                    inode: inode,
+                    root_differs: pid_root != init_root,
+                    root: pid_root.clone(),
+                    pids: Vec::new(),
+                    mounts_differ: mounts_differ,
+                    runtime: find_runtime(pid.stat.pid, &parents),
+                });
+                indexes.insert((inode, pid_root), groups.len() - 1);
+                groups.len() - 1
+            }
+        };
+        groups[index].pids.push(pid.stat.pid);
+    }
+    Ok(groups)
+}
+
+#[test]
+fn test_find_runtime() {
+    let mut parents = HashMap::new();
+    parents.insert(1, (0, "systemd".to_owned()));
+    parents.insert(10, (1, "containerd-shim".to_owned()));
+    parents.insert(20, (10, "nginx".to_owned()));
+    parents.insert(30, (1, "sshd".to_owned()));
+    assert_eq!(find_runtime(20, &parents), Some("containerd-shim".to_owned()));
+    assert_eq!(find_runtime(30, &parents), None);
+}
+
+#[test]
+fn test_own_namespace() {
+    let dir = Path::new("/proc/self");
+    assert!(mount_namespace(dir).unwrap() > 0);
+    assert!(!mount_points(dir).unwrap().is_empty());
+}
@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use error::ProcError;
+use net::dev::{self, NetDev};
+pub use net::dev::NetRate;
+
+/// Samples the interface counters seen by a process over time.
+///
+/// The kernel only keeps these counters per network namespace, so this
+/// measures the traffic of every process sharing the namespace (eg: a whole
+/// container), not of the process itself. Use namespace() to tell which
+/// processes share counters.
+pub struct NetNsSampler {
+    dir: PathBuf,
+    namespace: Option<u64>,
+    last: Option<(Instant, Vec<NetDev>)>,
+}
+
+impl NetNsSampler {
+    /// Create a sampler given a process directory. No sample is taken yet.
+    pub fn new(pid_dir: &Path) -> Self {
+        NetNsSampler {
+            dir: pid_dir.to_path_buf(),
+            namespace: net_namespace(pid_dir).ok(),
+            last: None,
+        }
+    }
+
+    /// The inode of the network namespace being sampled, if it could be read.
+    pub fn namespace(&self) -> Option<u64> {
+        self.namespace
+    }
+
+    /// Take a sample, returning the rates since the previous sample.
+    /// The first sample has nothing to compare to, so returns None.
+    pub fn sample(&mut self) -> Result<Option<Vec<NetRate>>, ProcError> {
+        let devs = try!(NetDev::new_dir(&self.dir));
+        let now = Instant::now();
+        let rates = self.last.as_ref()
+            .map(|&(then, ref last)| dev::rates(last, &devs, now.duration_since(then)));
+        self.last = Some((now, devs));
+        Ok(rates)
+    }
+}
+
+/// Read the inode of the network namespace of a process, given its process directory.
+pub fn net_namespace(pid_dir: &Path) -> Result<u64, ProcError> {
+    super::ns_inode(pid_dir, "net")
+}
+
+#[test]
+fn test_sampler() {
+    let mut sampler = NetNsSampler::new(Path::new("/proc/self"));
+    assert!(sampler.namespace().is_some());
+    assert_eq!(sampler.sample().unwrap(), None);
+    assert!(sampler.sample().unwrap().is_some());
+}
@@ -0,0 +1,59 @@
+use std::io;
+use libc;
+use error::{ProcError, ProcFile, ProcOper};
+use TaskId;
+
+/// Highest (most favourable) nice value.
+pub const NICE_MIN: i32 = -20;
+/// Lowest (least favourable) nice value.
+pub const NICE_MAX: i32 = 19;
+
+/// Get the nice value of a thread or process.
+pub fn nice(pid: TaskId) -> Result<i32, ProcError> {
+    // -1 is a valid nice value, so errors can only be told apart by errno.
+    let (nice, err) = unsafe {
+        *libc::__errno_location() = 0;
+        let nice = libc::getpriority(libc::PRIO_PROCESS, pid as libc::id_t);
+        (nice, io::Error::last_os_error())
+    };
+    if nice == -1 && err.raw_os_error() != Some(0) {
+        return Err(ProcError::new_err(ProcOper::Reading, ProcFile::PidPriority, err));
+    }
+    Ok(nice)
+}
+
+/// Set the nice value of a thread or process, from -20 (highest priority) to 19.
+///
+/// Lowering the nice value, or changing a process of another user, requires root
+/// (or CAP_SYS_NICE, within RLIMIT_NICE).
+pub fn set_nice(pid: TaskId, nice: i32) -> Result<(), ProcError> {
+    if nice < NICE_MIN || nice > NICE_MAX {
+        return Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidPriority,
+            Some("nice value out of range")));
+    }
+    let ret = unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice)
+    };
+    if ret < 0 {
+        return Err(ProcError::new_err(ProcOper::Writing, ProcFile::PidPriority,
+            io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_get_set() {
+    use std::thread;
+    let myself = ::pid::Pid::myself().unwrap();
+    assert_eq!(nice(myself.pid).unwrap() as i64, myself.stat.nice);
+    // Change a separate thread, so the priority of other tests isn't affected.
+    thread::spawn(|| {
+        let tid = ::pid::Pid::current_thread().unwrap().pid;
+        set_nice(tid, NICE_MAX).unwrap();
+        assert_eq!(nice(tid).unwrap(), NICE_MAX);
+        assert_eq!(set_nice(tid, NICE_MAX + 1),
+            Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidPriority,
+                Some("nice value out of range"))));
+    }).join().unwrap();
+    assert!(nice(0x7fff_fff0).is_err());
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufRead};
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use {MemSize, page_size};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A struct containing the NUMA placement of a single mapping.
+///
+/// This struct contains one line of the /proc/[pid]/numa_maps file.
+/// Page counts are in pages of kernel_page_size.
+pub struct PidNumaMap {
+    /// Start address of the mapping.
+    pub start: u64,
+    /// Memory policy of the mapping (eg: "default", "interleave:0-1", "bind:0").
+    pub policy: String,
+    /// File backing the mapping, if any.
+    pub file: Option<String>,
+    /// Pages on each NUMA node, from the N0=, N1=... fields.
+    pub nodes: BTreeMap<u32, u64>,
+    /// Anonymous pages.
+    pub anon: Option<u64>,
+    /// Dirty pages.
+    pub dirty: Option<u64>,
+    /// Mapped pages, if different from the anon and dirty counts.
+    pub mapped: Option<u64>,
+    /// Largest number of processes mapping a single page.
+    pub mapmax: Option<u64>,
+    /// Pages in the swap cache.
+    pub swapcache: Option<u64>,
+    /// Pages on the active list.
+    pub active: Option<u64>,
+    /// Pages under writeback.
+    pub writeback: Option<u64>,
+    /// Size of the pages backing the mapping in bytes.
+    pub kernel_page_size: Option<MemSize>,
+    /// Flags without a value (eg: "heap", "stack", "huge").
+    pub flags: Vec<String>,
+}
+
+impl PidNumaMap {
+    /// Parse a numa_maps line, eg:
+    /// "7f2a1c000000 bind:1 anon=3 dirty=3 N1=3 kernelpagesize_kB=4"
+    fn parse_line(line: &str) -> Result<Self, ProcError> {
+        let err = |more| ProcError::new_more(ProcOper::Parsing, ProcFile::PidNumaMaps, Some(more));
+        let mut split = line.split_whitespace();
+        let start = try!(
+            split.next()
+                .ok_or(err("missing address"))
+                .and_then(|a|
+                    u64::from_str_radix(a, 16)
+                        .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::PidNumaMaps,
+                            Some(e), Some("parsing address")))
+                )
+        );
+        let policy = try!(split.next().ok_or(err("missing policy")));
+        let mut map = PidNumaMap {
+            start: start,
+            policy: policy.to_owned(),
+            file: None,
+            nodes: BTreeMap::new(),
+            anon: None,
+            dirty: None,
+            mapped: None,
+            mapmax: None,
+            swapcache: None,
+            active: None,
+            writeback: None,
+            kernel_page_size: None,
+            flags: Vec::new(),
+        };
+        for field in split {
+            let mut kv = field.splitn(2, '=');
+            let key = kv.next().unwrap_or("");
+            let value = match kv.next() {
+                Some(v) => v,
+                None => {
+                    map.flags.push(key.to_owned());
+                    continue;
+                }
+            };
+            if key == "file" {
+                map.file = Some(value.to_owned());
+                continue;
+            }
+            let value = try!(
+                value.parse::<u64>()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::PidNumaMaps,
+                        Some(e), Some("parsing page count")))
+            );
+            match key {
+                "anon" => map.anon = Some(value),
+                "dirty" => map.dirty = Some(value),
+                "mapped" => map.mapped = Some(value),
+                "mapmax" => map.mapmax = Some(value),
+                "swapcache" => map.swapcache = Some(value),
+                "active" => map.active = Some(value),
+                "writeback" => map.writeback = Some(value),
+                "kernelpagesize_kB" => map.kernel_page_size = Some(value * 1024),
+                k if k.starts_with('N') => {
+                    if let Ok(node) = k[1..].parse() {
+                        map.nodes.insert(node, value);
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(map)
+    }
+}
+
+/// A struct containing every mapping of the /proc/[pid]/numa_maps file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidNumaMaps {
+    /// Each mapping, in address order.
+    pub maps: Vec<PidNumaMap>,
+}
+
+impl PidNumaMaps {
+    /// Generate a PidNumaMaps struct given a process directory.
+    ///
+    /// The file is missing on kernels built without NUMA support.
+    pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        let numa_file = try!(
+            File::open(pid_dir.join("numa_maps"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidNumaMaps, e))
+        );
+        let lines =
+            BufReader::with_capacity(4096, numa_file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidNumaMaps, e))
+                );
+        Self::parse_string(lines)
+    }
+
+    /// Parse an Iterator of lines as a numa_maps file.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I)
+        -> Result<Self, ProcError> {
+        let mut maps = Vec::new();
+        for line in lines {
+            maps.push(try!(PidNumaMap::parse_line(&try!(line))));
+        }
+        Ok(PidNumaMaps {
+            maps: maps
+        })
+    }
+
+    /// Total memory in bytes on each NUMA node, over all mappings.
+    pub fn node_usage(&self) -> BTreeMap<u32, MemSize> {
+        let mut usage = BTreeMap::new();
+        for map in &self.maps {
+            let page_size = map.kernel_page_size.unwrap_or_else(page_size);
+            for (node, pages) in &map.nodes {
+                *usage.entry(*node).or_insert(0) += pages * page_size;
+            }
+        }
+        usage
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let lines = "00400000 default file=/usr/bin/cat mapped=7 N0=7 kernelpagesize_kB=4\n\
+01d8c000 default heap anon=33 dirty=33 N0=20 N1=13 kernelpagesize_kB=4\n\
+7f0000000000 bind:1 anon=2 dirty=2 N1=2 kernelpagesize_kB=2048\n\
+7ffd3b1c9000 interleave:0-1 stack anon=3 dirty=3 N0=3 kernelpagesize_kB=4\n"
+        .lines().map(|l| Ok(l.to_owned()));
+    let numa = PidNumaMaps::parse_string(lines).unwrap();
+    assert_eq!(numa.maps.len(), 4);
+    assert_eq!(numa.maps[0].file, Some("/usr/bin/cat".to_owned()));
+    assert_eq!(numa.maps[0].mapped, Some(7));
+    assert_eq!(numa.maps[1].flags, vec!["heap".to_owned()]);
+    assert_eq!(numa.maps[1].nodes.get(&1), Some(&13));
+    assert_eq!(numa.maps[2].policy, "bind:1");
+    assert_eq!(numa.maps[2].kernel_page_size, Some(2048 * 1024));
+    let usage = numa.node_usage();
+    assert_eq!(usage.get(&0), Some(&((7 + 20 + 3) * 4096)));
+    assert_eq!(usage.get(&1), Some(&(13 * 4096 + 2 * 2048 * 1024)));
+}
+
+#[test]
+fn test_bad_count() {
+    assert_eq!(PidNumaMap::parse_line("00400000 default anon=x"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::PidNumaMaps,
+            Some("parsing page count"))));
+}
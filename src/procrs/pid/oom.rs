@@ -0,0 +1,36 @@
+use std::path::Path;
+use error::{ProcError, ProcFile};
+use super::read_value;
+
+/// Smallest value of oom_score_adj, which disables OOM killing.
+pub const OOM_SCORE_ADJ_MIN: i32 = -1000;
+/// Largest value of oom_score_adj, which makes a process the preferred victim.
+pub const OOM_SCORE_ADJ_MAX: i32 = 1000;
+
+/// A struct containing the OOM killer settings of a process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidOom {
+    /// The current badness score (higher is killed first).
+    pub score: u32,
+    /// The adjustment applied to the badness score (-1000 to 1000).
+    pub score_adj: i32,
+    /// The legacy adjustment (-17 to 15), if the kernel still provides it.
+    pub adj: Option<i32>,
+}
+
+impl PidOom {
+    /// Generate a PidOom struct given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        Ok(PidOom {
+            score: try!(read_value(&pid_dir.join("oom_score"), ProcFile::PidOomScore)),
+            score_adj: try!(read_value(&pid_dir.join("oom_score_adj"), ProcFile::PidOomScoreAdj)),
+            adj: read_value(&pid_dir.join("oom_adj"), ProcFile::PidOomAdj).ok(),
+        })
+    }
+}
+
+#[test]
+fn test_read() {
+    let oom = PidOom::new(Path::new("/proc/self")).unwrap();
+    assert!(oom.score_adj >= OOM_SCORE_ADJ_MIN && oom.score_adj <= OOM_SCORE_ADJ_MAX);
+}
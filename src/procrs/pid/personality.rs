@@ -0,0 +1,97 @@
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use super::read_value;
+
+/// The execution domain and flags of a process (/proc/[pid]/personality).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Personality(pub u32);
+
+/// Names of the personality flags, in bit order.
+const FLAG_NAMES: [(u32, &'static str); 11] = [
+    (Personality::UNAME26, "UNAME26"),
+    (Personality::ADDR_NO_RANDOMIZE, "ADDR_NO_RANDOMIZE"),
+    (Personality::FDPIC_FUNCPTRS, "FDPIC_FUNCPTRS"),
+    (Personality::MMAP_PAGE_ZERO, "MMAP_PAGE_ZERO"),
+    (Personality::ADDR_COMPAT_LAYOUT, "ADDR_COMPAT_LAYOUT"),
+    (Personality::READ_IMPLIES_EXEC, "READ_IMPLIES_EXEC"),
+    (Personality::ADDR_LIMIT_32BIT, "ADDR_LIMIT_32BIT"),
+    (Personality::SHORT_INODE, "SHORT_INODE"),
+    (Personality::WHOLE_SECONDS, "WHOLE_SECONDS"),
+    (Personality::STICKY_TIMEOUTS, "STICKY_TIMEOUTS"),
+    (Personality::ADDR_LIMIT_3GB, "ADDR_LIMIT_3GB"),
+];
+
+impl Personality {
+    /// The standard Linux execution domain.
+    pub const PER_LINUX: u32 = 0x0000;
+    /// The Linux execution domain with a 32 bit uname (see setarch linux32).
+    pub const PER_LINUX32: u32 = 0x0008;
+    /// Report the kernel version as 2.6.x.
+    pub const UNAME26: u32 = 0x0020000;
+    /// Disable address space randomization.
+    pub const ADDR_NO_RANDOMIZE: u32 = 0x0040000;
+    /// Function pointers point to descriptors.
+    pub const FDPIC_FUNCPTRS: u32 = 0x0080000;
+    /// Map page 0 as read-only.
+    pub const MMAP_PAGE_ZERO: u32 = 0x0100000;
+    /// Use the legacy virtual address space layout.
+    pub const ADDR_COMPAT_LAYOUT: u32 = 0x0200000;
+    /// Readable memory is also executable.
+    pub const READ_IMPLIES_EXEC: u32 = 0x0400000;
+    /// Limit the address space to 32 bits.
+    pub const ADDR_LIMIT_32BIT: u32 = 0x0800000;
+    /// No effect.
+    pub const SHORT_INODE: u32 = 0x1000000;
+    /// No effect.
+    pub const WHOLE_SECONDS: u32 = 0x2000000;
+    /// Don't modify select() timeouts.
+    pub const STICKY_TIMEOUTS: u32 = 0x4000000;
+    /// Limit the address space to 3GB.
+    pub const ADDR_LIMIT_3GB: u32 = 0x8000000;
+
+    /// Read the personality of a process, given its process directory.
+    ///
+    /// This needs the same permissions as ptrace, so usually only works
+    /// for processes of the same user.
+    pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        let raw: String = try!(read_value(&pid_dir.join("personality"), ProcFile::PidPersonality));
+        Self::parse_string(&raw)
+    }
+
+    /// Parse a hex personality value, eg: "00040000".
+    fn parse_string(raw: &str) -> Result<Self, ProcError> {
+        u32::from_str_radix(raw.trim(), 16)
+            .map(Personality)
+            .map_err(|e| ProcError::new_err(ProcOper::Parsing, ProcFile::PidPersonality, e))
+    }
+
+    /// The execution domain, eg: PER_LINUX or PER_LINUX32.
+    pub fn domain(&self) -> u32 {
+        self.0 & 0xff
+    }
+
+    /// Check whether a flag is set.
+    pub fn contains(&self, flag: u32) -> bool {
+        self.0 & flag == flag
+    }
+
+    /// Names of the flags that are set, eg: ["ADDR_NO_RANDOMIZE"].
+    pub fn flags(&self) -> Vec<&'static str> {
+        FLAG_NAMES.iter()
+            .filter(|&&(flag, _)| self.contains(flag))
+            .map(|&(_, name)| name)
+            .collect()
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let personality = Personality::parse_string("00440008\n").unwrap();
+    assert_eq!(personality.domain(), Personality::PER_LINUX32);
+    assert!(personality.contains(Personality::ADDR_NO_RANDOMIZE));
+    assert_eq!(personality.flags(), vec!["ADDR_NO_RANDOMIZE", "READ_IMPLIES_EXEC"]);
+    assert_eq!(Personality::parse_string("00000000").unwrap().flags(), Vec::<&str>::new());
+    assert!(Personality::parse_string("zz").is_err());
+    assert_eq!(::pid::Pid::myself().unwrap().personality().unwrap().domain(),
+        Personality::PER_LINUX);
+}
@@ -0,0 +1,112 @@
+use std::fs;
+use std::os::unix::fs::{PermissionsExt, MetadataExt};
+use error::ProcError;
+use super::{Pid, PidIter};
+#[cfg(test)]
+use testing::FakePid;
+
+/// Names of the capabilities, indexed by capability number.
+const CAPABILITY_NAMES: [&'static str; 41] = [
+    "CAP_CHOWN", "CAP_DAC_OVERRIDE", "CAP_DAC_READ_SEARCH", "CAP_FOWNER", "CAP_FSETID",
+    "CAP_KILL", "CAP_SETGID", "CAP_SETUID", "CAP_SETPCAP", "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE", "CAP_NET_BROADCAST", "CAP_NET_ADMIN", "CAP_NET_RAW",
+    "CAP_IPC_LOCK", "CAP_IPC_OWNER", "CAP_SYS_MODULE", "CAP_SYS_RAWIO", "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE", "CAP_SYS_PACCT", "CAP_SYS_ADMIN", "CAP_SYS_BOOT", "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE", "CAP_SYS_TIME", "CAP_SYS_TTY_CONFIG", "CAP_MKNOD", "CAP_LEASE",
+    "CAP_AUDIT_WRITE", "CAP_AUDIT_CONTROL", "CAP_SETFCAP", "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN", "CAP_SYSLOG", "CAP_WAKE_ALARM", "CAP_BLOCK_SUSPEND", "CAP_AUDIT_READ",
+    "CAP_PERFMON", "CAP_BPF", "CAP_CHECKPOINT_RESTORE",
+];
+
+/// Get the names of the capabilities in a mask, like CapEff from status.
+/// Capabilities newer than this crate are named by number, eg: "cap_41".
+pub fn capability_names(mask: u64) -> Vec<String> {
+    (0..64).filter(|bit| mask & (1 << bit) != 0)
+        .map(|bit| CAPABILITY_NAMES.get(bit as usize)
+            .map(|n| n.to_string())
+            .unwrap_or(format!("cap_{}", bit)))
+        .collect()
+}
+
+/// A reason a process is considered privileged.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Privilege {
+    /// The effective uid is root.
+    Root,
+    /// A non-root process with effective capabilities, as a bitmask.
+    Capabilities(u64),
+    /// The executable is setuid, with the owner's uid.
+    Setuid(u32),
+    /// The executable is setgid, with the group's gid.
+    Setgid(u32),
+}
+
+impl Privilege {
+    /// A short description, like "root" or "caps CAP_NET_RAW".
+    pub fn describe(&self) -> String {
+        match *self {
+            Privilege::Root => "root".to_owned(),
+            Privilege::Capabilities(mask) => format!("caps {}", capability_names(mask).join(",")),
+            Privilege::Setuid(uid) => format!("setuid {}", uid),
+            Privilege::Setgid(gid) => format!("setgid {}", gid),
+        }
+    }
+}
+
+/// List the reasons a process is privileged, empty if it isn't.
+///
+/// The setuid check stats /proc/[pid]/exe, which requires the same permissions
+/// as ptrace, so it is skipped for processes that can't be inspected. Stating
+/// the link itself (rather than the path it names) still works when the
+/// executable was deleted, or lives in another mount namespace.
+pub fn privileges(pid: &Pid) -> Vec<Privilege> {
+    let mut privileges = Vec::new();
+    if pid.status.uid.1 == 0 {
+        privileges.push(Privilege::Root);
+    } else if let Some(caps) = pid.status.capeff.filter(|&c| c != 0) {
+        privileges.push(Privilege::Capabilities(caps));
+    }
+    if let Ok(meta) = fs::metadata(pid.dir.join("exe")) {
+        let mode = meta.permissions().mode();
+        if mode & 0o4000 != 0 {
+            privileges.push(Privilege::Setuid(meta.uid()));
+        }
+        if mode & 0o2000 != 0 {
+            privileges.push(Privilege::Setgid(meta.gid()));
+        }
+    }
+    privileges
+}
+
+/// Find every privileged process, along with why it is privileged.
+///
+/// Processes that disappear or can't be read during the scan are skipped.
+pub fn privileged() -> Result<Vec<(Pid, Vec<Privilege>)>, ProcError> {
+    Ok(try!(PidIter::new())
+        .filter_map(|p| p.ok())
+        .map(|p| {
+            let privileges = privileges(&p);
+            (p, privileges)
+        })
+        .filter(|&(_, ref privileges)| !privileges.is_empty())
+        .collect())
+}
+
+#[test]
+fn test_capability_names() {
+    assert_eq!(capability_names(0x3000), vec!["CAP_NET_ADMIN", "CAP_NET_RAW"]);
+    assert_eq!(capability_names(1 << 41), vec!["cap_41"]);
+    assert!(capability_names(0).is_empty());
+}
+
+#[test]
+fn test_privileges() {
+    let mut pid = FakePid { uid: 1000, ..FakePid::new(10, 1, "ping") }.to_pid();
+    pid.status.capeff = Some(1 << 13);
+    assert_eq!(privileges(&pid), vec![Privilege::Capabilities(1 << 13)]);
+    assert_eq!(privileges(&pid)[0].describe(), "caps CAP_NET_RAW");
+    pid.status.capeff = Some(0);
+    assert!(privileges(&pid).is_empty());
+    pid.status.uid.1 = 0;
+    assert_eq!(privileges(&pid), vec![Privilege::Root]);
+}
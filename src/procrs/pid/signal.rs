@@ -0,0 +1,151 @@
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// Names of the standard signals, indexed by signal number minus one.
+const SIGNAL_NAMES: [&'static str; 31] = [
+    "SIGHUP", "SIGINT", "SIGQUIT", "SIGILL", "SIGTRAP", "SIGABRT", "SIGBUS", "SIGFPE",
+    "SIGKILL", "SIGUSR1", "SIGSEGV", "SIGUSR2", "SIGPIPE", "SIGALRM", "SIGTERM", "SIGSTKFLT",
+    "SIGCHLD", "SIGCONT", "SIGSTOP", "SIGTSTP", "SIGTTIN", "SIGTTOU", "SIGURG", "SIGXCPU",
+    "SIGXFSZ", "SIGVTALRM", "SIGPROF", "SIGWINCH", "SIGIO", "SIGPWR", "SIGSYS",
+];
+
+/// The first real-time signal number.
+pub const SIGRTMIN: u32 = 32;
+/// The last real-time signal number.
+pub const SIGRTMAX: u32 = 64;
+
+/// Get the name of a signal number, eg: 15 is "SIGTERM", and 34 is "SIGRTMIN+2".
+pub fn signal_name(signal: u32) -> Option<String> {
+    match signal {
+        1..=31 => Some(SIGNAL_NAMES[signal as usize - 1].to_owned()),
+        SIGRTMIN => Some("SIGRTMIN".to_owned()),
+        33..=SIGRTMAX => Some(format!("SIGRTMIN+{}", signal - SIGRTMIN)),
+        _ => None
+    }
+}
+
+/// Get the number of a signal given its name, with or without the "SIG" prefix.
+pub fn signal_number(name: &str) -> Option<u32> {
+    let name = if name.starts_with("SIG") { &name[3..] } else { name };
+    if name == "RTMIN" {
+        return Some(SIGRTMIN);
+    }
+    if name.starts_with("RTMIN+") {
+        return name[6..].parse::<u32>().ok()
+            .and_then(|n| n.checked_add(SIGRTMIN))
+            .and_then(|n| if n <= SIGRTMAX { Some(n) } else { None });
+    }
+    SIGNAL_NAMES.iter()
+        .position(|n| &n[3..] == name)
+        .map(|i| i as u32 + 1)
+}
+
+/// A set of signals, decoded from a signal mask like SigBlk in status or
+/// blocked in stat. Bit n - 1 is set for signal n.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SignalSet(pub u64);
+
+impl SignalSet {
+    /// Parse a hex signal mask, as used in /proc/[pid]/status.
+    pub fn from_hex(mask: &str) -> Result<Self, ParseIntError> {
+        u64::from_str_radix(mask.trim(), 16).map(SignalSet)
+    }
+
+    /// Check whether a signal number is in the set.
+    pub fn contains(&self, signal: u32) -> bool {
+        signal >= 1 && signal <= SIGRTMAX && self.0 & (1 << (signal - 1)) != 0
+    }
+
+    /// Check whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Number of signals in the set.
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    /// Iterate over the signal numbers in the set, lowest first.
+    pub fn iter(&self) -> SignalIter {
+        SignalIter { mask: self.0, signal: 1 }
+    }
+
+    /// Names of the signals in the set, eg: ["SIGINT", "SIGTERM"].
+    pub fn names(&self) -> Vec<String> {
+        self.iter().filter_map(signal_name).collect()
+    }
+}
+
+impl FromStr for SignalSet {
+    type Err = ParseIntError;
+
+    /// Parse a decimal signal mask, as used in /proc/[pid]/stat.
+    fn from_str(mask: &str) -> Result<Self, Self::Err> {
+        mask.parse().map(SignalSet)
+    }
+}
+
+impl fmt::Display for SignalSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.names().join(","))
+    }
+}
+
+impl IntoIterator for SignalSet {
+    type Item = u32;
+    type IntoIter = SignalIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the signal numbers in a SignalSet.
+pub struct SignalIter {
+    mask: u64,
+    signal: u32,
+}
+
+impl Iterator for SignalIter {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.signal <= SIGRTMAX {
+            let signal = self.signal;
+            self.signal += 1;
+            if self.mask & (1 << (signal - 1)) != 0 {
+                return Some(signal);
+            }
+        }
+        None
+    }
+}
+
+#[test]
+fn test_signal_set() {
+    // SIGINT, SIGTERM, SIGCHLD and SIGRTMIN+2.
+    let set = SignalSet::from_hex("0000000200014002").unwrap();
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![2, 15, 17, 34]);
+    assert_eq!(set.names(), vec!["SIGINT", "SIGTERM", "SIGCHLD", "SIGRTMIN+2"]);
+    assert!(set.contains(15));
+    assert!(!set.contains(9));
+    assert!(!set.contains(0));
+    assert_eq!(set.len(), 4);
+    assert_eq!(set, "8590016514".parse().unwrap());
+    assert_eq!(set.to_string(), "SIGINT,SIGTERM,SIGCHLD,SIGRTMIN+2");
+    assert_eq!(SignalSet(1 << 63).iter().collect::<Vec<_>>(), vec![64]);
+}
+
+#[test]
+fn test_signal_number() {
+    assert_eq!(signal_number("SIGTERM"), Some(15));
+    assert_eq!(signal_number("KILL"), Some(9));
+    assert_eq!(signal_number("SIGRTMIN+2"), Some(34));
+    assert_eq!(signal_number("SIGRTMIN+40"), None);
+    assert_eq!(signal_number("RTMIN+4294967295"), None);
+    assert_eq!(signal_number("SIGFOO"), None);
+    assert_eq!(signal_name(64), Some("SIGRTMIN+32".to_owned()));
+    assert_eq!(signal_name(65), None);
+}
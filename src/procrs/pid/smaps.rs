@@ -0,0 +1,480 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufRead};
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use {TaskId, MemSize};
+use super::parse_mem;
+use super::maps::PidMap;
+use root;
+
+/// Parse a line, by turning a parsing error into a ProcError
+macro_rules! parse {
+    ($value: expr, $key: expr) => { parse!($value, $key, ProcFile::PidSmaps) };
+    ($value: expr, $key: expr, $file: expr) => {
+        Some(try!(
+        $value.map_err(|e|
+            ProcError::new(ProcOper::ParsingField, $file,
+                Some(e), Some($key))
+        )))
+    }
+}
+
+/// Unwrap a line, emitting a "missing '$key'" ProcError if None
+macro_rules! unwrap {
+    ($value: expr, $key: expr) => { unwrap!($value, $key, ProcFile::PidSmaps) };
+    ($value: expr, $key: expr, $file: expr) => {
+        try!(
+        $value.ok_or(
+            ProcError::new_more(ProcOper::ParsingField, $file,
+                Some(concat!("missing ", $key)))
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A struct containing information about a single mapping in the smaps file.
+///
+/// The /proc/[pid]/smaps file contains one of these for every mapping
+/// of a process, with the same header line as /proc/[pid]/maps.
+pub struct PidSmap {
+    /// Start address of the mapping.
+    pub start: u64,
+    /// End address of the mapping.
+    pub end: u64,
+    /// Permissions of the mapping (eg: "r-xp").
+    pub perms: String,
+    /// Offset into the mapped file.
+    pub offset: u64,
+    /// Device of the mapped file (major:minor).
+    pub dev: (u32, u32),
+    /// Inode of the mapped file (0 if none).
+    pub inode: u64,
+    /// Pathname of the mapping, or pseudo-path like "[heap]".
+    pub pathname: Option<String>,
+    /// Size of the mapping.
+    pub size: MemSize,
+    /// Page size used by the kernel for this mapping.
+    pub kernelpagesize: Option<MemSize>,
+    /// Page size used by the MMU for this mapping.
+    pub mmupagesize: Option<MemSize>,
+    /// Amount of the mapping that is resident in memory.
+    pub rss: MemSize,
+    /// Proportional set size, resident memory divided among processes sharing it.
+    pub pss: MemSize,
+    /// Resident clean pages shared with other processes.
+    pub shared_clean: Option<MemSize>,
+    /// Resident dirty pages shared with other processes.
+    pub shared_dirty: Option<MemSize>,
+    /// Resident clean pages private to this process.
+    pub private_clean: Option<MemSize>,
+    /// Resident dirty pages private to this process.
+    pub private_dirty: Option<MemSize>,
+    /// Memory currently marked as referenced or accessed.
+    pub referenced: Option<MemSize>,
+    /// Memory that doesn't belong to any file.
+    pub anonymous: Option<MemSize>,
+    /// Memory marked with MADV_FREE.
+    pub lazyfree: Option<MemSize>,
+    /// Memory backed by transparent huge pages.
+    pub anonhugepages: Option<MemSize>,
+    /// Shared memory backed by huge pages.
+    pub shmempmdmapped: Option<MemSize>,
+    /// Hugetlbfs memory shared with other processes.
+    pub shared_hugetlb: Option<MemSize>,
+    /// Hugetlbfs memory private to this process.
+    pub private_hugetlb: Option<MemSize>,
+    /// Anonymous memory that has been swapped out.
+    pub swap: Option<MemSize>,
+    /// Proportional swap size, like pss.
+    pub swappss: Option<MemSize>,
+    /// Memory that is locked in RAM.
+    pub locked: Option<MemSize>,
+    /// Kernel flags associated with the mapping (eg: "rd", "ex").
+    pub vmflags: Vec<String>,
+}
+
+impl PidSmap {
+    /// Generate a Vec of PidSmap structs given a process directory.
+    pub fn new(pid_dir: &Path) -> Result<Vec<Self>, ProcError> {
+        let smaps_file = try!(
+            File::open(pid_dir.join("smaps"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidSmaps, e))
+        );
+
+        let lines =
+            BufReader::with_capacity(4096, smaps_file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidSmaps, e))
+                );
+        Self::parse_string(lines)
+    }
+
+    /// Parse an Iterator of lines as a /proc/[pid]/smaps file.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I)
+        -> Result<Vec<Self>, ProcError> {
+        let mut smaps = Vec::new();
+        let mut header: Option<String> = None;
+        let mut fields = Vec::new();
+        for line in lines {
+            let line = try!(line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Field lines always start with "Key:", header lines start with an address range.
+            let is_field = line.split_whitespace().next()
+                .map(|s| s.ends_with(':'))
+                .unwrap_or(false);
+            if is_field {
+                fields.push(line);
+                continue;
+            }
+            if let Some(h) = header.take() {
+                smaps.push(try!(Self::parse_mapping(&h, &fields)));
+                fields.clear();
+            }
+            header = Some(line);
+        }
+        if let Some(h) = header {
+            smaps.push(try!(Self::parse_mapping(&h, &fields)));
+        }
+        Ok(smaps)
+    }
+
+    /// Parse a single mapping, given its header line and field lines.
+    fn parse_mapping(header: &str, fields: &[String]) -> Result<Self, ProcError> {
+        let map = try!(PidMap::parse_line(header, ProcFile::PidSmaps));
+        let (mut size, mut kernelpagesize, mut mmupagesize, mut rss, mut pss,
+            mut shared_clean, mut shared_dirty, mut private_clean, mut private_dirty,
+            mut referenced, mut anonymous, mut lazyfree, mut anonhugepages,
+            mut shmempmdmapped, mut shared_hugetlb, mut private_hugetlb, mut swap,
+            mut swappss, mut locked) =
+            (None, None, None, None, None, None, None, None, None, None, None, None,
+            None, None, None, None, None, None, None);
+        let mut vmflags = Vec::new();
+
+        for line in fields {
+            let mut split = line.splitn(2, ':');
+            let key = split.next().unwrap_or("").trim();
+            let value = split.next().unwrap_or("").trim();
+
+            match key {
+                "Size" => size = parse!(parse_mem(value), "Size"),
+                "KernelPageSize" => kernelpagesize = parse!(parse_mem(value), "KernelPageSize"),
+                "MMUPageSize" => mmupagesize = parse!(parse_mem(value), "MMUPageSize"),
+                "Rss" => rss = parse!(parse_mem(value), "Rss"),
+                "Pss" => pss = parse!(parse_mem(value), "Pss"),
+                "Shared_Clean" => shared_clean = parse!(parse_mem(value), "Shared_Clean"),
+                "Shared_Dirty" => shared_dirty = parse!(parse_mem(value), "Shared_Dirty"),
+                "Private_Clean" => private_clean = parse!(parse_mem(value), "Private_Clean"),
+                "Private_Dirty" => private_dirty = parse!(parse_mem(value), "Private_Dirty"),
+                "Referenced" => referenced = parse!(parse_mem(value), "Referenced"),
+                "Anonymous" => anonymous = parse!(parse_mem(value), "Anonymous"),
+                "LazyFree" => lazyfree = parse!(parse_mem(value), "LazyFree"),
+                "AnonHugePages" => anonhugepages = parse!(parse_mem(value), "AnonHugePages"),
+                "ShmemPmdMapped" => shmempmdmapped = parse!(parse_mem(value), "ShmemPmdMapped"),
+                "Shared_Hugetlb" => shared_hugetlb = parse!(parse_mem(value), "Shared_Hugetlb"),
+                "Private_Hugetlb" => private_hugetlb = parse!(parse_mem(value), "Private_Hugetlb"),
+                "Swap" => swap = parse!(parse_mem(value), "Swap"),
+                "SwapPss" => swappss = parse!(parse_mem(value), "SwapPss"),
+                "Locked" => locked = parse!(parse_mem(value), "Locked"),
+                "VmFlags" => vmflags = value.split_whitespace().map(|s| s.to_owned()).collect(),
+                _ => continue,
+            };
+        }
+
+        Ok(PidSmap {
+            start: map.start,
+            end: map.end,
+            perms: map.perms,
+            offset: map.offset,
+            dev: map.dev,
+            inode: map.inode,
+            pathname: map.pathname,
+            size: unwrap!(size, "Size"),
+            kernelpagesize: kernelpagesize,
+            mmupagesize: mmupagesize,
+            rss: unwrap!(rss, "Rss"),
+            pss: unwrap!(pss, "Pss"),
+            shared_clean: shared_clean,
+            shared_dirty: shared_dirty,
+            private_clean: private_clean,
+            private_dirty: private_dirty,
+            referenced: referenced,
+            anonymous: anonymous,
+            lazyfree: lazyfree,
+            anonhugepages: anonhugepages,
+            shmempmdmapped: shmempmdmapped,
+            shared_hugetlb: shared_hugetlb,
+            private_hugetlb: private_hugetlb,
+            swap: swap,
+            swappss: swappss,
+            locked: locked,
+            vmflags: vmflags,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A struct containing the memory usage of all mappings of a process.
+///
+/// This is read from /proc/[pid]/smaps_rollup where the kernel supports
+/// it (linux 4.14+), which is much cheaper than parsing the full smaps file.
+/// Fields the kernel doesn't report are zero.
+pub struct PidSmapsRollup {
+    /// Resident set size.
+    pub rss: MemSize,
+    /// Proportional set size.
+    pub pss: MemSize,
+    /// Resident clean pages shared with other processes.
+    pub shared_clean: MemSize,
+    /// Resident dirty pages shared with other processes.
+    pub shared_dirty: MemSize,
+    /// Resident clean pages private to this process.
+    pub private_clean: MemSize,
+    /// Resident dirty pages private to this process.
+    pub private_dirty: MemSize,
+    /// Memory currently marked as referenced or accessed.
+    pub referenced: MemSize,
+    /// Memory that doesn't belong to any file.
+    pub anonymous: MemSize,
+    /// Memory marked with MADV_FREE.
+    pub lazyfree: MemSize,
+    /// Memory backed by transparent huge pages.
+    pub anonhugepages: MemSize,
+    /// Anonymous memory that has been swapped out.
+    pub swap: MemSize,
+    /// Proportional swap size, like pss.
+    pub swappss: MemSize,
+    /// Memory that is locked in RAM.
+    pub locked: MemSize,
+}
+
+impl PidSmapsRollup {
+    /// Generate a PidSmapsRollup struct for a process, given a pid.
+    pub fn new(pid: TaskId) -> Result<Self, ProcError> {
+        Self::new_dir(&root::pid_dir(pid))
+    }
+
+    /// Generate a PidSmapsRollup struct given a process directory.
+    ///
+    /// If smaps_rollup doesn't exist, fall back to summing the full smaps file.
+    pub fn new_dir(pid_dir: &Path) -> Result<Self, ProcError> {
+        let rollup_file = match File::open(pid_dir.join("smaps_rollup")) {
+            Ok(f) => f,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound =>
+                return PidSmap::new(pid_dir).map(|smaps| Self::from_smaps(&smaps)),
+            Err(e) => return Err(ProcError::new_err(ProcOper::Opening, ProcFile::PidSmapsRollup, e)),
+        };
+
+        let lines =
+            BufReader::with_capacity(4096, rollup_file)
+                .lines()
+                .map(|r|
+                    r.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::PidSmapsRollup, e))
+                );
+        Self::parse_string(lines)
+    }
+
+    /// Sum the usage of a list of mappings.
+    pub fn from_smaps(smaps: &[PidSmap]) -> Self {
+        let sum = |f: &Fn(&PidSmap) -> Option<MemSize>|
+            smaps.iter().map(|s| f(s).unwrap_or(0)).sum();
+        PidSmapsRollup {
+            rss: sum(&|s| Some(s.rss)),
+            pss: sum(&|s| Some(s.pss)),
+            shared_clean: sum(&|s| s.shared_clean),
+            shared_dirty: sum(&|s| s.shared_dirty),
+            private_clean: sum(&|s| s.private_clean),
+            private_dirty: sum(&|s| s.private_dirty),
+            referenced: sum(&|s| s.referenced),
+            anonymous: sum(&|s| s.anonymous),
+            lazyfree: sum(&|s| s.lazyfree),
+            anonhugepages: sum(&|s| s.anonhugepages),
+            swap: sum(&|s| s.swap),
+            swappss: sum(&|s| s.swappss),
+            locked: sum(&|s| s.locked),
+        }
+    }
+
+    /// Parse an Iterator of lines as a /proc/[pid]/smaps_rollup file.
+    fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I)
+        -> Result<Self, ProcError> {
+        let (mut rss, mut pss, mut shared_clean, mut shared_dirty, mut private_clean,
+            mut private_dirty, mut referenced, mut anonymous, mut lazyfree,
+            mut anonhugepages, mut swap, mut swappss, mut locked) =
+            (None, None, None, None, None, None, None, None, None, None, None, None, None);
+
+        for line in lines {
+            let line = try!(line);
+            // Skip the header line, which has an address range rather than a key.
+            let mut split = line.splitn(2, ':');
+            let key = split.next().unwrap_or("").trim();
+            let value = match split.next() {
+                Some(v) if !key.contains(' ') => v.trim(),
+                _ => continue,
+            };
+
+            match key {
+                "Rss" => rss = parse!(parse_mem(value), "Rss", ProcFile::PidSmapsRollup),
+                "Pss" => pss = parse!(parse_mem(value), "Pss", ProcFile::PidSmapsRollup),
+                "Shared_Clean" => shared_clean = parse!(parse_mem(value), "Shared_Clean", ProcFile::PidSmapsRollup),
+                "Shared_Dirty" => shared_dirty = parse!(parse_mem(value), "Shared_Dirty", ProcFile::PidSmapsRollup),
+                "Private_Clean" => private_clean = parse!(parse_mem(value), "Private_Clean", ProcFile::PidSmapsRollup),
+                "Private_Dirty" => private_dirty = parse!(parse_mem(value), "Private_Dirty", ProcFile::PidSmapsRollup),
+                "Referenced" => referenced = parse!(parse_mem(value), "Referenced", ProcFile::PidSmapsRollup),
+                "Anonymous" => anonymous = parse!(parse_mem(value), "Anonymous", ProcFile::PidSmapsRollup),
+                "LazyFree" => lazyfree = parse!(parse_mem(value), "LazyFree", ProcFile::PidSmapsRollup),
+                "AnonHugePages" => anonhugepages = parse!(parse_mem(value), "AnonHugePages", ProcFile::PidSmapsRollup),
+                "Swap" => swap = parse!(parse_mem(value), "Swap", ProcFile::PidSmapsRollup),
+                "SwapPss" => swappss = parse!(parse_mem(value), "SwapPss", ProcFile::PidSmapsRollup),
+                "Locked" => locked = parse!(parse_mem(value), "Locked", ProcFile::PidSmapsRollup),
+                _ => continue,
+            };
+        }
+
+        Ok(PidSmapsRollup {
+            rss: unwrap!(rss, "Rss", ProcFile::PidSmapsRollup),
+            pss: unwrap!(pss, "Pss", ProcFile::PidSmapsRollup),
+            shared_clean: shared_clean.unwrap_or(0),
+            shared_dirty: shared_dirty.unwrap_or(0),
+            private_clean: private_clean.unwrap_or(0),
+            private_dirty: private_dirty.unwrap_or(0),
+            referenced: referenced.unwrap_or(0),
+            anonymous: anonymous.unwrap_or(0),
+            lazyfree: lazyfree.unwrap_or(0),
+            anonhugepages: anonhugepages.unwrap_or(0),
+            swap: swap.unwrap_or(0),
+            swappss: swappss.unwrap_or(0),
+            locked: locked.unwrap_or(0),
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let lines = "56462bade000-56462bae0000 r--p 00000000 fe:00 317563                     /usr/bin/cat\n\
+                 Size:                  8 kB\n\
+                 KernelPageSize:        4 kB\n\
+                 MMUPageSize:           4 kB\n\
+                 Rss:                   8 kB\n\
+                 Pss:                   8 kB\n\
+                 Shared_Clean:          0 kB\n\
+                 Shared_Dirty:          0 kB\n\
+                 Private_Clean:         8 kB\n\
+                 Private_Dirty:         0 kB\n\
+                 Referenced:            8 kB\n\
+                 Anonymous:             0 kB\n\
+                 LazyFree:              0 kB\n\
+                 AnonHugePages:         0 kB\n\
+                 ShmemPmdMapped:        0 kB\n\
+                 Shared_Hugetlb:        0 kB\n\
+                 Private_Hugetlb:       0 kB\n\
+                 Swap:                  0 kB\n\
+                 SwapPss:               0 kB\n\
+                 Locked:                0 kB\n\
+                 THPeligible:           0\n\
+                 VmFlags: rd mr mw me \n\
+                 7ffd3b9e4000-7ffd3ba05000 rw-p 00000000 00:00 0                          [stack]\n\
+                 Size:                132 kB\n\
+                 Rss:                  12 kB\n\
+                 Pss:                  12 kB\n\
+                 7ffd3ba05000-7ffd3ba06000 rw-p 00000000 00:00 0 \n\
+                 Size:                  4 kB\n\
+                 Rss:                   0 kB\n\
+                 Pss:                   0 kB\n\
+                 ".lines().map(|l| Ok(l.to_owned()));
+    let smaps = PidSmap::parse_string(lines).unwrap();
+    assert_eq!(smaps.len(), 3);
+    assert_eq!(smaps[0], PidSmap {
+        start: 0x56462bade000,
+        end: 0x56462bae0000,
+        perms: "r--p".to_owned(),
+        offset: 0,
+        dev: (0xfe, 0),
+        inode: 317563,
+        pathname: Some("/usr/bin/cat".to_owned()),
+        size: 8192,
+        kernelpagesize: Some(4096),
+        mmupagesize: Some(4096),
+        rss: 8192,
+        pss: 8192,
+        shared_clean: Some(0),
+        shared_dirty: Some(0),
+        private_clean: Some(8192),
+        private_dirty: Some(0),
+        referenced: Some(8192),
+        anonymous: Some(0),
+        lazyfree: Some(0),
+        anonhugepages: Some(0),
+        shmempmdmapped: Some(0),
+        shared_hugetlb: Some(0),
+        private_hugetlb: Some(0),
+        swap: Some(0),
+        swappss: Some(0),
+        locked: Some(0),
+        vmflags: vec!["rd".to_owned(), "mr".to_owned(), "mw".to_owned(), "me".to_owned()],
+    });
+    assert_eq!(smaps[1].pathname, Some("[stack]".to_owned()));
+    assert_eq!(smaps[1].size, 135168);
+    assert_eq!(smaps[2].pathname, None);
+}
+
+#[test]
+fn test_missing_rss() {
+    let lines = "00400000-0040b000 r-xp 00000000 08:01 1234 /bin/cat\n\
+                 Size:                  8 kB\n\
+                 Pss:                   8 kB\n\
+                 ".lines().map(|l| Ok(l.to_owned()));
+    assert_eq!(PidSmap::parse_string(lines),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::PidSmaps, Some("missing Rss")))
+    );
+}
+
+#[test]
+fn test_invalid_header() {
+    let lines = "0040000g-0040b000 r-xp 00000000 08:01 1234 /bin/cat\n\
+                 ".lines().map(|l| Ok(l.to_owned()));
+    assert_eq!(PidSmap::parse_string(lines),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::PidSmaps,
+            Some("parsing start address")))
+    );
+}
+
+#[test]
+fn test_rollup_parsing() {
+    let lines = "55766aee8000-7fff1afbf000 ---p 00000000 00:00 0                          [rollup]\n\
+                 Rss:                1408 kB\n\
+                 Pss:                 486 kB\n\
+                 Pss_Anon:            100 kB\n\
+                 Shared_Clean:       1268 kB\n\
+                 Private_Dirty:       100 kB\n\
+                 Swap:                  4 kB\n\
+                 ".lines().map(|l| Ok(l.to_owned()));
+    let rollup = PidSmapsRollup::parse_string(lines).unwrap();
+    assert_eq!(rollup.rss, 1441792);
+    assert_eq!(rollup.pss, 497664);
+    assert_eq!(rollup.shared_clean, 1298432);
+    assert_eq!(rollup.private_dirty, 102400);
+    assert_eq!(rollup.swap, 4096);
+    assert_eq!(rollup.locked, 0);
+}
+
+#[test]
+fn test_rollup_from_smaps() {
+    let lines = "00400000-0040b000 r-xp 00000000 08:01 1234 /bin/cat\n\
+                 Size:                 44 kB\n\
+                 Rss:                  40 kB\n\
+                 Pss:                  20 kB\n\
+                 Swap:                  4 kB\n\
+                 0060a000-0060b000 rw-p 0000a000 08:01 1234 /bin/cat\n\
+                 Size:                  4 kB\n\
+                 Rss:                   4 kB\n\
+                 Pss:                   4 kB\n\
+                 ".lines().map(|l| Ok(l.to_owned()));
+    let smaps = PidSmap::parse_string(lines).unwrap();
+    let rollup = PidSmapsRollup::from_smaps(&smaps);
+    assert_eq!(rollup.rss, 45056);
+    assert_eq!(rollup.pss, 24576);
+    assert_eq!(rollup.swap, 4096);
+}
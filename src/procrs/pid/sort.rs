@@ -0,0 +1,169 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+use super::Pid;
+#[cfg(test)]
+use testing::FakePid;
+
+/// A field that processes can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortField {
+    /// Process (or thread) id.
+    Pid,
+    /// Parent process id.
+    Ppid,
+    /// Thread group id.
+    Tgid,
+    /// Real user id.
+    User,
+    /// Program name.
+    Cmd,
+    /// Resident set size.
+    Rss,
+    /// Virtual memory size.
+    Vsz,
+    /// Cpu time (user + system).
+    Time,
+    /// Start time since boot.
+    Start,
+    /// Number of threads.
+    Threads,
+}
+
+impl SortField {
+    /// Compare two processes by this field.
+    pub fn compare(&self, a: &Pid, b: &Pid) -> Ordering {
+        match *self {
+            SortField::Pid => a.stat.pid.cmp(&b.stat.pid),
+            SortField::Ppid => a.stat.ppid.cmp(&b.stat.ppid),
+            SortField::Tgid => a.status.tgid.cmp(&b.status.tgid),
+            SortField::User => a.status.uid.0.cmp(&b.status.uid.0),
+            SortField::Cmd => a.stat.comm.cmp(&b.stat.comm),
+            SortField::Rss => a.status.vmrss.cmp(&b.status.vmrss),
+            SortField::Vsz => a.stat.vsize.cmp(&b.stat.vsize),
+            SortField::Time => (a.stat.utime + a.stat.stime).cmp(&(b.stat.utime + b.stat.stime)),
+            SortField::Start => a.stat.starttime.cmp(&b.stat.starttime),
+            SortField::Threads => a.status.threads.cmp(&b.status.threads),
+        }
+    }
+}
+
+impl FromStr for SortField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match &*s.to_lowercase() {
+            "pid" | "tid" => SortField::Pid,
+            "ppid" => SortField::Ppid,
+            "tgid" => SortField::Tgid,
+            "user" | "uid" => SortField::User,
+            "cmd" | "name" | "comm" => SortField::Cmd,
+            "rss" => SortField::Rss,
+            "vsz" | "vsize" => SortField::Vsz,
+            "time" => SortField::Time,
+            "start" => SortField::Start,
+            "threads" => SortField::Threads,
+            _ => return Err(format!("Invalid sort field '{}'", s)),
+        })
+    }
+}
+
+/// A single key of a SortSpec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    /// The field to compare.
+    pub field: SortField,
+    /// Whether to sort largest first.
+    pub descending: bool,
+}
+
+/// A list of keys to sort processes by, in order of precedence.
+///
+/// Sorting is stable, so processes that compare equal on every key keep
+/// their original order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SortSpec {
+    pub keys: Vec<SortKey>,
+}
+
+impl SortSpec {
+    /// Compare two processes by each key in turn.
+    pub fn compare(&self, a: &Pid, b: &Pid) -> Ordering {
+        for key in &self.keys {
+            let ord = match key.descending {
+                false => key.field.compare(a, b),
+                true => key.field.compare(b, a),
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Sort a slice of processes by this spec.
+    pub fn sort(&self, pids: &mut [Pid]) {
+        pids.sort_by(|a, b| self.compare(a, b));
+    }
+}
+
+impl FromStr for SortSpec {
+    type Err = String;
+
+    /// Parse a comma separated list of fields, where a leading '-' means
+    /// descending and an optional leading '+' means ascending (eg: "user,-rss").
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = try!(
+            s.split(',')
+                .filter(|k| !k.is_empty())
+                .map(|k| {
+                    let (descending, name) = match k.chars().next() {
+                        Some('-') => (true, &k[1..]),
+                        Some('+') => (false, &k[1..]),
+                        _ => (false, k),
+                    };
+                    name.parse().map(|field| SortKey { field: field, descending: descending })
+                }).collect::<Result<Vec<_>, _>>()
+        );
+        Ok(SortSpec { keys: keys })
+    }
+}
+
+/// Compare processes by start time, using pid as a tiebreaker since
+/// starttime only has clock tick resolution.
+pub fn by_start_time(a: &Pid, b: &Pid) -> Ordering {
+    a.stat.starttime.cmp(&b.stat.starttime)
+        .then(a.stat.pid.cmp(&b.stat.pid))
+}
+
+/// Select the process that started first (like pgrep -o).
+pub fn oldest(pids: &[Pid]) -> Option<&Pid> {
+    pids.iter().min_by(|a, b| by_start_time(a, b))
+}
+
+/// Select the process that started last (like pgrep -n).
+pub fn newest(pids: &[Pid]) -> Option<&Pid> {
+    pids.iter().max_by(|a, b| by_start_time(a, b))
+}
+
+#[test]
+fn test_spec_parse() {
+    assert_eq!("user,-rss".parse(), Ok(SortSpec { keys: vec![
+        SortKey { field: SortField::User, descending: false },
+        SortKey { field: SortField::Rss, descending: true },
+    ]}));
+    assert_eq!("+pid".parse(), Ok(SortSpec { keys: vec![
+        SortKey { field: SortField::Pid, descending: false },
+    ]}));
+    assert_eq!("pid,bogus".parse::<SortSpec>(), Err("Invalid sort field 'bogus'".to_owned()));
+}
+
+#[test]
+fn test_start_time() {
+    let first = FakePid { starttime: 10, ..FakePid::new(30, 1, "sh") }.to_pid();
+    let second = FakePid { starttime: 10, ..FakePid::new(40, 1, "sh") }.to_pid();
+    let third = FakePid { starttime: 20, ..FakePid::new(5, 1, "sh") }.to_pid();
+    let pids = vec![second, third, first];
+    assert_eq!(oldest(&pids).unwrap().stat.pid, 30);
+    assert_eq!(newest(&pids).unwrap().stat.pid, 5);
+    assert_eq!(oldest(&[]), None);
+}
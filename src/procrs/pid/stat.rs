@@ -1,8 +1,10 @@
 use std::fs::File;
 use std::path::Path;
 use std::io::{Read, BufReader};
+use std::time::Duration;
 use error::{ProcError, ProcFile, ProcOper};
-use TaskId;
+use {TaskId, MemSize, ticks_per_second, page_size};
+use super::signal::SignalSet;
 
 /// A struct containing information from the stat file for a process.
 ///
@@ -13,7 +15,7 @@ use TaskId;
 pub struct PidStat {
     /// The process id.
     pub pid: TaskId,
-    /// The filename of the executable.
+    /// The filename of the executable, or the thread name for threads.
     pub comm: String,
     /// The process state.
     pub state: PidState,
@@ -57,7 +59,7 @@ pub struct PidStat {
     pub starttime: u64,
     /// Virtual memory size in bytes.
     pub vsize: u64,
-    /// Resident set size in pages.
+    /// Resident set size in pages, see rss_bytes.
     pub rss: i64,
     /// RSS soft limit of process.
     pub rsslim: u64,
@@ -71,14 +73,14 @@ pub struct PidStat {
     pub kstkesp: u64,
     /// The current EIP (instruction pointer).
     pub kstkeip: u64,
-    /// The bitmap of pending signals, displayed as a decimal number.
-    pub signal: u64,
-    /// The bitmap of blocked signals, displayed as a decimal number.
-    pub blocked: u64,
-    /// The bitmap of ignored signals, displayed as a decimal number.
-    pub sigignore: u64,
-    /// The bitmap of caught signals, displayed as a decimal number.
-    pub sigcatch: u64,
+    /// The set of pending signals.
+    pub signal: SignalSet,
+    /// The set of blocked signals.
+    pub blocked: SignalSet,
+    /// The set of ignored signals.
+    pub sigignore: SignalSet,
+    /// The set of caught signals.
+    pub sigcatch: SignalSet,
     /// This is the "channel" in which the process is waiting.
     pub wchan: u64,
     /// Number of pages swapped (not maintained).
@@ -115,7 +117,10 @@ pub struct PidStat {
     /// Address below which program environment is placed.
     pub env_end: Option<u64>,
     /// The thread's exit status.
-    pub exit_code: Option<i32>
+    pub exit_code: Option<i32>,
+    /// Fields after exit_code, added by kernels newer than this library, in
+    /// order. Negative values are kept as their two's complement.
+    pub extra: Vec<u64>
 }
 
 /// Macro to parse a number, replacing errors with PidError.
@@ -144,6 +149,7 @@ macro_rules! stat_parse_opt_num {
 impl PidStat {
     /// Generate PidStat struct given a process directory.
     pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        debug!("reading {}/stat", pid_dir.display());
         let file = try!(
             File::open(pid_dir.join("stat"))
                 .map_err(|e|
@@ -161,6 +167,69 @@ impl PidStat {
         Self::parse_string(bytes)
     }
 
+    /// Create a placeholder PidStat for a process whose stat file can't be
+    /// read (eg: /proc mounted with hidepid), knowing only its pid.
+    ///
+    /// Counters and the ppid are 0, the comm is empty and the state is
+    /// Unknown, so check Pid::access before trusting them.
+    pub fn unreadable(pid: TaskId) -> Self {
+        PidStat {
+            pid: pid,
+            comm: String::new(),
+            state: PidState::Unknown,
+            ppid: 0,
+            pgrp: 0,
+            session: 0,
+            tty_nr: 0,
+            tpgid: 0,
+            flags: 0,
+            minflt: 0,
+            cminflt: 0,
+            majflt: 0,
+            cmajflt: 0,
+            utime: 0,
+            stime: 0,
+            cutime: 0,
+            cstime: 0,
+            priority: 0,
+            nice: 0,
+            num_threads: 0,
+            itrealvalue: 0,
+            starttime: 0,
+            vsize: 0,
+            rss: 0,
+            rsslim: 0,
+            startcode: 0,
+            endcode: 0,
+            startstack: 0,
+            kstkesp: 0,
+            kstkeip: 0,
+            signal: SignalSet(0),
+            blocked: SignalSet(0),
+            sigignore: SignalSet(0),
+            sigcatch: SignalSet(0),
+            wchan: 0,
+            nswap: 0,
+            cnswap: 0,
+            exit_signal: None,
+            processor: None,
+            rt_priority: None,
+            policy: None,
+            delayacct_blkio_ticks: None,
+            guest_time: None,
+            cguest_time: None,
+            start_data: None,
+            end_data: None,
+            start_brk: None,
+            arg_start: None,
+            arg_end: None,
+            env_start: None,
+            env_end: None,
+            exit_code: None,
+            extra: Vec::new()
+        }
+    }
+
     /// Parse a String as a /proc/[pid]/stat file.
     fn parse_string(bytes: String) -> Result<Self, ProcError> {
         // /proc/.../stat is "numbers (prog_name) char numbers"
@@ -180,7 +249,9 @@ impl PidStat {
             ).trim();
         let prog_name = try!(bytes_split.next()
             .ok_or(ProcError::new_more(ProcOper::Parsing, ProcFile::PidStat, Some("splitting comm"))));
-        let mut split = suffix.split(' ');
+        // Fields after the comm are separated by single spaces, but be lenient
+        // in case a kernel pads them.
+        let mut split = suffix.split_whitespace();
 
         Ok(PidStat {
             pid: stat_parse_num!(prefix.split(' ').next()),
@@ -257,8 +328,53 @@ impl PidStat {
                 stat_parse_opt_num!(split.next()),
             exit_code:
                 stat_parse_opt_num!(split.next()),
+            extra: try!(
+                split.map(|s|
+                    s.parse::<u64>().or(s.parse::<i64>().map(|n| n as u64))
+                        .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::PidStat,
+                            Some(e), Some("parsing extra field")))
+                ).collect()
+            ),
         })
     }
+
+    /// Resident set size in bytes.
+    pub fn rss_bytes(&self) -> MemSize {
+        self.rss.max(0) as MemSize * page_size()
+    }
+
+    /// Average cpu usage over the lifetime of the process, as a percentage
+    /// of one cpu (like ps %CPU). Uptime is the system uptime in seconds.
+    pub fn cpu_percent_lifetime(&self, uptime: f64) -> f64 {
+        let hertz = ticks_per_second() as f64;
+        let elapsed = uptime - self.starttime as f64 / hertz;
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        let cputime = (self.utime + self.stime) as f64 / hertz;
+        cputime / elapsed * 100.0
+    }
+
+    /// How long ago the process started. Uptime is the system uptime in seconds.
+    pub fn age(&self, uptime: f64) -> Duration {
+        let secs = uptime - self.starttime as f64 / ticks_per_second() as f64;
+        match secs {
+            s if s > 0.0 => Duration::new(s as u64, (s.fract() * 1e9) as u32),
+            _ => Duration::from_secs(0)
+        }
+    }
+
+    /// Cpu usage between an earlier sample of this process and this one,
+    /// as a percentage of one cpu. Elapsed is the time between samples.
+    pub fn cpu_percent_sampled(&self, previous: &PidStat, elapsed: Duration) -> f64 {
+        let elapsed = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        if elapsed <= 0.0 {
+            return 0.0;
+        }
+        let ticks = (self.utime + self.stime)
+            .saturating_sub(previous.utime + previous.stime);
+        ticks as f64 / ticks_per_second() as f64 / elapsed * 100.0
+    }
 }
 
 /// A list of states that a process can be in.
@@ -283,11 +399,15 @@ pub enum PidState {
     /// Waking
     Waking,
     /// Parked
-    Parked
+    Parked,
+    /// Idle kernel thread
+    Idle,
+    /// The state couldn't be read (see PidStat::unreadable)
+    Unknown
 }
 
 /// Turn a char into an appropriate ProcState.
-fn get_procstate(state: &str) -> Option<PidState> {
+pub fn get_procstate(state: &str) -> Option<PidState> {
     match state {
         "R" => Some(PidState::Running),
         "S" => Some(PidState::Sleeping),
@@ -299,6 +419,7 @@ fn get_procstate(state: &str) -> Option<PidState> {
         "K" => Some(PidState::Wakekill),
         "W" => Some(PidState::Waking),
         "P" => Some(PidState::Parked),
+        "I" => Some(PidState::Idle),
          _  => None
     }
 }
@@ -336,10 +457,10 @@ fn test_parsing() {
         startstack: 140735096462144,
         kstkesp: 140735096450384,
         kstkeip: 94178659203252,
-        signal: 0,
-        blocked: 0,
-        sigignore: 4224,
-        sigcatch: 1088,
+        signal: SignalSet(0),
+        blocked: SignalSet(0),
+        sigignore: SignalSet(4224),
+        sigcatch: SignalSet(1088),
         wchan: 1,
         nswap: 0,
         cnswap: 0,
@@ -357,7 +478,8 @@ fn test_parsing() {
         arg_end: Some(140735096465049),
         env_start: Some(140735096465049),
         env_end: Some(140735096467429),
-        exit_code: Some(0)
+        exit_code: Some(0),
+        extra: Vec::new()
     };
 
     let input = "14557 (psq) T 14364 14557 14364 34823 14638 1077952512 1178 0 0 0 16 0 0 0 20 0 1 0 609164 23785472 1707 18446744073709551615 94178658361344 94178659818816 140735096462144 140735096450384 94178659203252 0 0 4224 1088 1 0 0 17 2 0 0 0 0 0 94178661916280 94178661971297 94178690334720 140735096465030 140735096465049 140735096465049 140735096467429 0".to_owned();
@@ -408,6 +530,41 @@ fn test_invalid_parens() {
         Err(ProcError::new_more(ProcOper::Parsing, ProcFile::PidStat, Some("splitting comm"))));
 }
 
+#[test]
+fn test_field_counts() {
+    let full = "14557 (psq) S 14364 14557 14364 34823 14638 1077952512 1178 0 0 0 16 0 0 0 20 0 1 0 609164 23785472 1707 18446744073709551615 94178658361344 94178659818816 140735096462144 140735096450384 94178659203252 0 0 4224 1088 1 0 0 17 2 0 0 0 0 0 94178661916280 94178661971297 94178690334720 140735096465030 140735096465049 140735096465049 140735096467429 0";
+    let fields: Vec<_> = full.split(' ').collect();
+    // Linux 2.6.18 has 42 fields, 2.6.24 to 3.4 have 44, and 3.5+ have 52.
+    for &count in &[42, 44, 52] {
+        let stat = PidStat::parse_string(fields[..count].join(" ")).unwrap();
+        assert_eq!(stat.delayacct_blkio_ticks, Some(0));
+        assert_eq!(stat.cguest_time.is_some(), count >= 44);
+        assert_eq!(stat.exit_code.is_some(), count >= 52);
+        assert!(stat.extra.is_empty());
+    }
+    // Fields from newer kernels are kept, and padding is ignored.
+    let stat = PidStat::parse_string(format!("{}  7 -1\n", full)).unwrap();
+    assert_eq!(stat.extra, vec![7, u64::max_value()]);
+    // Fields up to cnswap are always present.
+    assert!(PidStat::parse_string(fields[..30].join(" ")).is_err());
+    assert!(PidStat::parse_string(format!("{} x", full)).is_err());
+}
+
+#[test]
+fn test_cpu_percent() {
+    let mut prc = PidStat::parse_string("14557 (psq) T 14364 14557 14364 34823 14638 1077952512 1178 0 0 0 16 0 0 0 20 0 1 0 609164 23785472 1707 18446744073709551615 94178658361344 94178659818816 140735096462144 140735096450384 94178659203252 0 0 4224 1088 1 0 0 17 2 0 0 0 0 0 94178661916280 94178661971297 94178690334720 140735096465030 140735096465049 140735096465049 140735096467429 0".to_owned()).unwrap();
+    prc.starttime = 1000;
+    prc.utime = 300;
+    prc.stime = 100;
+    // Started at 10s, 4s of cpu time over 20s.
+    assert_eq!(prc.cpu_percent_lifetime(30.0), 20.0);
+    assert_eq!(prc.cpu_percent_lifetime(5.0), 0.0);
+
+    let previous = prc.clone();
+    prc.utime += 50;
+    assert_eq!(prc.cpu_percent_sampled(&previous, Duration::from_secs(1)), 50.0);
+}
+
 #[test]
 fn test_invalid_1() {
     let input = "14557 ".to_owned();
@@ -421,3 +578,21 @@ fn test_invalid_2() {
     assert_eq!(PidStat::parse_string(input),
         Err(ProcError::new_more(ProcOper::Parsing, ProcFile::PidStat, Some("parsing process state"))));
 }
+
+#[test]
+fn test_age() {
+    let mut stat = PidStat::new(Path::new("/proc/self")).unwrap();
+    stat.starttime = 1000;
+    assert_eq!(stat.age(25.5), Duration::from_millis(15500));
+    // Clock ticks can be rounded past the uptime for very young processes.
+    assert_eq!(stat.age(9.0), Duration::from_secs(0));
+}
+
+#[test]
+fn test_rss_bytes() {
+    let mut stat = PidStat::new(Path::new("/proc/self")).unwrap();
+    stat.rss = 3;
+    assert_eq!(stat.rss_bytes(), 3 * page_size());
+    stat.rss = -1;
+    assert_eq!(stat.rss_bytes(), 0);
+}
@@ -1,9 +1,13 @@
 use std::fs::File;
 use std::io::{BufReader, BufRead};
 use std::path::Path;
+use std::str::FromStr;
 use std::num::ParseIntError;
 use ::error::{ProcError, ProcFile, ProcOper};
 use ::{TaskId, MemSize};
+use super::parse_mem;
+use super::stat::{PidState, get_procstate};
+use super::signal::SignalSet;
 
 /// Parse a line, by turning a parsing error into a ProcError
 macro_rules! parse {
@@ -27,7 +31,30 @@ macro_rules! unwrap {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// The seccomp mode of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeccompMode {
+    /// Not using seccomp.
+    Disabled,
+    /// Only read, write, _exit and sigreturn are allowed.
+    Strict,
+    /// System calls are checked by BPF filters.
+    Filter,
+}
+
+impl FromStr for SeccompMode {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match try!(s.parse::<u8>()) {
+            1 => SeccompMode::Strict,
+            2 => SeccompMode::Filter,
+            _ => SeccompMode::Disabled,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 /// A struct containing information from the status file for a process.
 ///
 /// This struct contains information from the /proc/[pid]/status or
@@ -79,12 +106,61 @@ pub struct PidStatus {
     /// is not included.
     pub vmswap: Option<MemSize>,
     /// Number of threads in process containing this thread.
-    pub threads: u32
+    pub threads: u32,
+    /// State of the process.
+    pub state: Option<PidState>,
+    /// File mode creation mask.
+    pub umask: Option<u32>,
+    /// Supplementary group IDs.
+    pub groups: Vec<u32>,
+    /// Thread group ID in each nested PID namespace, outermost first.
+    pub nstgid: Vec<TaskId>,
+    /// Thread ID in each nested PID namespace, outermost first.
+    pub nspid: Vec<TaskId>,
+    /// Process group ID in each nested PID namespace, outermost first.
+    pub nspgid: Vec<TaskId>,
+    /// Session ID in each nested PID namespace, outermost first.
+    pub nssid: Vec<TaskId>,
+    /// Mask of CPUs this process may run on, as 32 bit words with CPUs 0-31 first.
+    pub cpus_allowed: Vec<u32>,
+    /// CPUs this process may run on.
+    pub cpus_allowed_list: Vec<u32>,
+    /// Memory nodes this process may allocate from.
+    pub mems_allowed_list: Vec<u32>,
+    /// Number of voluntary context switches (eg: waiting on I/O).
+    pub voluntary_ctxt_switches: Option<u64>,
+    /// Number of involuntary context switches (eg: preempted).
+    pub nonvoluntary_ctxt_switches: Option<u64>,
+    /// Seccomp mode, if the kernel was built with seccomp.
+    pub seccomp: Option<SeccompMode>,
+    /// Number of seccomp filters attached (Linux 5.9+).
+    pub seccomp_filters: Option<u32>,
+    /// Signals pending for this thread.
+    pub sigpnd: Option<SignalSet>,
+    /// Signals pending for the whole process.
+    pub shdpnd: Option<SignalSet>,
+    /// Signals being blocked.
+    pub sigblk: Option<SignalSet>,
+    /// Signals being ignored.
+    pub sigign: Option<SignalSet>,
+    /// Signals being caught.
+    pub sigcgt: Option<SignalSet>,
+    /// Inheritable capabilities, as a bitmask of capability numbers.
+    pub capinh: Option<u64>,
+    /// Permitted capabilities.
+    pub capprm: Option<u64>,
+    /// Effective capabilities.
+    pub capeff: Option<u64>,
+    /// Capability bounding set.
+    pub capbnd: Option<u64>,
+    /// Ambient capabilities (Linux 4.3+).
+    pub capamb: Option<u64>,
 }
 
 impl PidStatus {
     /// Generate PidStatus struct given a process directory
     pub fn new(pid_dir: &Path) -> Result<Self, ProcError> {
+        debug!("reading {}/status", pid_dir.display());
         // Try opening file
         let status_file = try!(
             File::open(pid_dir.join("status"))
@@ -103,6 +179,60 @@ impl PidStatus {
         Self::parse_string(lines)
     }
 
+    /// Create a placeholder PidStatus for a process whose status file can't
+    /// be read (eg: /proc mounted with hidepid), knowing only its pid and
+    /// owner. The owner is used for every uid and gid.
+    pub fn unreadable(pid: TaskId, uid: u32, gid: u32) -> Self {
+        PidStatus {
+            name: String::new(),
+            tgid: pid,
+            pid: pid,
+            ppid: 0,
+            tracerpid: 0,
+            uid: (uid, uid, uid, uid),
+            gid: (gid, gid, gid, gid),
+            fdsize: 0,
+            vmpeak: None,
+            vmsize: None,
+            vmlck: None,
+            vmpin: None,
+            vmhwm: None,
+            vmrss: None,
+            vmdata: None,
+            vmstk: None,
+            vmexe: None,
+            vmlib: None,
+            vmpte: None,
+            vmpmd: None,
+            vmswap: None,
+            threads: 0,
+            state: None,
+            umask: None,
+            groups: Vec::new(),
+            nstgid: Vec::new(),
+            nspid: Vec::new(),
+            nspgid: Vec::new(),
+            nssid: Vec::new(),
+            cpus_allowed: Vec::new(),
+            cpus_allowed_list: Vec::new(),
+            mems_allowed_list: Vec::new(),
+            voluntary_ctxt_switches: None,
+            nonvoluntary_ctxt_switches: None,
+            seccomp: None,
+            seccomp_filters: None,
+            sigpnd: None,
+            shdpnd: None,
+            sigblk: None,
+            sigign: None,
+            sigcgt: None,
+            capinh: None,
+            capprm: None,
+            capeff: None,
+            capbnd: None,
+            capamb: None,
+        }
+    }
+
     /// Parse an Iterator of lines as a /proc/[pid]/status file.
     fn parse_string<I: Iterator<Item=Result<String, ProcError>>>(lines: I) -> Result<Self, ProcError> {
         let (mut name, mut tgid, mut pid, mut ppid, mut tracerpid, mut uid,
@@ -111,6 +241,17 @@ impl PidStatus {
             mut vmpte, mut vmpmd, mut vmswap, mut threads) =
             (None, None, None, None, None, None, None, None, None, None, None, None,
             None, None, None, None, None, None, None, None, None, None);
+        let (mut state, mut umask, mut voluntary_ctxt_switches, mut nonvoluntary_ctxt_switches,
+            mut seccomp, mut seccomp_filters) =
+            (None, None, None, None, None, None);
+        let (mut sigpnd, mut shdpnd, mut sigblk, mut sigign, mut sigcgt) =
+            (None, None, None, None, None);
+        let (mut capinh, mut capprm, mut capeff, mut capbnd, mut capamb) =
+            (None, None, None, None, None);
+        let (mut groups, mut nstgid, mut nspid, mut nspgid, mut nssid, mut cpus_allowed,
+            mut cpus_allowed_list, mut mems_allowed_list) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
+            Vec::new(), Vec::new());
         for line in lines {
             let line = try!(line);
             // Find colon offset, error on no match.
@@ -149,6 +290,36 @@ impl PidStatus {
                 "VmPMD" => vmpmd = parse!(parse_mem(value), "VmPMD"),
                 "VmSwap" => vmswap = parse!(parse_mem(value), "VmSwap"),
                 "Threads" => threads = parse!(value.parse(), "Threads"),
+                "State" => state = Some(unwrap!(
+                    value.get(0..1).and_then(get_procstate), "State")),
+                "Umask" => umask = parse!(u32::from_str_radix(value, 8), "Umask"),
+                "Groups" => groups = unwrap!(parse!(parse_list(value), "Groups"), "Groups"),
+                "NStgid" => nstgid = unwrap!(parse!(parse_list(value), "NStgid"), "NStgid"),
+                "NSpid" => nspid = unwrap!(parse!(parse_list(value), "NSpid"), "NSpid"),
+                "NSpgid" => nspgid = unwrap!(parse!(parse_list(value), "NSpgid"), "NSpgid"),
+                "NSsid" => nssid = unwrap!(parse!(parse_list(value), "NSsid"), "NSsid"),
+                "Cpus_allowed" => cpus_allowed =
+                    unwrap!(parse!(parse_mask(value), "Cpus_allowed"), "Cpus_allowed"),
+                "Cpus_allowed_list" => cpus_allowed_list =
+                    unwrap!(parse!(parse_range_list(value), "Cpus_allowed_list"), "Cpus_allowed_list"),
+                "Mems_allowed_list" => mems_allowed_list =
+                    unwrap!(parse!(parse_range_list(value), "Mems_allowed_list"), "Mems_allowed_list"),
+                "voluntary_ctxt_switches" => voluntary_ctxt_switches =
+                    parse!(value.parse(), "voluntary_ctxt_switches"),
+                "nonvoluntary_ctxt_switches" => nonvoluntary_ctxt_switches =
+                    parse!(value.parse(), "nonvoluntary_ctxt_switches"),
+                "Seccomp" => seccomp = parse!(value.parse(), "Seccomp"),
+                "Seccomp_filters" => seccomp_filters = parse!(value.parse(), "Seccomp_filters"),
+                "SigPnd" => sigpnd = parse!(SignalSet::from_hex(value), "SigPnd"),
+                "ShdPnd" => shdpnd = parse!(SignalSet::from_hex(value), "ShdPnd"),
+                "SigBlk" => sigblk = parse!(SignalSet::from_hex(value), "SigBlk"),
+                "SigIgn" => sigign = parse!(SignalSet::from_hex(value), "SigIgn"),
+                "SigCgt" => sigcgt = parse!(SignalSet::from_hex(value), "SigCgt"),
+                "CapInh" => capinh = parse!(u64::from_str_radix(value, 16), "CapInh"),
+                "CapPrm" => capprm = parse!(u64::from_str_radix(value, 16), "CapPrm"),
+                "CapEff" => capeff = parse!(u64::from_str_radix(value, 16), "CapEff"),
+                "CapBnd" => capbnd = parse!(u64::from_str_radix(value, 16), "CapBnd"),
+                "CapAmb" => capamb = parse!(u64::from_str_radix(value, 16), "CapAmb"),
                 _ => continue,
             };
         }
@@ -175,6 +346,30 @@ impl PidStatus {
             vmpmd: vmpmd,
             vmswap: vmswap,
             threads: unwrap!(threads, "Threads"),
+            state: state,
+            umask: umask,
+            groups: groups,
+            nstgid: nstgid,
+            nspid: nspid,
+            nspgid: nspgid,
+            nssid: nssid,
+            cpus_allowed: cpus_allowed,
+            cpus_allowed_list: cpus_allowed_list,
+            mems_allowed_list: mems_allowed_list,
+            voluntary_ctxt_switches: voluntary_ctxt_switches,
+            nonvoluntary_ctxt_switches: nonvoluntary_ctxt_switches,
+            seccomp: seccomp,
+            seccomp_filters: seccomp_filters,
+            sigpnd: sigpnd,
+            shdpnd: shdpnd,
+            sigblk: sigblk,
+            sigign: sigign,
+            sigcgt: sigcgt,
+            capinh: capinh,
+            capprm: capprm,
+            capeff: capeff,
+            capbnd: capbnd,
+            capamb: capamb,
         })
     }
 }
@@ -200,11 +395,35 @@ fn parse_uids(uid_str: &str) -> Result<(u32, u32, u32, u32), ProcError> {
     Ok((uids[0], uids[1], uids[2], uids[3]))
 }
 
-/// Parse a string as a kB memory string.
-fn parse_mem(mem_str: &str) -> Result<MemSize, ParseIntError> {
-    mem_str.trim_right_matches(" kB")
-        .parse::<MemSize>()
-        .map(|n| n * 1024)
+/// Parse a whitespace separated list of numbers, like Groups or NSpid.
+fn parse_list<T: FromStr>(list: &str) -> Result<Vec<T>, T::Err> {
+    list.split_whitespace()
+        .map(|s| s.parse())
+        .collect()
+}
+
+/// Parse a comma separated hex mask (most significant word first), like
+/// Cpus_allowed, into 32 bit words with the least significant first.
+fn parse_mask(mask: &str) -> Result<Vec<u32>, ParseIntError> {
+    mask.split(',')
+        .rev()
+        .map(|w| u32::from_str_radix(w, 16))
+        .collect()
+}
+
+/// Parse a list of ranges, like Cpus_allowed_list ("0-3,8"), into each number.
+fn parse_range_list(list: &str) -> Result<Vec<u32>, ParseIntError> {
+    let mut numbers = Vec::new();
+    for range in list.split(',').filter(|r| !r.is_empty()) {
+        let mut split = range.splitn(2, '-');
+        let start: u32 = try!(split.next().unwrap_or("").parse());
+        let end = match split.next() {
+            Some(e) => try!(e.parse()),
+            None => start
+        };
+        numbers.extend(start..end + 1);
+    }
+    Ok(numbers)
 }
 
 #[test]
@@ -310,6 +529,8 @@ fn test_optional_parse() {
 #[test]
 fn test_parsing() {
     let lines = "Name:	bash\n\
+                 Umask:	0022\n\
+                 State:	S (sleeping)\n\
                  Tgid:	27899\n\
                  Pid:	27899\n\
                  PPid:	4351\n\
@@ -318,10 +539,10 @@ fn test_parsing() {
                  Gid:	1000	1000	1000	1000\n\
                  FDSize:	256\n\
                  Groups:	10 18 27 35 101 103 104 105 250 1000 1001 \n\
-                 NStgid:	27899\n\
-                 NSpid:	27899\n\
-                 NSpgid:	27899\n\
-                 NSsid:	27899\n\
+                 NStgid:	27899	1\n\
+                 NSpid:	27899	1\n\
+                 NSpgid:	27899	1\n\
+                 NSsid:	27899	1\n\
                  VmPeak:	   20896 kB\n\
                  VmSize:	   20868 kB\n\
                  VmLck:	       0 kB\n\
@@ -336,6 +557,23 @@ fn test_parsing() {
                  VmPMD:	      12 kB\n\
                  VmSwap:	       0 kB\n\
                  Threads:	1\n\
+                 Cpus_allowed:	ff,ffffffff\n\
+                 Cpus_allowed_list:	0-3,8\n\
+                 Mems_allowed_list:	0\n\
+                 voluntary_ctxt_switches:	150\n\
+                 nonvoluntary_ctxt_switches:	7\n\
+                 Seccomp:	2\n\
+                 Seccomp_filters:	3\n\
+                 SigPnd:	0000000000000000\n\
+                 ShdPnd:	0000000000000100\n\
+                 SigBlk:	0000000000010000\n\
+                 SigIgn:	0000000000001000\n\
+                 SigCgt:	0000000180004a02\n\
+                 CapInh:	0000000000000000\n\
+                 CapPrm:	0000000000003000\n\
+                 CapEff:	0000000000001000\n\
+                 CapBnd:	000001ffffffffff\n\
+                 CapAmb:	0000000000000000\n\
                  ".lines().map(|l| Ok(l.to_owned()));
     let status = PidStatus::parse_string(lines);
     assert_eq!(status,
@@ -361,7 +599,51 @@ fn test_parsing() {
             vmpte: Some(65536),
             vmpmd: Some(12288),
             vmswap: Some(0),
-            threads: 1
+            threads: 1,
+            state: Some(PidState::Sleeping),
+            umask: Some(0o022),
+            groups: vec![10, 18, 27, 35, 101, 103, 104, 105, 250, 1000, 1001],
+            nstgid: vec![27899, 1],
+            nspid: vec![27899, 1],
+            nspgid: vec![27899, 1],
+            nssid: vec![27899, 1],
+            cpus_allowed: vec![0xffffffff, 0xff],
+            cpus_allowed_list: vec![0, 1, 2, 3, 8],
+            mems_allowed_list: vec![0],
+            voluntary_ctxt_switches: Some(150),
+            nonvoluntary_ctxt_switches: Some(7),
+            seccomp: Some(SeccompMode::Filter),
+            seccomp_filters: Some(3),
+            sigpnd: Some(SignalSet(0)),
+            shdpnd: Some(SignalSet(0x100)),
+            sigblk: Some(SignalSet(0x10000)),
+            sigign: Some(SignalSet(0x1000)),
+            sigcgt: Some(SignalSet(0x180004a02)),
+            capinh: Some(0),
+            capprm: Some(0x3000),
+            capeff: Some(0x1000),
+            capbnd: Some(0x1ffffffffff),
+            capamb: Some(0),
         })
     );
 }
+
+#[test]
+fn test_idle_state() {
+    let lines = "Name:	kworker/0:0\n\
+                 State:	I (idle)\n\
+                 Tgid:	6\n\
+                 Pid:	6\n\
+                 PPid:	2\n\
+                 TracerPid:	0\n\
+                 Uid:	0	0	0	0\n\
+                 Gid:	0	0	0	0\n\
+                 FDSize:	64\n\
+                 Threads:	1\n\
+                 ".lines().map(|l| Ok(l.to_owned()));
+    let status = PidStatus::parse_string(lines).unwrap();
+    assert_eq!(status.state, Some(PidState::Idle));
+    assert_eq!(status.groups, Vec::<u32>::new());
+    assert_eq!(status.umask, None);
+    assert_eq!(status.seccomp, None);
+}
@@ -0,0 +1,84 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use error::ProcError;
+use rate::Rate;
+use MemSize;
+use super::{Pid, PidIter};
+use super::stat::PidStat;
+#[cfg(test)]
+use testing::FakePid;
+
+/// Rate of major faults per second above which a process with swapped
+/// memory is considered to be swapping in.
+///
+/// Major faults also come from reading mapped files, so a few per second
+/// are normal for busy processes.
+pub const SWAP_IN_MAJFLT_RATE: f64 = 1.0;
+
+/// A process that is actively faulting swapped memory back in.
+#[derive(Debug)]
+pub struct SwapIn {
+    /// The process, as read at the end of the sample.
+    pub pid: Pid,
+    /// Memory still swapped out.
+    pub vmswap: MemSize,
+    /// Major faults over the sample.
+    pub majflt: Rate<u64>,
+}
+
+/// Whether a process is swapping in, given its swap usage and major fault rate.
+///
+/// Having swapped pages alone doesn't matter, it's faulting them back in
+/// that stalls the process.
+pub fn is_swapping_in(vmswap: Option<MemSize>, majflt_rate: f64) -> bool {
+    vmswap.unwrap_or(0) > 0 && majflt_rate >= SWAP_IN_MAJFLT_RATE
+}
+
+/// Check whether a process is swapping in, given an earlier sample of its
+/// stat file and the time since that sample.
+pub fn swap_in(pid: Pid, previous: &PidStat, elapsed: Duration) -> Option<SwapIn> {
+    let majflt = Rate::from_delta(pid.stat.majflt.saturating_sub(previous.majflt), elapsed);
+    match is_swapping_in(pid.status.vmswap, majflt.per_sec()) {
+        true => Some(SwapIn {
+            vmswap: pid.status.vmswap.unwrap_or(0),
+            majflt: majflt,
+            pid: pid,
+        }),
+        false => None
+    }
+}
+
+/// Sample every process over interval, and list those swapping in, with
+/// the highest major fault rate first.
+///
+/// Processes without swapped memory aren't sampled again, and processes
+/// that exit or can't be read during the scan are skipped.
+pub fn swapping_in(interval: Duration) -> Result<Vec<SwapIn>, ProcError> {
+    let candidates: Vec<Pid> = try!(PidIter::new())
+        .filter_map(|p| p.ok())
+        .filter(|p| p.status.vmswap.unwrap_or(0) > 0)
+        .collect();
+    let start = Instant::now();
+    thread::sleep(interval);
+    let elapsed = start.elapsed();
+    let mut swapping: Vec<SwapIn> = candidates.into_iter()
+        .filter_map(|p| Pid::new(p.stat.pid).ok().and_then(|now| swap_in(now, &p.stat, elapsed)))
+        .collect();
+    swapping.sort_by(|a, b| b.majflt.delta.cmp(&a.majflt.delta));
+    Ok(swapping)
+}
+
+#[test]
+fn test_swap_in() {
+    let old = FakePid::new(10, 1, "sh").to_pid();
+    let mut new = old.clone();
+    new.stat.majflt = old.stat.majflt + 20;
+    new.status.vmswap = Some(4096);
+    let swap = swap_in(new, &old.stat, Duration::from_secs(2)).unwrap();
+    assert_eq!(swap.majflt.per_sec(), 10.0);
+    assert_eq!(swap.vmswap, 4096);
+
+    assert!(!is_swapping_in(Some(0), 100.0));
+    assert!(!is_swapping_in(None, 100.0));
+    assert!(!is_swapping_in(Some(4096), 0.0));
+}
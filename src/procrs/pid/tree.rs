@@ -0,0 +1,606 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use super::Pid;
+use super::stat::PidStat;
+use TaskId;
+use root;
+#[cfg(test)]
+use testing::FakePid;
+
+/// The number of children and descendants of a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FamilyCount {
+    /// Processes whose parent is this process.
+    pub children: usize,
+    /// Children, their children, and so on.
+    pub descendants: usize,
+}
+
+/// Count the children and descendants of every process in a list, using
+/// the parent pid of each process. Only processes in the list are counted,
+/// so pass every process on the system for accurate numbers.
+pub fn family_counts(pids: &[Pid]) -> HashMap<TaskId, FamilyCount> {
+    let mut children: HashMap<TaskId, Vec<TaskId>> = HashMap::new();
+    for p in pids {
+        // Pid 0 is its own parent on some kernels, and isn't a real process.
+        if p.stat.pid != p.stat.ppid {
+            children.entry(p.stat.ppid).or_insert(Vec::new()).push(p.stat.pid);
+        }
+    }
+    let mut counts = HashMap::new();
+    for p in pids {
+        let mut count = FamilyCount {
+            children: children.get(&p.stat.pid).map(|c| c.len()).unwrap_or(0),
+            descendants: 0,
+        };
+        // Walk the subtree with a stack, since trees can be deep. Pid reuse
+        // can create loops, so each process is only walked once.
+        let mut seen: HashSet<_> = Some(p.stat.pid).into_iter().collect();
+        let mut stack = vec![p.stat.pid];
+        while let Some(pid) = stack.pop() {
+            for &child in children.get(&pid).into_iter().flatten() {
+                if seen.insert(child) {
+                    count.descendants += 1;
+                    stack.push(child);
+                }
+            }
+        }
+        counts.insert(p.stat.pid, count);
+    }
+    counts
+}
+
+/// A map from each process to its parent, for walking up the process tree
+/// without reading each ancestor.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParentMap {
+    parents: HashMap<TaskId, TaskId>,
+}
+
+impl ParentMap {
+    /// Read the parent of every process in /proc.
+    ///
+    /// Processes that exit while scanning are skipped.
+    pub fn new() -> Result<Self, ProcError> {
+        let entries = try!(
+            fs::read_dir(root::root())
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcDir, e))
+        );
+        let mut parents = HashMap::new();
+        for entry in entries {
+            let entry = try!(
+                entry.map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcDir, e))
+            );
+            let is_pid = entry.file_name().to_str()
+                .map(|n| n.parse::<TaskId>().is_ok())
+                .unwrap_or(false);
+            if !is_pid {
+                continue;
+            }
+            if let Ok(stat) = PidStat::new(&entry.path()) {
+                parents.insert(stat.pid, stat.ppid);
+            }
+        }
+        Ok(ParentMap {
+            parents: parents,
+        })
+    }
+
+    /// Build a map from a list of processes.
+    pub fn from_pids(pids: &[Pid]) -> Self {
+        ParentMap {
+            parents: pids.iter().map(|p| (p.stat.pid, p.stat.ppid)).collect(),
+        }
+    }
+
+    /// The parent of a process, if it's in the map.
+    pub fn parent(&self, pid: TaskId) -> Option<TaskId> {
+        self.parents.get(&pid).cloned()
+    }
+
+    /// The parent, grandparent and so on of a process, nearest first. The
+    /// walk stops at a process missing from the map (usually pid 0).
+    pub fn ancestors(&self, pid: TaskId) -> Vec<TaskId> {
+        ParentWalk::new(pid, pid, |&p| self.parent(p).map(|parent| (parent, parent))).collect()
+    }
+
+    /// Whether a process is below another, starting the walk from its parent
+    /// (which is passed separately, since it's known even if the process is
+    /// newer than the map).
+    pub fn is_descendant(&self, pid: TaskId, ppid: TaskId, ancestor: TaskId) -> bool {
+        ppid == ancestor || (ppid != pid && self.ancestors(ppid).contains(&ancestor))
+    }
+
+    /// Whether a process is above another. If the other process is newer
+    /// than the map, its parent is read from /proc.
+    pub fn is_ancestor(&self, pid: TaskId, descendant: TaskId) -> bool {
+        let ppid = self.parent(descendant).or_else(||
+            PidStat::new(&root::pid_dir(descendant)).ok().map(|s| s.ppid)
+        );
+        match ppid {
+            Some(ppid) => self.is_descendant(descendant, ppid, pid),
+            None => false
+        }
+    }
+}
+
+/// Read the children of a process or thread directory, from the
+/// task/[tid]/children file of each thread (or the children file of a
+/// thread). Returns None if they can't be read, eg: the kernel was built
+/// without CONFIG_PROC_CHILDREN.
+pub fn read_children(dir: &Path) -> Option<Vec<TaskId>> {
+    let files = match fs::read_dir(dir.join("task")) {
+        Ok(tasks) => tasks.filter_map(|t| t.ok()).map(|t| t.path().join("children")).collect(),
+        Err(_) => vec![dir.join("children")],
+    };
+    let mut children = Vec::new();
+    for file in files {
+        let mut contents = String::new();
+        match File::open(&file).and_then(|mut f| f.read_to_string(&mut contents)) {
+            Ok(_) => children.extend(
+                contents.split_whitespace().filter_map(|p| p.parse::<TaskId>().ok())
+            ),
+            // The thread exited while being read.
+            Err(_) if !file.parent().map(|t| t.exists()).unwrap_or(false) => continue,
+            Err(_) => return None,
+        }
+    }
+    Some(children)
+}
+
+/// A walk up the process tree, nearest parent first, finding the parent of
+/// each process (and its pid) with parent_of.
+///
+/// The walk stops at a process without a known parent, or on coming back to
+/// a process already walked, since pid 0 is its own parent on some kernels
+/// and pid reuse can create loops.
+#[derive(Debug)]
+struct ParentWalk<T, F> {
+    current: Option<T>,
+    seen: HashSet<TaskId>,
+    parent_of: F,
+}
+
+impl<T, F: FnMut(&T) -> Option<(TaskId, T)>> ParentWalk<T, F> {
+    fn new(pid: TaskId, start: T, parent_of: F) -> Self {
+        ParentWalk {
+            current: Some(start),
+            seen: Some(pid).into_iter().collect(),
+            parent_of: parent_of,
+        }
+    }
+}
+
+impl<T: Clone, F: FnMut(&T) -> Option<(TaskId, T)>> Iterator for ParentWalk<T, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pid, parent) = match self.current.take().and_then(|c| (self.parent_of)(&c)) {
+            Some(parent) => parent,
+            None => return None
+        };
+        if !self.seen.insert(pid) {
+            return None;
+        }
+        self.current = Some(parent.clone());
+        Some(parent)
+    }
+}
+
+/// An iterator over the ancestors of a process, from Pid::ancestors().
+///
+/// This stops at init (pid 1), or early if a parent can't be read (eg: it
+/// exited, and the process between it and this one did too).
+#[derive(Debug)]
+pub struct Ancestors {
+    walk: ParentWalk<Pid, fn(&Pid) -> Option<(TaskId, Pid)>>,
+}
+
+impl Ancestors {
+    /// Iterate over the ancestors of a process.
+    pub fn new(pid: Pid) -> Self {
+        fn parent_of(pid: &Pid) -> Option<(TaskId, Pid)> {
+            pid.parent().ok().map(|p| (p.pid, p))
+        }
+        Ancestors {
+            walk: ParentWalk::new(pid.pid, pid, parent_of),
+        }
+    }
+}
+
+impl Iterator for Ancestors {
+    type Item = Pid;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.walk.next()
+    }
+}
+
+/// A tree of processes, linked by their parent pids.
+///
+/// Processes whose parent isn't in the tree become roots, as does one
+/// process of any parent loop (from pid reuse). The tree can be
+/// narrowed with subtree(), prune() and collapse_single_child_chains().
+#[derive(Debug)]
+pub struct ProcessTree {
+    pids: HashMap<TaskId, Pid>,
+    parents: HashMap<TaskId, TaskId>,
+    children: HashMap<TaskId, Vec<TaskId>>,
+    roots: Vec<TaskId>,
+    collapsed: HashMap<TaskId, usize>,
+}
+
+impl ProcessTree {
+    /// Build a tree from a list of processes.
+    pub fn new<I: IntoIterator<Item=Pid>>(pids: I) -> Self {
+        let pids: HashMap<_, _> = pids.into_iter().map(|p| (p.stat.pid, p)).collect();
+        let parents = pids.values()
+            .filter(|p| p.stat.pid != p.stat.ppid && pids.contains_key(&p.stat.ppid))
+            .map(|p| (p.stat.pid, p.stat.ppid))
+            .collect();
+        let mut tree = ProcessTree {
+            pids: pids,
+            parents: parents,
+            children: HashMap::new(),
+            roots: Vec::new(),
+            collapsed: HashMap::new(),
+        };
+        tree.link();
+        tree
+    }
+
+    /// The processes without a parent in the tree, in pid order.
+    pub fn roots(&self) -> &[TaskId] {
+        &self.roots
+    }
+
+    /// Get a process in the tree.
+    pub fn get(&self, pid: TaskId) -> Option<&Pid> {
+        self.pids.get(&pid)
+    }
+
+    /// The children of a process in the tree, in pid order.
+    pub fn children(&self, pid: TaskId) -> &[TaskId] {
+        self.children.get(&pid).map(|c| &c[..]).unwrap_or(&[])
+    }
+
+    /// The number of processes hidden between a process and its parent in
+    /// the tree by collapse_single_child_chains().
+    pub fn collapsed(&self, pid: TaskId) -> usize {
+        self.collapsed.get(&pid).cloned().unwrap_or(0)
+    }
+
+    /// The number of processes in the tree.
+    pub fn len(&self) -> usize {
+        self.pids.len()
+    }
+
+    /// Whether the tree has no processes.
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty()
+    }
+
+    /// Every process below a process in the tree.
+    pub fn descendants(&self, pid: TaskId) -> Vec<TaskId> {
+        let mut descendants = Vec::new();
+        let mut seen: HashSet<_> = Some(pid).into_iter().collect();
+        let mut stack = vec![pid];
+        while let Some(pid) = stack.pop() {
+            for &child in self.children(pid) {
+                if seen.insert(child) {
+                    descendants.push(child);
+                    stack.push(child);
+                }
+            }
+        }
+        descendants
+    }
+
+    /// Walk every process depth first, with each root followed by its
+    /// descendants (like ps -H). Each process comes with its depth, being 0
+    /// for roots.
+    pub fn depth_first(&self) -> DepthFirst<'_> {
+        DepthFirst {
+            tree: self,
+            stack: self.roots.iter().rev().map(|&pid| (pid, 0)).collect(),
+        }
+    }
+
+    /// Narrow the tree to a process and its descendants, with the process as
+    /// the only root. Returns None if the process isn't in the tree.
+    pub fn subtree(mut self, pid: TaskId) -> Option<Self> {
+        if !self.pids.contains_key(&pid) {
+            return None;
+        }
+        let mut keep: HashSet<_> = self.descendants(pid).into_iter().collect();
+        keep.insert(pid);
+        self.retain(&keep);
+        Some(self)
+    }
+
+    /// Remove every process that doesn't match, unless one of its descendants
+    /// matches. Matching processes stay connected to the roots through their ancestors.
+    pub fn prune<F: Fn(&Pid) -> bool>(&mut self, matches: F) {
+        let mut keep = HashSet::new();
+        for (&pid, p) in &self.pids {
+            if !matches(p) {
+                continue;
+            }
+            let mut current = Some(pid);
+            // Stop once an ancestor is already kept, since its ancestors will be too.
+            while let Some(c) = current {
+                if !keep.insert(c) {
+                    break;
+                }
+                current = self.parents.get(&c).cloned();
+            }
+        }
+        self.retain(&keep);
+    }
+
+    /// Remove processes that have a parent and exactly one child (eg: shells
+    /// or sudo wrapping a command), so long chains are shown as one step.
+    /// Use collapsed() to find how many processes were hidden.
+    pub fn collapse_single_child_chains(&mut self) {
+        let keep = self.pids.keys()
+            .filter(|pid| !self.parents.contains_key(pid) || self.children(**pid).len() != 1)
+            .cloned()
+            .collect();
+        self.retain(&keep);
+    }
+
+    /// Keep only the given processes, attaching each to its closest kept ancestor.
+    fn retain(&mut self, keep: &HashSet<TaskId>) {
+        let mut parents = HashMap::new();
+        let mut collapsed = HashMap::new();
+        for &pid in keep {
+            let mut hidden = self.collapsed(pid);
+            let mut current = self.parents.get(&pid).cloned();
+            while let Some(c) = current {
+                if keep.contains(&c) {
+                    parents.insert(pid, c);
+                    break;
+                }
+                hidden += 1 + self.collapsed(c);
+                current = self.parents.get(&c).cloned();
+            }
+            // Processes that lost every ancestor become roots, so aren't collapsed.
+            if hidden > 0 && parents.contains_key(&pid) {
+                collapsed.insert(pid, hidden);
+            }
+        }
+        self.pids.retain(|pid, _| keep.contains(pid));
+        self.parents = parents;
+        self.collapsed = collapsed;
+        self.link();
+    }
+
+    /// Rebuild the children and roots from the parents.
+    fn link(&mut self) {
+        self.break_loops();
+        self.children.clear();
+        self.roots.clear();
+        for &pid in self.pids.keys() {
+            match self.parents.get(&pid) {
+                Some(&parent) => self.children.entry(parent).or_insert(Vec::new()).push(pid),
+                None => self.roots.push(pid)
+            }
+        }
+        self.roots.sort();
+        for children in self.children.values_mut() {
+            children.sort();
+        }
+    }
+
+    /// Break any loops in the parents (from pid reuse), so that every
+    /// process can be reached from a root. The process whose parent leads
+    /// back into the loop becomes a root.
+    fn break_loops(&mut self) {
+        let mut checked = HashSet::new();
+        let mut pids: Vec<_> = self.parents.keys().cloned().collect();
+        pids.sort();
+        for pid in pids {
+            let mut walked = HashSet::new();
+            let mut current = pid;
+            while !checked.contains(&current) && walked.insert(current) {
+                match self.parents.get(&current).cloned() {
+                    Some(parent) if walked.contains(&parent) => {
+                        self.parents.remove(&current);
+                    },
+                    Some(parent) => current = parent,
+                    None => {}
+                }
+            }
+            checked.extend(walked);
+        }
+    }
+}
+
+/// An iterator over a ProcessTree in depth first order, from depth_first().
+#[derive(Debug)]
+pub struct DepthFirst<'a> {
+    tree: &'a ProcessTree,
+    /// The processes left to visit, with the next on top.
+    stack: Vec<(TaskId, usize)>,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = (&'a Pid, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (pid, depth) = match self.stack.pop() {
+            Some(next) => next,
+            None => return None
+        };
+        self.stack.extend(self.tree.children(pid).iter().rev().map(|&c| (c, depth + 1)));
+        Some((&self.tree.pids[&pid], depth))
+    }
+}
+
+#[test]
+fn test_family_counts() {
+    let family = [(1, 0), (2, 1), (3, 1), (4, 2), (5, 4), (6, 0), (7, 8), (8, 7)];
+    let pids: Vec<_> = family.iter()
+        .map(|&(pid, ppid)| FakePid::new(pid, ppid, "sh").to_pid())
+        .collect();
+    let counts = family_counts(&pids);
+    assert_eq!(counts[&1], FamilyCount { children: 2, descendants: 4 });
+    assert_eq!(counts[&2], FamilyCount { children: 1, descendants: 2 });
+    assert_eq!(counts[&5], FamilyCount { children: 0, descendants: 0 });
+    assert_eq!(counts[&6], FamilyCount::default());
+    // A loop from pid reuse is only counted once.
+    assert_eq!(counts[&7], FamilyCount { children: 1, descendants: 1 });
+}
+
+#[test]
+fn test_parent_map() {
+    let family = [(0, 0), (1, 0), (2, 1), (3, 2), (4, 1)];
+    let pids: Vec<_> = family.iter()
+        .map(|&(pid, ppid)| FakePid::new(pid, ppid, "sh").to_pid())
+        .collect();
+    let map = ParentMap::from_pids(&pids);
+    assert_eq!(map.parent(3), Some(2));
+    assert_eq!(map.ancestors(3), vec![2, 1, 0]);
+    assert_eq!(map.ancestors(0), Vec::<TaskId>::new());
+    assert!(map.is_descendant(3, 2, 1));
+    assert!(!map.is_descendant(3, 2, 4));
+    // A process newer than the map is found through its parent.
+    assert!(map.is_descendant(9, 4, 1));
+    assert!(map.is_ancestor(1, 3));
+    assert!(!map.is_ancestor(3, 1));
+    let myself = Pid::myself().unwrap();
+    assert_eq!(ParentMap::new().unwrap().parent(myself.pid), Some(myself.stat.ppid));
+}
+
+#[test]
+fn test_ancestors() {
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    fake.process(&FakePid::new(1, 0, "init")).unwrap();
+    fake.process(&FakePid { starttime: 10, ..FakePid::new(10, 1, "sshd") }).unwrap();
+    fake.process(&FakePid { starttime: 20, ..FakePid::new(20, 10, "bash") }).unwrap();
+    fake.process(&FakePid { starttime: 30, ..FakePid::new(30, 20, "vim") }).unwrap();
+    fake.thread(30, &FakePid { starttime: 30, ..FakePid::new(31, 20, "vim") }).unwrap();
+    // Parents are read from the /proc the process was, not the current root.
+    let vim = Pid::new_dir(fake.path(), 30).unwrap();
+    assert_eq!(vim.parent().unwrap().stat.comm, "bash");
+    let thread = Pid::new_path(fake.path().join("30/task/31"), true).unwrap();
+    assert_eq!(thread.parent().unwrap().stat.comm, "bash");
+    fake.run(|| {
+        let vim = Pid::new(30).unwrap();
+        assert_eq!(vim.parent().unwrap().stat.comm, "bash");
+        let names: Vec<_> = vim.ancestors().map(|p| p.stat.comm).collect();
+        assert_eq!(names, vec!["bash", "sshd", "init"]);
+        assert!(Pid::new(1).unwrap().parent().is_err());
+
+        // bash exits, and vim is reparented to init.
+        fake.process(&FakePid { starttime: 30, ..FakePid::new(30, 1, "vim") }).unwrap();
+        fs::remove_dir_all(fake.path().join("20")).unwrap();
+        assert_eq!(vim.parent().unwrap().pid, 1);
+        // bash's pid is reused by a newer process.
+        fake.process(&FakePid { starttime: 40, ..FakePid::new(20, 1, "cron") }).unwrap();
+        assert_eq!(vim.parent().unwrap().pid, 1);
+    });
+}
+
+#[test]
+fn test_children() {
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::new().unwrap();
+    for &(pid, ppid) in &[(1, 0), (10, 1), (11, 10), (12, 10), (13, 12), (20, 1)] {
+        fake.process(&FakePid::new(pid, ppid, "sh")).unwrap();
+    }
+    let pids = |pids: Vec<Pid>| pids.iter().map(|p| p.pid).collect::<Vec<_>>();
+    // A process read from a proc tree finds its children in that tree.
+    let sh = Pid::new_dir(fake.path(), 10).unwrap();
+    assert_eq!(pids(sh.children().unwrap()), vec![11, 12]);
+    assert_eq!(pids(sh.descendants().unwrap()), vec![11, 12, 13]);
+    fake.run(|| {
+        // Without children files, /proc is scanned.
+        let sh = Pid::new(10).unwrap();
+        assert_eq!(read_children(&sh.dir), None);
+        assert_eq!(pids(sh.children().unwrap()), vec![11, 12]);
+        assert_eq!(pids(sh.descendants().unwrap()), vec![11, 12, 13]);
+
+        for &(pid, children) in &[(1, "10 20 "), (10, "12 11 "), (11, ""), (12, "13 "),
+            (13, ""), (20, "")] {
+            fake.file(format!("{}/task/{}/children", pid, pid), children).unwrap();
+        }
+        assert_eq!(read_children(&sh.dir), Some(vec![12, 11]));
+        assert_eq!(pids(sh.children().unwrap()), vec![11, 12]);
+        assert_eq!(pids(sh.descendants().unwrap()), vec![11, 12, 13]);
+        assert_eq!(pids(Pid::new(1).unwrap().descendants().unwrap()), vec![10, 11, 12, 13, 20]);
+    });
+    assert_eq!(pids(sh.children().unwrap()), vec![11, 12]);
+    assert_eq!(pids(sh.descendants().unwrap()), vec![11, 12, 13]);
+    let myself = Pid::myself().unwrap();
+    assert!(myself.children().unwrap().is_empty());
+}
+
+#[cfg(test)]
+fn test_tree() -> ProcessTree {
+    // 1 -> 2 -> 3 -> 4 -> 5
+    //   -> 6 -> 7
+    //        -> 8
+    let family = [(1, 0), (2, 1), (3, 2), (4, 3), (5, 4), (6, 1), (7, 6), (8, 6)];
+    ProcessTree::new(family.iter().map(|&(pid, ppid)| FakePid::new(pid, ppid, "sh").to_pid()))
+}
+
+#[test]
+fn test_subtree() {
+    let tree = test_tree();
+    assert_eq!(tree.roots(), &[1]);
+    assert_eq!(tree.children(1), &[2, 6]);
+    let sub = tree.subtree(6).unwrap();
+    assert_eq!(sub.roots(), &[6]);
+    assert_eq!(sub.len(), 3);
+    assert!(test_tree().subtree(9).is_none());
+}
+
+#[test]
+fn test_depth_first() {
+    let tree = test_tree();
+    let order: Vec<_> = tree.depth_first().map(|(p, depth)| (p.stat.pid, depth)).collect();
+    assert_eq!(order, vec![(1, 0), (2, 1), (3, 2), (4, 3), (5, 4), (6, 1), (7, 2), (8, 2)]);
+    let sub = test_tree().subtree(6).unwrap();
+    let order: Vec<_> = sub.depth_first().map(|(p, depth)| (p.stat.pid, depth)).collect();
+    assert_eq!(order, vec![(6, 0), (7, 1), (8, 1)]);
+}
+
+#[test]
+fn test_loops() {
+    // 1 -> 2, and 3 -> 4 -> 5 -> 3 from pid reuse
+    let family = [(1, 0), (2, 1), (3, 5), (4, 3), (5, 4)];
+    let tree = ProcessTree::new(family.iter()
+        .map(|&(pid, ppid)| FakePid::new(pid, ppid, "sh").to_pid()));
+    assert_eq!(tree.roots(), &[1, 4]);
+    let order: Vec<_> = tree.depth_first().map(|(p, depth)| (p.stat.pid, depth)).collect();
+    assert_eq!(order, vec![(1, 0), (2, 1), (4, 0), (5, 1), (3, 2)]);
+    assert_eq!(tree.descendants(4), vec![5, 3]);
+    let sub = tree.subtree(3).unwrap();
+    assert_eq!(sub.roots(), &[3]);
+    assert_eq!(sub.len(), 1);
+}
+
+#[test]
+fn test_prune() {
+    let mut tree = test_tree();
+    tree.prune(|p| p.stat.pid == 4 || p.stat.pid == 8);
+    let mut kept: Vec<_> = tree.pids.keys().cloned().collect();
+    kept.sort();
+    assert_eq!(kept, vec![1, 2, 3, 4, 6, 8]);
+    assert_eq!(tree.children(6), &[8]);
+}
+
+#[test]
+fn test_collapse() {
+    let mut tree = test_tree();
+    tree.collapse_single_child_chains();
+    assert_eq!(tree.children(1), &[5, 6]);
+    assert_eq!(tree.collapsed(5), 3);
+    assert_eq!(tree.collapsed(6), 0);
+    assert_eq!(tree.children(6), &[7, 8]);
+}
@@ -0,0 +1,209 @@
+use std::path::Path;
+use std::time::Duration;
+#[cfg(test)]
+use std::io;
+use error::{ProcError, ProcFile, ProcOper};
+use super::{read_value, write_value};
+use super::oom::{OOM_SCORE_ADJ_MIN, OOM_SCORE_ADJ_MAX};
+
+/// Which kinds of memory mappings are written to a core dump (/proc/[pid]/coredump_filter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoredumpFilter(pub u32);
+
+impl CoredumpFilter {
+    /// Anonymous private memory.
+    pub const ANON_PRIVATE: u32 = 1 << 0;
+    /// Anonymous shared memory.
+    pub const ANON_SHARED: u32 = 1 << 1;
+    /// File-backed private memory.
+    pub const FILE_PRIVATE: u32 = 1 << 2;
+    /// File-backed shared memory.
+    pub const FILE_SHARED: u32 = 1 << 3;
+    /// ELF headers.
+    pub const ELF_HEADERS: u32 = 1 << 4;
+    /// Hugetlb private memory.
+    pub const HUGETLB_PRIVATE: u32 = 1 << 5;
+    /// Hugetlb shared memory.
+    pub const HUGETLB_SHARED: u32 = 1 << 6;
+    /// DAX private memory.
+    pub const DAX_PRIVATE: u32 = 1 << 7;
+    /// DAX shared memory.
+    pub const DAX_SHARED: u32 = 1 << 8;
+    /// All the bits the kernel knows about.
+    pub const ALL: u32 = (1 << 9) - 1;
+
+    /// Check whether a kind of mapping is included.
+    pub fn contains(&self, bits: u32) -> bool {
+        self.0 & bits == bits
+    }
+}
+
+/// Values that can be written to /proc/[pid]/clear_refs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearRefs {
+    /// Clear the referenced bits of all pages.
+    All,
+    /// Clear the referenced bits of anonymous pages.
+    Anon,
+    /// Clear the referenced bits of file-backed pages.
+    Mapped,
+    /// Clear the soft-dirty bits of all pages.
+    SoftDirty,
+    /// Reset the peak resident set size (VmHWM).
+    PeakRss,
+}
+
+impl ClearRefs {
+    fn value(&self) -> &'static str {
+        match *self {
+            ClearRefs::All => "1",
+            ClearRefs::Anon => "2",
+            ClearRefs::Mapped => "3",
+            ClearRefs::SoftDirty => "4",
+            ClearRefs::PeakRss => "5",
+        }
+    }
+}
+
+/// Write oom_score_adj for a process, given its process directory.
+///
+/// Lowering the value requires CAP_SYS_RESOURCE.
+pub fn set_oom_score_adj(pid_dir: &Path, score_adj: i32) -> Result<(), ProcError> {
+    if score_adj < OOM_SCORE_ADJ_MIN || score_adj > OOM_SCORE_ADJ_MAX {
+        return Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidOomScoreAdj,
+            Some("oom_score_adj out of range")));
+    }
+    write_value(&pid_dir.join("oom_score_adj"), ProcFile::PidOomScoreAdj, &score_adj.to_string())
+}
+
+/// Write coredump_filter for a process, given its process directory.
+pub fn set_coredump_filter(pid_dir: &Path, filter: CoredumpFilter) -> Result<(), ProcError> {
+    if filter.0 & !CoredumpFilter::ALL != 0 {
+        return Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidCoredumpFilter,
+            Some("unknown coredump_filter bits")));
+    }
+    write_value(&pid_dir.join("coredump_filter"), ProcFile::PidCoredumpFilter,
+        &format!("{:#x}", filter.0))
+}
+
+/// Write clear_refs for a process, given its process directory.
+pub fn clear_refs(pid_dir: &Path, refs: ClearRefs) -> Result<(), ProcError> {
+    write_value(&pid_dir.join("clear_refs"), ProcFile::PidClearRefs, refs.value())
+}
+
+/// Longest name the kernel stores for a process or thread, in bytes.
+pub const COMM_MAX_LEN: usize = 15;
+
+/// Write comm (the process or thread name) for a task, given its process directory.
+///
+/// Only tasks in the calling process can usually be renamed, so this is
+/// mostly useful for naming worker threads (with Tid::current_thread()).
+pub fn set_comm(pid_dir: &Path, name: &str) -> Result<(), ProcError> {
+    if name.len() > COMM_MAX_LEN || name.contains('\0') {
+        return Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidComm,
+            Some("comm too long or contains nul")));
+    }
+    write_value(&pid_dir.join("comm"), ProcFile::PidComm, name)
+}
+
+/// Read timerslack_ns for a process in nanoseconds, given its process directory.
+pub fn timer_slack(pid_dir: &Path) -> Result<u64, ProcError> {
+    timer_slack_supported(pid_dir,
+        read_value(&pid_dir.join("timerslack_ns"), ProcFile::PidTimerslackNs))
+}
+
+/// Write timerslack_ns for a process in nanoseconds, given its process directory.
+///
+/// Zero resets the process to the default timer slack. Changing another
+/// process requires CAP_SYS_NICE.
+pub fn set_timer_slack(pid_dir: &Path, slack_ns: u64) -> Result<(), ProcError> {
+    timer_slack_supported(pid_dir,
+        write_value(&pid_dir.join("timerslack_ns"), ProcFile::PidTimerslackNs,
+            &slack_ns.to_string()))
+}
+
+/// Read the timer slack of a process as a Duration, given its process directory.
+pub fn timer_slack_duration(pid_dir: &Path) -> Result<Duration, ProcError> {
+    let slack_ns = try!(timer_slack(pid_dir));
+    Ok(Duration::new(slack_ns / 1_000_000_000, (slack_ns % 1_000_000_000) as u32))
+}
+
+/// Write the timer slack of a process as a Duration, given its process directory.
+///
+/// A zero Duration resets the process to the default timer slack.
+pub fn set_timer_slack_duration(pid_dir: &Path, slack: Duration) -> Result<(), ProcError> {
+    let slack_ns = try!(
+        slack.as_secs().checked_mul(1_000_000_000)
+            .and_then(|ns| ns.checked_add(slack.subsec_nanos() as u64))
+            .ok_or(ProcError::new_more(ProcOper::Validating, ProcFile::PidTimerslackNs,
+                Some("timer slack out of range")))
+    );
+    set_timer_slack(pid_dir, slack_ns)
+}
+
+/// timerslack_ns was added in Linux 4.6, so when it is missing from a process
+/// directory that still exists, return an Unsupported error instead.
+fn timer_slack_supported<T>(pid_dir: &Path, result: Result<T, ProcError>)
+    -> Result<T, ProcError> {
+    match result {
+        Err(ref e) if e.is_not_found() && pid_dir.exists() =>
+            Err(ProcError::new_more(ProcOper::Unsupported, ProcFile::PidTimerslackNs,
+                Some("timerslack_ns needs Linux 4.6"))),
+        r => r
+    }
+}
+
+#[test]
+fn test_timer_slack() {
+    // Every process starts with some timer slack (50us by default).
+    let dir = Path::new("/proc/self");
+    let slack = timer_slack_duration(dir).unwrap();
+    assert!(slack > Duration::from_secs(0));
+    assert_eq!(slack.subsec_nanos() as u64, timer_slack(dir).unwrap() % 1_000_000_000);
+    assert_eq!(set_timer_slack_duration(dir, Duration::new(u64::max_value(), 0)),
+        Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidTimerslackNs,
+            Some("timer slack out of range"))));
+    // A missing process isn't mistaken for an old kernel.
+    assert!(!timer_slack(Path::new("/proc/0")).unwrap_err().is_unsupported_kernel());
+}
+
+#[test]
+fn test_set_comm() {
+    use std::thread;
+    thread::spawn(|| {
+        let dir = Path::new("/proc/thread-self");
+        set_comm(dir, "procrs-renamed").unwrap();
+        assert_eq!(read_value::<String>(&dir.join("comm"), ProcFile::PidComm).unwrap(),
+            "procrs-renamed");
+        assert_eq!(set_comm(dir, "a-name-that-is-too-long"),
+            Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidComm,
+                Some("comm too long or contains nul"))));
+    }).join().unwrap();
+}
+
+#[test]
+fn test_oom_range() {
+    assert_eq!(set_oom_score_adj(Path::new("/proc/self"), 1001),
+        Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidOomScoreAdj,
+            Some("oom_score_adj out of range"))));
+}
+
+#[test]
+fn test_coredump_filter() {
+    assert_eq!(set_coredump_filter(Path::new("/proc/self"), CoredumpFilter(1 << 9)),
+        Err(ProcError::new_more(ProcOper::Validating, ProcFile::PidCoredumpFilter,
+            Some("unknown coredump_filter bits"))));
+    let filter = CoredumpFilter(CoredumpFilter::ANON_PRIVATE | CoredumpFilter::ELF_HEADERS);
+    assert!(filter.contains(CoredumpFilter::ELF_HEADERS));
+    assert!(!filter.contains(CoredumpFilter::FILE_SHARED));
+}
+
+#[test]
+fn test_permission_denied() {
+    let eperm = ProcError::new_err(ProcOper::Opening, ProcFile::PidClearRefs,
+        io::Error::from_raw_os_error(1));
+    assert!(eperm.is_permission_denied());
+    let enoent = ProcError::new_err(ProcOper::Opening, ProcFile::PidClearRefs,
+        io::Error::from_raw_os_error(2));
+    assert!(!enoent.is_permission_denied());
+}
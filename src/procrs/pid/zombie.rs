@@ -0,0 +1,37 @@
+use std::time::Duration;
+use error::ProcError;
+use uptime::Uptime;
+use super::{Pid, PidIter};
+use super::stat::PidState;
+
+/// A zombie process, with the parent responsible for reaping it.
+#[derive(Debug)]
+pub struct Zombie {
+    /// The zombie process.
+    pub pid: Pid,
+    /// The parent process, if it could still be read.
+    pub parent: Option<Pid>,
+    /// Approximately how long the zombie has been waiting to be reaped.
+    ///
+    /// The kernel doesn't record when a process exited, so this is measured
+    /// from when it started, and is an upper bound.
+    pub age: Duration,
+}
+
+/// Find every zombie process along with its parent, so reaping bugs can be
+/// attributed to the service that isn't calling wait().
+///
+/// Processes that disappear or can't be read during the scan are skipped.
+pub fn zombies() -> Result<Vec<Zombie>, ProcError> {
+    let uptime = try!(Uptime::new()).uptime;
+    let zombies = try!(PidIter::new())
+        .filter_map(|p| p.ok())
+        .filter(|p| p.stat.state == PidState::Zombie)
+        .map(|p| Zombie {
+            parent: Pid::new(p.stat.ppid).ok(),
+            age: p.stat.age(uptime),
+            pid: p,
+        })
+        .collect();
+    Ok(zombies)
+}
@@ -0,0 +1,170 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use error::{ProcError, ProcFile, ProcOper};
+use MemSize;
+use root;
+
+/// Rate of major faults per second above which a process is considered
+/// to be thrashing.
+pub const THRASHING_MAJFLT_RATE: f64 = 50.0;
+
+/// Percentage of time (over the last 10 seconds) tasks were stalled on memory,
+/// above which the system is considered to be under memory pressure.
+pub const THRASHING_PSI_AVG10: f64 = 5.0;
+
+/// A single line of a pressure file, describing stalled time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PressureLine {
+    /// Percentage of time stalled over the last 10 seconds.
+    pub avg10: f64,
+    /// Percentage of time stalled over the last 60 seconds.
+    pub avg60: f64,
+    /// Percentage of time stalled over the last 300 seconds.
+    pub avg300: f64,
+    /// Total stalled time in microseconds.
+    pub total: u64,
+}
+
+/// A struct containing pressure stall information from /proc/pressure/.
+///
+/// This requires linux 4.20+ with PSI enabled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pressure {
+    /// Time at least some tasks were stalled on this resource.
+    pub some: PressureLine,
+    /// Time all non-idle tasks were stalled on this resource at once.
+    /// This isn't reported for cpu on older kernels.
+    pub full: Option<PressureLine>,
+}
+
+impl Pressure {
+    /// Read memory pressure from /proc/pressure/memory.
+    pub fn memory() -> Result<Self, ProcError> {
+        Self::new_file(&root::path("pressure/memory"))
+    }
+
+    /// Read cpu pressure from /proc/pressure/cpu.
+    pub fn cpu() -> Result<Self, ProcError> {
+        Self::new_file(&root::path("pressure/cpu"))
+    }
+
+    /// Read io pressure from /proc/pressure/io.
+    pub fn io() -> Result<Self, ProcError> {
+        Self::new_file(&root::path("pressure/io"))
+    }
+
+    fn new_file(path: &Path) -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(path)
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcPressure, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcPressure, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/pressure/ file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let (mut some, mut full) = (None, None);
+        for line in contents.lines() {
+            let mut split = line.split_whitespace();
+            match split.next() {
+                Some("some") => some = Some(try!(parse_line(split))),
+                Some("full") => full = Some(try!(parse_line(split))),
+                _ => continue,
+            }
+        }
+        Ok(Pressure {
+            some: try!(some.ok_or(
+                ProcError::new_more(ProcOper::Parsing, ProcFile::ProcPressure, Some("missing some"))
+            )),
+            full: full,
+        })
+    }
+}
+
+/// Parse the "key=value" pairs of a pressure line.
+fn parse_line<'a, I: Iterator<Item=&'a str>>(fields: I) -> Result<PressureLine, ProcError> {
+    let (mut avg10, mut avg60, mut avg300, mut total) = (None, None, None, None);
+    for field in fields {
+        let mut split = field.splitn(2, '=');
+        let (key, value) = (split.next().unwrap_or(""), split.next().unwrap_or(""));
+        let err = |more| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcPressure, Some(more));
+        match key {
+            "avg10" => avg10 = Some(try!(value.parse().map_err(|_| err("avg10")))),
+            "avg60" => avg60 = Some(try!(value.parse().map_err(|_| err("avg60")))),
+            "avg300" => avg300 = Some(try!(value.parse().map_err(|_| err("avg300")))),
+            "total" => total = Some(try!(value.parse().map_err(|_| err("total")))),
+            _ => continue,
+        }
+    }
+    let missing = |more| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcPressure, Some(more));
+    Ok(PressureLine {
+        avg10: try!(avg10.ok_or(missing("missing avg10"))),
+        avg60: try!(avg60.ok_or(missing("missing avg60"))),
+        avg300: try!(avg300.ok_or(missing("missing avg300"))),
+        total: try!(total.ok_or(missing("missing total"))),
+    })
+}
+
+/// A classification of how memory pressure is affecting a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemPressure {
+    /// The process is rapidly faulting pages in while the system is under memory pressure.
+    Thrashing,
+    /// Some of the process's memory is swapped out, but it isn't actively faulting.
+    Swapped,
+    /// All of the process's memory is resident.
+    Resident,
+}
+
+impl MemPressure {
+    /// Classify a process given its major fault rate (per second), swap usage
+    /// and, if available, system memory pressure.
+    ///
+    /// Without pressure information, the fault rate alone decides thrashing.
+    pub fn classify(majflt_rate: f64, vmswap: Option<MemSize>, psi: Option<&Pressure>)
+        -> MemPressure {
+        let pressured = psi.map(|p| p.some.avg10 >= THRASHING_PSI_AVG10).unwrap_or(true);
+        if majflt_rate >= THRASHING_MAJFLT_RATE && pressured {
+            MemPressure::Thrashing
+        } else if vmswap.unwrap_or(0) > 0 {
+            MemPressure::Swapped
+        } else {
+            MemPressure::Resident
+        }
+    }
+
+    /// Get a short name for this classification.
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            MemPressure::Thrashing => "thrashing",
+            MemPressure::Swapped => "swapped",
+            MemPressure::Resident => "resident",
+        }
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let pressure = Pressure::parse_string(
+        "some avg10=2.66 avg60=3.29 avg300=2.49 total=14863216\n\
+         full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n").unwrap();
+    assert_eq!(pressure.some,
+        PressureLine { avg10: 2.66, avg60: 3.29, avg300: 2.49, total: 14863216 });
+    assert_eq!(pressure.full.unwrap().total, 0);
+}
+
+#[test]
+fn test_classify() {
+    let calm = Pressure::parse_string("some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n").unwrap();
+    let busy = Pressure::parse_string("some avg10=30.00 avg60=20.00 avg300=2.00 total=900\n").unwrap();
+    assert_eq!(MemPressure::classify(100.0, Some(4096), Some(&busy)), MemPressure::Thrashing);
+    assert_eq!(MemPressure::classify(100.0, None, None), MemPressure::Thrashing);
+    assert_eq!(MemPressure::classify(100.0, Some(4096), Some(&calm)), MemPressure::Swapped);
+    assert_eq!(MemPressure::classify(0.0, Some(0), Some(&busy)), MemPressure::Resident);
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+/// A monotonically increasing counter read from /proc, like rx_bytes or minflt.
+pub trait Counter: Copy {
+    /// The increase from old to self, or zero if the counter went backwards
+    /// (eg: it was reset).
+    fn delta(self, old: Self) -> Self;
+
+    /// The increase from old to self, assuming the counter wrapped around
+    /// at the width of the type if it went backwards.
+    fn wrapping_delta(self, old: Self) -> Self;
+
+    /// The counter as a float, for calculating rates.
+    fn as_f64(self) -> f64;
+}
+
+impl Counter for u32 {
+    fn delta(self, old: Self) -> Self {
+        self.saturating_sub(old)
+    }
+
+    fn wrapping_delta(self, old: Self) -> Self {
+        self.wrapping_sub(old)
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Counter for u64 {
+    fn delta(self, old: Self) -> Self {
+        self.saturating_sub(old)
+    }
+
+    fn wrapping_delta(self, old: Self) -> Self {
+        self.wrapping_sub(old)
+    }
+
+    fn as_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+/// The change in a counter over a period of time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate<T> {
+    /// The increase of the counter.
+    pub delta: T,
+    /// The time between the two readings.
+    pub elapsed: Duration,
+}
+
+impl<T: Counter> Rate<T> {
+    /// Calculate the rate between an old and a new reading of a counter.
+    ///
+    /// A counter that went backwards (eg: it was reset) has a rate of zero.
+    pub fn new(old: T, new: T, elapsed: Duration) -> Self {
+        Rate::from_delta(new.delta(old), elapsed)
+    }
+
+    /// Calculate the rate between an old and a new reading of a counter
+    /// that wraps around at the width of its type, like the 32-bit counters
+    /// of some network statistics.
+    pub fn wrapping(old: T, new: T, elapsed: Duration) -> Self {
+        Rate::from_delta(new.wrapping_delta(old), elapsed)
+    }
+
+    /// Create a rate from an already calculated increase.
+    pub fn from_delta(delta: T, elapsed: Duration) -> Self {
+        Rate {
+            delta: delta,
+            elapsed: elapsed,
+        }
+    }
+
+    /// The increase per second, or zero if no time elapsed.
+    pub fn per_sec(&self) -> f64 {
+        self.per(Duration::from_secs(1))
+    }
+
+    /// The increase per period, eg: per minute.
+    pub fn per(&self, period: Duration) -> f64 {
+        match secs(self.elapsed) {
+            s if s > 0.0 => self.delta.as_f64() * secs(period) / s,
+            _ => 0.0
+        }
+    }
+}
+
+/// Convert a Duration to fractional seconds.
+fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+#[test]
+fn test_rate() {
+    let rate = Rate::new(1000u64, 3000, Duration::from_secs(2));
+    assert_eq!(rate.delta, 2000);
+    assert_eq!(rate.per_sec(), 1000.0);
+    assert_eq!(rate.per(Duration::from_secs(60)), 60000.0);
+    assert_eq!(Rate::new(1u64, 2, Duration::from_secs(0)).per_sec(), 0.0);
+    assert_eq!(Rate::new(5u64, 5, Duration::from_millis(500)).per_sec(), 0.0);
+}
+
+#[test]
+fn test_backwards() {
+    assert_eq!(Rate::new(u32::max_value() - 9, 10u32, Duration::from_secs(1)).delta, 0);
+    assert_eq!(Rate::new(u64::max_value(), 4u64, Duration::from_secs(1)).per_sec(), 0.0);
+}
+
+#[test]
+fn test_wraparound() {
+    assert_eq!(Rate::wrapping(u32::max_value() - 9, 10u32, Duration::from_secs(1)).delta, 20);
+    assert_eq!(Rate::wrapping(u64::max_value(), 4u64, Duration::from_secs(1)).per_sec(), 5.0);
+}
@@ -0,0 +1,222 @@
+use std::fmt::Write;
+use std::path::Path;
+use serde_json::Value;
+use error::ProcError;
+use format::{self, ByteUnits};
+use meminfo::Meminfo;
+use pid::{Pid, PidIter};
+use pid::stat::PidState;
+use pressure::Pressure;
+use schema::SCHEMA_VERSION;
+use uptime::Uptime;
+use {TaskId, MemSize};
+#[cfg(test)]
+use testing::FakePid;
+
+/// A process listed in a report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportProcess {
+    /// The pid of the process.
+    pub pid: TaskId,
+    /// The pid of the parent process.
+    pub ppid: TaskId,
+    /// The name of the process.
+    pub comm: String,
+    /// Resident set size in bytes.
+    pub rss: Option<MemSize>,
+    /// Average cpu usage over the lifetime of the process (like ps %CPU).
+    pub cpu_percent: f64,
+    /// Extra information for this section, like the wchan of a D state process.
+    pub detail: Option<String>,
+}
+
+impl ReportProcess {
+    fn new(pid: &Pid, uptime: f64, detail: Option<String>) -> Self {
+        ReportProcess {
+            pid: pid.stat.pid,
+            ppid: pid.stat.ppid,
+            comm: pid.stat.comm.clone(),
+            rss: pid.status.vmrss,
+            cpu_percent: pid.stat.cpu_percent_lifetime(uptime),
+            detail: detail,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "pid": self.pid,
+            "ppid": self.ppid,
+            "comm": self.comm,
+            "rss": self.rss,
+            "cpu_percent": self.cpu_percent,
+            "detail": self.detail,
+        })
+    }
+}
+
+/// An incident report of the state of the system and its processes, for
+/// attaching to tickets.
+///
+/// Processes that can't be read while scanning are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Seconds since the system booted.
+    pub uptime: f64,
+    /// Total memory in kB.
+    pub mem_total: Option<u64>,
+    /// Available memory in kB.
+    pub mem_available: Option<u64>,
+    /// Used swap in kB.
+    pub swap_used: Option<u64>,
+    /// Memory pressure (PSI some avg10), if the kernel supports it.
+    pub mem_pressure: Option<f64>,
+    /// Number of processes on the system.
+    pub processes: usize,
+    /// Processes using the most cpu.
+    pub top_cpu: Vec<ReportProcess>,
+    /// Processes using the most memory.
+    pub top_memory: Vec<ReportProcess>,
+    /// Zombie processes, with the parent that should reap them.
+    pub zombies: Vec<ReportProcess>,
+    /// Processes in uninterruptible sleep, with their wchan.
+    pub dstate: Vec<ReportProcess>,
+    /// Processes whose executable was deleted or replaced (eg: not restarted after an upgrade).
+    pub deleted_binaries: Vec<ReportProcess>,
+}
+
+impl Report {
+    /// Generate a report, listing up to top processes for cpu and memory.
+    pub fn new(top: usize) -> Result<Self, ProcError> {
+        let uptime = try!(Uptime::new()).uptime;
+        let pids: Vec<_> = try!(PidIter::new()).filter_map(|p| p.ok()).collect();
+        Ok(Self::from_pids(&pids, uptime, Meminfo::new().ok(), Pressure::memory().ok(), top))
+    }
+
+    /// Generate a report from already read processes and system information.
+    fn from_pids(pids: &[Pid], uptime: f64, meminfo: Option<Meminfo>, psi: Option<Pressure>,
+        top: usize) -> Self {
+        let summary = |p: &Pid, detail| ReportProcess::new(p, uptime, detail);
+
+        let mut by_cpu: Vec<_> = pids.iter().collect();
+        by_cpu.sort_by(|a, b|
+            b.stat.cpu_percent_lifetime(uptime)
+                .partial_cmp(&a.stat.cpu_percent_lifetime(uptime))
+                .unwrap_or(::std::cmp::Ordering::Equal)
+        );
+        let mut by_memory: Vec<_> = pids.iter().filter(|p| p.status.vmrss.is_some()).collect();
+        by_memory.sort_by(|a, b| b.status.vmrss.cmp(&a.status.vmrss));
+
+        Report {
+            uptime: uptime,
+            mem_total: meminfo.as_ref().map(|m| m.memtotal),
+            mem_available: meminfo.as_ref().and_then(|m| m.memavailable),
+            swap_used: meminfo.as_ref().map(|m| m.mainswapused),
+            mem_pressure: psi.map(|p| p.some.avg10),
+            processes: pids.len(),
+            top_cpu: by_cpu.iter().take(top).map(|p| summary(p, None)).collect(),
+            top_memory: by_memory.iter().take(top).map(|p| summary(p, None)).collect(),
+            zombies: pids.iter()
+                .filter(|p| p.stat.state == PidState::Zombie)
+                .map(|p| summary(p, pids.iter().find(|parent| parent.stat.pid == p.stat.ppid)
+                    .map(|parent| format!("parent {}", parent.stat.comm))))
+                .collect(),
+            dstate: pids.iter()
+                .filter(|p| p.stat.state == PidState::Waiting)
+                .map(|p| summary(p, p.wchan().unwrap_or(None)))
+                .collect(),
+            deleted_binaries: pids.iter()
+                .filter_map(|p| p.exe().ok().and_then(|exe| deleted_exe(&exe).map(|e| (p, e))))
+                .map(|(p, exe)| summary(p, Some(exe)))
+                .collect(),
+        }
+    }
+
+    /// Render the report as human readable text.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        // Meminfo values are in kB.
+        let kb = |v: Option<u64>| v.map(|v| format::bytes(v * 1024, ByteUnits::Binary))
+            .unwrap_or("unknown".to_owned());
+        writeln!(out, "System").unwrap();
+        writeln!(out, "  uptime: {}", format::duration(self.uptime as u64)).unwrap();
+        writeln!(out, "  memory: {} total, {} available", kb(self.mem_total), kb(self.mem_available))
+            .unwrap();
+        writeln!(out, "  swap used: {}", kb(self.swap_used)).unwrap();
+        if let Some(p) = self.mem_pressure {
+            writeln!(out, "  memory pressure: {:.2}%", p).unwrap();
+        }
+        writeln!(out, "  processes: {}", format::thousands(self.processes as u64)).unwrap();
+        let sections = [
+            ("Top cpu", &self.top_cpu),
+            ("Top memory", &self.top_memory),
+            ("Zombies", &self.zombies),
+            ("Uninterruptible sleep (D state)", &self.dstate),
+            ("Deleted binaries", &self.deleted_binaries),
+        ];
+        for &(title, procs) in sections.iter() {
+            writeln!(out, "\n{} ({})", title, procs.len()).unwrap();
+            for p in procs.iter() {
+                let rss = p.rss.map(|r| format::bytes(r, ByteUnits::Binary)).unwrap_or("-".to_owned());
+                write!(out, "  {:>7} {:>7} {:>5.1}% {:>10} {}", p.pid, p.ppid, p.cpu_percent, rss,
+                    p.comm).unwrap();
+                match p.detail {
+                    Some(ref d) => writeln!(out, " ({})", d).unwrap(),
+                    None => writeln!(out, "").unwrap(),
+                }
+            }
+        }
+        out
+    }
+
+    /// Render the report as JSON.
+    pub fn to_json(&self) -> Value {
+        let list = |procs: &[ReportProcess]| procs.iter().map(|p| p.to_json()).collect::<Vec<_>>();
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "system": {
+                "uptime": self.uptime,
+                "mem_total": self.mem_total,
+                "mem_available": self.mem_available,
+                "swap_used": self.swap_used,
+                "mem_pressure": self.mem_pressure,
+                "processes": self.processes,
+            },
+            "top_cpu": list(&self.top_cpu),
+            "top_memory": list(&self.top_memory),
+            "zombies": list(&self.zombies),
+            "dstate": list(&self.dstate),
+            "deleted_binaries": list(&self.deleted_binaries),
+        })
+    }
+}
+
+/// If an exe link points to a deleted file, return the original path.
+fn deleted_exe(exe: &Path) -> Option<String> {
+    let exe = exe.to_string_lossy();
+    if exe.ends_with(" (deleted)") {
+        Some(exe.trim_right_matches(" (deleted)").to_owned())
+    } else {
+        None
+    }
+}
+
+#[test]
+fn test_report() {
+    let zombie = FakePid { state: 'Z', rss: None, ..FakePid::new(2, 1, "sh") }.to_pid();
+    let parent = FakePid { rss: Some(4096), ..FakePid::new(1, 0, "init") }.to_pid();
+    let report = Report::from_pids(&[zombie, parent], 100.0, None, None, 1);
+    assert_eq!(report.processes, 2);
+    assert_eq!(report.top_memory.len(), 1);
+    assert_eq!(report.top_memory[0].pid, 1);
+    assert_eq!(report.zombies[0].detail, Some("parent init".to_owned()));
+    assert!(report.to_text().contains("Zombies (1)"));
+    assert_eq!(report.to_json()["zombies"][0]["pid"], json!(2));
+    assert_eq!(report.to_json()["system"]["mem_total"], Value::Null);
+    assert_eq!(::schema::check(&report.to_json()), Ok(SCHEMA_VERSION));
+}
+
+#[test]
+fn test_deleted_exe() {
+    assert_eq!(deleted_exe(Path::new("/usr/sbin/sshd (deleted)")), Some("/usr/sbin/sshd".to_owned()));
+    assert_eq!(deleted_exe(Path::new("/usr/sbin/sshd")), None);
+}
@@ -0,0 +1,77 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+/// Where procfs is usually mounted.
+pub const DEFAULT_ROOT: &'static str = "/proc";
+
+static ROOT: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+thread_local! {
+    static THREAD_ROOT: RefCell<Option<PathBuf>> = RefCell::new(None);
+}
+
+/// The directory procfs is read from.
+///
+/// This is the root set by `with_root` for this thread, otherwise the one
+/// set by `set_root`, otherwise /proc.
+pub fn root() -> PathBuf {
+    if let Some(root) = THREAD_ROOT.with(|r| r.borrow().clone()) {
+        return root;
+    }
+    match ROOT.read() {
+        Ok(root) => root.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }.unwrap_or_else(|| PathBuf::from(DEFAULT_ROOT))
+}
+
+/// Read procfs from another directory for the whole process, eg: /host/proc
+/// inside a container, or a copied proc tree for post-mortem analysis.
+///
+/// Iterators that were already created keep using the old root.
+pub fn set_root<P: Into<PathBuf>>(root: P) {
+    let root = Some(root.into());
+    match ROOT.write() {
+        Ok(mut r) => *r = root,
+        Err(poisoned) => *poisoned.into_inner() = root,
+    }
+}
+
+/// Read procfs from another directory on this thread while running a
+/// function, eg: so tests can use different proc trees at the same time.
+pub fn with_root<P, F, T>(root: P, f: F) -> T
+    where P: Into<PathBuf>, F: FnOnce() -> T {
+    // Restore the previous root on drop, even if the function panics.
+    struct Restore(Option<PathBuf>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            THREAD_ROOT.with(|r| *r.borrow_mut() = previous);
+        }
+    }
+    let previous = THREAD_ROOT.with(|r| r.borrow_mut().replace(root.into()));
+    let _restore = Restore(previous);
+    f()
+}
+
+/// The path to a file under the procfs root, eg: `path("meminfo")`.
+pub fn path<P: AsRef<Path>>(relative: P) -> PathBuf {
+    root().join(relative)
+}
+
+/// The directory of a process (or thread) under the procfs root.
+pub fn pid_dir<T: ToString>(pid: T) -> PathBuf {
+    path(pid.to_string())
+}
+
+#[test]
+fn test_root() {
+    assert_eq!(root(), PathBuf::from("/proc"));
+    assert_eq!(path("meminfo"), PathBuf::from("/proc/meminfo"));
+    let inner = with_root("/host/proc", || {
+        assert_eq!(pid_dir(1), PathBuf::from("/host/proc/1"));
+        with_root("/tmp/copy", || path("net/dev"))
+    });
+    assert_eq!(inner, PathBuf::from("/tmp/copy/net/dev"));
+    assert_eq!(root(), PathBuf::from("/proc"));
+}
@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::fmt;
+use serde_json::Value;
+
+/// Version of the JSON formats written by this crate (reports, and every
+/// object written by the JSON OutputSink).
+///
+/// This is bumped whenever a field is removed, renamed or changes meaning.
+/// Adding a field doesn't change the version, so readers should ignore
+/// fields they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Why recorded output can't be loaded by this version of the crate.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// The output has no schema_version field, so wasn't written by procrs.
+    Missing,
+    /// The output was written by a newer procrs, with this schema version.
+    Newer(u64),
+}
+
+impl Error for SchemaError {
+    fn description(&self) -> &str {
+        match *self {
+            SchemaError::Missing => "missing schema_version",
+            SchemaError::Newer(_) => "written by a newer schema version",
+        }
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SchemaError::Missing => write!(f, "missing schema_version"),
+            SchemaError::Newer(v) =>
+                write!(f, "schema version {} is newer than supported version {}", v, SCHEMA_VERSION),
+        }
+    }
+}
+
+/// Check that recorded output can be read by this version of the crate,
+/// returning its schema version. Older versions are accepted.
+pub fn check(value: &Value) -> Result<u32, SchemaError> {
+    match value.get("schema_version").and_then(|v| v.as_u64()) {
+        None => Err(SchemaError::Missing),
+        Some(v) if v > SCHEMA_VERSION as u64 => Err(SchemaError::Newer(v)),
+        Some(v) => Ok(v as u32),
+    }
+}
+
+#[test]
+fn test_check() {
+    assert_eq!(check(&json!({"schema_version": SCHEMA_VERSION})), Ok(SCHEMA_VERSION));
+    assert_eq!(check(&json!({"schema_version": 0})), Ok(0));
+    assert_eq!(check(&json!({"schema_version": SCHEMA_VERSION + 1})),
+        Err(SchemaError::Newer(SCHEMA_VERSION as u64 + 1)));
+    assert_eq!(check(&json!({"schema_version": 1u64 << 32})), Err(SchemaError::Newer(1 << 32)));
+    assert_eq!(check(&json!({"system": {}})), Err(SchemaError::Missing));
+    assert_eq!(check(&json!([1, 2])), Err(SchemaError::Missing));
+}
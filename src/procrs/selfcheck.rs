@@ -0,0 +1,157 @@
+use std::fs;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use error::{ProcError, ProcFile, ProcOper};
+use pid::Pid;
+use MemSize;
+use root;
+
+/// Thresholds for the resources of the calling process.
+///
+/// A limit of None is never exceeded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Limits {
+    /// Maximum resident set size.
+    pub max_rss: Option<MemSize>,
+    /// Maximum number of open file descriptors.
+    pub max_fds: Option<usize>,
+    /// Maximum number of threads.
+    pub max_threads: Option<u32>,
+}
+
+/// A limit that was exceeded, with the current value and the limit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// Resident set size exceeded max_rss.
+    Rss(MemSize, MemSize),
+    /// Open file descriptors exceeded max_fds.
+    Fds(usize, usize),
+    /// Thread count exceeded max_threads.
+    Threads(u32, u32),
+}
+
+/// Checks the resource usage of the calling process against some limits.
+pub struct ResourceGuard {
+    limits: Limits,
+}
+
+impl ResourceGuard {
+    /// Create a new guard with the given limits.
+    pub fn new(limits: Limits) -> Self {
+        ResourceGuard {
+            limits: limits,
+        }
+    }
+
+    /// Read /proc/self once, and return any limits that are exceeded.
+    pub fn check(&self) -> Result<Vec<Violation>, ProcError> {
+        let myself = try!(Pid::myself());
+        let mut violations = Vec::new();
+
+        if let (Some(limit), Some(rss)) = (self.limits.max_rss, myself.status.vmrss) {
+            if rss > limit {
+                violations.push(Violation::Rss(rss, limit));
+            }
+        }
+        if let Some(limit) = self.limits.max_fds {
+            // Counting entries is much cheaper than resolving every fd,
+            // but the directory being read is itself an open fd.
+            let fds = try!(
+                fs::read_dir(root::path("self/fd"))
+                    .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::PidFd, e))
+            ).count().saturating_sub(1);
+            if fds > limit {
+                violations.push(Violation::Fds(fds, limit));
+            }
+        }
+        if let Some(limit) = self.limits.max_threads {
+            if myself.status.threads > limit {
+                violations.push(Violation::Threads(myself.status.threads, limit));
+            }
+        }
+        Ok(violations)
+    }
+
+    /// Check the limits every interval on a background thread, calling the
+    /// callback whenever a check finds violations (or fails to read /proc).
+    ///
+    /// Polling stops when the returned handle is stopped or dropped.
+    pub fn spawn<F>(self, interval: Duration, callback: F) -> GuardHandle
+        where F: Fn(Result<Vec<Violation>, ProcError>) + Send + 'static {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::SeqCst) {
+                match self.check() {
+                    Ok(ref v) if v.is_empty() => {},
+                    result => callback(result),
+                }
+                thread::park_timeout(interval);
+            }
+        });
+        GuardHandle {
+            stop: stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// A handle to a ResourceGuard polling on a background thread.
+pub struct GuardHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl GuardHandle {
+    /// Stop polling, and wait for the background thread to finish.
+    pub fn stop(mut self) {
+        self.join();
+    }
+
+    fn join(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for GuardHandle {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+#[test]
+fn test_check() {
+    let guard = ResourceGuard::new(Limits::default());
+    assert_eq!(guard.check(), Ok(Vec::new()));
+
+    let guard = ResourceGuard::new(Limits {
+        max_rss: Some(1),
+        max_fds: Some(0),
+        max_threads: Some(0),
+    });
+    let violations = guard.check().unwrap();
+    assert_eq!(violations.len(), 3);
+}
+
+#[test]
+fn test_spawn() {
+    use std::sync::mpsc::channel;
+    let (tx, rx) = channel();
+    let tx = ::std::sync::Mutex::new(tx);
+    let guard = ResourceGuard::new(Limits { max_threads: Some(0), ..Limits::default() });
+    let handle = guard.spawn(Duration::from_millis(10), move |r| {
+        let _ = tx.lock().unwrap().send(r.ok());
+    });
+    let result = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    handle.stop();
+    match result.unwrap()[0] {
+        Violation::Threads(_, 0) => {},
+        ref v => panic!("unexpected violation {:?}", v),
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::Read;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// A slab cache, from a row of /proc/slabinfo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlabCache {
+    /// The name of the cache, eg: "dentry" or "kmalloc-64".
+    pub name: String,
+    /// Objects in use.
+    pub active_objs: u64,
+    /// Objects allocated, in use or not.
+    pub num_objs: u64,
+    /// The size of each object in bytes.
+    pub objsize: u64,
+    /// Objects in each slab.
+    pub objperslab: u64,
+    /// Pages in each slab.
+    pub pagesperslab: u64,
+    /// Slabs with at least one object in use.
+    pub active_slabs: u64,
+    /// Slabs allocated.
+    pub num_slabs: u64,
+}
+
+impl SlabCache {
+    /// The memory used by allocated objects in bytes, like the cache size
+    /// in slabtop.
+    pub fn size(&self) -> u64 {
+        self.num_objs * self.objsize
+    }
+
+    /// The memory used by objects in use in bytes.
+    pub fn active_size(&self) -> u64 {
+        self.active_objs * self.objsize
+    }
+}
+
+/// A struct containing the slab caches from /proc/slabinfo.
+///
+/// This file is only readable by root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slabinfo {
+    /// Each cache, in file order.
+    pub caches: Vec<SlabCache>,
+}
+
+impl Slabinfo {
+    /// Read the current slab caches from /proc/slabinfo.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("slabinfo"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcSlabinfo, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcSlabinfo, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Get a cache by name.
+    pub fn get(&self, name: &str) -> Option<&SlabCache> {
+        self.caches.iter().find(|c| c.name == name)
+    }
+
+    /// The caches using the most memory first, for finding leaks.
+    pub fn largest(&self) -> Vec<&SlabCache> {
+        let mut caches: Vec<_> = self.caches.iter().collect();
+        caches.sort_by(|a, b| b.size().cmp(&a.size()));
+        caches
+    }
+
+    /// Parse a String as a /proc/slabinfo file (version 2.x), with lines like
+    /// "dentry  7956  8022  192  21  1 : tunables  0  0  0 : slabdata  382  382  0".
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let err = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcSlabinfo,
+            Some(field));
+        let mut lines = contents.lines();
+        match lines.next() {
+            Some(header) if header.starts_with("slabinfo - version: 2.") => {},
+            _ => return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcSlabinfo,
+                Some("unsupported version")))
+        }
+        let mut caches = Vec::new();
+        for line in lines.filter(|l| !l.starts_with('#') && !l.trim().is_empty()) {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            // The name, five numbers, then ": tunables" and ": slabdata" each
+            // followed by three numbers.
+            if fields.len() < 16 || fields[6] != ":" || fields[11] != ":" {
+                return Err(err("columns"));
+            }
+            let num = |i: usize| fields[i].parse().map_err(|_| err("number"));
+            caches.push(SlabCache {
+                name: fields[0].to_owned(),
+                active_objs: try!(num(1)),
+                num_objs: try!(num(2)),
+                objsize: try!(num(3)),
+                objperslab: try!(num(4)),
+                pagesperslab: try!(num(5)),
+                active_slabs: try!(num(13)),
+                num_slabs: try!(num(14)),
+            });
+        }
+        Ok(Slabinfo {
+            caches: caches,
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let slabinfo = Slabinfo::parse_string(
+"slabinfo - version: 2.1
+# name            <active_objs> <num_objs> <objsize> <objperslab> <pagesperslab> : tunables <limit> <batchcount> <sharedfactor> : slabdata <active_slabs> <num_slabs> <sharedavail>
+ext4_groupinfo_4k   2054   2054    152   26    1 : tunables    0    0    0 : slabdata     79     79      0
+dentry             30240  30240    192   21    1 : tunables    0    0    0 : slabdata   1440   1440      0
+kmalloc-8k           108    116   8192    4    8 : tunables    0    0    0 : slabdata     29     29      0
+").unwrap();
+    assert_eq!(slabinfo.caches.len(), 3);
+    assert_eq!(slabinfo.caches[0], SlabCache {
+        name: "ext4_groupinfo_4k".to_owned(),
+        active_objs: 2054,
+        num_objs: 2054,
+        objsize: 152,
+        objperslab: 26,
+        pagesperslab: 1,
+        active_slabs: 79,
+        num_slabs: 79,
+    });
+    let kmalloc = slabinfo.get("kmalloc-8k").unwrap();
+    assert_eq!(kmalloc.size(), 116 * 8192);
+    assert_eq!(kmalloc.active_size(), 108 * 8192);
+    assert_eq!(slabinfo.largest()[0].name, "dentry");
+    assert!(Slabinfo::parse_string("slabinfo - version: 1.1\n").is_err());
+    assert!(Slabinfo::parse_string("slabinfo - version: 2.1\ndentry 1 2 3\n").is_err());
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+use error::{ProcError, ProcFile, ProcOper};
+use loadavg::Loadavg;
+use meminfo::{Meminfo, MeminfoError};
+use pid::{Pid, PidIter, PidQuery};
+use pid::ioacct::PidIo;
+use stat::CpuStat;
+use TaskId;
+
+/// The processes and system state read in a single pass, so they can be
+/// compared with each other and with a later snapshot.
+#[derive(Debug)]
+pub struct Snapshot {
+    /// When the snapshot was taken.
+    pub time: SystemTime,
+    /// When the snapshot was taken, for measuring the time between snapshots.
+    pub instant: Instant,
+    /// The matching processes, sorted by pid. Processes that exited while
+    /// being read are left out.
+    pub pids: Vec<Pid>,
+    /// The I/O counters of the processes that could be read (usually
+    /// needing root for other users' processes).
+    pub io: HashMap<TaskId, PidIo>,
+    /// The system memory usage.
+    pub meminfo: Meminfo,
+    /// The cpu times of the whole system, read before the processes so
+    /// their cpu usage can be compared against it.
+    pub cpu: CpuStat,
+    /// The system load averages.
+    pub loadavg: Loadavg,
+}
+
+impl Snapshot {
+    /// Read every process matching a query, and the system memory, cpu
+    /// times and load averages.
+    ///
+    /// Processes that exit while being read are left out, as are I/O
+    /// counters that aren't permitted to be read, but any other error is
+    /// returned.
+    pub fn capture(query: PidQuery) -> Result<Self, ProcError> {
+        let time = SystemTime::now();
+        let instant = Instant::now();
+        let cpu = try!(CpuStat::new());
+        let mut pids = Vec::new();
+        for pid in try!(PidIter::new_query(query)) {
+            match pid {
+                Ok(pid) => pids.push(pid),
+                Err(ref e) if e.is_not_found() => {},
+                Err(e) => return Err(e)
+            }
+        }
+        pids.sort_by_key(|p| p.pid);
+        let mut io = HashMap::new();
+        for pid in &pids {
+            match pid.io() {
+                Ok(pid_io) => { io.insert(pid.pid, pid_io); },
+                Err(ref e) if e.is_not_found() || e.is_permission_denied() => {},
+                Err(e) => return Err(e)
+            }
+        }
+        let meminfo = try!(Meminfo::new().map_err(|e| match e {
+            MeminfoError::Io(e) => ProcError::new_err(ProcOper::Reading, ProcFile::ProcMeminfo, e),
+            MeminfoError::NotFound => ProcError::new_more(ProcOper::Parsing, ProcFile::ProcMeminfo,
+                None),
+        }));
+        Ok(Snapshot {
+            time: time,
+            instant: instant,
+            pids: pids,
+            io: io,
+            meminfo: meminfo,
+            cpu: cpu,
+            loadavg: try!(Loadavg::new()),
+        })
+    }
+
+    /// Find a process in the snapshot by pid.
+    pub fn get(&self, pid: TaskId) -> Option<&Pid> {
+        self.pids.binary_search_by_key(&pid, |p| p.pid).ok().map(|i| &self.pids[i])
+    }
+
+    /// Whether a process was in the snapshot.
+    pub fn contains(&self, pid: TaskId) -> bool {
+        self.get(pid).is_some()
+    }
+
+    /// Find the same process as one from another snapshot, having the same
+    /// pid and start time.
+    fn same_process(&self, pid: &Pid) -> Option<&Pid> {
+        self.get(pid.pid).filter(|p| p.stat.starttime == pid.stat.starttime)
+    }
+
+    /// Compare this snapshot with a later one.
+    ///
+    /// A pid whose start time differs between the snapshots was reused by a
+    /// new process, so it is listed as both exited and started.
+    pub fn diff(&self, later: &Snapshot) -> SnapshotDiff {
+        let started = later.pids.iter()
+            .filter(|p| self.same_process(p).is_none())
+            .map(|p| p.pid)
+            .collect();
+        let exited = self.pids.iter()
+            .filter(|p| later.same_process(p).is_none())
+            .map(|p| p.pid)
+            .collect();
+        let deltas = later.pids.iter()
+            .filter_map(|new|
+                self.same_process(new).map(|old| PidDelta::new(self, old, later, new))
+            )
+            .collect();
+        SnapshotDiff {
+            elapsed: later.instant.duration_since(self.instant),
+            started: started,
+            exited: exited,
+            deltas: deltas,
+        }
+    }
+}
+
+/// The processes that started and exited between two snapshots, and how
+/// the processes in both changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotDiff {
+    /// The time between the snapshots.
+    pub elapsed: Duration,
+    /// Processes only in the later snapshot, sorted by pid.
+    pub started: Vec<TaskId>,
+    /// Processes only in the earlier snapshot, sorted by pid.
+    pub exited: Vec<TaskId>,
+    /// The changes to each process in both snapshots, sorted by pid.
+    pub deltas: Vec<PidDelta>,
+}
+
+impl SnapshotDiff {
+    /// Find the changes to a process in both snapshots.
+    pub fn get(&self, pid: TaskId) -> Option<&PidDelta> {
+        self.deltas.binary_search_by_key(&pid, |d| d.pid).ok().map(|i| &self.deltas[i])
+    }
+}
+
+/// How a process changed between two snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PidDelta {
+    /// The process, which was in both snapshots.
+    pub pid: TaskId,
+    /// User and system cpu time used, in clock ticks (see ticks_per_second).
+    pub cputime: u64,
+    /// The change in resident set size in bytes, or None if it's unknown
+    /// (eg: kernel threads).
+    pub rss: Option<i64>,
+    /// Bytes fetched from storage, if the I/O counters could be read.
+    pub read_bytes: Option<u64>,
+    /// Bytes sent to storage, if the I/O counters could be read.
+    pub write_bytes: Option<u64>,
+}
+
+impl PidDelta {
+    fn new(earlier: &Snapshot, old: &Pid, later: &Snapshot, new: &Pid) -> Self {
+        let io = match (earlier.io.get(&old.pid), later.io.get(&new.pid)) {
+            (Some(old), Some(new)) => Some((old, new)),
+            _ => None,
+        };
+        PidDelta {
+            pid: new.pid,
+            cputime: (new.stat.utime + new.stat.stime)
+                .saturating_sub(old.stat.utime + old.stat.stime),
+            rss: match (old.status.vmrss, new.status.vmrss) {
+                (Some(old), Some(new)) => Some(new as i64 - old as i64),
+                _ => None,
+            },
+            read_bytes: io.map(|(o, n)| n.read_bytes.saturating_sub(o.read_bytes)),
+            write_bytes: io.map(|(o, n)| n.write_bytes.saturating_sub(o.write_bytes)),
+        }
+    }
+}
+
+#[test]
+fn test_capture() {
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::with_system().unwrap();
+    for &(pid, comm) in &[(30, "sshd"), (1, "init"), (7, "cron")] {
+        fake.process(&FakePid::new(pid, 1, comm)).unwrap();
+    }
+    let snapshot = fake.run(|| Snapshot::capture(PidQuery::NoneQuery)).unwrap();
+    assert_eq!(snapshot.pids.iter().map(|p| p.pid).collect::<Vec<_>>(), vec![1, 7, 30]);
+    assert_eq!(snapshot.get(30).unwrap().stat.comm, "sshd");
+    assert!(!snapshot.contains(2));
+    assert_eq!(snapshot.meminfo.memtotal, 6158152);
+    assert_eq!(snapshot.loadavg.tasks, 74);
+    assert_eq!(snapshot.cpu.total.user, 10132153);
+    // Processes without I/O counters are left out, but broken ones aren't.
+    assert!(snapshot.io.is_empty());
+    fake.file("7/io", "read_bytes: lots\n").unwrap();
+    assert!(fake.run(|| Snapshot::capture(PidQuery::NoneQuery)).is_err());
+}
+
+#[test]
+fn test_diff() {
+    use testing::{FakeProc, FakePid};
+    let fake = FakeProc::with_system().unwrap();
+    fake.process(&FakePid::new(1, 0, "init")).unwrap();
+    fake.process(&FakePid { rss: Some(10 << 20), ..FakePid::new(20, 1, "db") }).unwrap();
+    fake.process(&FakePid::new(21, 1, "job")).unwrap();
+    fake.file("20/io", "read_bytes: 1000\nwrite_bytes: 0\n").unwrap();
+    let earlier = fake.run(|| Snapshot::capture(PidQuery::NoneQuery)).unwrap();
+
+    fake.process(&FakePid {
+        rss: Some(8 << 20),
+        utime: 30,
+        stime: 20,
+        ..FakePid::new(20, 1, "db")
+    }).unwrap();
+    fake.file("20/io", "read_bytes: 5096\nwrite_bytes: 100\n").unwrap();
+    // Pid 21 exited and was reused by a new process.
+    fake.process(&FakePid { starttime: 500, ..FakePid::new(21, 1, "other") }).unwrap();
+    fake.process(&FakePid::new(22, 1, "new")).unwrap();
+    let later = fake.run(|| Snapshot::capture(PidQuery::NoneQuery)).unwrap();
+
+    let diff = earlier.diff(&later);
+    assert_eq!(diff.started, vec![21, 22]);
+    assert_eq!(diff.exited, vec![21]);
+    assert_eq!(diff.deltas.iter().map(|d| d.pid).collect::<Vec<_>>(), vec![1, 20]);
+    assert_eq!(diff.get(20), Some(&PidDelta {
+        pid: 20,
+        cputime: 50,
+        rss: Some(-(2 << 20)),
+        read_bytes: Some(4096),
+        write_bytes: Some(100),
+    }));
+    assert_eq!(diff.get(1).unwrap().read_bytes, None);
+}
@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+use error::{ProcError, ProcFile, ProcOper};
+use rate::Rate;
+use root;
+
+/// A row of /proc/softirqs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Softirq {
+    /// The type of softirq, eg: "NET_RX" or "TIMER".
+    pub name: String,
+    /// The count on each cpu, in the same order as Softirqs::cpus.
+    pub counts: Vec<u64>,
+}
+
+impl Softirq {
+    /// The count across every cpu.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// A struct containing the softirq counters from /proc/softirqs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Softirqs {
+    /// The online cpus, which are the columns of the counts.
+    pub cpus: Vec<u32>,
+    /// Each type of softirq, in file order.
+    pub softirqs: Vec<Softirq>,
+}
+
+impl Softirqs {
+    /// Read the current counters from /proc/softirqs.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("softirqs"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcSoftirqs, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcSoftirqs, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Get a softirq by name, eg: "NET_RX".
+    pub fn get(&self, name: &str) -> Option<&Softirq> {
+        self.softirqs.iter().find(|s| s.name == name)
+    }
+
+    /// Parse a String as a /proc/softirqs file. The header names the online
+    /// cpus, so the number of count columns varies between systems.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let err = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcSoftirqs,
+            Some(field));
+        let mut lines = contents.lines();
+        let cpus = try!(
+            lines.next()
+                .ok_or(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcSoftirqs,
+                    Some("missing header")))
+                .and_then(|header| header.split_whitespace()
+                    .map(|cpu| cpu.trim_left_matches("CPU").parse().map_err(|_| err("cpu")))
+                    .collect::<Result<Vec<u32>, _>>())
+        );
+        let mut softirqs = Vec::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let mut split = line.splitn(2, ':');
+            let (name, counts) = match (split.next(), split.next()) {
+                (Some(name), Some(counts)) => (name.trim(), counts),
+                _ => return Err(err("name"))
+            };
+            softirqs.push(Softirq {
+                name: name.to_owned(),
+                counts: try!(
+                    counts.split_whitespace()
+                        .map(|c| c.parse().map_err(|_| err("count")))
+                        .collect()
+                ),
+            });
+        }
+        Ok(Softirqs {
+            cpus: cpus,
+            softirqs: softirqs,
+        })
+    }
+}
+
+/// Calculate the rate of each softirq present in both samples, taken
+/// elapsed apart.
+pub fn rates(last: &Softirqs, current: &Softirqs, elapsed: Duration) -> Vec<(String, Rate<u64>)> {
+    current.softirqs.iter().filter_map(|cur|
+        last.get(&cur.name).map(|l|
+            (cur.name.clone(), Rate::from_delta(cur.total().saturating_sub(l.total()), elapsed))
+        )
+    ).collect()
+}
+
+#[test]
+fn test_parsing() {
+    let softirqs = Softirqs::parse_string(
+"                    CPU0       CPU1
+          HI:          1          0
+       TIMER:     372806     300091
+      NET_TX:          4         17
+      NET_RX:      63114     170392
+").unwrap();
+    assert_eq!(softirqs.cpus, vec![0, 1]);
+    assert_eq!(softirqs.softirqs.len(), 4);
+    assert_eq!(softirqs.softirqs[0], Softirq { name: "HI".to_owned(), counts: vec![1, 0] });
+    assert_eq!(softirqs.get("NET_RX").unwrap().total(), 233506);
+    assert!(Softirqs::parse_string("    CPU0\n  HI:  x\n").is_err());
+}
+
+#[test]
+fn test_rates() {
+    let last = Softirqs::parse_string("  CPU0\nNET_RX:  100\n").unwrap();
+    let current = Softirqs::parse_string("  CPU0\nNET_RX:  300\n").unwrap();
+    let rates = rates(&last, &current, Duration::from_secs(2));
+    assert_eq!(rates[0].0, "NET_RX");
+    assert_eq!(rates[0].1.per_sec(), 100.0);
+}
+
+#[test]
+fn test_read() {
+    assert!(Softirqs::new().unwrap().get("TIMER").is_some());
+}
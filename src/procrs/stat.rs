@@ -0,0 +1,280 @@
+use std::fs::File;
+use std::io::Read;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use error::{ProcError, ProcFile, ProcOper};
+use rate::Rate;
+use ticks_to_duration;
+use root;
+
+/// A struct containing the system wide counters from /proc/stat.
+///
+/// The cpu times are read separately by CpuStat, and the interrupts and
+/// softirqs in the same file aren't read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KernelStat {
+    /// When the system booted, in seconds since the epoch.
+    pub btime: u64,
+    /// Context switches since boot.
+    pub ctxt: u64,
+    /// Processes and threads created (forks) since boot.
+    pub processes: u64,
+    /// Threads that are runnable.
+    pub procs_running: u64,
+    /// Threads blocked waiting for I/O.
+    pub procs_blocked: u64,
+}
+
+impl KernelStat {
+    /// Read the current counters from /proc/stat.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("stat"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcStat, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcStat, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// When the system booted.
+    pub fn boot_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.btime)
+    }
+
+    /// Convert a time in clock ticks after boot (eg: PidStat::starttime) to
+    /// wall clock time.
+    pub fn ticks_to_time(&self, ticks: u64) -> SystemTime {
+        self.boot_time() + ticks_to_duration(ticks)
+    }
+
+    /// The rate of process creation between an earlier sample and this one.
+    pub fn fork_rate(&self, previous: &KernelStat, elapsed: Duration) -> Rate<u64> {
+        Rate::new(previous.processes, self.processes, elapsed)
+    }
+
+    /// The rate of context switches between an earlier sample and this one.
+    pub fn context_switch_rate(&self, previous: &KernelStat, elapsed: Duration) -> Rate<u64> {
+        Rate::new(previous.ctxt, self.ctxt, elapsed)
+    }
+
+    /// Parse a String as a /proc/stat file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let (mut btime, mut ctxt, mut processes) = (None, None, None);
+        let (mut procs_running, mut procs_blocked) = (None, None);
+        for line in contents.lines() {
+            let mut split = line.split_whitespace();
+            let key = split.next().unwrap_or("");
+            let value = match key {
+                "btime" => &mut btime,
+                "ctxt" => &mut ctxt,
+                "processes" => &mut processes,
+                "procs_running" => &mut procs_running,
+                "procs_blocked" => &mut procs_blocked,
+                _ => continue,
+            };
+            *value = Some(try!(
+                split.next().unwrap_or("").parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcStat,
+                        Some(e), Some("parsing counter")))
+            ));
+        }
+        let missing = |field| ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcStat,
+            Some(field));
+        Ok(KernelStat {
+            btime: try!(btime.ok_or(missing("missing btime"))),
+            ctxt: try!(ctxt.ok_or(missing("missing ctxt"))),
+            processes: try!(processes.ok_or(missing("missing processes"))),
+            procs_running: try!(procs_running.ok_or(missing("missing procs_running"))),
+            procs_blocked: try!(procs_blocked.ok_or(missing("missing procs_blocked"))),
+        })
+    }
+}
+
+/// Time spent by a cpu (or all cpus) in each state since boot, in clock
+/// ticks (see ticks_per_second).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuTimes {
+    /// Time running processes in user mode.
+    pub user: u64,
+    /// Time running niced (low priority) processes in user mode.
+    pub nice: u64,
+    /// Time running in kernel mode.
+    pub system: u64,
+    /// Idle, not counting waiting for I/O.
+    pub idle: u64,
+    /// Idle while waiting for I/O.
+    pub iowait: u64,
+    /// Time servicing hardware interrupts.
+    pub irq: u64,
+    /// Time servicing software interrupts.
+    pub softirq: u64,
+    /// Time stolen by the hypervisor for other guests.
+    pub steal: u64,
+    /// Time running guests, which is also counted in user.
+    pub guest: u64,
+    /// Time running niced guests, which is also counted in nice.
+    pub guest_nice: u64,
+}
+
+impl CpuTimes {
+    /// The total time in every state, not double counting guest time.
+    pub fn total(&self) -> u64 {
+        self.user + self.nice + self.system + self.idle + self.iowait + self.irq
+            + self.softirq + self.steal
+    }
+
+    /// The time not idle (or waiting for I/O).
+    pub fn busy(&self) -> u64 {
+        self.total() - self.idle - self.iowait
+    }
+
+    /// The percentage of time busy between an earlier sample and this one.
+    pub fn busy_percent(&self, previous: &CpuTimes) -> f64 {
+        let total = self.total().saturating_sub(previous.total());
+        match total {
+            0 => 0.0,
+            total => self.busy().saturating_sub(previous.busy()) as f64 / total as f64 * 100.0,
+        }
+    }
+
+    /// Parse the numbers of a cpu line, where kernels before 2.6.33 lack
+    /// the later fields.
+    fn parse_fields<'a, I: Iterator<Item=&'a str>>(fields: I) -> Result<Self, ProcError> {
+        let values = try!(
+            fields.map(|f| f.parse()).collect::<Result<Vec<u64>, _>>()
+                .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcStat,
+                    Some(e), Some("parsing cpu time")))
+        );
+        if values.len() < 4 {
+            return Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcStat,
+                Some("missing cpu times")));
+        }
+        let value = |i: usize| values.get(i).cloned().unwrap_or(0);
+        Ok(CpuTimes {
+            user: value(0),
+            nice: value(1),
+            system: value(2),
+            idle: value(3),
+            iowait: value(4),
+            irq: value(5),
+            softirq: value(6),
+            steal: value(7),
+            guest: value(8),
+            guest_nice: value(9),
+        })
+    }
+}
+
+/// The cpu times from /proc/stat, for all cpus together and each online cpu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuStat {
+    /// The times summed over every cpu.
+    pub total: CpuTimes,
+    /// The times of each online cpu, by cpu number.
+    pub cpus: Vec<(u32, CpuTimes)>,
+}
+
+impl CpuStat {
+    /// Read the current cpu times from /proc/stat.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("stat"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcStat, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcStat, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/stat file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let mut total = None;
+        let mut cpus = Vec::new();
+        for line in contents.lines() {
+            let mut split = line.split_whitespace();
+            match split.next() {
+                Some("cpu") => total = Some(try!(CpuTimes::parse_fields(split))),
+                Some(key) if key.starts_with("cpu") => {
+                    let cpu = try!(
+                        key["cpu".len()..].parse()
+                            .map_err(|e| ProcError::new(ProcOper::ParsingField,
+                                ProcFile::ProcStat, Some(e), Some("parsing cpu number")))
+                    );
+                    cpus.push((cpu, try!(CpuTimes::parse_fields(split))));
+                },
+                _ => continue,
+            }
+        }
+        Ok(CpuStat {
+            total: try!(total.ok_or(ProcError::new_more(ProcOper::ParsingField,
+                ProcFile::ProcStat, Some("missing cpu")))),
+            cpus: cpus,
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let stat = KernelStat::parse_string(
+        "cpu  10132153 290696 3084719 46828483 16683 0 25195 0 0 0\n\
+         cpu0 1393280 32966 572056 13343292 6130 0 17875 0 0 0\n\
+         intr 1462898 39 9 0 0 0 0 0 0 1 0\n\
+         ctxt 115315\n\
+         btime 769041601\n\
+         processes 86031\n\
+         procs_running 6\n\
+         procs_blocked 2\n\
+         softirq 229245889 94 60001584 13619 5175704 2471304 28 51212741 59130143 0\n")
+        .unwrap();
+    assert_eq!(stat, KernelStat {
+        btime: 769041601,
+        ctxt: 115315,
+        processes: 86031,
+        procs_running: 6,
+        procs_blocked: 2,
+    });
+    assert_eq!(stat.boot_time(), UNIX_EPOCH + Duration::from_secs(769041601));
+    let hertz = ::ticks_per_second();
+    assert_eq!(stat.ticks_to_time(hertz * 10 + hertz / 2),
+        stat.boot_time() + Duration::from_millis(10500));
+    let later = KernelStat { processes: 86131, ..stat };
+    assert_eq!(later.fork_rate(&stat, Duration::from_secs(10)).per_sec(), 10.0);
+    // Samples from before a reboot (or swapped by mistake) don't wrap around.
+    assert_eq!(stat.fork_rate(&later, Duration::from_secs(10)).per_sec(), 0.0);
+    assert_eq!(KernelStat::parse_string("ctxt 1\nbtime 2\n"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcStat,
+            Some("missing processes"))));
+    assert!(KernelStat::parse_string("ctxt x\n").is_err());
+}
+
+#[test]
+fn test_read() {
+    let stat = KernelStat::new().unwrap();
+    assert!(stat.processes > 0);
+    assert!(stat.boot_time() < SystemTime::now());
+}
+
+#[test]
+fn test_cpu_parsing() {
+    let stat = CpuStat::parse_string(
+        "cpu  100 10 50 800 20 0 20 0 0 0\n\
+         cpu0 60 10 30 380 10 0 10 0 0 0\n\
+         cpu2 40 0 20 420 10 0 10 0\n\
+         ctxt 115315\n").unwrap();
+    assert_eq!(stat.total.total(), 1000);
+    assert_eq!(stat.total.busy(), 180);
+    assert_eq!(stat.cpus.len(), 2);
+    assert_eq!(stat.cpus[1].0, 2);
+    assert_eq!(stat.cpus[1].1.guest, 0);
+    let later = CpuTimes { user: 190, idle: 810, ..stat.total };
+    assert_eq!(later.busy_percent(&stat.total), 90.0);
+    assert!(CpuStat::parse_string("ctxt 1\n").is_err());
+    assert!(CpuStat::parse_string("cpu 1 2\n").is_err());
+    assert!(CpuStat::new().unwrap().total.total() > 0);
+}
@@ -0,0 +1,185 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// Convert a sysctl name like "kernel.pid_max" to its file, being
+/// /proc/sys/kernel/pid_max.
+///
+/// As with sysctl(8), a '/' within a dotted name stands for a '.' in the
+/// file name (eg: "net.ipv4.conf.eth0/100.forwarding"), and names can also
+/// be given as paths (eg: "net/ipv4/conf/eth0.100/forwarding"). Names that
+/// would leave /proc/sys (eg: containing "..") are an error.
+pub fn path(name: &str) -> Result<PathBuf, ProcError> {
+    let name = name.trim_matches(|c| c == '.' || c == '/');
+    let relative = match name.find(|c| c == '.' || c == '/') {
+        Some(i) if name[i..].starts_with('/') => name.to_owned(),
+        _ => swap_separators(name),
+    };
+    let outside = Path::new(&relative).components().any(|c| match c {
+        Component::Normal(_) | Component::CurDir => false,
+        _ => true,
+    });
+    if outside {
+        return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcSys,
+            Some("sysctl name outside /proc/sys")));
+    }
+    Ok(root::path("sys").join(relative))
+}
+
+/// Swap '.' and '/', converting between dotted sysctl names and paths.
+fn swap_separators(name: &str) -> String {
+    name.chars().map(|c| match c {
+        '.' => '/',
+        '/' => '.',
+        c => c,
+    }).collect()
+}
+
+/// Read a sysctl as a string, without the trailing newline.
+pub fn read_string(name: &str) -> Result<String, ProcError> {
+    debug!("reading sysctl {}", name);
+    let mut file = try!(
+        File::open(try!(path(name)))
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcSys, e))
+    );
+    let mut contents = String::new();
+    try!(
+        file.read_to_string(&mut contents)
+            .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcSys, e))
+    );
+    Ok(contents.trim_right_matches('\n').to_owned())
+}
+
+/// Read a sysctl with a single value, eg: read::<u32>("kernel.pid_max").
+pub fn read<T>(name: &str) -> Result<T, ProcError>
+    where T: FromStr, T::Err: Error + 'static {
+    try!(read_string(name)).trim().parse()
+        .map_err(|e| ProcError::new_err(ProcOper::Parsing, ProcFile::ProcSys, e))
+}
+
+/// Read a sysctl with several values on one line, like
+/// "net.ipv4.ip_local_port_range" or "kernel.printk".
+pub fn read_values<T>(name: &str) -> Result<Vec<T>, ProcError>
+    where T: FromStr, T::Err: Error + 'static {
+    parse_values(&try!(read_string(name)))
+}
+
+/// Write a value to a sysctl, eg: write("vm.swappiness", 10). This usually
+/// needs root.
+pub fn write<T: Display>(name: &str, value: T) -> Result<(), ProcError> {
+    write_string(name, &value.to_string())
+}
+
+/// Write several values to a sysctl, separated by tabs like the kernel
+/// prints them.
+pub fn write_values<T: Display>(name: &str, values: &[T]) -> Result<(), ProcError> {
+    let values: Vec<_> = values.iter().map(|v| v.to_string()).collect();
+    write_string(name, &values.join("\t"))
+}
+
+fn write_string(name: &str, value: &str) -> Result<(), ProcError> {
+    debug!("writing sysctl {}", name);
+    let path = try!(path(name));
+    OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcSys, e))
+        .and_then(|mut f|
+            f.write_all(value.as_bytes())
+                .map_err(|e| ProcError::new_err(ProcOper::Writing, ProcFile::ProcSys, e))
+        )
+}
+
+/// List the names of every sysctl under a prefix (eg: "vm", or "" for all),
+/// sorted. Directories that can't be read are skipped.
+pub fn list(prefix: &str) -> Result<Vec<String>, ProcError> {
+    let dir = try!(path(prefix));
+    let mut names = Vec::new();
+    try!(list_dir(&dir, &mut names));
+    let sysctl_dir = root::path("sys");
+    let mut names: Vec<_> = names.iter()
+        .filter_map(|p| p.strip_prefix(&sysctl_dir).ok())
+        .map(|p| swap_separators(&p.to_string_lossy()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Add every file under a directory to names, recursively.
+fn list_dir(dir: &Path, names: &mut Vec<PathBuf>) -> Result<(), ProcError> {
+    let entries = try!(
+        fs::read_dir(dir)
+            .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcSys, e))
+    );
+    for entry in entries.filter_map(|e| e.ok()) {
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        match is_dir {
+            // Some directories (eg: under net) need privileges to read.
+            true => { let _ = list_dir(&entry.path(), names); },
+            false => names.push(entry.path()),
+        }
+    }
+    Ok(())
+}
+
+/// Parse whitespace separated values.
+fn parse_values<T>(contents: &str) -> Result<Vec<T>, ProcError>
+    where T: FromStr, T::Err: Error + 'static {
+    contents.split_whitespace()
+        .map(|v| v.parse().map_err(|e| ProcError::new_err(ProcOper::Parsing, ProcFile::ProcSys, e)))
+        .collect()
+}
+
+#[test]
+fn test_path() {
+    assert_eq!(path("kernel.pid_max"), Ok(PathBuf::from("/proc/sys/kernel/pid_max")));
+    let vlan = PathBuf::from("/proc/sys/net/ipv4/conf/eth0.100/forwarding");
+    assert_eq!(path("net/ipv4/conf/eth0.100/forwarding"), Ok(vlan.clone()));
+    assert_eq!(path("net.ipv4.conf.eth0/100.forwarding"), Ok(vlan));
+    assert_eq!(path(""), Ok(PathBuf::from("/proc/sys")));
+    let outside = ProcError::new_more(ProcOper::Parsing, ProcFile::ProcSys,
+        Some("sysctl name outside /proc/sys"));
+    assert_eq!(path("a/../../../etc/x").unwrap_err(), outside);
+    assert_eq!(path("kernel/../../self/environ").unwrap_err(), outside);
+    assert_eq!(write("kernel/../../../tmp/x", 1).unwrap_err(), outside);
+    assert_eq!(read_string("kernel.//.//.//.etc.hostname").unwrap_err(), outside);
+}
+
+#[test]
+fn test_list() {
+    use testing::FakeProc;
+    let fake = FakeProc::new().unwrap();
+    fake.file("sys/net/ipv4/conf/eth0.100/forwarding", "0\n").unwrap();
+    fake.file("sys/vm/swappiness", "60\n").unwrap();
+    fake.run(|| {
+        let names = list("").unwrap();
+        assert_eq!(names, vec!["net.ipv4.conf.eth0/100.forwarding", "vm.swappiness"]);
+        for name in &names {
+            assert!(read_string(name).is_ok());
+        }
+    });
+}
+
+#[test]
+fn test_parse_values() {
+    assert_eq!(parse_values::<u32>("32768\t60999"), Ok(vec![32768, 60999]));
+    assert_eq!(parse_values::<u32>("4\t4\t1\t7"), Ok(vec![4, 4, 1, 7]));
+    assert!(parse_values::<u32>("1 x").is_err());
+}
+
+#[test]
+fn test_read() {
+    assert!(read::<u32>("kernel.pid_max").unwrap() > 0);
+    assert_eq!(read_values::<u32>("kernel.printk").unwrap().len(), 4);
+    assert!(read::<u32>("kernel.ostype").is_err());
+    assert_eq!(read_string("kernel.ostype").unwrap(), "Linux");
+    assert!(read::<u32>("kernel.nosuch").is_err());
+    let names = list("kernel").unwrap();
+    assert!(names.iter().any(|n| n == "kernel.pid_max"));
+    assert!(names.iter().all(|n| n.starts_with("kernel.")));
+}
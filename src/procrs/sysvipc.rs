@@ -0,0 +1,301 @@
+use std::str::FromStr;
+use error::{ProcError, ProcFile, ProcOper};
+use read_file;
+use TaskId;
+use root;
+
+/// The ownership and permissions shared by every SysV IPC object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpcPerm {
+    /// The key passed to shmget/semget/msgget, or 0 for IPC_PRIVATE.
+    pub key: i32,
+    /// The id of the object.
+    pub id: i32,
+    /// The permission bits, plus flags like SHM_DEST (0o1000).
+    pub perms: u32,
+    /// The uid of the owner.
+    pub uid: u32,
+    /// The gid of the owner.
+    pub gid: u32,
+    /// The uid of the creator.
+    pub cuid: u32,
+    /// The gid of the creator.
+    pub cgid: u32,
+}
+
+/// A shared memory segment, from a row of /proc/sysvipc/shm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShmSegment {
+    /// The key, id, owner and permissions.
+    pub perm: IpcPerm,
+    /// The size of the segment in bytes.
+    pub size: u64,
+    /// The pid of the creator.
+    pub cpid: TaskId,
+    /// The pid of the last process to attach or detach.
+    pub lpid: TaskId,
+    /// The number of processes attached.
+    pub nattch: u64,
+    /// When it was last attached, in seconds since the epoch.
+    pub atime: u64,
+    /// When it was last detached, in seconds since the epoch.
+    pub dtime: u64,
+    /// When it was last changed, in seconds since the epoch.
+    pub ctime: u64,
+    /// Bytes of the segment resident in memory (since Linux 2.6.25).
+    pub rss: Option<u64>,
+    /// Bytes of the segment swapped out (since Linux 2.6.25).
+    pub swap: Option<u64>,
+}
+
+impl ShmSegment {
+    /// Read every shared memory segment in the IPC namespace.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&root::path("sysvipc/shm"), ProcFile::ProcSysvipcShm));
+        Self::parse_string(&contents)
+    }
+
+    /// Whether the segment is marked for removal once the last process
+    /// detaches (SHM_DEST).
+    pub fn is_destroyed(&self) -> bool {
+        self.perm.perms & 0o1000 != 0
+    }
+
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        let table = try!(Table::new(contents, ProcFile::ProcSysvipcShm));
+        table.rows().map(|row| Ok(ShmSegment {
+            perm: try!(row.perm("shmid")),
+            size: try!(row.get("size")),
+            cpid: try!(row.get("cpid")),
+            lpid: try!(row.get("lpid")),
+            nattch: try!(row.get("nattch")),
+            atime: try!(row.get("atime")),
+            dtime: try!(row.get("dtime")),
+            ctime: try!(row.get("ctime")),
+            rss: try!(row.get_opt("rss")),
+            swap: try!(row.get_opt("swap")),
+        })).collect()
+    }
+}
+
+/// A semaphore set, from a row of /proc/sysvipc/sem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemaphoreSet {
+    /// The key, id, owner and permissions.
+    pub perm: IpcPerm,
+    /// The number of semaphores in the set.
+    pub nsems: u64,
+    /// When semop was last called, in seconds since the epoch.
+    pub otime: u64,
+    /// When it was last changed, in seconds since the epoch.
+    pub ctime: u64,
+}
+
+impl SemaphoreSet {
+    /// Read every semaphore set in the IPC namespace.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&root::path("sysvipc/sem"), ProcFile::ProcSysvipcSem));
+        Self::parse_string(&contents)
+    }
+
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        let table = try!(Table::new(contents, ProcFile::ProcSysvipcSem));
+        table.rows().map(|row| Ok(SemaphoreSet {
+            perm: try!(row.perm("semid")),
+            nsems: try!(row.get("nsems")),
+            otime: try!(row.get("otime")),
+            ctime: try!(row.get("ctime")),
+        })).collect()
+    }
+}
+
+/// A message queue, from a row of /proc/sysvipc/msg.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageQueue {
+    /// The key, id, owner and permissions.
+    pub perm: IpcPerm,
+    /// Bytes of messages on the queue.
+    pub cbytes: u64,
+    /// The number of messages on the queue.
+    pub qnum: u64,
+    /// The pid of the last process to send a message.
+    pub lspid: TaskId,
+    /// The pid of the last process to receive a message.
+    pub lrpid: TaskId,
+    /// When a message was last sent, in seconds since the epoch.
+    pub stime: u64,
+    /// When a message was last received, in seconds since the epoch.
+    pub rtime: u64,
+    /// When it was last changed, in seconds since the epoch.
+    pub ctime: u64,
+}
+
+impl MessageQueue {
+    /// Read every message queue in the IPC namespace.
+    pub fn new() -> Result<Vec<Self>, ProcError> {
+        let contents = try!(read_file(&root::path("sysvipc/msg"), ProcFile::ProcSysvipcMsg));
+        Self::parse_string(&contents)
+    }
+
+    fn parse_string(contents: &str) -> Result<Vec<Self>, ProcError> {
+        let table = try!(Table::new(contents, ProcFile::ProcSysvipcMsg));
+        table.rows().map(|row| Ok(MessageQueue {
+            perm: try!(row.perm("msqid")),
+            cbytes: try!(row.get("cbytes")),
+            qnum: try!(row.get("qnum")),
+            lspid: try!(row.get("lspid")),
+            lrpid: try!(row.get("lrpid")),
+            stime: try!(row.get("stime")),
+            rtime: try!(row.get("rtime")),
+            ctime: try!(row.get("ctime")),
+        })).collect()
+    }
+}
+
+/// A /proc/sysvipc file, with columns found by the header so that columns
+/// added by newer kernels don't break parsing.
+struct Table<'a> {
+    header: Vec<&'a str>,
+    lines: Vec<&'a str>,
+    file: ProcFile,
+}
+
+impl<'a> Table<'a> {
+    fn new(contents: &'a str, file: ProcFile) -> Result<Self, ProcError> {
+        let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+        let header = try!(lines.next()
+            .ok_or(ProcError::new_more(ProcOper::Parsing, file, Some("missing header"))));
+        Ok(Table {
+            header: header.split_whitespace().collect(),
+            lines: lines.collect(),
+            file: file,
+        })
+    }
+
+    fn rows<'b>(&'b self) -> Box<Iterator<Item=Row<'a, 'b>> + 'b> {
+        Box::new(self.lines.iter().map(move |line| Row {
+            table: self,
+            fields: line.split_whitespace().collect(),
+        }))
+    }
+}
+
+struct Row<'a: 'b, 'b> {
+    table: &'b Table<'a>,
+    fields: Vec<&'a str>,
+}
+
+impl<'a, 'b> Row<'a, 'b> {
+    fn get_opt<T: FromStr>(&self, name: &'static str) -> Result<Option<T>, ProcError> {
+        let index = match self.table.header.iter().position(|&h| h == name) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let file = self.table.file;
+        let err = || ProcError::new_more(ProcOper::ParsingField, file, Some(name));
+        let field = try!(self.fields.get(index).ok_or_else(&err));
+        field.parse().map(Some).map_err(|_| err())
+    }
+
+    fn get<T: FromStr>(&self, name: &'static str) -> Result<T, ProcError> {
+        try!(self.get_opt(name)).ok_or(
+            ProcError::new_more(ProcOper::ParsingField, self.table.file, Some(name)))
+    }
+
+    /// The fields common to every IPC object, with the id in column id_name.
+    fn perm(&self, id_name: &'static str) -> Result<IpcPerm, ProcError> {
+        let perms: String = try!(self.get("perms"));
+        Ok(IpcPerm {
+            key: try!(self.get("key")),
+            id: try!(self.get(id_name)),
+            // The permissions are printed in octal.
+            perms: try!(u32::from_str_radix(&perms, 8).map_err(|_|
+                ProcError::new_more(ProcOper::ParsingField, self.table.file, Some("perms")))),
+            uid: try!(self.get("uid")),
+            gid: try!(self.get("gid")),
+            cuid: try!(self.get("cuid")),
+            cgid: try!(self.get("cgid")),
+        })
+    }
+}
+
+#[test]
+fn test_shm() {
+    let segments = ShmSegment::parse_string(
+"       key      shmid perms                  size  cpid  lpid nattch   uid   gid  cuid  cgid      atime      dtime      ctime                   rss                  swap
+         0          5  1600                524288  2133  2790      2  1000  1000  1000  1000 1700000010 1700000000 1700000000                  8192                     0
+1392640101         12   644                  4096   901   901      0     0     0     0     0          0          0 1699999000                     0                  4096
+").unwrap();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0], ShmSegment {
+        perm: IpcPerm {
+            key: 0,
+            id: 5,
+            perms: 0o1600,
+            uid: 1000,
+            gid: 1000,
+            cuid: 1000,
+            cgid: 1000,
+        },
+        size: 524288,
+        cpid: 2133,
+        lpid: 2790,
+        nattch: 2,
+        atime: 1700000010,
+        dtime: 1700000000,
+        ctime: 1700000000,
+        rss: Some(8192),
+        swap: Some(0),
+    });
+    assert!(segments[0].is_destroyed());
+    assert!(!segments[1].is_destroyed());
+    assert_eq!(segments[1].perm.key, 1392640101);
+    // Kernels before 2.6.25 have no rss or swap columns.
+    let old = ShmSegment::parse_string(
+"       key      shmid perms       size  cpid  lpid nattch   uid   gid  cuid  cgid      atime      dtime      ctime
+         0          5   600     524288  2133  2790      2  1000  1000  1000  1000 1700000010 1700000000 1700000000
+").unwrap();
+    assert_eq!(old[0].rss, None);
+    assert!(ShmSegment::parse_string("key shmid perms\n0 5 9\n").is_err());
+}
+
+#[test]
+fn test_sem_msg() {
+    let sets = SemaphoreSet::parse_string(
+"       key      semid perms      nsems   uid   gid  cuid  cgid      otime      ctime
+  16842799          3   600          1     0     0     0     0          0 1700000000
+").unwrap();
+    assert_eq!(sets[0].perm.key, 16842799);
+    assert_eq!(sets[0].perm.perms, 0o600);
+    assert_eq!(sets[0].nsems, 1);
+    let queues = MessageQueue::parse_string(
+"       key      msqid perms      cbytes       qnum lspid lrpid   uid   gid  cuid  cgid      stime      rtime      ctime
+        -1          0   666         128          2  4012     0  1000  1000  1000  1000 1700000020          0 1700000000
+").unwrap();
+    assert_eq!(queues[0], MessageQueue {
+        perm: IpcPerm {
+            key: -1,
+            id: 0,
+            perms: 0o666,
+            uid: 1000,
+            gid: 1000,
+            cuid: 1000,
+            cgid: 1000,
+        },
+        cbytes: 128,
+        qnum: 2,
+        lspid: 4012,
+        lrpid: 0,
+        stime: 1700000020,
+        rtime: 0,
+        ctime: 1700000000,
+    });
+    assert!(MessageQueue::parse_string("").is_err());
+}
+
+#[test]
+fn test_read() {
+    assert!(ShmSegment::new().is_ok());
+    assert!(SemaphoreSet::new().is_ok());
+    assert!(MessageQueue::new().is_ok());
+}
@@ -0,0 +1,276 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use pid::Pid;
+use root;
+use {TaskId, MemSize, page_size};
+
+/// A /proc/meminfo file from a machine with 6GB of memory and no swap.
+pub const MEMINFO: &'static str = "\
+MemTotal:        6158152 kB
+MemFree:         3820856 kB
+MemAvailable:    5652192 kB
+Buffers:           17624 kB
+Cached:          1993828 kB
+SwapCached:            0 kB
+Active:           803356 kB
+Inactive:        1357592 kB
+Active(anon):       1764 kB
+Inactive(anon):   156780 kB
+Active(file):     801592 kB
+Inactive(file):  1200812 kB
+Unevictable:        9268 kB
+Mlocked:            9268 kB
+SwapTotal:             0 kB
+SwapFree:              0 kB
+Dirty:               840 kB
+Writeback:             0 kB
+AnonPages:        158816 kB
+Mapped:           140680 kB
+Shmem:              9048 kB
+Slab:             104212 kB
+SReclaimable:      81488 kB
+SUnreclaim:        22724 kB
+KernelStack:        1200 kB
+PageTables:         2344 kB
+NFS_Unstable:          0 kB
+Bounce:                0 kB
+WritebackTmp:          0 kB
+CommitLimit:     3079076 kB
+Committed_AS:     338576 kB
+VmallocTotal:   34359738367 kB
+VmallocUsed:       15940 kB
+VmallocChunk:          0 kB
+HardwareCorrupted:     0 kB
+AnonHugePages:         0 kB
+HugePages_Total:       0
+HugePages_Free:        0
+HugePages_Rsvd:        0
+HugePages_Surp:        0
+Hugepagesize:       2048 kB
+DirectMap4k:       26624 kB
+DirectMap2M:     2070528 kB
+DirectMap1G:     6291456 kB
+";
+
+/// A /proc/uptime file, one hour after boot.
+pub const UPTIME: &'static str = "3600.00 7000.00\n";
+
+/// A /proc/loadavg file.
+pub const LOADAVG: &'static str = "0.26 0.30 0.27 2/74 29640\n";
+
+/// A /proc/stat file, without the per-cpu lines.
+pub const STAT: &'static str = "\
+cpu  10132153 290696 3084719 46828483 16683 0 25195 0 0 0
+ctxt 115315
+btime 769041601
+processes 86031
+procs_running 2
+procs_blocked 0
+";
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A synthetic proc tree in a temporary directory, for testing code that
+/// reads procfs without depending on the processes of the real system.
+///
+/// The tree is removed when this is dropped. Run code against it with
+/// `run`, or `root::set_root(fake.path())`.
+#[derive(Debug)]
+pub struct FakeProc {
+    dir: PathBuf,
+}
+
+impl FakeProc {
+    /// Create an empty proc tree.
+    pub fn new() -> io::Result<Self> {
+        let dir = ::std::env::temp_dir().join(format!("procrs-fake-{}-{}",
+            process::id(), NEXT_ID.fetch_add(1, Ordering::SeqCst)));
+        try!(fs::create_dir_all(&dir));
+        Ok(FakeProc { dir: dir })
+    }
+
+    /// Create a proc tree with the system wide files from this module
+    /// (meminfo, uptime, loadavg and stat), which can then be overwritten.
+    pub fn with_system() -> io::Result<Self> {
+        let fake = try!(Self::new());
+        try!(fake.file("meminfo", MEMINFO));
+        try!(fake.file("uptime", UPTIME));
+        try!(fake.file("loadavg", LOADAVG));
+        try!(fake.file("stat", STAT));
+        Ok(fake)
+    }
+
+    /// The root of the proc tree.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write a file under the root (eg: "net/dev"), creating any parent
+    /// directories.
+    pub fn file<P: AsRef<Path>>(&self, path: P, contents: &str) -> io::Result<()> {
+        let path = self.dir.join(path);
+        if let Some(parent) = path.parent() {
+            try!(fs::create_dir_all(parent));
+        }
+        let mut file = try!(fs::File::create(path));
+        file.write_all(contents.as_bytes())
+    }
+
+    /// Add a process, whose main thread is also listed under task.
+    pub fn process(&self, pid: &FakePid) -> io::Result<()> {
+        let dir = PathBuf::from(pid.pid.to_string());
+        try!(self.pid_files(&dir, pid, pid.pid));
+        self.pid_files(&dir.join("task").join(pid.pid.to_string()), pid, pid.pid)
+    }
+
+    /// Add a thread to a process added earlier.
+    pub fn thread(&self, tgid: TaskId, thread: &FakePid) -> io::Result<()> {
+        let dir = Path::new(&tgid.to_string()).join("task").join(thread.pid.to_string());
+        self.pid_files(&dir, thread, tgid)
+    }
+
+    fn pid_files(&self, dir: &Path, pid: &FakePid, tgid: TaskId) -> io::Result<()> {
+        try!(self.file(dir.join("stat"), &pid.stat()));
+        try!(self.file(dir.join("status"), &pid.status(tgid)));
+        let mut cmdline = pid.cmdline.join("\0");
+        if !cmdline.is_empty() {
+            cmdline.push('\0');
+        }
+        self.file(dir.join("cmdline"), &cmdline)
+    }
+
+    /// Run a function with this as the proc root of the calling thread.
+    pub fn run<F, T>(&self, f: F) -> T where F: FnOnce() -> T {
+        root::with_root(self.dir.clone(), f)
+    }
+}
+
+impl Drop for FakeProc {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// The fields of a fake process (or thread), used to write its stat, status
+/// and cmdline files. Fields that aren't here are written as zero.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakePid {
+    /// The process (or thread) id.
+    pub pid: TaskId,
+    /// The parent process id.
+    pub ppid: TaskId,
+    /// The process name, being at most 15 characters in a real procfs.
+    pub comm: String,
+    /// The state character, eg: 'R', 'S' or 'Z'.
+    pub state: char,
+    /// The owner, written as the real, effective, saved and filesystem uid.
+    pub uid: u32,
+    /// The group, written like uid.
+    pub gid: u32,
+    /// The arguments, with the program first.
+    pub cmdline: Vec<String>,
+    /// The nice value, from -20 to 19.
+    pub nice: i64,
+    /// The number of threads in the thread group.
+    pub num_threads: i64,
+    /// When the process started, in clock ticks after boot.
+    pub starttime: u64,
+    /// Virtual memory size in bytes.
+    pub vsize: MemSize,
+    /// Resident set size in bytes, or None for kernel threads.
+    pub rss: Option<MemSize>,
+    /// User cpu time in clock ticks.
+    pub utime: u64,
+    /// System cpu time in clock ticks.
+    pub stime: u64,
+}
+
+impl FakePid {
+    /// A sleeping process owned by root, with its name as its cmdline.
+    pub fn new(pid: TaskId, ppid: TaskId, comm: &str) -> Self {
+        FakePid {
+            pid: pid,
+            ppid: ppid,
+            comm: comm.to_owned(),
+            state: 'S',
+            uid: 0,
+            gid: 0,
+            cmdline: vec![comm.to_owned()],
+            nice: 0,
+            num_threads: 1,
+            starttime: 0,
+            vsize: 0,
+            rss: Some(0),
+            utime: 0,
+            stime: 0,
+        }
+    }
+
+    /// The contents of the stat file.
+    pub fn stat(&self) -> String {
+        let rss_pages = self.rss.unwrap_or(0) / page_size();
+        format!("{} ({}) {} {} {} {} 0 -1 4194560 0 0 0 0 {} {} 0 0 20 {} {} 0 {} {} {} \
+            18446744073709551615 0 0 0 0 0 0 0 0 0 0 0 17 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n",
+            self.pid, self.comm, self.state, self.ppid, self.pid, self.pid,
+            self.utime, self.stime, self.nice, self.num_threads, self.starttime,
+            self.vsize, rss_pages)
+    }
+
+    /// The contents of the status file, for a task in the thread group tgid.
+    pub fn status(&self, tgid: TaskId) -> String {
+        let mut status = format!("Name:\t{}\nState:\t{}\nTgid:\t{}\nPid:\t{}\nPPid:\t{}\n\
+            TracerPid:\t0\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\nGid:\t{gid}\t{gid}\t{gid}\t{gid}\n\
+            FDSize:\t64\n",
+            self.comm, self.state, tgid, self.pid, self.ppid, uid = self.uid, gid = self.gid);
+        if let Some(rss) = self.rss {
+            status.push_str(&format!("VmSize:\t{} kB\nVmRSS:\t{} kB\n",
+                self.vsize / 1024, rss / 1024));
+        }
+        status.push_str(&format!("Threads:\t{}\n", self.num_threads));
+        status
+    }
+
+    /// Read this back as a Pid, from a proc tree holding only this process.
+    ///
+    /// This is for tests of code that takes a Pid, and panics if the proc
+    /// tree can't be written or read.
+    pub fn to_pid(&self) -> Pid {
+        let fake = FakeProc::new().unwrap();
+        fake.process(self).unwrap();
+        fake.run(|| Pid::new(self.pid)).unwrap()
+    }
+}
+
+#[test]
+fn test_fake_processes() {
+    use meminfo::Meminfo;
+    use pid::{PidIter, PidQuery, TidIter};
+    let fake = FakeProc::with_system().unwrap();
+    fake.process(&FakePid::new(1, 0, "init")).unwrap();
+    fake.process(&FakePid {
+        uid: 1000,
+        cmdline: vec!["nginx".to_owned(), "-g".to_owned(), "daemon off;".to_owned()],
+        rss: Some(64 << 20),
+        num_threads: 2,
+        ..FakePid::new(42, 1, "nginx")
+    }).unwrap();
+    fake.thread(42, &FakePid::new(43, 1, "nginx-worker")).unwrap();
+    fake.run(|| {
+        let pids: Vec<_> = PidIter::new().unwrap().map(|p| p.unwrap()).collect();
+        let mut names: Vec<_> = pids.iter().map(|p| (p.pid, p.stat.comm.clone())).collect();
+        names.sort();
+        assert_eq!(names, vec![(1, "init".to_owned()), (42, "nginx".to_owned())]);
+        let nginx = pids.iter().find(|p| p.pid == 42).unwrap();
+        assert_eq!(nginx.cmdline, vec!["nginx", "-g", "daemon off;"]);
+        assert_eq!(nginx.status.uid.0, 1000);
+        assert_eq!(nginx.status.vmrss, Some(64 << 20));
+        assert!(nginx.query(&"rss>32M".parse().unwrap()));
+        let workers: Vec<_> = TidIter::new_query(PidQuery::NameQuery("worker".to_owned()))
+            .unwrap().map(|t| t.unwrap().pid).collect();
+        assert_eq!(workers, vec![43]);
+        assert_eq!(Meminfo::new().unwrap().memtotal, 6158152);
+    });
+}
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::Read;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// A struct containing the system uptime from /proc/uptime.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Uptime {
+    /// Seconds since the system booted.
+    pub uptime: f64,
+    /// Seconds each cpu has spent idle, summed over all cpus.
+    pub idle: f64,
+}
+
+impl Uptime {
+    /// Read the current uptime from /proc/uptime.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("uptime"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcUptime, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcUptime, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/uptime file.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let mut split = contents.split_whitespace();
+        let mut next = |field| split.next()
+            .ok_or(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcUptime,
+                Some(field)))
+            .and_then(|s| s.parse()
+                .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcUptime,
+                    Some(e), Some(field)))
+            );
+        Ok(Uptime {
+            uptime: try!(next("uptime")),
+            idle: try!(next("idle")),
+        })
+    }
+}
+
+#[test]
+fn test_parsing() {
+    assert_eq!(Uptime::parse_string("350735.47 234388.90\n"),
+        Ok(Uptime { uptime: 350735.47, idle: 234388.90 }));
+}
+
+#[test]
+fn test_missing_idle() {
+    assert_eq!(Uptime::parse_string("350735.47\n"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcUptime, Some("idle"))));
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::Read;
+
+/// A user, from a line of /etc/passwd.
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    /// The login name.
+    pub name: String,
+    /// The user id.
+    pub uid: u32,
+    /// The primary group.
+    pub gid: u32,
+}
+
+/// Read the local users from /etc/passwd.
+///
+/// Users from other sources (eg: LDAP through nsswitch) aren't found. If the
+/// file can't be read, no users are returned.
+pub fn users() -> Vec<User> {
+    let mut contents = String::new();
+    match File::open("/etc/passwd").and_then(|mut f| f.read_to_string(&mut contents)) {
+        Ok(_) => parse_passwd(&contents),
+        Err(e) => {
+            warn!("couldn't read /etc/passwd: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Find the uid of a user by name.
+pub fn uid_by_name(name: &str) -> Option<u32> {
+    users().into_iter().find(|u| u.name == name).map(|u| u.uid)
+}
+
+/// Find the name of a user by uid.
+pub fn name_by_uid(uid: u32) -> Option<String> {
+    users().into_iter().find(|u| u.uid == uid).map(|u| u.name)
+}
+
+/// Parse a String as a passwd file, with lines like
+/// "postgres:x:113:120:PostgreSQL administrator:/var/lib/postgresql:/bin/bash".
+/// Malformed lines are skipped.
+fn parse_passwd(contents: &str) -> Vec<User> {
+    contents.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split(':').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            match (fields[2].parse(), fields[3].parse()) {
+                (Ok(uid), Ok(gid)) => Some(User {
+                    name: fields[0].to_owned(),
+                    uid: uid,
+                    gid: gid,
+                }),
+                _ => None
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_parsing() {
+    let users = parse_passwd("root:x:0:0:root:/root:/bin/bash\n\
+        # a comment\n\
+        postgres:x:113:120:PostgreSQL administrator:/var/lib/postgresql:/bin/bash\n\
+        broken:x:abc:1::/:/bin/false\n\
+        short:x\n");
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[1], User { name: "postgres".to_owned(), uid: 113, gid: 120 });
+}
+
+#[test]
+fn test_lookup() {
+    assert_eq!(uid_by_name("root"), Some(0));
+    assert_eq!(name_by_uid(0), Some("root".to_owned()));
+    assert_eq!(uid_by_name("no such user"), None);
+}
@@ -0,0 +1,229 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+use error::{ProcError, ProcFile, ProcOper};
+use sysctl;
+use root;
+
+/// A kernel version number like 6.1.55, ordered so that newer kernels are
+/// greater, eg: `KernelVersion::current()? >= KernelVersion::new(4, 3, 0)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KernelVersion {
+    /// The major version, eg: 6 in 6.1.55.
+    pub major: u32,
+    /// The minor version, eg: 1 in 6.1.55.
+    pub minor: u32,
+    /// The patch level, eg: 55 in 6.1.55, or 0 if the release has none.
+    pub patch: u32,
+}
+
+impl KernelVersion {
+    /// Create a version to compare against, eg: `KernelVersion::new(4, 3, 0)`.
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        KernelVersion {
+            major: major,
+            minor: minor,
+            patch: patch,
+        }
+    }
+
+    /// Read the version of the running kernel from
+    /// /proc/sys/kernel/osrelease.
+    pub fn current() -> Result<Self, ProcError> {
+        Ok(try!(Release::current()).version)
+    }
+}
+
+impl FromStr for KernelVersion {
+    type Err = ProcError;
+
+    /// Parse the numbers at the start of a release, like "6.1", "6.1.55" or
+    /// "5.15.0-91-generic" (ignoring everything after the numbers).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ProcError::new_more(ProcOper::Parsing, ProcFile::ProcVersion,
+            Some("kernel version"));
+        let end = s.find(|c: char| !c.is_digit(10) && c != '.').unwrap_or(s.len());
+        let mut numbers = s[..end].split('.');
+        let mut next = |required| match numbers.next() {
+            Some(n) => n.parse().map_err(|_| err()),
+            None if required => Err(err()),
+            None => Ok(0),
+        };
+        Ok(KernelVersion {
+            major: try!(next(true)),
+            minor: try!(next(true)),
+            patch: try!(next(false)),
+        })
+    }
+}
+
+impl fmt::Display for KernelVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A kernel release, like "5.15.0-91-generic".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// The version numbers.
+    pub version: KernelVersion,
+    /// Everything after the version numbers, set by the distribution or
+    /// builder, eg: "-91-generic" or "-rc3".
+    pub extraversion: String,
+}
+
+impl Release {
+    /// Read the release of the running kernel from
+    /// /proc/sys/kernel/osrelease.
+    pub fn current() -> Result<Self, ProcError> {
+        try!(sysctl::read_string("kernel.osrelease")).parse()
+    }
+
+    /// Whether this is a release candidate.
+    pub fn is_rc(&self) -> bool {
+        self.extraversion.starts_with("-rc")
+    }
+}
+
+impl FromStr for Release {
+    type Err = ProcError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // A trailing '.' isn't part of the numbers, eg: "2.6.32.".
+        let end = s.find(|c: char| !c.is_digit(10) && c != '.').unwrap_or(s.len());
+        let end = s[..end].trim_right_matches('.').len();
+        Ok(Release {
+            version: try!(s.parse()),
+            extraversion: s[end..].to_owned(),
+        })
+    }
+}
+
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.version, self.extraversion)
+    }
+}
+
+/// A struct containing the kernel build information from /proc/version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// The release, being the same as /proc/sys/kernel/osrelease.
+    pub release: Release,
+    /// Who built the kernel, as "user@host".
+    pub built_by: Option<String>,
+    /// The compiler (and on newer kernels, linker) used to build the kernel.
+    pub compiler: Option<String>,
+    /// The build number, options and date, eg: "#1 SMP PREEMPT_DYNAMIC Mon
+    /// Jan 1 00:00:00 UTC 2024".
+    pub build: String,
+}
+
+impl Version {
+    /// Read the version of the running kernel from /proc/version.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("version"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcVersion, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcVersion, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/version file, being
+    /// "Linux version RELEASE (USER@HOST) (COMPILER) BUILD".
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let rest = contents.trim();
+        if !rest.starts_with("Linux version ") {
+            return Err(ProcError::new_more(ProcOper::Parsing, ProcFile::ProcVersion,
+                Some("missing \"Linux version\"")));
+        }
+        let rest = &rest["Linux version ".len()..];
+        let end = rest.find(' ').unwrap_or(rest.len());
+        let release = try!(rest[..end].parse());
+        let (built_by, rest) = parenthesized(&rest[end..]);
+        let (compiler, rest) = parenthesized(rest);
+        Ok(Version {
+            release: release,
+            built_by: built_by,
+            compiler: compiler,
+            build: rest.trim().to_owned(),
+        })
+    }
+}
+
+/// Split off a leading parenthesized group, which can contain nested
+/// parentheses like "(gcc version 7.3.0 (Ubuntu 7.3.0-16ubuntu3))".
+fn parenthesized(text: &str) -> (Option<String>, &str) {
+    let text = text.trim_left();
+    if !text.starts_with('(') {
+        return (None, text);
+    }
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => continue,
+        }
+        if depth == 0 {
+            return (Some(text[1..i].to_owned()), &text[i + 1..]);
+        }
+    }
+    (None, text)
+}
+
+#[test]
+fn test_kernel_version() {
+    assert_eq!("6.1.55".parse::<KernelVersion>().unwrap(), KernelVersion::new(6, 1, 55));
+    assert_eq!("4.3".parse::<KernelVersion>().unwrap(), KernelVersion::new(4, 3, 0));
+    assert_eq!("5.15.0-91-generic".parse::<KernelVersion>().unwrap(),
+        KernelVersion::new(5, 15, 0));
+    assert!("6".parse::<KernelVersion>().is_err());
+    assert!("linux".parse::<KernelVersion>().is_err());
+    assert!(KernelVersion::new(5, 10, 200) < KernelVersion::new(6, 1, 0));
+    assert!(KernelVersion::new(4, 19, 0) > KernelVersion::new(4, 9, 300));
+    assert_eq!(KernelVersion::new(6, 1, 0).to_string(), "6.1.0");
+}
+
+#[test]
+fn test_release() {
+    let release: Release = "5.15.0-91-generic".parse().unwrap();
+    assert_eq!(release.version, KernelVersion::new(5, 15, 0));
+    assert_eq!(release.extraversion, "-91-generic");
+    assert_eq!(release.to_string(), "5.15.0-91-generic");
+    let rc: Release = "6.8-rc3".parse().unwrap();
+    assert!(rc.is_rc());
+    assert_eq!(rc.version, KernelVersion::new(6, 8, 0));
+    assert_eq!("2.6.32.".parse::<Release>().unwrap().extraversion, ".");
+}
+
+#[test]
+fn test_parsing() {
+    let version = Version::parse_string("Linux version 4.15.0-20-generic (buildd@lgw01-amd64-039) \
+        (gcc version 7.3.0 (Ubuntu 7.3.0-16ubuntu3)) #21-Ubuntu SMP Tue Apr 24 06:16:15 UTC 2018\n")
+        .unwrap();
+    assert_eq!(version.release.version, KernelVersion::new(4, 15, 0));
+    assert_eq!(version.release.extraversion, "-20-generic");
+    assert_eq!(version.built_by, Some("buildd@lgw01-amd64-039".to_owned()));
+    assert_eq!(version.compiler, Some("gcc version 7.3.0 (Ubuntu 7.3.0-16ubuntu3)".to_owned()));
+    assert_eq!(version.build, "#21-Ubuntu SMP Tue Apr 24 06:16:15 UTC 2018");
+    let bare = Version::parse_string("Linux version 6.1.0 #1 SMP\n").unwrap();
+    assert_eq!(bare.built_by, None);
+    assert_eq!(bare.build, "#1 SMP");
+    assert!(Version::parse_string("FreeBSD 14.0\n").is_err());
+}
+
+#[test]
+fn test_read() {
+    let version = Version::new().unwrap();
+    assert_eq!(version.release, Release::current().unwrap());
+    assert!(KernelVersion::current().unwrap() >= KernelVersion::new(2, 6, 0));
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use error::{ProcError, ProcFile, ProcOper};
+use root;
+
+/// A struct containing the virtual memory counters from /proc/vmstat.
+///
+/// Counters missing from the running kernel are 0. Page counts are in pages,
+/// and pgpgin/pgpgout are in kB.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vmstat {
+    /// Free pages.
+    pub nr_free_pages: u64,
+    /// Pages waiting to be written back.
+    pub nr_dirty: u64,
+    /// Pages being written back.
+    pub nr_writeback: u64,
+    /// kB paged in from disk.
+    pub pgpgin: u64,
+    /// kB paged out to disk.
+    pub pgpgout: u64,
+    /// Pages swapped in.
+    pub pswpin: u64,
+    /// Pages swapped out.
+    pub pswpout: u64,
+    /// Page faults (minor and major).
+    pub pgfault: u64,
+    /// Major page faults, needing disk reads.
+    pub pgmajfault: u64,
+    /// Pages scanned by kswapd.
+    pub pgscan_kswapd: u64,
+    /// Pages scanned by direct reclaim.
+    pub pgscan_direct: u64,
+    /// Pages reclaimed by kswapd.
+    pub pgsteal_kswapd: u64,
+    /// Pages reclaimed by direct reclaim.
+    pub pgsteal_direct: u64,
+    /// Processes killed by the OOM killer (Linux 4.13+).
+    pub oom_kill: u64,
+    /// Every counter by name, including the ones above.
+    pub counters: HashMap<String, u64>,
+}
+
+impl Vmstat {
+    /// Read the current counters from /proc/vmstat.
+    pub fn new() -> Result<Self, ProcError> {
+        let mut file = try!(
+            File::open(root::path("vmstat"))
+                .map_err(|e| ProcError::new_err(ProcOper::Opening, ProcFile::ProcVmstat, e))
+        );
+        let mut contents = String::new();
+        try!(
+            file.read_to_string(&mut contents)
+                .map_err(|e| ProcError::new_err(ProcOper::Reading, ProcFile::ProcVmstat, e))
+        );
+        Self::parse_string(&contents)
+    }
+
+    /// Parse a String as a /proc/vmstat file, with a "name value" pair on each line.
+    fn parse_string(contents: &str) -> Result<Self, ProcError> {
+        let mut counters = HashMap::new();
+        for line in contents.lines() {
+            let mut split = line.split_whitespace();
+            let (name, value) = match (split.next(), split.next()) {
+                (Some(name), Some(value)) => (name, value),
+                (None, _) => continue,
+                _ => return Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcVmstat,
+                    Some("missing value"))),
+            };
+            let value = try!(
+                value.parse()
+                    .map_err(|e| ProcError::new(ProcOper::ParsingField, ProcFile::ProcVmstat,
+                        Some(e), Some("parsing counter")))
+            );
+            counters.insert(name.to_owned(), value);
+        }
+        let get = |name: &str| counters.get(name).cloned().unwrap_or(0);
+        Ok(Vmstat {
+            nr_free_pages: get("nr_free_pages"),
+            nr_dirty: get("nr_dirty"),
+            nr_writeback: get("nr_writeback"),
+            pgpgin: get("pgpgin"),
+            pgpgout: get("pgpgout"),
+            pswpin: get("pswpin"),
+            pswpout: get("pswpout"),
+            pgfault: get("pgfault"),
+            pgmajfault: get("pgmajfault"),
+            pgscan_kswapd: get("pgscan_kswapd"),
+            pgscan_direct: get("pgscan_direct"),
+            pgsteal_kswapd: get("pgsteal_kswapd"),
+            pgsteal_direct: get("pgsteal_direct"),
+            oom_kill: get("oom_kill"),
+            counters: counters,
+        })
+    }
+
+    /// Get a counter by name, eg: "thp_fault_alloc".
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.counters.get(name).cloned()
+    }
+}
+
+#[test]
+fn test_parsing() {
+    let vmstat = Vmstat::parse_string(
+        "nr_free_pages 842043\n\
+         pgpgin 2178428\n\
+         pswpin 12\n\
+         pgmajfault 5617\n\
+         thp_fault_alloc 3\n").unwrap();
+    assert_eq!(vmstat.nr_free_pages, 842043);
+    assert_eq!(vmstat.pgpgin, 2178428);
+    assert_eq!(vmstat.pswpin, 12);
+    assert_eq!(vmstat.pgmajfault, 5617);
+    assert_eq!(vmstat.oom_kill, 0);
+    assert_eq!(vmstat.get("thp_fault_alloc"), Some(3));
+    assert_eq!(vmstat.get("oom_kill"), None);
+}
+
+#[test]
+fn test_missing_value() {
+    assert_eq!(Vmstat::parse_string("pgpgin\n"),
+        Err(ProcError::new_more(ProcOper::ParsingField, ProcFile::ProcVmstat,
+            Some("missing value"))));
+}
+
+#[test]
+fn test_read() {
+    assert!(Vmstat::new().unwrap().pgfault > 0);
+}
@@ -1,10 +1,9 @@
 use std::str::FromStr;
-use std::iter::IntoIterator;
-use std::collections::HashSet;
-use procrs::pid::{PidFile, Pid};
+use procrs::fields::{self, Field};
 
-// FIXME: This may be better in procps
-enum PidCol {
+/// A column of the process table, chosen by psq's flags or with -O.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PidCol {
     /// Process ID
     Pid,
     /// Thread ID (kernel's Pid)
@@ -13,64 +12,134 @@ enum PidCol {
     Tgid,
     /// Parent Process ID
     Ppid,
+    /// Number of direct children
+    NChild,
+    /// Number of descendants
+    NDesc,
+    /// Processor last run on
+    Psr,
     /// Resident Memory
     RSS,
     /// CPU Time
     Time,
+    /// CPU usage over the process lifetime
+    Cpu,
+    /// CPU usage sampled over an interval
+    CpuNow,
+    /// Memory pressure classification
+    MemPressure,
+    /// Security context (SELinux or AppArmor label)
+    Label,
     /// Process Name
     Cmd,
     /// Process Arguments
     Cmdline
 }
 
+/// Every column, in the order psq's flags display them.
+pub const ALL_COLUMNS: &'static [PidCol] = &[
+    PidCol::Pid, PidCol::Tid, PidCol::Tgid, PidCol::Ppid, PidCol::NChild, PidCol::NDesc,
+    PidCol::Psr, PidCol::RSS, PidCol::Cpu, PidCol::CpuNow, PidCol::Time, PidCol::MemPressure,
+    PidCol::Label, PidCol::Cmd, PidCol::Cmdline,
+];
+
 impl PidCol {
-    /// Get the file that this column requires.
-    fn get_file(&self) -> PidFile {
+    /// Get the field of the registry that this column displays, or None if
+    /// psq calculates it (like child counts and cpu usage).
+    pub fn field(&self) -> Option<&'static Field> {
+        let name = match *self {
+            PidCol::Pid => "stat.pid",
+            PidCol::Tid => "status.pid",
+            PidCol::Tgid => "status.tgid",
+            PidCol::Ppid => "stat.ppid",
+            PidCol::Psr => "stat.processor",
+            PidCol::RSS => "status.vmrss",
+            PidCol::Label => "attr.current",
+            PidCol::Cmd => "stat.comm",
+            PidCol::Cmdline => "cmdline",
+            _ => return None
+        };
+        fields::find(name)
+    }
+
+    /// Get the description of this column, from the registry if it displays
+    /// a field.
+    pub fn help(&self) -> &'static str {
+        if let Some(field) = self.field() {
+            return field.help;
+        }
         match *self {
-            PidCol::Pid => PidFile::PidStat,
-            PidCol::Tid => PidFile::PidStat,
-            PidCol::Ppid => PidFile::PidStat,
-            PidCol::Tgid => PidFile::PidStatus,
-            PidCol::RSS => PidFile::PidStatus,
-            PidCol::Time => PidFile::PidStatus,
-            PidCol::Cmd => PidFile::PidStat,
-            PidCol::Cmdline => PidFile::PidCmdline
+            PidCol::NChild => "Number of direct children",
+            PidCol::NDesc => "Number of descendants",
+            PidCol::Time => "User and system cpu time",
+            PidCol::Cpu => "Cpu usage over the process lifetime",
+            PidCol::CpuNow => "Cpu usage sampled over an interval",
+            PidCol::MemPressure => "Memory pressure classification",
+            _ => ""
         }
     }
 
     /// Get the str of this column.
-    fn to_str(&self) -> Result<&'static str, ()> {
-        Ok(match *self {
+    pub fn to_str(&self) -> &'static str {
+        match *self {
             PidCol::Pid => "pid",
             PidCol::Tid => "tid",
             PidCol::Ppid => "ppid",
+            PidCol::NChild => "nchild",
+            PidCol::NDesc => "ndesc",
+            PidCol::Psr => "psr",
             PidCol::Tgid => "tgid",
             PidCol::RSS => "rss",
             PidCol::Time => "time",
+            PidCol::Cpu => "cpu",
+            PidCol::CpuNow => "cpunow",
+            PidCol::MemPressure => "mem",
+            PidCol::Label => "label",
             PidCol::Cmd => "cmd",
             PidCol::Cmdline => "cmdline",
-        })
+        }
     }
 
-    /// Get the title of this column>
-    fn to_title(&self) -> Result<&'static str, ()> {
-        Ok(match *self {
+    /// Get the title of this column.
+    pub fn to_title(&self) -> &'static str {
+        match *self {
             PidCol::Pid => "Pid",
-            PidCol::Tid => "Iid",
+            PidCol::Tid => "Tid",
             PidCol::Ppid => "Ppid",
+            PidCol::NChild => "NChild",
+            PidCol::NDesc => "NDesc",
+            PidCol::Psr => "Psr",
             PidCol::Tgid => "Tgid",
             PidCol::RSS => "RSS",
             PidCol::Time => "Time",
+            PidCol::Cpu => "%CPU",
+            PidCol::CpuNow => "%Now",
+            PidCol::MemPressure => "Memory",
+            PidCol::Label => "Label",
             PidCol::Cmd => "Cmd",
             PidCol::Cmdline => "Cmdline",
-        })
+        }
     }
 
-    /// Get the set of files that some list of columns require.
-    fn get_file_set<I: IntoIterator<Item=PidCol>>(cols_iter: I) -> HashSet<PidFile> {
-        cols_iter.into_iter()
-            .map(|pid_col| pid_col.get_file())
-            .collect()
+    /// Parse a comma separated list of columns, like "pid,rss,cmd". The
+    /// field a column displays can also be used, like "status.vmrss".
+    pub fn parse_list(list: &str) -> Result<Vec<PidCol>, String> {
+        let cols = try!(list.split(',')
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                if let Ok(col) = name.parse() {
+                    return Ok(col);
+                }
+                let field = try!(fields::validate(Some(name)))[0];
+                ALL_COLUMNS.iter().cloned()
+                    .find(|col| col.field() == Some(field))
+                    .ok_or(format!("Field '{}' has no column, see -O help", name))
+            })
+            .collect::<Result<Vec<_>, _>>());
+        match cols.is_empty() {
+            true => Err("No columns given, see -O help".to_owned()),
+            false => Ok(cols)
+        }
     }
 }
 
@@ -80,30 +149,25 @@ impl FromStr for PidCol {
 
     /// Get the column for a given column str.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s {
-            "pid" => PidCol::Pid,
-            "tid" => PidCol::Tid,
-            "ppid" => PidCol::Ppid,
-            "tgid" => PidCol::Tgid,
-            "rss" => PidCol::RSS,
-            "time" => PidCol::Time,
-            "cmd" => PidCol::Cmd,
-            "cmdline" => PidCol::Cmdline,
-            _ => return Err(()),
-        })
+        ALL_COLUMNS.iter().cloned().find(|col| col.to_str() == s).ok_or(())
     }
 }
 
-fn create_titles(cols: &[PidCol]) -> Vec<String> {
-  cols.iter().map(|c| {
-    c.to_title().unwrap().to_owned()
-  }).collect()
+#[test]
+fn test_parse_list() {
+    assert_eq!(PidCol::parse_list("pid,rss,cmd"), Ok(vec![PidCol::Pid, PidCol::RSS, PidCol::Cmd]));
+    assert_eq!(PidCol::parse_list("status.vmrss,stat.comm"), Ok(vec![PidCol::RSS, PidCol::Cmd]));
+    assert_eq!(PidCol::parse_list("pid,bogus"), Err("Unknown field 'bogus'".to_owned()));
+    assert_eq!(PidCol::parse_list("stat.utime"),
+        Err("Field 'stat.utime' has no column, see -O help".to_owned()));
+    assert!(PidCol::parse_list("").is_err());
 }
 
-fn create_row(cols: &[PidCol], pid: Pid) -> Vec<String> {
-  cols.iter().map(|c| {
-    match c.to_str() {
-      _ => unimplemented!()
+#[test]
+fn test_registry() {
+    for col in ALL_COLUMNS {
+        assert_eq!(col.to_str().parse(), Ok(*col));
+        assert!(!col.help().is_empty());
     }
-  }).collect()
+    assert_eq!(PidCol::RSS.field().unwrap().unit, fields::Unit::Bytes);
 }
@@ -1,39 +1,253 @@
 extern crate procrs;
 extern crate argparse;
-#[macro_use]
-extern crate prettytable;
-use prettytable::Table;
-use prettytable::row::Row;
-use prettytable::format::FormatBuilder;
 use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
 use std::iter::repeat;
 use std::cmp::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+use std::fs;
+use std::process;
+use std::path::PathBuf;
+use std::str::FromStr;
 use procrs::pid::*;
-use procrs::TaskId;
-use argparse::{ArgumentParser, StoreTrue, Store};
+use procrs::pid::sort::{self, SortSpec};
+use procrs::pid::aggregate::{GroupBy, group_by};
+use procrs::pid::tree::{self, ProcessTree};
+use procrs::pid::swap;
+use procrs::pid::privilege;
+use procrs::pid::environ::Redactor;
+use procrs::uptime::Uptime;
+use procrs::report::Report;
+use procrs::output::OutputFormat;
+use procrs::format::{self, ByteUnits};
+use procrs::root;
+use procrs::fields;
+use procrs::cancel::{self, Throttle};
+use procrs::pressure::{Pressure, MemPressure};
+use procrs::rate::Rate;
+use procrs::MemSize;
+use argparse::{ArgumentParser, StoreTrue, Store, StoreOption, StoreConst};
 
 mod columns;
+mod saved;
+
+use columns::PidCol;
+
+// With --gentle, pause for GENTLE_PAUSE_MS after every GENTLE_ENTRIES entries of /proc.
+const GENTLE_ENTRIES: usize = 50;
+const GENTLE_PAUSE_MS: u64 = 10;
 
 fn main() {
     let opts = parse_args();
-    let (query, long, perf, verbose, tree, threads) =
-        (opts.query, opts.long, opts.perf, opts.verbose, opts.tree, opts.threads);
 
-    let mut pids: Vec<_> = match threads {
+    if !opts.save_query.is_empty() {
+        match saved::save(&opts.save_query, &opts.query_text) {
+            Ok(path) => println!("Saved query '{}' to {}", opts.save_query, path.display()),
+            Err(e) => println!("ERROR, couldn't save query '{}': {}", opts.save_query, e)
+        }
+        return
+    }
+
+    if opts.report {
+        let report = Report::new(10).unwrap();
+        match opts.format {
+            OutputFormat::Json => println!("{}", report.to_json()),
+            _ => print!("{}", report.to_text())
+        }
+        return
+    }
+    if opts.list_fields {
+        list_fields(opts.format);
+        return
+    }
+    if opts.output == "help" {
+        list_columns(opts.format);
+        return
+    }
+    if opts.interactive {
+        interactive(&opts);
+        return
+    }
+    let pids = scan(opts.query.clone(), opts.threads, opts.gentle);
+    display(&opts, pids);
+}
+
+// Print every field procrs parses, from the field registry.
+fn list_fields(format: OutputFormat) {
+    let stdout = io::stdout();
+    let mut sink = format.sink(stdout.lock());
+    sink.write_table(&["Field", "Type", "Unit", "Since", "Description"]).unwrap();
+    for field in fields::PID_FIELDS {
+        sink.write_row(&[field.name.to_owned(), field.field_type.to_str().to_owned(),
+            field.unit.to_str().to_owned(), field.since.unwrap_or("").to_owned(),
+            field.help.to_owned()]).unwrap();
+    }
+    sink.finish().unwrap();
+}
+
+// Print the columns that can be chosen with -O, with the registry fields
+// they display.
+fn list_columns(format: OutputFormat) {
+    let stdout = io::stdout();
+    let mut sink = format.sink(stdout.lock());
+    sink.write_table(&["Column", "Title", "Field", "Type", "Unit", "Description"]).unwrap();
+    for col in columns::ALL_COLUMNS {
+        let field = col.field();
+        sink.write_row(&[col.to_str().to_owned(), col.to_title().to_owned(),
+            field.map(|f| f.name).unwrap_or("").to_owned(),
+            field.map(|f| f.field_type.to_str()).unwrap_or("").to_owned(),
+            field.map(|f| f.unit.to_str()).unwrap_or("").to_owned(),
+            col.help().to_owned()]).unwrap();
+    }
+    sink.finish().unwrap();
+}
+
+// Read every process (or thread with threads) matching a query. With
+// gentle, the scan is throttled and runs at the lowest priority.
+fn scan(query: PidQuery, threads: bool, gentle: bool) -> Vec<Pid> {
+    let throttle = Throttle::new(GENTLE_ENTRIES, Duration::from_millis(GENTLE_PAUSE_MS));
+    if gentle {
+        // Failing to lower the priority isn't worth stopping for.
+        let _ = cancel::lower_own_priority();
+    }
+    match threads {
         false => {
-            PidIter::new_query(query)
-                .unwrap()
-                .collect::<Result<_, _>>().unwrap()
+            let iter = PidIter::new_query(query).unwrap();
+            let iter = if gentle { iter.throttled(throttle) } else { iter };
+            iter.collect::<Result<_, _>>().unwrap()
         },
         true => {
-            TidIter::new_query(query)
-                .unwrap()
-                .collect::<Result<_, _>>().unwrap()
+            let iter = TidIter::new_query(query).unwrap();
+            let iter = if gentle { iter.throttled(throttle) } else { iter };
+            iter.collect::<Result<_, _>>().unwrap()
         }
+    }
+}
+
+// Read queries from stdin, and display the processes from a single scan
+// matching each one. This avoids rescanning /proc while trying out queries,
+// until "refresh" is entered.
+fn interactive(opts: &ProgOpts) {
+    let mut snapshot = scan(PidQuery::NoneQuery, opts.threads, opts.gentle);
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("psq> ");
+        io::stdout().flush().unwrap();
+        line.clear();
+        match stdin.lock().read_line(&mut line) {
+            Ok(0) | Err(_) => { println!(); break },
+            Ok(_) => {}
+        }
+        let query = match line.trim() {
+            "" => PidQuery::NoneQuery,
+            "quit" | "exit" => break,
+            "refresh" => {
+                snapshot = scan(PidQuery::NoneQuery, opts.threads, opts.gentle);
+                println!("{} processes", snapshot.len());
+                continue
+            },
+            "help" => {
+                println!("Enter a query (eg: 1234, sshd, ppid=1, user=root, cmdline=--daemon) \
+                    to display matching processes,\nan empty line for every process, refresh to \
+                    rescan /proc, or quit.");
+                continue
+            },
+            q => match q.parse() {
+                Ok(query) => query,
+                Err(e) => { println!("{}", e); continue }
+            }
+        };
+        let context = QueryContext::new(&query);
+        display(opts, snapshot.iter().filter(|p| p.query_with(&query, &context))
+            .cloned().collect());
+    }
+}
+
+// Filter, sort and print processes, with the columns chosen in opts.
+fn display(opts: &ProgOpts, mut pids: Vec<Pid>) {
+    let (verbose, tree, threads, mut interval) =
+        (opts.verbose, opts.tree, opts.threads, opts.interval);
+    // Swapping in is measured by major faults, so it needs a sample.
+    if opts.swapping && interval <= 0.0 {
+        interval = 1.0;
+    }
+    let cols = match opts.columns {
+        Some(ref cols) => cols.clone(),
+        None => default_columns(opts, interval > 0.0)
+    };
+    // Like swapping, sampled cpu usage chosen with -O needs a sample.
+    if cols.contains(&PidCol::CpuNow) && interval <= 0.0 {
+        interval = 1.0;
+    }
+
+    // Age and memory thresholds are applied on top of the positional query.
+    let thresholds = PidQuery::And(opts.older_than.as_ref().map(|d| PidQuery::OlderThan(d.0))
+        .into_iter()
+        .chain(opts.newer_than.as_ref().map(|d| PidQuery::NewerThan(d.0)))
+        .chain(opts.rss_above.as_ref().map(|s| PidQuery::RssAbove(s.0)))
+        .chain(opts.vsz_above.as_ref().map(|s| PidQuery::VszAbove(s.0)))
+        .collect());
+    let context = QueryContext::new(&thresholds);
+    pids.retain(|p| p.query_with(&thresholds, &context));
+
+    // Like fuser, only keep processes using this file or directory.
+    if let Some(ref path) = opts.using {
+        let query = PidQuery::OpenFileQuery(fs::canonicalize(path).unwrap_or(path.clone()));
+        pids.retain(|p| p.query(&query));
+    }
+
+    // For security reviews, only keep root, capable or setuid processes.
+    if opts.privileged {
+        pids.retain(|p| !privilege::privileges(p).is_empty());
+    }
+
+    // Like pgrep -o/-n, only keep the oldest or newest match.
+    let selected = match (opts.oldest, opts.newest) {
+        (true, _) => sort::oldest(&pids).map(|p| p.stat.pid),
+        (_, true) => sort::newest(&pids).map(|p| p.stat.pid),
+        _ => None
     };
+    if let Some(pid) = selected {
+        pids.retain(|p| p.stat.pid == pid);
+    }
 
     let mut name_indent = HashMap::new();
 
+    let format = opts.format;
+    if opts.cpu_spread {
+        let stdout = io::stdout();
+        let mut sink = format.sink(stdout.lock());
+        sink.write_table(&["Pid", "Allowed", "Threads", "Cmd"]).unwrap();
+        for p in &pids {
+            let spread = p.cpu_spread().unwrap_or_default().iter()
+                .map(|(cpu, count)| format!("{}:{}", cpu, count))
+                .collect::<Vec<_>>().join(" ");
+            sink.write_row(&[p.stat.pid.to_string(), format_cpu_list(&p.status.cpus_allowed_list),
+                spread, p.stat.comm.clone()]).unwrap();
+        }
+        sink.finish().unwrap();
+        return
+    }
+
+    if opts.env {
+        let redactor = opts.redact.split(',')
+            .filter(|p| !p.is_empty())
+            .fold(Redactor::default(), |r, p| r.pattern(p));
+        let stdout = io::stdout();
+        let mut sink = format.sink(stdout.lock());
+        sink.write_table(&["Pid", "Variable", "Value"]).unwrap();
+        for p in &pids {
+            // Like the other ptrace protected files, skip processes we can't read.
+            for (name, value) in redactor.redact(&p.environ().unwrap_or_default()) {
+                sink.write_row(&[p.stat.pid.to_string(), name, value]).unwrap();
+            }
+        }
+        sink.finish().unwrap();
+        return
+    }
+
     if verbose {
         for pid in pids {
             println!("{:?}", pid);
@@ -42,7 +256,22 @@ fn main() {
     }
 
     if opts.tree {
-        pids = treeify_names(pids, &mut name_indent);
+        // The parent of a Restricted process is unknown, so list them after
+        // the tree rather than as roots.
+        let (readable, mut restricted): (Vec<_>, Vec<_>) = pids.into_iter()
+            .partition(|p| p.access == AccessLevel::Full);
+        let tree = ProcessTree::new(readable);
+        pids = tree.depth_first()
+            .map(|(p, depth)| {
+                name_indent.insert(p.stat.pid, repeat("  ").take(depth).collect());
+                p.clone()
+            })
+            .collect();
+        restricted.sort_by_key(|p| p.stat.pid);
+        for p in restricted {
+            name_indent.insert(p.stat.pid, String::new());
+            pids.push(p);
+        }
     } else {
         pids.sort_by(|p1, p2| 
             match threads {
@@ -54,141 +283,278 @@ fn main() {
                 }
             }
         );
+        // Since sorting is stable, ties are left in pid order.
+        if let Some(ref spec) = opts.sort {
+            spec.sort(&mut pids);
+        }
     };
-    // Assume hertz is 100.
-    // TODO: Look this up via syscall (no /proc value for it)
-    let hertz = 100;
-    let minute_hertz = hertz * 60;
-    let hour_hertz = minute_hertz * 60;
-
-    let mut table = Table::init(
-        pids.iter().map(|p| {
-            // When we have a tree, the name is prepended with an indent.
-            let mut name = match tree {
-                false => String::new(),
-                true => name_indent.remove(&p.stat.pid).unwrap()
-            };
-
-            name.push_str(&p.stat.comm);
-
-            let mut row = Vec::new();
-            match threads {
-                false => row.push(cell!(p.stat.pid)),
-                true => {
-                    row.push(cell!(p.status.tgid));
-                    row.push(cell!(p.status.pid));
-                }
-            };
-            row.push(cell!(p.stat.ppid));
-            if long {
+    // Average cpu usage needs the system uptime, and sampled columns
+    // need a second reading of each process after the interval.
+    let uptime = match Uptime::new() {
+        Ok(uptime) => uptime.uptime,
+        Err(e) => {
+            println!("ERROR, couldn't read the uptime: {}", e);
+            return
+        }
+    };
+    let mut samples = HashMap::new();
+    let mut sample_elapsed = Duration::from_secs(0);
+    if interval > 0.0 {
+        let start = Instant::now();
+        thread::sleep(Duration::from_millis((interval * 1000.0) as u64));
+        for p in &pids {
+            if let Ok(stat) = p.read_stat() {
+                samples.insert(p.stat.pid, stat);
             }
-            match (long, perf) {
-                (_, false) => {},
-                (_, true) => {
-                    let rss = p.status.vmrss.map(|m| (m / 1024).to_string()).unwrap_or("".to_owned());
-                    let raw_time = p.stat.utime + p.stat.stime;
-                    let second_utime = raw_time / hertz % 60;
-                    let minute_utime = raw_time / minute_hertz % 60;
-                    let hour_utime = raw_time / hour_hertz % 60;
-                    let cputime = format!(
-                        "{:02}:{:02}:{:02}",
-                        hour_utime,
-                        minute_utime,
-                        second_utime
-                    );
-                    row.push(cell!(rss));
-                    row.push(cell!(cputime));
-                }
+        }
+        sample_elapsed = start.elapsed();
+    }
+    if opts.swapping {
+        pids.retain(|p| samples.get(&p.stat.pid)
+            .map(|s| Rate::from_delta(s.majflt.saturating_sub(p.stat.majflt), sample_elapsed))
+            .map(|r| swap::is_swapping_in(p.status.vmswap, r.per_sec()))
+            .unwrap_or(false));
+    }
+    // System memory pressure is optional, since older kernels don't have PSI.
+    let psi = match cols.contains(&PidCol::MemPressure) {
+        true => Pressure::memory().ok(),
+        false => None
+    };
+
+    // Children are counted before filtering, so every process is needed.
+    let family = match cols.contains(&PidCol::NChild) || cols.contains(&PidCol::NDesc) {
+        true => tree::family_counts(
+            &PidIter::new().unwrap().filter_map(|p| p.ok()).collect::<Vec<_>>()),
+        false => HashMap::new()
+    };
+
+    let make_row = |p: &Pid| -> Vec<String> {
+        cols.iter().map(|col| match *col {
+            PidCol::Pid => match threads {
+                false => p.stat.pid.to_string(),
+                true => p.status.tgid.to_string()
+            },
+            PidCol::Tid => p.status.pid.to_string(),
+            PidCol::Tgid => p.status.tgid.to_string(),
+            PidCol::Ppid => p.stat.ppid.to_string(),
+            PidCol::NChild =>
+                family.get(&p.stat.pid).map(|c| c.children).unwrap_or_default().to_string(),
+            PidCol::NDesc =>
+                family.get(&p.stat.pid).map(|c| c.descendants).unwrap_or_default().to_string(),
+            PidCol::Psr => p.stat.processor.map(|c| c.to_string()).unwrap_or("-".to_owned()),
+            PidCol::RSS => p.status.vmrss.map(|m| format::bytes(m, ByteUnits::Binary))
+                .unwrap_or("".to_owned()),
+            PidCol::Cpu => format!("{:.1}", p.stat.cpu_percent_lifetime(uptime)),
+            PidCol::CpuNow => samples.get(&p.stat.pid)
+                .map(|s| format!("{:.1}", s.cpu_percent_sampled(&p.stat, sample_elapsed)))
+                .unwrap_or("".to_owned()),
+            PidCol::Time => format::cputime(p.stat.utime + p.stat.stime),
+            PidCol::MemPressure => {
+                // The sample is newer than the first reading, so take the rate from it.
+                let secs = sample_elapsed.as_secs() as f64
+                    + sample_elapsed.subsec_nanos() as f64 / 1e9;
+                let majflt_rate = match samples.get(&p.stat.pid) {
+                    Some(s) if secs > 0.0 =>
+                        s.majflt.saturating_sub(p.stat.majflt) as f64 / secs,
+                    _ => 0.0
+                };
+                MemPressure::classify(majflt_rate, p.status.vmswap, psi.as_ref())
+                    .to_str().to_string()
+            },
+            // Without a security module there is no label, like ps -Z.
+            PidCol::Label => p.label().unwrap_or(None).unwrap_or("-".to_owned()),
+            PidCol::Cmd => {
+                // When we have a tree, the name is prepended with an indent.
+                let mut name = match tree {
+                    false => String::new(),
+                    true => name_indent.get(&p.stat.pid).cloned().unwrap_or_default()
+                };
+                name.push_str(&p.stat.comm);
+                name
+            },
+            PidCol::Cmdline => p.cmdline.join(" "),
+        }).collect()
+    };
+
+    let titles: Vec<&str> = cols.iter().map(|c| c.to_title()).collect();
+    let stdout = io::stdout();
+    let mut sink = format.sink(stdout.lock());
+    match opts.group_by {
+        None => {
+            sink.write_table(&titles).unwrap();
+            for p in &pids {
+                sink.write_row(&make_row(p)).unwrap();
             }
-            row.push(cell!(name));
-            if long {
-                row.push(cell!(p.cmdline.join(" ")));
+        },
+        Some(by) => {
+            for group in group_by(pids, by) {
+                sink.write_record(&[
+                    ("group", group.key.clone()),
+                    ("processes", group.count().to_string()),
+                    ("rss_kb", (group.rss / 1024).to_string()),
+                    ("time", format::cputime(group.cputime)),
+                ]).unwrap();
+                sink.write_table(&titles).unwrap();
+                for p in &group.pids {
+                    sink.write_row(&make_row(p)).unwrap();
+                }
             }
-            Row::new(row)
-        }).collect::<Vec<_>>()
-    );
+        }
+    }
+    sink.finish().unwrap();
+}
 
-    let mut titles = Vec::new();
-    titles.push(cell!("Pid"));
-    if threads {
-        titles.push(cell!("Tid"));
+// The columns displayed without -O, chosen by the flags in opts.
+fn default_columns(opts: &ProgOpts, sampled: bool) -> Vec<PidCol> {
+    let mut cols = vec![PidCol::Pid];
+    if opts.threads {
+        cols.push(PidCol::Tid);
+    }
+    cols.push(PidCol::Ppid);
+    if opts.children {
+        cols.extend_from_slice(&[PidCol::NChild, PidCol::NDesc]);
+    }
+    if opts.psr {
+        cols.push(PidCol::Psr);
     }
-    titles.push(cell!("Ppid"));
     // TODO: Possible remove Ppid from when long is false,
     // and have Cmd/Args as separate columns for long.
-    match (long, perf) {
-        (_, false) =>
-            titles.extend_from_slice(&[cell!("Cmd")]),
-        (_, true) =>
-            titles.extend_from_slice(&[cell!("RSS"), cell!("Time"), cell!("Cmd")])
-    };
-    if long {
-        titles.push(cell!("Cmdline"));
-    }
-    table.set_titles(Row::new(titles));
-    table.set_format(
-        FormatBuilder::new()
-            .column_separator(' ')
-            .build()
-    );
-    table.printstd();
-}
-
-// Given a vector of Pid structs, treeify their names, and return them in the right order.
-// This is similar to ps -AH.
-fn treeify_names(pids: Vec<Pid>, name_indents: &mut HashMap<TaskId, String>) -> Vec<Pid> {
-    let mut child_pids = HashMap::new();
-    for pid in pids {
-        let ppid = pid.stat.ppid;
-        child_pids.entry(ppid)
-            .or_insert(Vec::new())
-            .push(pid);
-    }
-    enumerate_children(0, &mut child_pids, name_indents, -1)
+    if opts.perf {
+        cols.extend_from_slice(&[PidCol::RSS, PidCol::Cpu]);
+        if sampled {
+            cols.push(PidCol::CpuNow);
+        }
+        cols.push(PidCol::Time);
+    }
+    if opts.mem_pressure {
+        cols.push(PidCol::MemPressure);
+    }
+    if opts.label {
+        cols.push(PidCol::Label);
+    }
+    cols.push(PidCol::Cmd);
+    if opts.long {
+        cols.push(PidCol::Cmdline);
+    }
+    cols
 }
 
-// Enumerate children pids, and return them.
-fn enumerate_children(pid: TaskId, child_pids: &mut HashMap<TaskId, Vec<Pid>>,
-    name_indents: &mut HashMap<TaskId, String>, indent: i32) -> Vec<Pid> {
-    name_indents.insert(pid,
-        match indent {
-            i if i >= 0 =>
-                repeat("  ").take(i as usize).collect::<String>(),
-            _ => "".to_owned()
+// Format a list of cpus as ranges, like "0-3,6".
+fn format_cpu_list(cpus: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &cpu in cpus {
+        match ranges.last_mut() {
+            Some(range) if range.1 + 1 == cpu => { range.1 = cpu; continue },
+            _ => {}
         }
-    );
-    let mut pids = Vec::new();
-    let ppids = match child_pids.remove(&pid) {
-        Some(v) => v,
-        None => { return pids; }
-    };
-    for pid in ppids {
-        let pid_num = pid.stat.pid;
-        pids.push(pid);
-        pids.append(
-            &mut enumerate_children(pid_num, child_pids, name_indents, indent + 1)
-        );
+        ranges.push((cpu, cpu));
     }
-    pids
+    ranges.iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end)
+        }).collect::<Vec<_>>().join(",")
 }
 
 struct ProgOpts {
     query: PidQuery,
+    query_text: String,
+    saved_query: String,
+    save_query: String,
     tree: bool,
     threads: bool,
     perf: bool,
     long: bool,
-    verbose: bool
+    verbose: bool,
+    interval: f64,
+    mem_pressure: bool,
+    sort: Option<SortSpec>,
+    group_by: Option<GroupBy>,
+    oldest: bool,
+    newest: bool,
+    using: Option<PathBuf>,
+    older_than: Option<Age>,
+    newer_than: Option<Age>,
+    rss_above: Option<Size>,
+    vsz_above: Option<Size>,
+    children: bool,
+    psr: bool,
+    cpu_spread: bool,
+    swapping: bool,
+    label: bool,
+    privileged: bool,
+    report: bool,
+    interactive: bool,
+    list_fields: bool,
+    env: bool,
+    redact: String,
+    gentle: bool,
+    proc_root: Option<PathBuf>,
+    output: String,
+    columns: Option<Vec<PidCol>>,
+    format: OutputFormat
+}
+
+// A duration given on the command line, like "90s", "30m", "2h30m" or "1d".
+struct Age(Duration);
+
+impl FromStr for Age {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        format::parse_duration(s).map(Age)
+    }
+}
+
+// A size in bytes given on the command line, like "512K", "1.5G" or "4096".
+struct Size(MemSize);
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        format::parse_size(s).map(Size)
+    }
 }
 
 fn parse_args() -> ProgOpts {
     let mut opts = ProgOpts {
         query: PidQuery::NoneQuery,
+        query_text: String::new(),
+        saved_query: String::new(),
+        save_query: String::new(),
         tree: false,
         threads: false,
         perf: false,
         long: false,
-        verbose: false
+        verbose: false,
+        interval: 0.0,
+        mem_pressure: false,
+        sort: None,
+        group_by: None,
+        oldest: false,
+        newest: false,
+        using: None,
+        older_than: None,
+        newer_than: None,
+        rss_above: None,
+        vsz_above: None,
+        children: false,
+        psr: false,
+        cpu_spread: false,
+        swapping: false,
+        label: false,
+        privileged: false,
+        report: false,
+        interactive: false,
+        list_fields: false,
+        env: false,
+        redact: String::new(),
+        gentle: false,
+        proc_root: None,
+        output: String::new(),
+        columns: None,
+        format: OutputFormat::Table
     };
 
     {
@@ -202,12 +568,138 @@ fn parse_args() -> ProgOpts {
             .add_option(&["-p", "--perf"], StoreTrue, "Display columns about performance");
         ap.refer(&mut opts.long)
             .add_option(&["-l", "--long"], StoreTrue, "Display columns with more information");
+        ap.refer(&mut opts.interval)
+            .add_option(&["-i", "--interval"], Store,
+                "Sample cpu usage and page faults over this many seconds");
+        ap.refer(&mut opts.mem_pressure)
+            .add_option(&["-m", "--mem-pressure"], StoreTrue,
+                "Display whether processes are thrashing, swapped or resident");
+        ap.refer(&mut opts.sort)
+            .add_option(&["-s", "--sort"], StoreOption,
+                "Sort by a comma separated list of fields, prefix with '-' for descending");
+        ap.refer(&mut opts.group_by)
+            .add_option(&["-g", "--group-by"], StoreOption,
+                "Group processes by user, cgroup or comm, with totals for each group");
+        ap.refer(&mut opts.children)
+            .add_option(&["-c", "--children"], StoreTrue,
+                "Display the number of children and descendants of each process");
+        ap.refer(&mut opts.psr)
+            .add_option(&["--psr"], StoreTrue, "Display the cpu each process or thread last ran on");
+        ap.refer(&mut opts.cpu_spread)
+            .add_option(&["--cpu-spread"], StoreTrue,
+                "Display how many threads of each process last ran on each cpu");
+        ap.refer(&mut opts.label)
+            .add_option(&["-Z", "--context"], StoreTrue,
+                "Display the security context (SELinux or AppArmor label) of each process");
+        ap.refer(&mut opts.privileged)
+            .add_option(&["--privileged"], StoreTrue,
+                "Only display processes running as root, with capabilities, or from setuid binaries");
+        ap.refer(&mut opts.swapping)
+            .add_option(&["--swapping"], StoreTrue,
+                "Only display processes faulting swapped memory back in (samples for -i or 1 second)");
+        ap.refer(&mut opts.oldest)
+            .add_option(&["-o", "--oldest"], StoreTrue, "Only display the oldest matching process");
+        ap.refer(&mut opts.newest)
+            .add_option(&["-n", "--newest"], StoreTrue, "Only display the newest matching process");
+        ap.refer(&mut opts.using)
+            .add_option(&["-u", "--using"], StoreOption,
+                "Only display processes with this file, or anything under this directory, open");
+        ap.refer(&mut opts.older_than)
+            .add_option(&["--older-than"], StoreOption,
+                "Only display processes started longer ago than this (eg: 30m, 2h30m, 1d)");
+        ap.refer(&mut opts.newer_than)
+            .add_option(&["--newer-than"], StoreOption,
+                "Only display processes started more recently than this (eg: 90s, 2h)");
+        ap.refer(&mut opts.rss_above)
+            .add_option(&["--rss-above"], StoreOption,
+                "Only display processes using more resident memory than this (eg: 512M, 1.5G)");
+        ap.refer(&mut opts.vsz_above)
+            .add_option(&["--vsz-above"], StoreOption,
+                "Only display processes using more virtual memory than this (eg: 512M, 1.5G)");
+        ap.refer(&mut opts.report)
+            .add_option(&["--report"], StoreTrue,
+                "Print an incident report (top processes, zombies, D state, deleted binaries)");
+        ap.refer(&mut opts.env)
+            .add_option(&["-e", "--env"], StoreTrue,
+                "Display the environment of each process, with secrets redacted");
+        ap.refer(&mut opts.redact)
+            .add_option(&["--redact"], Store,
+                "Also redact variables matching these comma separated patterns (eg: 'AWS_*,DB_*')");
+        ap.refer(&mut opts.gentle)
+            .add_option(&["--gentle"], StoreTrue,
+                "Scan slowly at the lowest cpu and I/O priority, to avoid disturbing loaded hosts");
+        ap.refer(&mut opts.proc_root)
+            .add_option(&["--proc-root"], StoreOption,
+                "Read procfs from this directory instead of /proc (eg: /host/proc)");
+        ap.refer(&mut opts.interactive)
+            .add_option(&["--interactive"], StoreTrue,
+                "Read queries from stdin, displaying matches from a single scan of /proc");
+        ap.refer(&mut opts.list_fields)
+            .add_option(&["--fields"], StoreTrue,
+                "List every process field procrs reads, with its type, unit and kernel version");
+        ap.refer(&mut opts.output)
+            .add_option(&["-O", "--output"], Store,
+                "Display these comma separated columns or fields (eg: pid,rss,cmd), \
+                or list them with -O help");
+        ap.refer(&mut opts.format)
+            .add_option(&["-f", "--format"], Store, "Output format, being table, json or csv")
+            .add_option(&["--json"], StoreConst(OutputFormat::Json), "Same as --format json");
         ap.refer(&mut opts.verbose)
             .add_option(&["-v", "--verbose"], StoreTrue, "Verbose output");
-        ap.refer(&mut opts.query)
+        ap.refer(&mut opts.saved_query)
+            .add_option(&["-q", "--query"], Store, "Search by a query saved with --save-query");
+        ap.refer(&mut opts.save_query)
+            .add_option(&["--save-query"], Store,
+                "Save the query under this name in the config file, instead of searching");
+        ap.refer(&mut opts.query_text)
             .add_argument("query", Store, "Optional query to search by, pid or string");
         ap.parse_args_or_exit();
     }
 
+    if let Some(ref dir) = opts.proc_root {
+        root::set_root(dir.clone());
+    }
+
+    // A saved query is used in place of the positional one.
+    if !opts.saved_query.is_empty() {
+        if !opts.query_text.is_empty() {
+            println!("ERROR, --query can't be used with a query argument");
+            process::exit(2);
+        }
+        opts.query_text = match saved::find(&opts.saved_query) {
+            Ok(Some(query)) => query,
+            Ok(None) => {
+                println!("ERROR, no saved query named '{}'", opts.saved_query);
+                process::exit(2);
+            },
+            Err(e) => {
+                println!("ERROR, couldn't read saved queries: {}", e);
+                process::exit(2);
+            }
+        };
+    }
+    if !opts.save_query.is_empty() && opts.query_text.is_empty() {
+        println!("ERROR, --save-query needs a query argument to save");
+        process::exit(2);
+    }
+    if !opts.output.is_empty() && opts.output != "help" {
+        opts.columns = match PidCol::parse_list(&opts.output) {
+            Ok(cols) => Some(cols),
+            Err(e) => {
+                println!("ERROR, {}", e);
+                process::exit(2);
+            }
+        };
+    }
+    if !opts.query_text.is_empty() {
+        opts.query = match opts.query_text.parse() {
+            Ok(query) => query,
+            Err(e) => {
+                println!("ERROR, {}", e);
+                process::exit(2);
+            }
+        };
+    }
+
     opts
 }
@@ -0,0 +1,129 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Saved queries are kept in a config file with a "name = query" pair on
+// each line, so the file can be edited by hand or shared between people.
+// Blank lines and lines starting with '#' are ignored.
+
+// The config file, being $PSQ_CONFIG, or psq/queries in the XDG config directory.
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("PSQ_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or(env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|config| config.join("psq").join("queries"))
+}
+
+// Read every saved query in file order. A missing config file has none.
+pub fn load() -> io::Result<Vec<(String, String)>> {
+    let path = match config_path() {
+        Some(path) => path,
+        None => return Ok(Vec::new())
+    };
+    read_config(&path).map(|contents| parse(&contents))
+}
+
+// Look up a saved query by name.
+pub fn find(name: &str) -> io::Result<Option<String>> {
+    Ok(try!(load()).into_iter().find(|&(ref n, _)| n == name).map(|(_, q)| q))
+}
+
+// Save a query under a name, replacing any query already saved with it.
+pub fn save(name: &str, query: &str) -> io::Result<PathBuf> {
+    let path = try!(config_path().ok_or(io::Error::new(io::ErrorKind::NotFound,
+        "no config directory, set HOME or PSQ_CONFIG")));
+    try!(save_to(&path, name, query));
+    Ok(path)
+}
+
+// Save a query under a name in a given config file.
+fn save_to(path: &Path, name: &str, query: &str) -> io::Result<()> {
+    if name.is_empty() || name.contains('=') || name.contains(char::is_whitespace) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "query names can't be empty, or contain '=' or whitespace"));
+    }
+    let contents = try!(read_config(path));
+    // Keep comments and every other query as they were.
+    let mut lines: Vec<_> = contents.lines()
+        .filter(|l| parse(l).first().map(|&(ref n, _)| n != name).unwrap_or(true))
+        .map(|l| l.to_owned())
+        .collect();
+    lines.push(format!("{} = {}", name, query));
+    if let Some(dir) = path.parent() {
+        try!(fs::create_dir_all(dir));
+    }
+    let mut file = try!(File::create(path));
+    file.write_all((lines.join("\n") + "\n").as_bytes())
+}
+
+// Read the config file, which is empty if it doesn't exist yet.
+fn read_config(path: &Path) -> io::Result<String> {
+    let mut contents = String::new();
+    match File::open(path) {
+        Ok(mut file) => { try!(file.read_to_string(&mut contents)); },
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {},
+        Err(e) => return Err(e)
+    }
+    Ok(contents)
+}
+
+fn parse(contents: &str) -> Vec<(String, String)> {
+    contents.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut split = l.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(name), Some(query)) =>
+                    Some((name.trim().to_owned(), query.trim().to_owned())),
+                _ => None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("psq-saved-{}-{}", ::std::process::id(), name))
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(parse("# comment\n\nweb = name=nginx\nnot a query\n  db=ppid=1  \n"), vec![
+        ("web".to_owned(), "name=nginx".to_owned()),
+        ("db".to_owned(), "ppid=1".to_owned()),
+    ]);
+}
+
+#[test]
+fn test_save_round_trip() {
+    let dir = test_dir("round-trip");
+    let path = dir.join("psq").join("queries");
+    save_to(&path, "web", "name=nginx").unwrap();
+    save_to(&path, "db", "name=postgres & rss>1G").unwrap();
+    assert_eq!(parse(&read_config(&path).unwrap()), vec![
+        ("web".to_owned(), "name=nginx".to_owned()),
+        ("db".to_owned(), "name=postgres & rss>1G".to_owned()),
+    ]);
+    assert!(save_to(&path, "has space", "name=x").is_err());
+    assert!(save_to(&path, "a=b", "name=x").is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_save_replace() {
+    let dir = test_dir("replace");
+    let path = dir.join("queries");
+    fs::create_dir_all(&dir).unwrap();
+    File::create(&path).unwrap()
+        .write_all(b"# Web servers\nweb = name=apache\n\n# Databases\ndb = name=postgres\n")
+        .unwrap();
+    save_to(&path, "web", "name=nginx").unwrap();
+    // Comments and blank lines stay, and the replaced query moves to the end.
+    assert_eq!(read_config(&path).unwrap(),
+        "# Web servers\n\n# Databases\ndb = name=postgres\nweb = name=nginx\n");
+    fs::remove_dir_all(&dir).unwrap();
+}